@@ -1,3 +1,47 @@
+//! An interpreter for a small python-like language, which can also be
+//! embedded. Errors are `Traceback`s, which work with `?`:
+//!
+//! ```
+//! use myton::Interpreter;
+//! use std::error::Error;
+//!
+//! fn main() -> Result<(), Box<dyn Error>> {
+//!     let mut interpreter = Interpreter::new();
+//!     interpreter.run("x = 1 + 2".to_string())?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! and carry where and why the script failed:
+//!
+//! ```
+//! use myton::{Interpreter, TracebackKind};
+//!
+//! let mut interpreter = Interpreter::new();
+//! let error = interpreter.run("x = [1]\nx[3]".to_string()).unwrap_err();
+//! assert!(matches!(error.kind(), TracebackKind::IndexError));
+//! assert_eq!(error.line(), 2);
+//! assert!(error.to_string().starts_with("error[index error]: list index out of range"));
+//! ```
+
 mod myton;
 
-pub use self::myton::run_to_string;
+pub use self::myton::{
+    diff_snapshots, lex, parse, resolve, run_main, run_to_string, run_to_strings,
+    run_to_strings_with_input, Ast, DynValue, Global, GlobalChange, Interpreter, MyRead, MyWrite,
+    MytonFunction, ResolvedProgram, RunStats, SendInterpreter, Token, TokenKind, TraceMode,
+    Traceback, TracebackKind, TypeKind,
+};
+
+// what the tests in tests/ share with the crate's own
+#[doc(hidden)]
+pub use self::myton::test_support;
+
+// entry point for a cargo-fuzz target, whatever the
+// input it should give back output or an error, never panic
+#[cfg(fuzzing)]
+pub fn fuzz(data: &[u8]) {
+    if let Ok(source) = std::str::from_utf8(data) {
+        run_to_string(source.to_string());
+    }
+}
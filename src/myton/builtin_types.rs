@@ -0,0 +1,329 @@
+use super::environment::Env;
+use super::functions::Callable;
+use super::hashable::Dict;
+use super::limits::{check_size, list_size};
+use super::numeric::{parse_float, parse_int};
+use super::traceback::{Traceback, TracebackKind};
+use super::types::{DynValue, TypeKind};
+
+// the types of the built-in values, which scripts use both to
+// convert values (`int("3")`) and to check them (`type(x) is int`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinType {
+    Int,
+    Float,
+    Str,
+    Bool,
+    List,
+    Tuple,
+    Dict,
+    Type,
+    NoneType,
+    Function,
+    BuiltinFunction,
+    // named after the kind of error, or just Exception for all of them
+    Exception(&'static str),
+    Module,
+    Iterator,
+    Range,
+}
+
+// the ones defined as globals, the others are only reachable through type()
+pub const GLOBAL_TYPES: [BuiltinType; 17] = [
+    BuiltinType::Int,
+    BuiltinType::Float,
+    BuiltinType::Str,
+    BuiltinType::Bool,
+    BuiltinType::List,
+    BuiltinType::Tuple,
+    BuiltinType::Dict,
+    BuiltinType::Type,
+    BuiltinType::Exception("Exception"),
+    BuiltinType::Exception("RuntimeError"),
+    BuiltinType::Exception("IndexError"),
+    BuiltinType::Exception("KeyError"),
+    BuiltinType::Exception("TypeError"),
+    BuiltinType::Exception("MemoryError"),
+    BuiltinType::Exception("RecursionError"),
+    BuiltinType::Exception("ValueError"),
+    BuiltinType::Exception("EOFError"),
+];
+
+impl BuiltinType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::Str => "str",
+            Self::Bool => "bool",
+            Self::List => "list",
+            Self::Tuple => "tuple",
+            Self::Dict => "dict",
+            Self::Type => "type",
+            Self::NoneType => "NoneType",
+            Self::Function => "function",
+            Self::BuiltinFunction => "builtin_function_or_method",
+            Self::Exception(name) => name,
+            Self::Module => "module",
+            Self::Iterator => "iterator",
+            Self::Range => "range",
+        }
+    }
+
+    // numbers are all floats underneath, so the whole ones are ints
+    fn of(value: &DynValue) -> Option<Self> {
+        match value.tipe {
+            TypeKind::Number if value.as_number().fract() == 0.0 => Some(Self::Int),
+            TypeKind::Number => Some(Self::Float),
+            TypeKind::Stringue => Some(Self::Str),
+            TypeKind::Boolean => Some(Self::Bool),
+            TypeKind::List => Some(Self::List),
+            TypeKind::Tuple => Some(Self::Tuple),
+            TypeKind::Dict => Some(Self::Dict),
+            TypeKind::Class | TypeKind::Type => Some(Self::Type),
+            TypeKind::Nil => Some(Self::NoneType),
+            TypeKind::Function => Some(Self::Function),
+            TypeKind::NativeFunction => Some(Self::BuiltinFunction),
+            TypeKind::Exception => Some(Self::Exception(
+                value
+                    .as_exception()
+                    .and_then(|exception| exception.traceback.tipe.exception_name())
+                    .unwrap_or("Exception"),
+            )),
+            TypeKind::Module => Some(Self::Module),
+            TypeKind::Iterator => Some(Self::Iterator),
+            TypeKind::Range => Some(Self::Range),
+            TypeKind::Instance => None,
+        }
+    }
+
+    // bools count as ints and every error is an Exception, like in python
+    pub fn contains(&self, value: &DynValue) -> bool {
+        if let Self::Exception(name) = self {
+            return value
+                .as_exception()
+                .is_some_and(|exception| exception.traceback.tipe.is_caught_by(name));
+        }
+        Self::of(value) == Some(*self) || (*self == Self::Int && value.tipe == TypeKind::Boolean)
+    }
+
+    fn default_value(&self) -> Result<DynValue, Traceback> {
+        match self {
+            Self::Int | Self::Float => Ok(DynValue::from(0.0)),
+            Self::Str => Ok(DynValue::from("")),
+            Self::Bool => Ok(DynValue::from(false)),
+            Self::List => Ok(DynValue::from(Vec::new())),
+            Self::Tuple => Ok(DynValue::from_tuple(Vec::new())),
+            Self::Dict => Ok(DynValue::from_dict(Vec::new())),
+            Self::NoneType => Ok(DynValue::none()),
+            _ => Err(Traceback::from_exception(
+                TracebackKind::TypeError,
+                &format!("cannot create '{}' instances", self.name()),
+            )),
+        }
+    }
+
+    fn convert(&self, env: &Env, value: DynValue) -> Result<DynValue, Traceback> {
+        match self {
+            Self::Int => to_int(&value),
+            Self::Float => to_float(&value),
+            Self::Str => Ok(DynValue::from(value.as_string())),
+            Self::Bool => Ok(DynValue::from(value.as_bool())),
+            Self::List => Ok(DynValue::from(elements(env, &value)?)),
+            Self::Tuple => Ok(DynValue::from_tuple(elements(env, &value)?)),
+            Self::Dict => match value.as_dict() {
+                Some(entries) => Ok(DynValue::from_dict(entries)),
+                None => Ok(DynValue::from(Dict::new(pairs(env, &value)?)?)),
+            },
+            Self::Type => Ok(type_of(&value)),
+            Self::NoneType => Err(Traceback::from_exception(
+                TracebackKind::TypeError,
+                "NoneType takes no arguments",
+            )),
+            _ => self.default_value(),
+        }
+    }
+}
+
+fn to_int(value: &DynValue) -> Result<DynValue, Traceback> {
+    match value.tipe {
+        TypeKind::Number => Ok(DynValue::from(value.as_number().trunc())),
+        TypeKind::Boolean => Ok(DynValue::from(value.as_number())),
+        TypeKind::Stringue => parse_string_int(&value.as_string(), 10),
+        _ => Err(Traceback::from_exception(
+            TracebackKind::TypeError,
+            &format!(
+                "int() argument must be a string or a number, not '{}'",
+                value.tipe
+            ),
+        )),
+    }
+}
+
+fn parse_string_int(text: &str, base: u32) -> Result<DynValue, Traceback> {
+    match parse_int(text, base) {
+        Some(n) => Ok(DynValue::from(n)),
+        None => Err(Traceback::from_exception(
+            TracebackKind::ValueError,
+            &format!("invalid literal for int() with base {}: '{}'", base, text),
+        )),
+    }
+}
+
+// int(text, base), only strings are written in a base
+fn to_int_with_base(value: &DynValue, base: &DynValue) -> Result<DynValue, Traceback> {
+    if value.tipe != TypeKind::Stringue {
+        return Err(Traceback::from_exception(
+            TracebackKind::TypeError,
+            "int() can't convert non-string with explicit base",
+        ));
+    }
+    let number = base.as_number();
+    let valid = base.tipe == TypeKind::Number
+        && number.fract() == 0.0
+        && (number == 0.0 || (2.0..=36.0).contains(&number));
+    if !valid {
+        return Err(Traceback::from_exception(
+            TracebackKind::ValueError,
+            "int() base must be >= 2 and <= 36, or 0",
+        ));
+    }
+    parse_string_int(&value.as_string(), number as u32)
+}
+
+fn to_float(value: &DynValue) -> Result<DynValue, Traceback> {
+    match value.tipe {
+        TypeKind::Number | TypeKind::Boolean => Ok(DynValue::from(value.as_number())),
+        TypeKind::Stringue => match parse_float(&value.as_string()) {
+            Some(n) => Ok(DynValue::from(n)),
+            None => Err(Traceback::from_exception(
+                TracebackKind::ValueError,
+                &format!("could not convert string to float: '{}'", value.as_string()),
+            )),
+        },
+        _ => Err(Traceback::from_exception(
+            TracebackKind::TypeError,
+            &format!(
+                "float() argument must be a string or a number, not '{}'",
+                value.tipe
+            ),
+        )),
+    }
+}
+
+// what iterating over the value gives, the keys for a dict,
+// everything an iterator has left
+pub fn elements(env: &Env, value: &DynValue) -> Result<Vec<DynValue>, Traceback> {
+    match value.tipe {
+        TypeKind::Iterator => value.as_iterator().unwrap().collect(env),
+        TypeKind::Range => {
+            let range = value.as_range().unwrap();
+            check_size(env, list_size(range.len()), "list too large")?;
+            range.iter().collect(env)
+        }
+        TypeKind::List | TypeKind::Tuple => Ok(value.as_sequence().unwrap()),
+        TypeKind::Stringue => Ok(value
+            .as_string()
+            .chars()
+            .map(|c| DynValue::from(c.to_string()))
+            .collect()),
+        TypeKind::Dict => Ok(value
+            .as_dict()
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect()),
+        _ => Err(not_iterable(value)),
+    }
+}
+
+// the key and value pairs dict() is given as two-element lists or tuples
+fn pairs(env: &Env, value: &DynValue) -> Result<Vec<(DynValue, DynValue)>, Traceback> {
+    elements(env, value)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, element)| match element.as_sequence().as_deref() {
+            Some([key, value]) => Ok((key.clone(), value.clone())),
+            Some(pair) => Err(Traceback::from_exception(
+                TracebackKind::ValueError,
+                &format!(
+                    "dictionary update sequence element #{} has length {}; 2 is required",
+                    i,
+                    pair.len()
+                ),
+            )),
+            None => Err(Traceback::from_exception(
+                TracebackKind::TypeError,
+                &format!(
+                    "cannot convert dictionary update sequence element #{} to a sequence",
+                    i
+                ),
+            )),
+        })
+        .collect()
+}
+
+fn not_iterable(value: &DynValue) -> Traceback {
+    Traceback::from_exception(
+        TracebackKind::TypeError,
+        &format!("'{}' object is not iterable", value.tipe),
+    )
+}
+
+// instances have their class as type, everything else a builtin one
+pub fn type_of(value: &DynValue) -> DynValue {
+    match value.as_instance() {
+        Some(instance) => DynValue::from(instance.borrow().class.clone()),
+        None => DynValue::from(BuiltinType::of(value).unwrap()),
+    }
+}
+
+pub fn isinstance(value: &DynValue, classinfo: &DynValue) -> Result<bool, Traceback> {
+    if let Some(tipe) = classinfo.as_builtin_type() {
+        Ok(tipe.contains(value))
+    } else if let Some(class) = classinfo.as_class() {
+        Ok(value
+            .as_instance()
+            .is_some_and(|instance| instance.borrow().class.inherits_from(&class)))
+    } else {
+        Err(Traceback::from_exception(
+            TracebackKind::TypeError,
+            "isinstance() arg 2 must be a type or a class",
+        ))
+    }
+}
+
+impl Callable for BuiltinType {
+    fn call(&self, env: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+        match args.as_slice() {
+            // only int() takes two
+            [value, base] => to_int_with_base(value, base),
+            [value] => self.convert(env, value.clone()),
+            _ => self.default_value(),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn accepts(&self, nb_args: usize) -> bool {
+        match self {
+            Self::Type => nb_args == 1,
+            Self::Int => nb_args <= 2,
+            _ => nb_args <= 1,
+        }
+    }
+
+    fn arity_error(&self, nb_args: usize) -> String {
+        match self {
+            Self::Type => format!("type() takes 1 argument ({} given)", nb_args),
+            Self::Int => format!("int() takes at most 2 arguments ({} given)", nb_args),
+            _ => format!(
+                "{}() takes at most 1 argument ({} given)",
+                self.name(),
+                nb_args
+            ),
+        }
+    }
+}
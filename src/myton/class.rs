@@ -1,23 +1,34 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use super::environment::Env;
 use super::functions::{Callable, Function};
+use super::stats::count_instance;
 use super::traceback::Traceback;
 use super::types::DynValue;
 
+// every class statement execution makes a new class
+static NEXT_CLASS_ID: AtomicUsize = AtomicUsize::new(0);
+
 #[derive(Clone)]
 pub struct Class {
+    // identity, shared by the clones of the same class
+    pub id: usize,
     pub name: String,
     pub methods: HashMap<String, Function>,
+    // the values assigned in its body, shared by its instances
+    pub attributes: HashMap<String, DynValue>,
     pub superclass: Option<Box<Class>>,
 }
 
-#[derive(Clone)]
+// only ever reached through an Rc<RefCell<Instance>>, which every
+// reference to the instance shares
 pub struct Instance {
     pub class: Class,
-    pub fields: Rc<RefCell<HashMap<String, DynValue>>>,
+    pub fields: HashMap<String, DynValue>,
 }
 
 impl Class {
@@ -27,12 +38,42 @@ impl Class {
         superclass: Option<Class>,
     ) -> Self {
         Self {
+            id: NEXT_CLASS_ID.fetch_add(1, Ordering::Relaxed),
             name,
             methods,
+            attributes: HashMap::new(),
             superclass: superclass.map(|c| Box::new(c)),
         }
     }
 
+    // whether this is the other class or one of its subclasses
+    pub fn inherits_from(&self, other: &Class) -> bool {
+        self == other
+            || self
+                .superclass
+                .as_ref()
+                .is_some_and(|superclass| superclass.inherits_from(other))
+    }
+
+    // the names of its own and inherited methods and attributes
+    pub fn member_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.methods.keys().cloned().collect();
+        names.extend(self.attributes.keys().cloned());
+        if let Some(superclass) = &self.superclass {
+            names.extend(superclass.member_names());
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    pub fn find_attribute(&self, name: &str) -> Option<DynValue> {
+        match self.attributes.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.superclass.as_ref()?.find_attribute(name),
+        }
+    }
+
     pub fn find_method(&self, name: &str) -> Option<&Function> {
         if let Some(method) = self.methods.get(name) {
             Some(method)
@@ -44,33 +85,56 @@ impl Class {
     }
 }
 
+impl PartialEq for Class {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Class {}
+
+impl Hash for Class {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
 impl Instance {
     pub fn new(class: Class) -> Self {
         Self {
             class,
-            fields: Rc::new(RefCell::new(HashMap::new())),
+            fields: HashMap::new(),
         }
     }
 
-    pub fn get(&self, name: &str) -> Option<DynValue> {
-        if let Some(val) = self.fields.borrow().get(name) {
-            Some(val.clone())
-        } else if let Some(method) = self.class.find_method(name) {
-            let refcell = Rc::new(RefCell::new(self.clone()));
-            Some(DynValue::from(method.bind(refcell)))
-        } else {
-            None
-        }
+    // a copy of the fields, sorted by name
+    pub fn fields_dict(&self) -> DynValue {
+        let mut fields: Vec<(String, DynValue)> = self
+            .fields
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+        DynValue::from_dict(
+            fields
+                .into_iter()
+                .map(|(name, value)| (DynValue::from(name), value))
+                .collect(),
+        )
     }
 
     pub fn set(&mut self, name: String, value: DynValue) {
-        self.fields.borrow_mut().insert(name, value);
+        self.fields.insert(name, value);
     }
 }
 
-pub fn get_from_refcell(instance: Rc<RefCell<Instance>>, name: &str) -> Option<DynValue> {
-    if let Some(val) = instance.borrow().fields.borrow().get(name) {
+// a field, or else an attribute or a method of the class, bound
+// to the instance itself so that what it sets is kept
+pub fn get_from_instance(instance: &Rc<RefCell<Instance>>, name: &str) -> Option<DynValue> {
+    if let Some(val) = instance.borrow().fields.get(name) {
         Some(val.clone())
+    } else if let Some(value) = instance.borrow().class.find_attribute(name) {
+        Some(value)
     } else if let Some(method) = instance.borrow().class.find_method(name) {
         Some(DynValue::from(method.bind(instance.clone())))
     } else {
@@ -81,6 +145,7 @@ pub fn get_from_refcell(instance: Rc<RefCell<Instance>>, name: &str) -> Option<D
 impl Callable for Class {
     fn call(&self, env: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
         let refcell = Rc::new(RefCell::new(Instance::new(self.clone())));
+        count_instance();
 
         if let Some(initializer) = self.find_method("__init__") {
             initializer.bind(refcell.clone()).call(env, args)?;
@@ -96,4 +161,14 @@ impl Callable for Class {
             0
         }
     }
+
+    fn arity_error(&self, nb_args: usize) -> String {
+        match self.find_method("__init__") {
+            Some(initializer) => initializer.arity_error(nb_args),
+            None => format!(
+                "{}() takes no arguments ({} given) — class defines no __init__",
+                self.name, nb_args
+            ),
+        }
+    }
 }
@@ -0,0 +1,175 @@
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+
+use super::environment::{call_stack, Env};
+use super::lexer::Lexer;
+use super::native_functions::is_dunder;
+use super::parser::Parser;
+use super::statement::ExpressionStatement;
+use super::traceback::{Traceback, TracebackKind};
+use super::types::{DynValue, TypeKind};
+use super::{MyRead, MyWrite};
+
+const PROMPT: &str = "(mdb) ";
+
+// what breakpoint() does: lets the user look around the scope it was
+// called from until they continue, or just shows where the program is
+// when nobody can answer
+pub fn breakpoint(
+    env: &Env,
+    input: &RefCell<Box<dyn MyRead>>,
+    output: &Rc<RefCell<Box<dyn MyWrite>>>,
+) -> Result<DynValue, Traceback> {
+    let debugger = Debugger { env, output };
+    let stack = call_stack(env);
+    debugger.write(&format!("> breakpoint in {}\n", stack.last().unwrap()));
+
+    if !input.borrow().is_interactive() {
+        debugger.write(&debugger.locals());
+        return Ok(DynValue::none());
+    }
+
+    loop {
+        debugger.write(PROMPT);
+        let line = input.borrow_mut().next_line();
+        let line = match line {
+            Ok(Some(line)) => line,
+            Ok(None) => {
+                debugger.write("\n");
+                break;
+            }
+            Err(err) => return Err(Traceback::from(format!("breakpoint: {}", err))),
+        };
+
+        match line.trim() {
+            "" => {}
+            "c" | "continue" => break,
+            "w" | "where" => debugger.write(&where_output(&stack)),
+            "locals" => debugger.write(&debugger.locals()),
+            source => match debugger.evaluate(source) {
+                Ok(()) => {}
+                Err(
+                    traceback @ Traceback {
+                        tipe: TracebackKind::Exit(_),
+                        ..
+                    },
+                ) => return Err(traceback),
+                Err(traceback) => debugger.write(&format!(
+                    "*** {}: {}\n",
+                    traceback.tipe,
+                    traceback.message()
+                )),
+            },
+        }
+    }
+
+    Ok(DynValue::none())
+}
+
+struct Debugger<'a> {
+    env: &'a Env,
+    output: &'a Rc<RefCell<Box<dyn MyWrite>>>,
+}
+
+impl Debugger<'_> {
+    fn write(&self, text: &str) {
+        let mut output = self.output.borrow_mut();
+        write!(output, "{}", text).ok();
+        output.flush().ok();
+    }
+
+    // the bindings of the paused scope, builtins aside
+    fn locals(&self) -> String {
+        self.env
+            .borrow()
+            .variables()
+            .iter()
+            .filter(|(name, value)| {
+                !is_dunder(name)
+                    && !matches!(
+                        value.tipe,
+                        TypeKind::NativeFunction | TypeKind::Type | TypeKind::Module
+                    )
+            })
+            .map(|(name, value)| format!("  {} = {}\n", name, value.repr()))
+            .collect()
+    }
+
+    // runs the line in the paused scope, showing the value of an expression.
+    // It is not resolved: its variables, with ids no distance is known for,
+    // are looked up by name, which works wherever the program stopped
+    fn evaluate(&self, source: &str) -> Result<(), Traceback> {
+        let tokens = Lexer::new(source.to_string()).tokenize()?;
+        let program = Parser::new(tokens).fresh_uuids().parse()?;
+
+        program.iter().try_for_each(|statement| {
+            match statement.as_any().downcast_ref::<ExpressionStatement>() {
+                Some(expression) => {
+                    let value = expression.expression.eval(self.env)?;
+                    if value.tipe != TypeKind::Nil {
+                        self.write(&format!("{}\n", value.repr()));
+                    }
+                    Ok(())
+                }
+                None => statement.execute(self.env),
+            }
+        })
+    }
+}
+
+// the calls leading to the breakpoint, the innermost one marked
+fn where_output(stack: &[String]) -> String {
+    stack
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let marker = if i + 1 == stack.len() { ">" } else { " " };
+            format!("{} {}\n", marker, name)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Interpreter;
+    use super::*;
+
+    // like stdin redirected from a file
+    struct Piped;
+
+    impl MyRead for Piped {
+        fn next_line(&mut self) -> std::io::Result<Option<String>> {
+            panic!("the debugger should not read from a pipe")
+        }
+
+        fn is_interactive(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_breakpoint_without_terminal_shows_frame() {
+        let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+        let mut interpreter = Interpreter::new_with_output(output.clone());
+        interpreter.set_input(Box::new(Piped));
+
+        interpreter
+            .run("def f(a):\n  b = \"two\"\n  breakpoint()\n  return a\nprint f(1)".to_string())
+            .unwrap();
+        assert_eq!(
+            output.borrow().get_string().unwrap(),
+            "> breakpoint in f\n  a = 1\n  b = 'two'\n1\n"
+        );
+    }
+
+    #[test]
+    fn test_where_output() {
+        let stack = vec![
+            "<module>".to_string(),
+            "outer".to_string(),
+            "inner".to_string(),
+        ];
+        assert_eq!(where_output(&stack), "  <module>\n  outer\n> inner\n");
+    }
+}
@@ -0,0 +1,103 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::environment::Env;
+use super::lexer::Lexer;
+use super::limits::enter_call;
+use super::parser::Parser;
+use super::resolver::Resolver;
+use super::sources::SourceMap;
+use super::statement::STMT;
+use super::stats::count_statement;
+use super::traceback::{Traceback, TracebackKind};
+use super::types::{DynValue, TypeKind};
+
+// what errors in the code show it as, like in python
+const CHUNK_NAME: &str = "string";
+
+// what exec() and eval() need from the interpreter to run code
+// in the scope they are called from
+#[derive(Clone)]
+pub struct Dynamic {
+    pub sources: Rc<RefCell<SourceMap>>,
+}
+
+impl Dynamic {
+    pub fn exec(&self, env: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+        let source = source_argument("exec", &args[0])?;
+        self.in_chunk(env, source, |source, chunk| {
+            for statement in self.parse(env, source, chunk)? {
+                count_statement();
+                statement.execute(env)?;
+            }
+            Ok(DynValue::none())
+        })
+    }
+
+    pub fn eval(&self, env: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+        let source = source_argument("eval", &args[0])?;
+        self.in_chunk(env, source, |source, chunk| {
+            let tokens = Lexer::new(source).chunk(chunk).tokenize()?;
+            let expression = Parser::new(tokens).fresh_uuids().parse_expression()?;
+
+            resolve(env, |resolver| expression.resolve(resolver))?;
+            expression.eval(env)
+        })
+    }
+
+    // runs with the source registered as a chunk of its own, which
+    // the errors raised directly in it are then located in
+    fn in_chunk(
+        &self,
+        env: &Env,
+        source: String,
+        run: impl FnOnce(String, usize) -> Result<DynValue, Traceback>,
+    ) -> Result<DynValue, Traceback> {
+        // exec'd code may exec itself again
+        let _call = enter_call(env)?;
+        let chunk = self
+            .sources
+            .borrow_mut()
+            .add(source.clone(), Some(CHUNK_NAME.to_string()));
+        run(source, chunk).map_err(|mut traceback| {
+            traceback.chunk.get_or_insert(chunk);
+            traceback
+        })
+    }
+
+    fn parse(&self, env: &Env, source: String, chunk: usize) -> Result<Vec<STMT>, Traceback> {
+        let tokens = Lexer::new(source).chunk(chunk).tokenize()?;
+        let program = Parser::new(tokens).fresh_uuids().parse()?;
+
+        resolve(env, |resolver| {
+            program
+                .iter()
+                .try_for_each(|statement| statement.resolve(resolver))
+        })?;
+        Ok(program)
+    }
+}
+
+// the code has ids of its own, so its distances can join the program's
+fn resolve(
+    env: &Env,
+    resolve: impl FnOnce(&mut Resolver) -> Result<(), Traceback>,
+) -> Result<(), Traceback> {
+    let mut resolver = Resolver::new();
+    resolve(&mut resolver)?;
+    env.borrow().add_resolved_locals(resolver.locals);
+    Ok(())
+}
+
+fn source_argument(function: &str, value: &DynValue) -> Result<String, Traceback> {
+    match value.tipe {
+        TypeKind::Stringue => Ok(value.as_string()),
+        _ => Err(Traceback::from_exception(
+            TracebackKind::TypeError,
+            &format!(
+                "{}() arg 1 must be a string, not '{}'",
+                function, value.tipe
+            ),
+        )),
+    }
+}
@@ -1,19 +1,38 @@
 use std::collections::HashMap;
 
 use super::expression::{Expression, Variable};
+use super::limits::{DEFAULT_MAX_RECURSION, DEFAULT_MAX_SIZE};
 use super::resolver::UUID;
+use super::statement::FunctionStatement;
 use super::types::DynValue;
-use std::cell::RefCell;
-use std::rc::Rc;
+use super::MyWrite;
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
 
 pub type Env = Rc<RefCell<Environment>>;
 
 pub struct Environment {
     values: HashMap<String, DynValue>,
     pub enclosing: Option<Env>,
-    resolved_locals: Option<HashMap<UUID, usize>>,
+    // shared by all the environments, it only ever grows, see add_resolved_locals
+    resolved_locals: Rc<RefCell<HashMap<UUID, usize>>>,
     globals: Vec<String>,
     non_locals: Vec<String>,
+    // set on the scope of a function call
+    pub frame: Option<Frame>,
+    // shared by all the environments, see limits
+    max_size: Rc<Cell<usize>>,
+    max_recursion: Rc<Cell<usize>>,
+    call_depth: Rc<Cell<usize>>,
+    // where print writes, shared by all the environments
+    output: Rc<RefCell<Box<dyn MyWrite>>>,
+}
+
+// the function a scope was created for and the scope
+// it was called from, which outlives the call
+pub struct Frame {
+    pub function: FunctionStatement,
+    pub caller: Weak<RefCell<Environment>>,
 }
 
 impl Environment {
@@ -21,9 +40,14 @@ impl Environment {
         Environment {
             values: HashMap::new(),
             enclosing: None,
-            resolved_locals: None,
+            resolved_locals: Rc::new(RefCell::new(HashMap::new())),
             globals: Vec::new(),
             non_locals: Vec::new(),
+            frame: None,
+            max_size: Rc::new(Cell::new(DEFAULT_MAX_SIZE)),
+            max_recursion: Rc::new(Cell::new(DEFAULT_MAX_RECURSION)),
+            call_depth: Rc::new(Cell::new(0)),
+            output: Rc::new(RefCell::new(Box::new(std::io::stdout()))),
         }
     }
 
@@ -34,31 +58,82 @@ impl Environment {
             resolved_locals: enclosing.borrow().resolved_locals.clone(),
             globals: enclosing.borrow().globals.clone(),
             non_locals: enclosing.borrow().non_locals.clone(),
+            frame: None,
+            max_size: enclosing.borrow().max_size.clone(),
+            max_recursion: enclosing.borrow().max_recursion.clone(),
+            call_depth: enclosing.borrow().call_depth.clone(),
+            output: enclosing.borrow().output.clone(),
         }
     }
 
     pub fn get(&self, name: String) -> Option<DynValue> {
-        if let Some(value) = self.values.get(&name) {
-            Some(value.clone())
-        } else if let Some(enclosing) = &self.enclosing {
-            enclosing.borrow_mut().get(name)
-        } else {
-            None
+        self.lookup(&name, 0).map(|(value, _)| value)
+    }
+
+    // looks for the name from the given number of scopes up,
+    // returns its value and the distance it was found at
+    fn lookup(&self, name: &str, from: usize) -> Option<(DynValue, usize)> {
+        if from == 0 {
+            if let Some(value) = self.values.get(name) {
+                return Some((value.clone(), 0));
+            }
         }
+        let mut distance = 1;
+        let mut env = self.enclosing.clone()?;
+        loop {
+            let enclosing = {
+                let borrowed = env.borrow();
+                if distance >= from {
+                    if let Some(value) = borrowed.values.get(name) {
+                        return Some((value.clone(), distance));
+                    }
+                }
+                borrowed.enclosing.clone()?
+            };
+            env = enclosing;
+            distance += 1;
+        }
+    }
+
+    // the bindings of this scope only, sorted by name
+    pub fn variables(&self) -> Vec<(String, DynValue)> {
+        let mut variables = self
+            .values
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect::<Vec<_>>();
+        variables.sort_by(|a, b| a.0.cmp(&b.0));
+        variables
     }
 
-    // tries to get the value from the resolved
-    // locals, if it fails, it tries to get it
-    // with the name, with get
+    // starts from the distance found by the resolver, or else the one
+    // the variable was last found at, before looking everywhere
     pub fn get_from_variable(&self, variable: &Variable) -> Option<DynValue> {
-        if let Some(locals) = &self.resolved_locals {
-            if let Some(distance) = locals.get(&variable.uuid()) {
-                if let Some(enclosing) = self.ancestor(*distance) {
-                    return enclosing.borrow_mut().get(variable.name.value.to_string());
-                }
+        let name = &variable.name.value;
+        let resolved = self.resolved_locals.borrow().get(&variable.uuid()).copied();
+        if let Some(distance) = resolved {
+            if let Some((value, _)) = self.lookup(name, distance) {
+                return Some(value);
             }
+        } else if let Some(distance) = variable.cached_distance.get() {
+            if let Some((value, _)) = self.lookup(name, distance) {
+                return Some(value);
+            }
+        }
+        let (value, distance) = self.lookup(name, 0)?;
+        if resolved.is_none() {
+            variable.cached_distance.set(Some(distance));
         }
-        self.get(variable.name.value.to_string())
+        Some(value)
+    }
+
+    // for the names the resolver always sees, like `this`
+    pub fn get_resolved(&self, uuid: UUID, name: &str) -> Option<DynValue> {
+        let distance = self.resolved_locals.borrow().get(&uuid).copied();
+        distance
+            .and_then(|distance| self.lookup(name, distance))
+            .or_else(|| self.lookup(name, 0))
+            .map(|(value, _)| value)
     }
 
     pub fn set(&mut self, name: String, value: DynValue) {
@@ -81,22 +156,6 @@ impl Environment {
         }
     }
 
-    pub fn get_env_var(&self, var: EnvVariable) -> DynValue {
-        let name = var.get_name();
-
-        self.get(name.to_string()).unwrap_or_else(|| {
-            panic!(
-                "Undefined environment variable variable '{}'",
-                name.to_string()
-            );
-        })
-    }
-
-    pub fn set_env_var(&mut self, var: EnvVariable, value: DynValue) {
-        let name = var.get_name();
-        self.set_global_variable(name, value);
-    }
-
     pub fn ancestor(&self, distance: usize) -> Option<Env> {
         if distance == 0 {
             None
@@ -113,8 +172,51 @@ impl Environment {
         }
     }
 
-    pub fn set_resolved_locals(&mut self, resolved_locals: HashMap<UUID, usize>) {
-        self.resolved_locals = Some(resolved_locals);
+    // the distances of a newly resolved piece of code, seen by every
+    // environment at once. Each parse run has ids of its own (see
+    // Parser::fresh_uuids), so an id is only ever given one distance:
+    // the entries of code defined earlier stay right for its closures
+    pub fn add_resolved_locals(&self, resolved_locals: HashMap<UUID, usize>) {
+        let mut locals = self.resolved_locals.borrow_mut();
+        for (uuid, distance) in resolved_locals {
+            let previous = locals.insert(uuid, distance);
+            debug_assert!(
+                previous.is_none() || previous == Some(distance),
+                "expression {} resolved to two distances",
+                uuid
+            );
+        }
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size.get()
+    }
+
+    pub fn set_max_size(&self, bytes: usize) {
+        self.max_size.set(bytes);
+    }
+
+    pub fn max_recursion(&self) -> usize {
+        self.max_recursion.get()
+    }
+
+    pub fn set_max_recursion(&self, depth: usize) {
+        self.max_recursion.set(depth);
+    }
+
+    // the calls being run, see limits::enter_call
+    pub fn call_depth(&self) -> Rc<Cell<usize>> {
+        self.call_depth.clone()
+    }
+
+    pub fn output(&self) -> Rc<RefCell<Box<dyn MyWrite>>> {
+        self.output.clone()
+    }
+
+    // only seen by the environments enclosed from then on,
+    // the interpreter sets it before running anything
+    pub fn set_output(&mut self, output: Rc<RefCell<Box<dyn MyWrite>>>) {
+        self.output = output;
     }
 
     pub fn set_global(&mut self, name: String) {
@@ -134,16 +236,29 @@ pub fn make_env_enclosed(enclosing: Env) -> Env {
     Rc::new(RefCell::new(Environment::new_enclosed(enclosing)))
 }
 
-pub enum EnvVariable {
-    NewLines,
-}
-
-impl EnvVariable {
-    fn get_name(&self) -> String {
-        match self {
-            EnvVariable::NewLines => String::from(".new_lines"),
+// the names of the functions being run, from the outermost
+// (the module) to the one the given scope belongs to
+pub fn call_stack(env: &Env) -> Vec<String> {
+    let mut stack = Vec::new();
+    let mut env = env.clone();
+    loop {
+        let next = {
+            let borrowed = env.borrow();
+            if let Some(frame) = &borrowed.frame {
+                stack.push(frame.function.inner.borrow().name.value.clone());
+                frame.caller.upgrade()
+            } else {
+                borrowed.enclosing.clone()
+            }
+        };
+        match next {
+            Some(next) => env = next,
+            None => break,
         }
     }
+    stack.push("<module>".to_string());
+    stack.reverse();
+    stack
 }
 
 #[cfg(test)]
@@ -168,10 +283,7 @@ mod tests {
     #[test]
     fn test_builtin_function() {
         let value = DynValue::new_with_name(
-            Box::new(NativeFunction {
-                nb_args: 0,
-                func: native_clock,
-            }),
+            Box::new(NativeFunction::new(native_clock, 0)),
             TypeKind::NativeFunction,
             "clock".to_string(),
         );
@@ -249,39 +361,6 @@ mod tests {
         );
     }
 
-    #[test]
-    fn test_env_var() {
-        let env = make_env();
-
-        env.borrow_mut()
-            .set_env_var(EnvVariable::NewLines, DynValue::from(1.0));
-
-        assert_eq!(
-            env.borrow_mut()
-                .get_env_var(EnvVariable::NewLines)
-                .as_number(),
-            1.0
-        );
-
-        let enclosed_env = make_env_enclosed(env.clone());
-        let mut borrowed_enclosed_env = enclosed_env.borrow_mut();
-
-        assert!(
-            borrowed_enclosed_env
-                .get_env_var(EnvVariable::NewLines)
-                .as_number()
-                == 1.0
-        );
-        borrowed_enclosed_env.set_env_var(EnvVariable::NewLines, DynValue::from(2.0));
-
-        assert!(
-            env.borrow_mut()
-                .get_env_var(EnvVariable::NewLines)
-                .as_number()
-                == 2.0
-        );
-    }
-
     #[test]
     fn test_ancestor() {
         let env = make_env();
@@ -385,7 +464,7 @@ mod tests {
 
         let resolved_locals = HashMap::from_iter(vec![(0, 1)].into_iter());
 
-        env.borrow_mut().set_resolved_locals(resolved_locals);
+        env.borrow().add_resolved_locals(resolved_locals);
 
         let local = make_env_enclosed(env.clone());
 
@@ -398,4 +477,72 @@ mod tests {
             2.0
         );
     }
+
+    #[test]
+    fn test_get_from_variable_caches_distance() {
+        let global = make_env();
+        let local = make_env_enclosed(make_env_enclosed(global.clone()));
+        let token = Token {
+            kind: TokenKind::Identifier,
+            value: "a".to_string(),
+            ..Default::default()
+        };
+        let var = Variable::new(token, 0);
+
+        global
+            .borrow_mut()
+            .set("a".to_string(), DynValue::from(1.0));
+
+        assert_eq!(var.cached_distance.get(), None);
+        assert_eq!(
+            local.borrow().get_from_variable(&var).unwrap().as_number(),
+            1.0
+        );
+        assert_eq!(var.cached_distance.get(), Some(2));
+
+        // a stale distance falls back to looking everywhere
+        let shallow = make_env_enclosed(global.clone());
+        assert_eq!(
+            shallow
+                .borrow()
+                .get_from_variable(&var)
+                .unwrap()
+                .as_number(),
+            1.0
+        );
+        assert_eq!(var.cached_distance.get(), Some(1));
+    }
+
+    #[test]
+    fn test_resolved_locals_are_shared() {
+        let global = make_env();
+        // made before the distances are known, like a closure's scope
+        let local = make_env_enclosed(global.clone());
+        let token = Token {
+            kind: TokenKind::Identifier,
+            value: "a".to_string(),
+            ..Default::default()
+        };
+        let var = Variable::new(token, 7);
+
+        global
+            .borrow_mut()
+            .set("a".to_string(), DynValue::from(1.0));
+        local.borrow_mut().set("a".to_string(), DynValue::from(2.0));
+        global.borrow().add_resolved_locals(HashMap::from([(7, 1)]));
+
+        assert_eq!(
+            local.borrow().get_from_variable(&var).unwrap().as_number(),
+            1.0
+        );
+
+        // later code has ids of its own and leaves the entry alone
+        local
+            .borrow()
+            .add_resolved_locals(HashMap::from([(8, 0), (7, 1)]));
+        assert_eq!(
+            local.borrow().get_from_variable(&var).unwrap().as_number(),
+            1.0
+        );
+    }
 }
@@ -1,61 +1,161 @@
 use super::traceback;
+use std::io::stderr;
 
-static mut HAD_ERROR: bool = false;
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorChoice {
+    Always,
+    Never,
+    Auto,
+}
+
+impl ColorChoice {
+    pub fn from_flag(value: &str) -> Option<ColorChoice> {
+        match value {
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            "auto" => Some(ColorChoice::Auto),
+            _ => None,
+        }
+    }
+}
 
-pub fn had_error() -> bool {
-    unsafe { HAD_ERROR }
+// how reports are rendered: colored for terminals, plain text
+// for everything else (pipes, logs, golden files)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Style {
+    pub colored: bool,
 }
 
-fn set_had_error() {
-    unsafe { HAD_ERROR = true }
+impl Style {
+    pub const PLAIN: Style = Style { colored: false };
+
+    // an explicit choice wins, otherwise NO_COLOR (when set
+    // and not empty) disables colors, otherwise it's up to the tty
+    pub fn new(choice: ColorChoice, no_color: Option<&str>, is_tty: bool) -> Style {
+        let colored = match choice {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => no_color.is_none_or(str::is_empty) && is_tty,
+        };
+        Style { colored }
+    }
+
+    pub fn for_stderr(choice: ColorChoice) -> Style {
+        let no_color = std::env::var("NO_COLOR").ok();
+        Style::new(choice, no_color.as_deref(), termion::is_tty(&stderr()))
+    }
+
+    pub fn red(&self, s: &str) -> String {
+        self.paint(s, "\x1b[31m")
+    }
+
+    pub fn yellow(&self, s: &str) -> String {
+        self.paint(s, "\x1b[33m")
+    }
+
+    fn paint(&self, s: &str, color: &str) -> String {
+        if self.colored {
+            format!("{}{}\x1b[0m", color, s)
+        } else {
+            s.to_string()
+        }
+    }
 }
 
-pub fn report_trace(trace: traceback::Traceback) -> String {
+pub fn report_trace(mut trace: traceback::Traceback, style: Style) -> String {
     let mut s = String::new();
-    let spaces = " ".repeat(count_digits(trace.pos.1 + 1));
 
-    s.push_str(&format!(
-        "error[{}]: {}\n",
+    // the errors it was raised while handling come first
+    if let Some(cause) = trace.cause.take() {
+        s.push_str(&report_trace(*cause, style));
+        s.push_str("\nDuring handling of the above exception, another exception occurred:\n\n");
+    }
+
+    s.push_str(&style.red(&format!(
+        "error[{}]: {}",
         trace.tipe,
-        trace.message.unwrap_or("no message".to_string())
-    ));
+        trace.message.clone().unwrap_or("no message".to_string())
+    )));
+    s.push('\n');
     s.push_str("----- Traceback -----\n");
-    s.push_str(&format!("{} ┌─ ", spaces.clone()));
+    s.push_str(&location(trace, style));
+
+    s
+}
+
+// warnings don't stop the program, they only point at the code
+pub fn report_warning(trace: traceback::Traceback, style: Style) -> String {
+    let mut s = String::new();
+
+    s.push_str(&style.yellow(&format!(
+        "warning: {}",
+        trace.message.clone().unwrap_or("no message".to_string())
+    )));
+    s.push('\n');
+    s.push_str(&location(trace, style));
+
+    s
+}
+
+// lines shown on each side of the offending one
+const CONTEXT_LINES: usize = 2;
+
+fn location(trace: traceback::Traceback, style: Style) -> String {
+    let mut s = String::new();
+    let (mut column, mut line_nb) = trace.pos;
+
+    let lines: Vec<&str> = match &trace.code {
+        Some(code) => code.lines().map(str::trim_end).collect(),
+        None => Vec::new(),
+    };
+    // past the end (the end of the input or a stale position),
+    // shown at the end of the last line
+    if !lines.is_empty() && line_nb >= lines.len() {
+        line_nb = lines.len() - 1;
+        column = lines[line_nb].len();
+    }
+    let first = line_nb.saturating_sub(CONTEXT_LINES);
+    let last = (line_nb + CONTEXT_LINES).min(lines.len().saturating_sub(1));
+
+    let width = count_digits(last.max(line_nb) + 1);
+    let gutter = " ".repeat(width);
+
+    s.push_str(&format!("{} ┌─ ", gutter));
     if let Some(file) = trace.filename {
         s.push_str(&format!("<{}>:", file));
     } else {
         s.push_str("<unknown>:");
     }
     s.push_str(&format!("{}:{}\n", trace.pos.1 + 1, trace.pos.0));
-    if let Some(code) = &trace.code {
-        for i in 0..2 {
-            let line_nb: i32 = (trace.pos.1 + i) as i32 - 1;
-            let line = if line_nb >= 0 {
-                code.lines().nth(line_nb as usize).unwrap_or("").trim_end()
-            } else {
-                ""
-            };
-            let prefix = if i == 1 {
-                (trace.pos.1 + 1).to_string()
-            } else {
-                spaces.clone()
-            };
-            s.push_str(&format!("{} | {}\n", prefix, line));
+
+    if lines.is_empty() {
+        return s;
+    }
+    for (i, line) in lines.iter().enumerate().take(last + 1).skip(first) {
+        s.push_str(&format!("{:>width$} | {}\n", i + 1, line, width = width));
+        if i == line_nb {
+            s.push_str(&format!(
+                "{} | {}{}\n",
+                gutter,
+                caret_indent(line, column),
+                style.yellow("^")
+            ));
         }
-        s.push_str(&format!(
-            "{} | {}\n",
-            spaces.clone(),
-            " ".repeat(trace.pos.0) + "^"
-        ));
     }
 
-    set_had_error();
-
-    to_red(&s)
+    s
 }
 
-pub fn to_red(s: &str) -> String {
-    format!("\x1b[31m{}\x1b[0m", s)
+// blanks up to the column, keeping the tabs of the line
+// so that the caret lines up whatever their width
+fn caret_indent(line: &str, column: usize) -> String {
+    let mut indent: String = line
+        .char_indices()
+        .take_while(|(i, _)| *i < column)
+        .map(|(_, c)| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    indent.push_str(&" ".repeat(column.saturating_sub(line.len())));
+    indent
 }
 
 fn count_digits(n: usize) -> usize {
@@ -67,3 +167,137 @@ fn count_digits(n: usize) -> usize {
     }
     count
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::myton::traceback::Traceback;
+
+    #[test]
+    fn test_style_decision() {
+        use ColorChoice::*;
+
+        for no_color in [None, Some(""), Some("1")] {
+            for is_tty in [false, true] {
+                assert!(Style::new(Always, no_color, is_tty).colored);
+                assert!(!Style::new(Never, no_color, is_tty).colored);
+            }
+        }
+
+        assert!(Style::new(Auto, None, true).colored);
+        assert!(Style::new(Auto, Some(""), true).colored);
+        assert!(!Style::new(Auto, Some("1"), true).colored);
+        assert!(!Style::new(Auto, None, false).colored);
+        assert!(!Style::new(Auto, Some("1"), false).colored);
+
+        assert_eq!(ColorChoice::from_flag("never"), Some(Never));
+        assert_eq!(ColorChoice::from_flag("sometimes"), None);
+    }
+
+    fn traceback() -> Traceback {
+        Traceback {
+            message: Some("oops".to_string()),
+            pos: (4, 0),
+            code: Some("a = b".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_plain_rendering() {
+        assert_eq!(
+            report_trace(traceback(), Style::PLAIN),
+            "error[runtime error]: oops\n----- Traceback -----\n  ┌─ <unknown>:1:4\n1 | a = b\n  |     ^\n"
+        );
+    }
+
+    const SCRIPT: &str = "a = 1
+b = 2
+c = 3
+d = 4
+e = 5
+f = 6
+g = 7
+h = 8
+i = 9
+j = 10
+";
+
+    fn rendered_location(pos: (usize, usize), code: &str) -> String {
+        location(
+            Traceback {
+                pos,
+                code: Some(code.to_string()),
+                ..Default::default()
+            },
+            Style::PLAIN,
+        )
+    }
+
+    #[test]
+    fn test_context_lines() {
+        assert_eq!(
+            rendered_location((4, 0), SCRIPT),
+            "  ┌─ <unknown>:1:4
+1 | a = 1
+  |     ^
+2 | b = 2
+3 | c = 3
+"
+        );
+        assert_eq!(
+            rendered_location((2, 4), SCRIPT),
+            "  ┌─ <unknown>:5:2
+3 | c = 3
+4 | d = 4
+5 | e = 5
+  |   ^
+6 | f = 6
+7 | g = 7
+"
+        );
+        // the gutter fits the widest number shown
+        assert_eq!(
+            rendered_location((6, 9), SCRIPT),
+            "   ┌─ <unknown>:10:6
+ 8 | h = 8
+ 9 | i = 9
+10 | j = 10
+   |       ^
+"
+        );
+    }
+
+    #[test]
+    fn test_position_past_the_end() {
+        // at the end of the last line rather than on a missing one
+        assert_eq!(
+            rendered_location((0, 12), "a = 1\nb = (\n"),
+            "  ┌─ <unknown>:13:0
+1 | a = 1
+2 | b = (
+  |      ^
+"
+        );
+        assert_eq!(rendered_location((3, 2), ""), "  ┌─ <unknown>:3:3\n");
+    }
+
+    #[test]
+    fn test_caret_under_tabs() {
+        assert_eq!(
+            rendered_location((5, 1), "if a:\n\tb = c\n"),
+            "  ┌─ <unknown>:2:5
+1 | if a:
+2 | \tb = c
+  | \t    ^
+"
+        );
+    }
+
+    #[test]
+    fn test_colored_rendering() {
+        let report = report_trace(traceback(), Style { colored: true });
+        assert!(report.starts_with("\x1b[31merror[runtime error]: oops\x1b[0m\n"));
+        assert!(report.ends_with("  |     \x1b[33m^\x1b[0m\n"));
+    }
+}
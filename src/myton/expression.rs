@@ -1,11 +1,24 @@
 use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::rc::Rc;
 
-use super::class::get_from_refcell;
+use super::builtin_types::elements;
+use super::class::Instance;
 use super::environment::Env;
+use super::functions::{Callable, Function, Kwargs};
+use super::hashable::Dict;
+use super::limits::{check_size, list_size};
+use super::methods::{
+    attribute_error, attribute_hint, get_attribute, none_hinted, set_attribute, with_dict,
+    with_list,
+};
 use super::resolver::{Resolvable, UUID};
+use super::stats::count_call;
 use super::token::{Token, TokenKind};
-use super::traceback::Traceback;
+use super::traceback::{Traceback, TracebackKind};
 use super::types::{DynValue, TypeKind};
+use super::unparse::unparse;
 
 pub trait Evaluable {
     fn eval(&self, env: &Env) -> Result<DynValue, Traceback>;
@@ -15,12 +28,14 @@ pub trait Expression: Evaluable + Resolvable + Any {
     fn uuid(&self) -> UUID;
 
     fn as_any(&self) -> &dyn Any;
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
 }
 
 pub type EXPR = Box<dyn Expression>;
 
 pub struct Operator {
-    token: Token,
+    pub token: Token,
     kind: OperatorKind,
 }
 
@@ -34,10 +49,25 @@ pub struct List {
     uuid: UUID,
 }
 
+// `(a, b)`, or `a, b` where a statement allows it
+pub struct Tuple {
+    pub start: Token,
+    pub elements: Vec<EXPR>,
+    uuid: UUID,
+}
+
+pub struct DictLiteral {
+    pub brace: Token,
+    pub entries: Vec<(EXPR, EXPR)>,
+    uuid: UUID,
+}
+
 #[derive(Clone)]
 pub struct Variable {
     pub name: Token,
     uuid: UUID,
+    // where the name was last found, for the ones the resolver left to globals
+    pub cached_distance: Cell<Option<usize>>,
 }
 
 pub struct Binary {
@@ -63,10 +93,19 @@ pub struct Unary {
 pub struct Call {
     pub callee: EXPR,
     pub paren: Token,
-    pub arguments: Vec<EXPR>,
+    pub arguments: Vec<Argument>,
+    // after the positional ones, by name
+    pub keywords: Vec<(Token, EXPR)>,
     uuid: UUID,
 }
 
+// a positional argument, or with its `*` the elements of an
+// iterable spread in its place
+pub struct Argument {
+    pub star: Option<Token>,
+    pub value: EXPR,
+}
+
 pub struct Grouping {
     pub expression: EXPR,
     uuid: UUID,
@@ -76,15 +115,51 @@ pub struct Get {
     pub object: EXPR,
     pub name: Token,
     uuid: UUID,
+    // the method found last time and the id of its instance's class
+    method_cache: RefCell<Option<(usize, Function)>>,
 }
 
-pub struct Set {
+pub struct Subscript {
     pub object: EXPR,
+    pub bracket: Token,
+    pub index: EXPR,
+    uuid: UUID,
+}
+
+// `target = value`, itself valued like the walrus
+pub struct Assign {
+    pub target: AssignTarget,
+    pub value: EXPR,
+    uuid: UUID,
+}
+
+pub struct AssignExpr {
     pub name: Token,
     pub value: EXPR,
     uuid: UUID,
 }
 
+// what a value can be stored into: the left of `=`, the variable of a
+// for loop. Each statement binding values goes through store, so they
+// all accept and reject the same targets with the same errors
+pub enum AssignTarget {
+    Name(Token),
+    Attribute {
+        object: EXPR,
+        name: Token,
+    },
+    Index {
+        object: EXPR,
+        bracket: Token,
+        index: EXPR,
+    },
+    // `a, b = pair`, the value's elements stored into each in turn
+    Unpack {
+        start: Token,
+        targets: Vec<AssignTarget>,
+    },
+}
+
 #[derive(Clone)]
 pub struct This {
     pub keyword: Token,
@@ -107,12 +182,16 @@ pub enum OperatorKind {
     Equal,
     StrictEqual,
     NotEqual,
+    Is,
+    In,
+    NotIn,
     Not,
     Greater,
     GreaterEqual,
     Less,
     LessEqual,
     Modulo,
+    Power,
 }
 
 pub enum LogicalKind {
@@ -124,7 +203,7 @@ impl Unary {
     pub fn new(token: Token, right: EXPR, uuid: UUID) -> Unary {
         let type_ = match token.kind {
             TokenKind::Minus => OperatorKind::Negate,
-            TokenKind::Bang => OperatorKind::Not,
+            TokenKind::Bang | TokenKind::Not => OperatorKind::Not,
             _ => panic!("Invalid token type for unary operator"),
         };
 
@@ -146,11 +225,15 @@ impl Binary {
             TokenKind::EqualEqual => OperatorKind::Equal,
             TokenKind::EqualEqualEqual => OperatorKind::StrictEqual,
             TokenKind::BangEqual => OperatorKind::NotEqual,
+            TokenKind::Is => OperatorKind::Is,
+            TokenKind::In => OperatorKind::In,
+            TokenKind::NotIn => OperatorKind::NotIn,
             TokenKind::Greater => OperatorKind::Greater,
             TokenKind::GreaterEqual => OperatorKind::GreaterEqual,
             TokenKind::Less => OperatorKind::Less,
             TokenKind::LessEqual => OperatorKind::LessEqual,
             TokenKind::Percent => OperatorKind::Modulo,
+            TokenKind::StarStar => OperatorKind::Power,
             _ => panic!("Invalid token type for binary operator"),
         };
 
@@ -161,6 +244,12 @@ impl Binary {
             uuid,
         }
     }
+
+    // the first operand and the operators following it,
+    // see left_chain
+    pub fn chain(&self) -> (&EXPR, Vec<&Binary>) {
+        left_chain(self, |binary| &binary.left)
+    }
 }
 
 impl Logical {
@@ -178,6 +267,50 @@ impl Logical {
             uuid,
         }
     }
+
+    pub fn chain(&self) -> (&EXPR, Vec<&Logical>) {
+        left_chain(self, |logical| &logical.left)
+    }
+}
+
+// `a + b - c` is the operand a, then the nodes adding b and taking
+// c, in that order. The chain can be thousands of operators long, so
+// what goes through it does so in a loop rather than recursively
+fn left_chain<T: 'static>(node: &T, left: fn(&T) -> &EXPR) -> (&EXPR, Vec<&T>) {
+    let mut nodes = vec![node];
+    let mut first = left(node);
+    while let Some(inner) = first.as_any().downcast_ref::<T>() {
+        nodes.push(inner);
+        first = left(inner);
+    }
+    nodes.reverse();
+    (first, nodes)
+}
+
+// the same, a link at a time, for dropping the chain
+fn drop_left_chain<T: 'static>(left: &mut EXPR, left_of: fn(&mut T) -> &mut EXPR) {
+    let taken = || -> EXPR {
+        Box::new(List {
+            elements: Vec::new(),
+            uuid: 0,
+        })
+    };
+    let mut next = std::mem::replace(left, taken());
+    while let Ok(mut node) = next.into_any().downcast::<T>() {
+        next = std::mem::replace(left_of(&mut node), taken());
+    }
+}
+
+impl Drop for Binary {
+    fn drop(&mut self) {
+        drop_left_chain(&mut self.left, |binary: &mut Binary| &mut binary.left);
+    }
+}
+
+impl Drop for Logical {
+    fn drop(&mut self) {
+        drop_left_chain(&mut self.left, |logical: &mut Logical| &mut logical.left);
+    }
 }
 
 impl Evaluable for Literal {
@@ -203,6 +336,52 @@ impl List {
     }
 }
 
+impl Evaluable for Tuple {
+    fn eval(&self, env: &Env) -> Result<DynValue, Traceback> {
+        Ok(DynValue::from_tuple(
+            self.elements
+                .iter()
+                .map(|e| e.eval(env))
+                .collect::<Result<Vec<DynValue>, Traceback>>()?,
+        ))
+    }
+}
+
+impl Tuple {
+    pub fn new(start: Token, elements: Vec<EXPR>, uuid: UUID) -> Tuple {
+        Tuple {
+            start,
+            elements,
+            uuid,
+        }
+    }
+}
+
+impl Evaluable for DictLiteral {
+    fn eval(&self, env: &Env) -> Result<DynValue, Traceback> {
+        let pairs = self
+            .entries
+            .iter()
+            .map(|(key, value)| Ok((key.eval(env)?, value.eval(env)?)))
+            .collect::<Result<Vec<_>, Traceback>>()?;
+        let dict = Dict::new(pairs).map_err(|traceback| Traceback {
+            pos: self.brace.pos.unwrap_or_default(),
+            ..traceback
+        })?;
+        Ok(DynValue::from(dict))
+    }
+}
+
+impl DictLiteral {
+    pub fn new(brace: Token, entries: Vec<(EXPR, EXPR)>, uuid: UUID) -> DictLiteral {
+        DictLiteral {
+            brace,
+            entries,
+            uuid,
+        }
+    }
+}
+
 impl Literal {
     pub fn new(token: Token, uuid: UUID) -> Literal {
         Literal { token, uuid }
@@ -215,7 +394,7 @@ impl Evaluable for Variable {
             Some(value) => Ok(value),
             None => Err(Traceback {
                 message: Some(format!("Undefined variable '{}'", self.name.value)),
-                pos: self.name.pos.unwrap(),
+                pos: self.name.pos.unwrap_or_default(),
                 ..Default::default()
             }),
         }
@@ -224,7 +403,11 @@ impl Evaluable for Variable {
 
 impl Variable {
     pub fn new(token: Token, uuid: UUID) -> Variable {
-        Variable { name: token, uuid }
+        Variable {
+            name: token,
+            uuid,
+            cached_distance: Cell::new(None),
+        }
     }
 }
 
@@ -249,25 +432,249 @@ impl Evaluable for Unary {
                 if !right.is_number() {
                     return Err(Traceback {
                         message: Some(format!("bad operand type for unary -: '{}'", right.tipe)),
-                        pos: self.operator.token.pos.unwrap(),
+                        pos: self.operator.token.pos.unwrap_or_default(),
+                        tipe: TracebackKind::TypeError,
                         ..Default::default()
                     });
                 }
                 Ok(DynValue::from(-right.as_number()))
             }
             OperatorKind::Not => Ok(DynValue::from(!right.as_bool())),
-            _ => panic!("Invalid token type for unary operator"),
+            _ => Err(Traceback {
+                message: Some(format!(
+                    "invalid unary operator '{}'",
+                    self.operator.token.value
+                )),
+                pos: self.operator.token.pos.unwrap_or_default(),
+                ..Default::default()
+            }),
         }
     }
 }
 
 impl Binary {
+    // an instance's __eq__ decides, when its class defines one
+    fn equals(&self, left: &DynValue, right: &DynValue, env: &Env) -> Result<bool, Traceback> {
+        let custom = left
+            .as_instance()
+            .and_then(|instance| instance.borrow().class.find_method("__eq__").cloned());
+        match custom {
+            Some(method) => {
+                let instance = left.as_instance().unwrap();
+                let result = method.bind(instance).call(env, vec![right.clone()])?;
+                Ok(result.as_bool())
+            }
+            None => left.try_eq(right),
+        }
+    }
+
+    // the operators shared by the sequences, so that they agree: a list
+    // only joins a list, a tuple a tuple, and any of them repeats by an integer
+    fn concatenate(
+        &self,
+        env: &Env,
+        left: &DynValue,
+        right: &DynValue,
+    ) -> Result<DynValue, Traceback> {
+        if left.tipe != right.tipe {
+            return Err(Traceback {
+                message: Some(format!(
+                    "can only concatenate {} (not \"{}\") to {}",
+                    left.tipe, right.tipe, left.tipe
+                )),
+                pos: self.operator.token.pos.unwrap_or_default(),
+                tipe: TracebackKind::TypeError,
+                ..Default::default()
+            });
+        }
+        let (mut joined, right) = (
+            left.as_sequence().unwrap_or_default(),
+            right.as_sequence().unwrap_or_default(),
+        );
+        check_size(
+            env,
+            list_size(joined.len() + right.len()),
+            &format!("{} too large", left.tipe),
+        )
+        .map_err(|traceback| self.at_operator(traceback))?;
+        joined.extend(right);
+        Ok(same_sequence(left, joined))
+    }
+
+    fn at_operator(&self, traceback: Traceback) -> Traceback {
+        Traceback {
+            pos: self.operator.token.pos.unwrap_or_default(),
+            ..traceback
+        }
+    }
+
+    fn repeat(&self, env: &Env, sequence: &DynValue, count: f64) -> Result<DynValue, Traceback> {
+        if count.fract() != 0.0 {
+            return Err(Traceback {
+                message: Some(format!("can't multiply sequence by non-integer {}", count)),
+                pos: self.operator.token.pos.unwrap_or_default(),
+                tipe: TracebackKind::TypeError,
+                ..Default::default()
+            });
+        }
+        let times = count.max(0.0) as usize;
+        // checked before allocating, the reserving can still fail
+        // when the limit is above what the system has
+        let too_large = |size| {
+            check_size(env, size, "repeat count too large")
+                .map_err(|traceback| self.at_operator(traceback))
+        };
+        // None is always too large
+        let out_of_memory = |_| too_large(None).unwrap_err();
+        match sequence.tipe {
+            TypeKind::Stringue => {
+                let string = sequence.as_string();
+                let len = string.len().checked_mul(times);
+                too_large(len)?;
+                let mut repeated = String::new();
+                repeated
+                    .try_reserve_exact(len.unwrap_or_default())
+                    .map_err(out_of_memory)?;
+                if !string.is_empty() {
+                    (0..times).for_each(|_| repeated.push_str(&string));
+                }
+                Ok(DynValue::from(repeated))
+            }
+            _ => {
+                let elements = sequence.as_sequence().unwrap_or_default();
+                let len = elements.len().checked_mul(times);
+                too_large(len.and_then(list_size))?;
+                let len = len.unwrap_or_default();
+                let mut repeated = Vec::new();
+                repeated.try_reserve_exact(len).map_err(out_of_memory)?;
+                repeated.extend(elements.iter().cycle().take(len).cloned());
+                Ok(same_sequence(sequence, repeated))
+            }
+        }
+    }
+
+    // an element of a list or a tuple is one equal to the item, a dict
+    // holds its keys, and a string the strings it has inside
+    fn contains(
+        &self,
+        env: &Env,
+        container: &DynValue,
+        item: &DynValue,
+    ) -> Result<bool, Traceback> {
+        match container.tipe {
+            TypeKind::List | TypeKind::Tuple => {
+                for element in container.as_sequence().unwrap_or_default() {
+                    if element.is(item) || self.equals(item, &element, env)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            TypeKind::Stringue if item.tipe == TypeKind::Stringue => {
+                Ok(container.as_string().contains(&item.as_string()))
+            }
+            TypeKind::Stringue => Err(Traceback {
+                message: Some(format!(
+                    "'in <string>' requires string as left operand, not {}",
+                    item.tipe
+                )),
+                pos: self.operator.token.pos.unwrap_or_default(),
+                tipe: TracebackKind::TypeError,
+                ..Default::default()
+            }),
+            TypeKind::Dict => with_dict(container, |dict| dict.contains_key(item))
+                .map_err(|traceback| self.at_operator(traceback)),
+            TypeKind::Range => {
+                Ok(item.is_number() && container.as_range().unwrap().contains(item.as_number()))
+            }
+            _ => Err(Traceback {
+                message: Some(format!(
+                    "argument of type '{}' is not iterable",
+                    container.tipe
+                )),
+                pos: self.operator.token.pos.unwrap_or_default(),
+                tipe: TracebackKind::TypeError,
+                ..Default::default()
+            }),
+        }
+    }
+
+    // elements or dicts that can't be ordered only show once compared
+    fn order(&self, left: &DynValue, right: &DynValue) -> Result<DynValue, Traceback> {
+        let ordering = left.try_cmp(right).map_err(|error| {
+            error.into_traceback(|a, b| Traceback {
+                message: Some(format!(
+                    "'{}' not supported between instances of '{}' and '{}'",
+                    self.operator.token.value, a, b
+                )),
+                pos: self.operator.token.pos.unwrap_or_default(),
+                tipe: TracebackKind::TypeError,
+                ..Default::default()
+            })
+        })?;
+        let holds = match ordering {
+            Some(Ordering::Greater) => matches!(
+                self.operator.kind,
+                OperatorKind::Greater | OperatorKind::GreaterEqual
+            ),
+            Some(Ordering::Less) => matches!(
+                self.operator.kind,
+                OperatorKind::Less | OperatorKind::LessEqual
+            ),
+            Some(Ordering::Equal) => matches!(
+                self.operator.kind,
+                OperatorKind::GreaterEqual | OperatorKind::LessEqual
+            ),
+            None => false,
+        };
+        Ok(DynValue::from(holds))
+    }
+
+    fn unsupported_operands(&self, left: &DynValue, right: &DynValue) -> Traceback {
+        let operator = &self.operator.token.value;
+        let message = match self.operator.kind {
+            OperatorKind::Greater
+            | OperatorKind::GreaterEqual
+            | OperatorKind::Less
+            | OperatorKind::LessEqual => format!(
+                "'{}' not supported between instances of '{}' and '{}'",
+                operator, left.tipe, right.tipe
+            ),
+            _ => format!(
+                "unsupported operand type(s) for {}: '{}' and '{}'",
+                operator, left.tipe, right.tipe
+            ),
+        };
+        Traceback {
+            message: Some(message),
+            pos: self.operator.token.pos.unwrap_or_default(),
+            tipe: TracebackKind::TypeError,
+            ..Default::default()
+        }
+    }
+
     fn check_types(&self, left: DynValue, right: DynValue) -> bool {
         match self.operator.kind {
-            OperatorKind::Minus | OperatorKind::Divide | OperatorKind::Modulo => {
-                left.is_number() && right.is_number()
+            OperatorKind::Minus
+            | OperatorKind::Divide
+            | OperatorKind::Modulo
+            | OperatorKind::Power => left.is_number() && right.is_number(),
+            OperatorKind::Multiply => {
+                // a sequence is repeated whichever side the count is on
+                matches!(
+                    left.tipe,
+                    TypeKind::Number
+                        | TypeKind::Boolean
+                        | TypeKind::Stringue
+                        | TypeKind::List
+                        | TypeKind::Tuple
+                ) && right.is_number()
+                    || matches!(left.tipe, TypeKind::Number | TypeKind::Boolean)
+                        && matches!(
+                            right.tipe,
+                            TypeKind::Stringue | TypeKind::List | TypeKind::Tuple
+                        )
             }
-            OperatorKind::Multiply => (!left.is_nil()) && right.is_number(),
             OperatorKind::Greater
             | OperatorKind::GreaterEqual
             | OperatorKind::Less
@@ -277,119 +684,229 @@ impl Binary {
                         || left.tipe == TypeKind::Number && right.tipe == TypeKind::Boolean
                         || left.tipe == TypeKind::Boolean && right.tipe == TypeKind::Number)
             }
-            OperatorKind::Plus => !(left.is_nil() || right.is_nil()),
-            OperatorKind::Equal | OperatorKind::NotEqual | OperatorKind::StrictEqual => true,
-            _ => panic!("Invalid token type for binary operator"),
+            OperatorKind::Plus => {
+                matches!(left.tipe, TypeKind::List | TypeKind::Tuple)
+                    || left.is_number() && right.is_number()
+                    || left.tipe == TypeKind::Stringue && right.tipe == TypeKind::Stringue
+            }
+            OperatorKind::Equal
+            | OperatorKind::NotEqual
+            | OperatorKind::StrictEqual
+            | OperatorKind::Is
+            | OperatorKind::In
+            | OperatorKind::NotIn => true,
+            _ => false,
         }
     }
 }
 
+// the elements as a list or a tuple, whichever the sequence is
+fn same_sequence(sequence: &DynValue, elements: Vec<DynValue>) -> DynValue {
+    match sequence.tipe {
+        TypeKind::Tuple => DynValue::from_tuple(elements),
+        _ => DynValue::from(elements),
+    }
+}
+
 impl Evaluable for Binary {
     fn eval(&self, env: &Env) -> Result<DynValue, Traceback> {
-        let left = self.left.eval(env)?;
-        let right = self.right.eval(env)?;
+        let (first, chain) = self.chain();
+        let mut value = first.eval(env)?;
+        for binary in chain {
+            let right = binary.right.eval(env)?;
+            value = binary.apply(env, value, right)?;
+        }
+        Ok(value)
+    }
+}
 
+impl Binary {
+    fn apply(&self, env: &Env, left: DynValue, right: DynValue) -> Result<DynValue, Traceback> {
         if !self.check_types(left.clone(), right.clone()) {
-            return Err(Traceback {
-                message: Some(format!(
-                    "unsupported operand type(s) for {}: '{}' and '{}'",
-                    self.operator.token.value, left.tipe, right.tipe
-                )),
-                pos: self.operator.token.pos.unwrap(),
-                ..Default::default()
-            });
+            return Err(self.unsupported_operands(&left, &right));
         }
 
         match self.operator.kind {
             OperatorKind::Plus => {
-                if left.is_number() && right.is_number() {
+                if matches!(left.tipe, TypeKind::List | TypeKind::Tuple) {
+                    self.concatenate(env, &left, &right)
+                } else if left.is_number() {
                     Ok(DynValue::from(left.as_number() + right.as_number()))
                 } else {
-                    Ok(DynValue::from(left.as_string() + &right.as_string()))
+                    let (left, right) = (left.as_string(), right.as_string());
+                    check_size(env, left.len().checked_add(right.len()), "str too large")
+                        .map_err(|traceback| self.at_operator(traceback))?;
+                    Ok(DynValue::from(left + &right))
                 }
             }
             OperatorKind::Minus => Ok(DynValue::from(left.as_number() - right.as_number())),
-            OperatorKind::Multiply => match left.tipe {
-                TypeKind::Number => Ok(DynValue::from(left.as_number() * right.as_number())),
-                TypeKind::Stringue => Ok(DynValue::from(
-                    left.as_string().repeat(right.as_number() as usize),
-                )),
-                TypeKind::List => {
-                    let list = left.as_list().unwrap();
-                    let num = right.as_number() as usize;
-                    Ok(DynValue::from(
-                        list.iter()
-                            .cycle()
-                            .take(list.len() * num)
-                            .cloned()
-                            .collect::<Vec<DynValue>>(),
-                    ))
-                }
-                _ => panic!("Invalid left type for * operator"),
-            },
+            OperatorKind::Multiply => {
+                let (sequence, count) = match (&left.tipe, &right.tipe) {
+                    (TypeKind::Stringue | TypeKind::List | TypeKind::Tuple, _)
+                        if right.is_number() =>
+                    {
+                        (&left, right.as_number())
+                    }
+                    (_, TypeKind::Stringue | TypeKind::List | TypeKind::Tuple)
+                        if !right.is_number() =>
+                    {
+                        (&right, left.as_number())
+                    }
+                    _ => return Ok(DynValue::from(left.as_number() * right.as_number())),
+                };
+                self.repeat(env, sequence, count)
+            }
             OperatorKind::Divide => Ok(DynValue::from(left.as_number() / right.as_number())),
             OperatorKind::Modulo => Ok(DynValue::from(left.as_number() % right.as_number())),
-            OperatorKind::Equal => Ok(DynValue::from(left == right)),
-            OperatorKind::StrictEqual => {
-                Ok(DynValue::from(left.tipe == right.tipe && left == right))
-            }
-            OperatorKind::NotEqual => Ok(DynValue::from(left != right)),
-            OperatorKind::Greater => Ok(DynValue::from(left > right)),
-            OperatorKind::GreaterEqual => Ok(DynValue::from(left >= right)),
-            OperatorKind::Less => Ok(DynValue::from(left < right)),
-            OperatorKind::LessEqual => Ok(DynValue::from(left <= right)),
-            _ => panic!("Invalid token type for binary operator"),
+            OperatorKind::Power => Ok(DynValue::from(left.as_number().powf(right.as_number()))),
+            OperatorKind::Equal => Ok(DynValue::from(self.equals(&left, &right, env)?)),
+            OperatorKind::StrictEqual => Ok(DynValue::from(
+                left.tipe == right.tipe && left.try_eq(&right)?,
+            )),
+            OperatorKind::NotEqual => Ok(DynValue::from(!self.equals(&left, &right, env)?)),
+            OperatorKind::Is => Ok(DynValue::from(left.is(&right))),
+            OperatorKind::In => Ok(DynValue::from(self.contains(env, &right, &left)?)),
+            OperatorKind::NotIn => Ok(DynValue::from(!self.contains(env, &right, &left)?)),
+            OperatorKind::Greater
+            | OperatorKind::GreaterEqual
+            | OperatorKind::Less
+            | OperatorKind::LessEqual => self.order(&left, &right),
+            _ => Err(Traceback {
+                message: Some(format!(
+                    "invalid binary operator '{}'",
+                    self.operator.token.value
+                )),
+                pos: self.operator.token.pos.unwrap_or_default(),
+                ..Default::default()
+            }),
         }
     }
 }
 
 impl Evaluable for Logical {
     fn eval(&self, env: &Env) -> Result<DynValue, Traceback> {
-        let left = self.left.eval(env)?;
-
-        match self.kind {
-            LogicalKind::Or => {
-                if left.as_bool() {
-                    return Ok(left);
-                }
-            }
-            LogicalKind::And => {
-                if !left.as_bool() {
-                    return Ok(left);
-                }
+        let (first, chain) = self.chain();
+        let mut value = first.eval(env)?;
+        for logical in chain {
+            // the right operand only when the left one doesn't decide
+            let decided = match logical.kind {
+                LogicalKind::Or => value.as_bool(),
+                LogicalKind::And => !value.as_bool(),
+            };
+            if !decided {
+                value = logical.right.eval(env)?;
             }
         }
+        Ok(value)
+    }
+}
+
+// which link of a chain failed, when the receiver is itself one:
+// the message for get_user(id).profile.name names get_user(id).profile
+fn while_evaluating(receiver: &EXPR) -> String {
+    let any = receiver.as_any();
+    if any.is::<Get>() || any.is::<Call>() || any.is::<Variable>() {
+        format!(" (while evaluating '{}')", unparse(receiver))
+    } else {
+        String::new()
+    }
+}
 
-        self.right.eval(env)
+// the elements of xs in f(*xs), the error pointing at its star
+fn splat(env: &Env, star: &Token, value: &DynValue) -> Result<Vec<DynValue>, Traceback> {
+    match elements(env, value) {
+        // an iterator's own errors are kept
+        Err(_) if value.tipe != TypeKind::Iterator => Err(Traceback {
+            message: Some(format!(
+                "argument after * must be an iterable, not '{}'",
+                value.tipe
+            )),
+            pos: star.pos.unwrap_or_default(),
+            tipe: TracebackKind::TypeError,
+            ..Default::default()
+        }),
+        result => result,
+    }
+}
+
+// the values of the arguments, the splatted ones expanded
+pub fn eval_arguments(env: &Env, arguments: &[Argument]) -> Result<Vec<DynValue>, Traceback> {
+    let mut args = Vec::new();
+    for argument in arguments {
+        let value = argument.value.eval(env)?;
+        match &argument.star {
+            Some(star) => args.extend(splat(env, star, &value)?),
+            None => args.push(value),
+        }
     }
+    Ok(args)
 }
 
 impl Evaluable for Call {
     fn eval(&self, env: &Env) -> Result<DynValue, Traceback> {
-        let args = self
-            .arguments
+        let args = eval_arguments(env, &self.arguments)?;
+        let keywords = self
+            .keywords
             .iter()
-            .map(|arg| arg.eval(env))
-            .collect::<Result<Vec<_>, _>>()?;
+            .map(|(name, value)| Ok((name.value.clone(), value.eval(env)?)))
+            .collect::<Result<Vec<_>, Traceback>>()?;
         let maybe_callee = self.callee.eval(env)?;
+        let kwargs = Kwargs::new(
+            maybe_callee.name.clone().unwrap_or("function".to_string()),
+            keywords,
+        );
 
         if let Some(callee) = maybe_callee.as_callable() {
-            if args.len() != callee.arity() {
+            // before the arity, which would only see missing arguments
+            let kwargs = if callee.takes_keywords() {
+                kwargs
+            } else {
+                kwargs.finish().map_err(|traceback| Traceback {
+                    pos: self.paren.pos.unwrap_or_default(),
+                    ..traceback
+                })?;
+                Kwargs::default()
+            };
+            if !callee.accepts(args.len()) {
+                let mut message = callee.arity_error(args.len());
+                if self
+                    .arguments
+                    .iter()
+                    .any(|argument| argument.star.is_some())
+                {
+                    message.push_str(" (after * expansion)");
+                }
                 return Err(Traceback {
-                    message: Some(format!(
-                        "Expected {} arguments but got {}",
-                        callee.arity(),
-                        args.len()
-                    )),
-                    pos: self.paren.pos.unwrap(),
+                    message: Some(message),
+                    pos: self.paren.pos.unwrap_or_default(),
+                    tipe: TracebackKind::TypeError,
                     ..Default::default()
                 });
             }
-            callee.call(env, args)
+            count_call();
+            let result = if kwargs.is_empty() {
+                callee.call(env, args)
+            } else {
+                callee.call_with_kwargs(env, args, kwargs)
+            };
+            // natives don't know where they were called from
+            result.map_err(|mut traceback| {
+                let pos = self.paren.pos.unwrap_or_default();
+                if traceback.pos == (0, 0) {
+                    traceback.pos = pos;
+                }
+                traceback.leave_call(pos);
+                traceback
+            })
         } else {
             Err(Traceback {
-                message: Some(format!("'{}' object is not callable", maybe_callee.tipe)),
-                pos: self.paren.pos.unwrap(),
+                message: Some(format!(
+                    "'{}' object is not callable{}",
+                    maybe_callee.tipe,
+                    while_evaluating(&self.callee)
+                )),
+                pos: self.paren.pos.unwrap_or_default(),
+                tipe: TracebackKind::TypeError,
                 ..Default::default()
             })
         }
@@ -397,11 +914,18 @@ impl Evaluable for Call {
 }
 
 impl Call {
-    pub fn new(callee: EXPR, paren: Token, arguments: Vec<EXPR>, uuid: UUID) -> Self {
+    pub fn new(
+        callee: EXPR,
+        paren: Token,
+        arguments: Vec<Argument>,
+        keywords: Vec<(Token, EXPR)>,
+        uuid: UUID,
+    ) -> Self {
         Self {
             callee,
             paren,
             arguments,
+            keywords,
             uuid,
         }
     }
@@ -410,79 +934,382 @@ impl Call {
 impl Evaluable for Get {
     fn eval(&self, env: &Env) -> Result<DynValue, Traceback> {
         let object = self.object.eval(env)?;
-        if let Some(mut instance) = object.as_instance() {
-            // if let Some(value) = instance.borrow().get(&self.name.value) {
-            if let Some(value) = get_from_refcell(instance.clone(), &self.name.value) {
-                return Ok(value);
-            } else {
-                return Err(Traceback {
-                    message: Some(format!(
-                        "'{}' object has no attribute '{}'",
-                        instance.borrow().class.name,
-                        self.name.value
-                    )),
-                    pos: self.name.pos.unwrap(),
-                    ..Default::default()
-                });
+        if let Some(instance) = object.as_instance() {
+            if let Some(method) = self.find_method(&instance) {
+                return Ok(DynValue::from(method.bind(instance)));
             }
-        } else {
-            Err(Traceback {
-                message: Some(format!(
-                    "'{}' object has no attribute '{}'",
-                    object.tipe, self.name.value
-                )),
-                pos: self.name.pos.unwrap(),
-                ..Default::default()
-            })
         }
+        get_attribute(&object, &self.name.value).ok_or_else(|| {
+            let mut message = attribute_error(&object, &self.name.value);
+            message.push_str(&while_evaluating(&self.object));
+            if let Some(hint) = attribute_hint(&object, &self.name.value) {
+                message = format!("{} — {}", message, hint);
+            }
+            Traceback {
+                message: Some(message),
+                pos: self.name.pos.unwrap_or_default(),
+                ..Default::default()
+            }
+        })
     }
 }
 
 impl Get {
     pub fn new(object: EXPR, name: Token, uuid: UUID) -> Self {
-        Self { object, name, uuid }
+        Self {
+            object,
+            name,
+            uuid,
+            method_cache: RefCell::new(None),
+        }
+    }
+
+    // the method unless a field shadows it, looked up in the
+    // class hierarchy only when the class changed since last time
+    fn find_method(&self, instance: &Rc<RefCell<Instance>>) -> Option<Function> {
+        let instance = instance.borrow();
+        if instance.fields.contains_key(&self.name.value) {
+            return None;
+        }
+        let class = &instance.class;
+        if let Some((class_id, method)) = &*self.method_cache.borrow() {
+            if *class_id == class.id {
+                return Some(method.clone());
+            }
+        }
+        let method = class.find_method(&self.name.value)?.clone();
+        *self.method_cache.borrow_mut() = Some((class.id, method.clone()));
+        Some(method)
     }
 }
 
-impl Evaluable for Set {
+impl Evaluable for Subscript {
     fn eval(&self, env: &Env) -> Result<DynValue, Traceback> {
         let object = self.object.eval(env)?;
+        let index = self.index.eval(env)?;
 
-        let borrow = object.as_instance();
-
-        if let Some(instance) = borrow {
-            let value = self.value.eval(env)?;
-            instance
-                .borrow_mut()
-                .set(self.name.value.clone(), value.clone());
-            Ok(value)
-        } else {
-            Err(Traceback {
-                message: Some(format!(
-                    "'{}' object has no attribute '{}'",
-                    object.tipe, self.name.value
-                )),
-                pos: self.name.pos.unwrap(),
-                ..Default::default()
-            })
+        if object.tipe == TypeKind::Dict {
+            return with_dict(&object, |dict| dict.lookup(&index))
+                .map_err(|traceback| at_bracket(traceback, &self.bracket));
         }
+        let (sequence, len) = match object.tipe {
+            TypeKind::List | TypeKind::Tuple => {
+                // only the element is cloned, not the whole sequence
+                let sequence = if object.tipe == TypeKind::List {
+                    "list"
+                } else {
+                    "tuple"
+                };
+                return object
+                    .with_elements(|elements| {
+                        let position =
+                            checked_index(sequence, elements.len(), &index, &self.bracket)?;
+                        Ok(elements[position].clone())
+                    })
+                    .unwrap();
+            }
+            TypeKind::Range => ("range", object.as_range().unwrap().len()),
+            TypeKind::Stringue => ("string", object.as_string().chars().count()),
+            _ => {
+                return Err(subscript_error(
+                    &self.bracket,
+                    none_hinted(
+                        &object,
+                        format!("'{}' object is not subscriptable", object.tipe),
+                    ),
+                ))
+            }
+        };
+
+        let position = checked_index(sequence, len, &index, &self.bracket)?;
+        Ok(match object.tipe {
+            TypeKind::Range => DynValue::from(object.as_range().unwrap().get(position)),
+            _ => DynValue::from(
+                object
+                    .as_string()
+                    .chars()
+                    .nth(position)
+                    .unwrap_or_default()
+                    .to_string(),
+            ),
+        })
     }
 }
 
-impl Set {
-    pub fn new(object: EXPR, name: Token, value: EXPR, uuid: UUID) -> Self {
+impl Subscript {
+    pub fn new(object: EXPR, bracket: Token, index: EXPR, uuid: UUID) -> Self {
         Self {
             object,
-            name,
+            bracket,
+            index,
+            uuid,
+        }
+    }
+}
+
+fn at_bracket(traceback: Traceback, bracket: &Token) -> Traceback {
+    Traceback {
+        pos: bracket.pos.unwrap_or_default(),
+        ..traceback
+    }
+}
+
+fn subscript_error(bracket: &Token, message: String) -> Traceback {
+    Traceback {
+        message: Some(message),
+        pos: bracket.pos.unwrap_or_default(),
+        tipe: TracebackKind::TypeError,
+        ..Default::default()
+    }
+}
+
+// the element an index is at in a sequence of len, reading or storing.
+// A negative one counts from the end, -1 being the last element
+fn checked_index(
+    sequence: &str,
+    len: usize,
+    index: &DynValue,
+    bracket: &Token,
+) -> Result<usize, Traceback> {
+    if index.tipe != TypeKind::Number {
+        return Err(subscript_error(
+            bracket,
+            format!(
+                "{} indices must be integers, not '{}'",
+                sequence, index.tipe
+            ),
+        ));
+    }
+    let position = index.as_number();
+    if position.fract() != 0.0 {
+        return Err(subscript_error(
+            bracket,
+            format!(
+                "{} indices must be integers, not {}",
+                sequence,
+                index.as_string()
+            ),
+        ));
+    }
+    let position = if position < 0.0 {
+        position + len as f64
+    } else {
+        position
+    };
+    if position < 0.0 || position >= len as f64 {
+        return Err(Traceback {
+            tipe: TracebackKind::IndexError,
+            ..subscript_error(
+                bracket,
+                format!(
+                    "{} index out of range (index {}, length {})",
+                    sequence,
+                    index.as_string(),
+                    len
+                ),
+            )
+        });
+    }
+    Ok(position as usize)
+}
+
+impl Evaluable for Assign {
+    fn eval(&self, env: &Env) -> Result<DynValue, Traceback> {
+        let place = self.target.place(env)?;
+        let value = self.value.eval(env)?;
+        place.store(env, value.clone())?;
+        Ok(value)
+    }
+}
+
+impl Assign {
+    pub fn new(target: AssignTarget, value: EXPR, uuid: UUID) -> Self {
+        Self {
+            target,
             value,
             uuid,
         }
     }
 }
 
+impl Evaluable for AssignExpr {
+    fn eval(&self, env: &Env) -> Result<DynValue, Traceback> {
+        let value = self.value.eval(env)?;
+        env.borrow_mut().set(self.name.value.clone(), value.clone());
+        Ok(value)
+    }
+}
+
+impl AssignExpr {
+    pub fn new(name: Token, value: EXPR, uuid: UUID) -> Self {
+        Self { name, value, uuid }
+    }
+}
+
+impl AssignTarget {
+    // the target an expression stands for, or what it is
+    // when it can't be assigned to, for the parser's error
+    pub fn from_expr(expr: EXPR) -> Result<Self, &'static str> {
+        let any = expr.into_any();
+        let any = match any.downcast::<Variable>() {
+            Ok(variable) => return Ok(AssignTarget::Name(variable.name)),
+            Err(any) => any,
+        };
+        let any = match any.downcast::<Get>() {
+            Ok(get) => {
+                return Ok(AssignTarget::Attribute {
+                    object: get.object,
+                    name: get.name,
+                })
+            }
+            Err(any) => any,
+        };
+        let any = match any.downcast::<Subscript>() {
+            Ok(subscript) => {
+                return Ok(AssignTarget::Index {
+                    object: subscript.object,
+                    bracket: subscript.bracket,
+                    index: subscript.index,
+                })
+            }
+            Err(any) => any,
+        };
+        let any = match any.downcast::<Tuple>() {
+            Ok(tuple) => {
+                return Ok(AssignTarget::Unpack {
+                    start: tuple.start,
+                    targets: tuple
+                        .elements
+                        .into_iter()
+                        .map(Self::from_expr)
+                        .collect::<Result<_, _>>()?,
+                })
+            }
+            Err(any) => any,
+        };
+        let any = match any.downcast::<Grouping>() {
+            Ok(grouping) => return Self::from_expr(grouping.expression),
+            Err(any) => any,
+        };
+
+        Err(if any.is::<Call>() {
+            "function call"
+        } else if any.is::<Literal>() || any.is::<List>() || any.is::<DictLiteral>() {
+            "literal"
+        } else if any.is::<AssignExpr>() {
+            "named expression"
+        } else {
+            "expression"
+        })
+    }
+
+    // what to store into, its object evaluated before the
+    // value like python does for attributes
+    pub fn place(&self, env: &Env) -> Result<Place<'_>, Traceback> {
+        Ok(match self {
+            AssignTarget::Name(name) => Place::Name(name),
+            AssignTarget::Attribute { object, name } => Place::Attribute(object.eval(env)?, name),
+            AssignTarget::Index {
+                object,
+                bracket,
+                index,
+            } => {
+                let object = object.eval(env)?;
+                Place::Index(object, index.eval(env)?, bracket)
+            }
+            AssignTarget::Unpack { start, targets } => Place::Unpack(
+                targets
+                    .iter()
+                    .map(|target| target.place(env))
+                    .collect::<Result<_, _>>()?,
+                start,
+            ),
+        })
+    }
+
+    pub fn store(&self, env: &Env, value: DynValue) -> Result<(), Traceback> {
+        self.place(env)?.store(env, value)
+    }
+}
+
+// an assignment target whose expressions were evaluated
+pub enum Place<'a> {
+    Name(&'a Token),
+    Attribute(DynValue, &'a Token),
+    Index(DynValue, DynValue, &'a Token),
+    Unpack(Vec<Place<'a>>, &'a Token),
+}
+
+impl Place<'_> {
+    pub fn store(self, env: &Env, value: DynValue) -> Result<(), Traceback> {
+        match self {
+            Place::Name(name) => {
+                env.borrow_mut().set(name.value.clone(), value);
+                Ok(())
+            }
+            Place::Attribute(object, name) => {
+                if set_attribute(&object, &name.value, value) {
+                    Ok(())
+                } else {
+                    Err(Traceback {
+                        message: Some(none_hinted(&object, attribute_error(&object, &name.value))),
+                        pos: name.pos.unwrap_or_default(),
+                        ..Default::default()
+                    })
+                }
+            }
+            // in place, for every reference to the list to see
+            Place::Index(object, index, bracket) if object.tipe == TypeKind::List => {
+                let len = with_list(&object, |list| list.len());
+                let position = checked_index("list", len, &index, bracket)?;
+                with_list(&object, |list| list[position] = value);
+                Ok(())
+            }
+            Place::Index(object, index, bracket) if object.tipe == TypeKind::Dict => {
+                with_dict(&object, |dict| dict.insert(index, value))
+                    .map_err(|traceback| at_bracket(traceback, bracket))
+            }
+            Place::Unpack(places, start) => {
+                let values = elements(env, &value).map_err(|traceback| Traceback {
+                    pos: start.pos.unwrap_or_default(),
+                    ..traceback
+                })?;
+                if values.len() != places.len() {
+                    let message = if values.len() < places.len() {
+                        format!(
+                            "not enough values to unpack (expected {}, got {})",
+                            places.len(),
+                            values.len()
+                        )
+                    } else {
+                        format!("too many values to unpack (expected {})", places.len())
+                    };
+                    return Err(Traceback {
+                        message: Some(message),
+                        pos: start.pos.unwrap_or_default(),
+                        tipe: TracebackKind::ValueError,
+                        ..Default::default()
+                    });
+                }
+                places
+                    .into_iter()
+                    .zip(values)
+                    .try_for_each(|(place, value)| place.store(env, value))
+            }
+            Place::Index(object, _, bracket) => Err(subscript_error(
+                bracket,
+                none_hinted(
+                    &object,
+                    format!("'{}' object does not support item assignment", object.tipe),
+                ),
+            )),
+        }
+    }
+}
+
 impl Evaluable for This {
     fn eval(&self, env: &Env) -> Result<DynValue, Traceback> {
-        Ok(env.borrow().get("this".to_string()).unwrap().clone())
+        Ok(env.borrow().get_resolved(self.uuid, "this").unwrap())
     }
 }
 
@@ -523,7 +1350,7 @@ impl Evaluable for Super {
         } else {
             Err(Traceback {
                 message: Some(format!("Undefined property '{}'", self.method.value)),
-                pos: self.method.pos.unwrap(),
+                pos: self.method.pos.unwrap_or_default(),
                 ..Default::default()
             })
         }
@@ -551,8 +1378,29 @@ macro_rules! impl_expr {
                 fn as_any(&self) -> &dyn Any {
                     self
                 }
+
+                fn into_any(self: Box<Self>) -> Box<dyn Any> {
+                    self
+                }
             }
         )*
     }
 }
-impl_expr!(Unary, Binary, Logical, Call, Grouping, Literal, Variable, List, Get, Set, This, Super);
+impl_expr!(
+    Unary,
+    Binary,
+    Logical,
+    Call,
+    Grouping,
+    Literal,
+    Variable,
+    List,
+    Tuple,
+    DictLiteral,
+    Get,
+    Subscript,
+    Assign,
+    AssignExpr,
+    This,
+    Super
+);
@@ -0,0 +1,419 @@
+use super::environment::Env;
+use super::functions::Kwargs;
+use super::limits::check_size;
+use super::traceback::{Traceback, TracebackKind};
+use super::types::{format_number, DynValue, TypeKind};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+    // the padding between the sign and the digits, like zeros
+    AfterSign,
+}
+
+// what follows the colon of a replacement field, like the ">8" of
+// "{:>8}": [[fill]align][sign][0][width][,][.precision][type]
+#[derive(Debug, Default, PartialEq)]
+pub struct FormatSpec {
+    fill: Option<char>,
+    align: Option<Align>,
+    sign: Option<char>,
+    // padding with zeros after the sign, unless told otherwise
+    zero: bool,
+    width: usize,
+    grouping: bool,
+    precision: Option<usize>,
+    tipe: Option<char>,
+}
+
+fn align_of(c: char) -> Option<Align> {
+    match c {
+        '<' => Some(Align::Left),
+        '>' => Some(Align::Right),
+        '^' => Some(Align::Center),
+        '=' => Some(Align::AfterSign),
+        _ => None,
+    }
+}
+
+fn value_error(message: &str) -> Traceback {
+    Traceback::from_exception(TracebackKind::ValueError, message)
+}
+
+impl FormatSpec {
+    pub fn parse(spec: &str) -> Result<Self, Traceback> {
+        let chars: Vec<char> = spec.chars().collect();
+        let mut result = Self::default();
+        let mut i = 0;
+
+        if let Some(align) = chars.get(1).and_then(|c| align_of(*c)) {
+            result.fill = Some(chars[0]);
+            result.align = Some(align);
+            i = 2;
+        } else if let Some(align) = chars.first().and_then(|c| align_of(*c)) {
+            result.align = Some(align);
+            i = 1;
+        }
+        if let Some(sign @ ('+' | '-' | ' ')) = chars.get(i) {
+            result.sign = Some(*sign);
+            i += 1;
+        }
+        if chars.get(i) == Some(&'0') {
+            result.zero = true;
+            i += 1;
+        }
+        result.width = digits(&chars, &mut i).unwrap_or(0);
+        if chars.get(i) == Some(&',') {
+            result.grouping = true;
+            i += 1;
+        }
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+            result.precision = Some(
+                digits(&chars, &mut i)
+                    .ok_or_else(|| value_error("Format specifier missing precision"))?,
+            );
+        }
+        match &chars[i..] {
+            [] => {}
+            [tipe] if tipe.is_alphabetic() || *tipe == '%' => result.tipe = Some(*tipe),
+            _ => return Err(value_error("Invalid format specifier")),
+        }
+        Ok(result)
+    }
+
+    // what format(value, spec) gives
+    pub fn apply(&self, value: &DynValue) -> Result<String, Traceback> {
+        match value.tipe {
+            _ if *self == Self::default() => Ok(value.as_string()),
+            TypeKind::Stringue => self.format_string(value.as_string()),
+            TypeKind::Number | TypeKind::Boolean => self.format_number(value.as_number()),
+            _ => Err(Traceback::from_exception(
+                TracebackKind::TypeError,
+                &format!(
+                    "unsupported format string passed to {}.__format__",
+                    value.tipe
+                ),
+            )),
+        }
+    }
+
+    // the bytes the width and the precision alone can make the
+    // value take, known before formatting it
+    fn reserved_size(&self, value: &DynValue) -> Option<usize> {
+        let width = self
+            .width
+            .checked_mul(self.fill.map_or(1, char::len_utf8))?;
+        match value.tipe {
+            TypeKind::Number | TypeKind::Boolean => width.checked_add(self.precision.unwrap_or(0)),
+            _ => Some(width),
+        }
+    }
+
+    fn unknown_code(&self, tipe: char, of: &str) -> Traceback {
+        value_error(&format!(
+            "Unknown format code '{}' for object of type '{}'",
+            tipe, of
+        ))
+    }
+
+    fn format_string(&self, string: String) -> Result<String, Traceback> {
+        if let Some(tipe) = self.tipe.filter(|tipe| *tipe != 's') {
+            return Err(self.unknown_code(tipe, "str"));
+        }
+        if self.sign.is_some() {
+            return Err(value_error("Sign not allowed in string format specifier"));
+        }
+        if self.align == Some(Align::AfterSign) {
+            return Err(value_error(
+                "'=' alignment not allowed in string format specifier",
+            ));
+        }
+        let string = match self.precision {
+            Some(precision) => string.chars().take(precision).collect(),
+            None => string,
+        };
+        Ok(self.pad("", &string, Align::Left))
+    }
+
+    fn format_number(&self, number: f64) -> Result<String, Traceback> {
+        let precision = self.precision.unwrap_or(6);
+        let digits = if !number.is_finite() {
+            format_number(number.abs())
+        } else {
+            match self.tipe {
+                None if self.precision.is_none() => format_number(number.abs()),
+                None | Some('g') => general(number.abs(), precision),
+                Some('d') if number.fract() == 0.0 => format!("{}", number.abs()),
+                Some('d') => return Err(self.unknown_code('d', "float")),
+                Some('f') => format!("{:.*}", precision, number.abs()),
+                Some('e') => scientific(number.abs(), precision),
+                Some('%') => format!("{:.*}%", precision, number.abs() * 100.0),
+                Some(tipe) => return Err(self.unknown_code(tipe, "float")),
+            }
+        };
+        let digits = if self.grouping {
+            group(&digits)
+        } else {
+            digits
+        };
+        let sign = match self.sign {
+            _ if number.is_sign_negative() && number != 0.0 => "-",
+            Some('+') => "+",
+            Some(' ') => " ",
+            _ => "",
+        };
+        let default = if self.zero {
+            Align::AfterSign
+        } else {
+            Align::Right
+        };
+        Ok(self.pad(sign, &digits, default))
+    }
+
+    fn pad(&self, sign: &str, body: &str, default: Align) -> String {
+        let length = sign.chars().count() + body.chars().count();
+        let missing = self.width.saturating_sub(length);
+        let fill = self
+            .fill
+            .unwrap_or(if self.zero { '0' } else { ' ' })
+            .to_string();
+        let fill = |n: usize| fill.repeat(n);
+        match self.align.unwrap_or(default) {
+            Align::Left => format!("{}{}{}", sign, body, fill(missing)),
+            Align::Right => format!("{}{}{}", fill(missing), sign, body),
+            Align::Center => format!(
+                "{}{}{}{}",
+                fill(missing / 2),
+                sign,
+                body,
+                fill(missing - missing / 2)
+            ),
+            Align::AfterSign => format!("{}{}{}", sign, fill(missing), body),
+        }
+    }
+}
+
+fn digits(chars: &[char], i: &mut usize) -> Option<usize> {
+    let start = *i;
+    while chars.get(*i).is_some_and(|c| c.is_ascii_digit()) {
+        *i += 1;
+    }
+    chars[start..*i].iter().collect::<String>().parse().ok()
+}
+
+// like rust's {:e}, with python's signed two digit exponent
+fn scientific(number: f64, precision: usize) -> String {
+    let formatted = format!("{:.*e}", precision, number);
+    let (mantissa, exponent) = formatted.split_once('e').unwrap_or((&formatted, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!("{}e{}{:02}", mantissa, sign, exponent.abs())
+}
+
+// the shorter of fixed and scientific, without trailing zeros
+fn general(number: f64, precision: usize) -> String {
+    let precision = precision.max(1);
+    let rounded = scientific(number, precision - 1);
+    let exponent: i32 = rounded
+        .split_once('e')
+        .and_then(|(_, exponent)| exponent.parse().ok())
+        .unwrap_or(0);
+    if -4 <= exponent && exponent < precision as i32 {
+        let decimals = (precision as i32 - 1 - exponent) as usize;
+        strip_zeros(&format!("{:.*}", decimals, number))
+    } else {
+        let (mantissa, exponent) = rounded.split_once('e').unwrap();
+        format!("{}e{}", strip_zeros(mantissa), exponent)
+    }
+}
+
+fn strip_zeros(number: &str) -> String {
+    if number.contains('.') {
+        number
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    } else {
+        number.to_string()
+    }
+}
+
+// 1234567.5 -> 1,234,567.5
+fn group(digits: &str) -> String {
+    let end = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    let (integer, rest) = digits.split_at(end);
+    let mut grouped = String::new();
+    for (i, c) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    grouped + rest
+}
+
+// the value a replacement field refers to, by position or by name
+fn field_value(
+    name: &str,
+    next_index: &mut Option<usize>,
+    args: &[DynValue],
+    kwargs: &Kwargs,
+) -> Result<DynValue, Traceback> {
+    let index =
+        if name.is_empty() {
+            match next_index {
+                Some(index) => {
+                    *index += 1;
+                    *index - 1
+                }
+                None => return Err(value_error(
+                    "cannot switch from manual field specification to automatic field numbering",
+                )),
+            }
+        } else if let Ok(index) = name.parse::<usize>() {
+            if next_index.is_some_and(|index| index > 0) {
+                return Err(value_error(
+                    "cannot switch from automatic field numbering to manual field specification",
+                ));
+            }
+            *next_index = None;
+            index
+        } else {
+            return kwargs.get(name).cloned().ok_or_else(|| {
+                Traceback::from_exception(TracebackKind::KeyError, &format!("'{}'", name))
+            });
+        };
+    args.get(index).cloned().ok_or_else(|| {
+        Traceback::from_exception(
+            TracebackKind::IndexError,
+            &format!(
+                "Replacement index {} out of range for positional args tuple",
+                index
+            ),
+        )
+    })
+}
+
+// what "...".format(*args, **kwargs) gives
+pub fn format_template(
+    env: &Env,
+    template: &str,
+    args: &[DynValue],
+    kwargs: &Kwargs,
+) -> Result<String, Traceback> {
+    let mut result = String::new();
+    // None once the fields are numbered by hand
+    let mut next_index = Some(0);
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            }
+            '}' => return Err(value_error("Single '}' encountered in format string")),
+            '{' => {
+                let mut field = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => field.push(c),
+                        None if field.is_empty() => {
+                            return Err(value_error("Single '{' encountered in format string"))
+                        }
+                        None => return Err(value_error("expected '}' before end of string")),
+                    }
+                }
+                let (field, spec) = field.split_once(':').unwrap_or((&field, ""));
+                let (name, conversion) = match field.split_once('!') {
+                    Some((name, conversion)) => (name, Some(conversion)),
+                    None => (field, None),
+                };
+                let value = field_value(name, &mut next_index, args, kwargs)?;
+                let value = match conversion {
+                    None => value,
+                    Some("s") => DynValue::from(value.as_string()),
+                    Some("r") => DynValue::from(value.repr()),
+                    Some(conversion) => {
+                        return Err(value_error(&format!(
+                            "Unknown conversion specifier {}",
+                            conversion
+                        )))
+                    }
+                };
+                let spec = FormatSpec::parse(spec)?;
+                check_size(env, spec.reserved_size(&value), "format width too large")?;
+                result.push_str(&spec.apply(&value)?);
+            }
+            c => result.push(c),
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(spec: &str, value: impl Into<DynValue>) -> String {
+        FormatSpec::parse(spec)
+            .unwrap()
+            .apply(&value.into())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_specs_like_python() {
+        assert_eq!(format("", 3.0), "3");
+        assert_eq!(format(".2f", 3.14159), "3.14");
+        assert_eq!(format(">8", "ab"), "      ab");
+        assert_eq!(format("*^7", "ab"), "**ab***");
+        assert_eq!(format("08.3f", -3.14159), "-003.142");
+        assert_eq!(format("+d", 42.0), "+42");
+        assert_eq!(format(",.2f", 1234567.891), "1,234,567.89");
+        assert_eq!(format("e", 12345.678), "1.234568e+04");
+        assert_eq!(format(".3", 3.14159), "3.14");
+        assert_eq!(format("g", 0.00001234), "1.234e-05");
+        assert_eq!(format(".1%", 0.256), "25.6%");
+        assert_eq!(format(".3s", "abcdef"), "abc");
+        assert_eq!(format("^6", true), "  1   ");
+        assert_eq!(format("05", "ab"), "ab000");
+        assert_eq!(format("<05", 7.0), "70000");
+    }
+
+    #[test]
+    fn test_invalid_specs() {
+        let error = |spec: &str, value: DynValue| {
+            FormatSpec::parse(spec)
+                .and_then(|spec| spec.apply(&value))
+                .unwrap_err()
+                .message()
+                .to_string()
+        };
+        assert_eq!(
+            error("d", DynValue::from("a")),
+            "Unknown format code 'd' for object of type 'str'"
+        );
+        assert_eq!(
+            error("d", DynValue::from(2.5)),
+            "Unknown format code 'd' for object of type 'float'"
+        );
+        assert_eq!(
+            error(">>>", DynValue::from(1.0)),
+            "Invalid format specifier"
+        );
+        assert_eq!(
+            error("+", DynValue::from("a")),
+            "Sign not allowed in string format specifier"
+        );
+    }
+}
@@ -1,16 +1,41 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
-use super::class::Instance;
-use super::environment::{make_env_enclosed, Env};
+use super::class::{Class, Instance};
+use super::environment::{make_env_enclosed, Env, Frame};
+use super::limits::enter_call;
 use super::statement::FunctionStatement;
+use super::stats::{enter_function, leave_function};
 use super::traceback::{Traceback, TracebackKind};
-use super::types::DynValue;
+use super::types::{DynValue, TypeKind};
 
 pub trait Callable {
     fn call(&self, env: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback>;
 
+    // only natives made with_keywords take any
+    fn takes_keywords(&self) -> bool {
+        false
+    }
+
+    fn call_with_kwargs(
+        &self,
+        env: &Env,
+        args: Vec<DynValue>,
+        kwargs: Kwargs,
+    ) -> Result<DynValue, Traceback> {
+        kwargs.finish()?;
+        self.call(env, args)
+    }
+
     fn arity(&self) -> usize;
+
+    fn accepts(&self, nb_args: usize) -> bool {
+        nb_args == self.arity()
+    }
+
+    fn arity_error(&self, nb_args: usize) -> String {
+        format!("Expected {} arguments but got {}", self.arity(), nb_args)
+    }
 }
 
 #[derive(Clone)]
@@ -19,10 +44,46 @@ pub struct Function {
     pub closure: Env,
 }
 
+pub type NativeFn = dyn Fn(&Env, Vec<DynValue>) -> Result<DynValue, Traceback>;
+pub type KeywordFn = dyn Fn(&Env, Vec<DynValue>, Kwargs) -> Result<DynValue, Traceback>;
+
+// a builtin or a host callback, which may capture state
 #[derive(Clone)]
 pub struct NativeFunction {
-    pub func: fn(&Env, Vec<DynValue>) -> Result<DynValue, Traceback>,
+    pub func: Rc<NativeFn>,
+    // the same function, for the natives taking keyword arguments
+    pub keyword_func: Option<Rc<KeywordFn>>,
     pub nb_args: usize,
+    // more than nb_args when the last ones are optional
+    pub max_args: usize,
+}
+
+// the keyword arguments of a call, which the native takes
+// one by one before checking that none is left
+#[derive(Default)]
+pub struct Kwargs {
+    // the called function, for the errors
+    function: String,
+    values: Vec<(String, DynValue)>,
+}
+
+pub type MethodFn = fn(&Env, &DynValue, Vec<DynValue>) -> Result<DynValue, Traceback>;
+
+// a built-in method of a str or a list, bound to its receiver
+#[derive(Clone)]
+pub struct NativeMethod {
+    pub receiver: DynValue,
+    pub func: MethodFn,
+    pub nb_args: usize,
+    // more than nb_args when the last ones are optional
+    pub max_args: usize,
+}
+
+// a method taken from its class, called with the instance first
+#[derive(Clone)]
+pub struct UnboundMethod {
+    pub class: Class,
+    pub method: Function,
 }
 
 impl Function {
@@ -42,8 +103,44 @@ impl Function {
 }
 
 impl Callable for Function {
-    fn call(&self, _: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    fn call(&self, env: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+        let tracer = self.statement.inner.borrow().tracer.clone();
+        match tracer {
+            Some(tracer) => {
+                let name = self.statement.inner.borrow().name.value.clone();
+                tracer.call(&name, &args);
+                let result = self.run(env, args);
+                tracer.exit(&name, &result);
+                result
+            }
+            None => self.run(env, args),
+        }
+        .map_err(|mut traceback| {
+            let inner = self.statement.inner.borrow();
+            // the body may come from an earlier run than the caller
+            if traceback.chunk.is_none() {
+                traceback.chunk = inner.name.chunk;
+            }
+            if traceback.tipe.exception_name().is_some() {
+                traceback.leave_function(&inner.name.value);
+            }
+            traceback
+        })
+    }
+
+    fn arity(&self) -> usize {
+        self.statement.inner.as_ref().borrow().parameters.len()
+    }
+}
+
+impl Function {
+    fn run(&self, caller: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+        let _call = enter_call(caller)?;
         let function_env = make_env_enclosed(self.closure.clone());
+        function_env.borrow_mut().frame = Some(Frame {
+            function: self.statement.clone(),
+            caller: Rc::downgrade(caller),
+        });
 
         for (param, value) in self
             .statement
@@ -57,14 +154,16 @@ impl Callable for Function {
             function_env.borrow_mut().set(param.value.clone(), value);
         }
 
-        match self
+        enter_function();
+        let result = self
             .statement
             .inner
             .as_ref()
             .borrow()
             .body
-            .execute(&function_env)
-        {
+            .execute(&function_env);
+        leave_function();
+        match result {
             Err(Traceback {
                 tipe: TracebackKind::Return,
                 value: Some(value),
@@ -74,9 +173,149 @@ impl Callable for Function {
             Err(traceback) => Err(traceback),
         }
     }
+}
+
+impl NativeFunction {
+    pub fn new(
+        func: impl Fn(&Env, Vec<DynValue>) -> Result<DynValue, Traceback> + 'static,
+        nb_args: usize,
+    ) -> Self {
+        Self {
+            func: Rc::new(func),
+            keyword_func: None,
+            nb_args,
+            max_args: nb_args,
+        }
+    }
+
+    // taking from nb_args to max_args arguments, the last ones optional
+    pub fn optional(
+        func: impl Fn(&Env, Vec<DynValue>) -> Result<DynValue, Traceback> + 'static,
+        nb_args: usize,
+        max_args: usize,
+    ) -> Self {
+        Self {
+            max_args,
+            ..Self::new(func, nb_args)
+        }
+    }
+
+    // taking any number of arguments from nb_args on
+    pub fn variadic(
+        func: impl Fn(&Env, Vec<DynValue>) -> Result<DynValue, Traceback> + 'static,
+        nb_args: usize,
+    ) -> Self {
+        Self {
+            max_args: usize::MAX,
+            ..Self::new(func, nb_args)
+        }
+    }
+
+    pub fn with_keywords(
+        func: impl Fn(&Env, Vec<DynValue>, Kwargs) -> Result<DynValue, Traceback> + 'static,
+        nb_args: usize,
+    ) -> Self {
+        let keyword_func: Rc<KeywordFn> = Rc::new(func);
+        let without_keywords = keyword_func.clone();
+        Self {
+            keyword_func: Some(keyword_func),
+            ..Self::new(
+                move |env, args| without_keywords(env, args, Kwargs::default()),
+                nb_args,
+            )
+        }
+    }
+}
+
+impl Kwargs {
+    pub fn new(function: String, values: Vec<(String, DynValue)>) -> Self {
+        Self { function, values }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    // without taking it, for the natives which may use it twice
+    pub fn get(&self, name: &str) -> Option<&DynValue> {
+        self.values
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn pop(&mut self, name: &str) -> Option<DynValue> {
+        let index = self.values.iter().position(|(n, _)| n == name)?;
+        Some(self.values.remove(index).1)
+    }
+
+    pub fn pop_or(&mut self, name: &str, default: impl Into<DynValue>) -> DynValue {
+        self.pop(name).unwrap_or_else(|| default.into())
+    }
+
+    // the ones the function didn't take are errors
+    pub fn finish(self) -> Result<(), Traceback> {
+        match self.values.first() {
+            Some((name, _)) => Err(Traceback::from_exception(
+                TracebackKind::TypeError,
+                &format!(
+                    "'{}' is an invalid keyword argument for {}()",
+                    name, self.function
+                ),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl UnboundMethod {
+    fn name(&self) -> String {
+        self.method.statement.inner.borrow().name.value.clone()
+    }
+}
+
+impl Callable for UnboundMethod {
+    fn call(&self, env: &Env, mut args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+        let receiver = args.remove(0);
+        let instance = receiver
+            .as_instance()
+            .filter(|instance| instance.borrow().class.inherits_from(&self.class));
+
+        match instance {
+            Some(instance) => self.method.bind(instance).call(env, args),
+            None => {
+                let tipe = match receiver.tipe {
+                    TypeKind::Instance => {
+                        receiver.as_instance().unwrap().borrow().class.name.clone()
+                    }
+                    _ => receiver.tipe.to_string(),
+                };
+                Err(Traceback::from_exception(
+                    TracebackKind::TypeError,
+                    &format!(
+                        "descriptor '{}' for '{}' objects doesn't apply to a '{}' object",
+                        self.name(),
+                        self.class.name,
+                        tipe
+                    ),
+                ))
+            }
+        }
+    }
 
     fn arity(&self) -> usize {
-        self.statement.inner.as_ref().borrow().parameters.len()
+        self.method.arity() + 1
+    }
+
+    fn arity_error(&self, nb_args: usize) -> String {
+        if nb_args == 0 {
+            format!(
+                "{}() missing 1 required positional argument: 'self'",
+                self.name()
+            )
+        } else {
+            format!("Expected {} arguments but got {}", self.arity(), nb_args)
+        }
     }
 }
 
@@ -85,7 +324,66 @@ impl Callable for NativeFunction {
         (self.func)(env, args)
     }
 
+    fn takes_keywords(&self) -> bool {
+        self.keyword_func.is_some()
+    }
+
+    fn call_with_kwargs(
+        &self,
+        env: &Env,
+        args: Vec<DynValue>,
+        kwargs: Kwargs,
+    ) -> Result<DynValue, Traceback> {
+        match &self.keyword_func {
+            Some(func) => func(env, args, kwargs),
+            None => {
+                kwargs.finish()?;
+                self.call(env, args)
+            }
+        }
+    }
+
+    fn arity(&self) -> usize {
+        self.nb_args
+    }
+
+    fn accepts(&self, nb_args: usize) -> bool {
+        (self.nb_args..=self.max_args).contains(&nb_args)
+    }
+
+    fn arity_error(&self, nb_args: usize) -> String {
+        range_arity_error(self.nb_args, self.max_args, nb_args)
+    }
+}
+
+// for the natives taking from nb_args to max_args arguments
+fn range_arity_error(nb_args: usize, max_args: usize, got: usize) -> String {
+    if max_args == nb_args {
+        format!("Expected {} arguments but got {}", nb_args, got)
+    } else if max_args == usize::MAX {
+        format!("Expected at least {} arguments but got {}", nb_args, got)
+    } else {
+        format!(
+            "Expected {} to {} arguments but got {}",
+            nb_args, max_args, got
+        )
+    }
+}
+
+impl Callable for NativeMethod {
+    fn call(&self, env: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+        (self.func)(env, &self.receiver, args)
+    }
+
     fn arity(&self) -> usize {
         self.nb_args
     }
+
+    fn accepts(&self, nb_args: usize) -> bool {
+        (self.nb_args..=self.max_args).contains(&nb_args)
+    }
+
+    fn arity_error(&self, nb_args: usize) -> String {
+        range_arity_error(self.nb_args, self.max_args, nb_args)
+    }
 }
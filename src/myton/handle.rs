@@ -0,0 +1,64 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::environment::Env;
+use super::sources::SourceMap;
+use super::traceback::{Traceback, TracebackKind};
+use super::types::DynValue;
+use super::MyWrite;
+
+/// A script function the host can call, see `Interpreter::get_function`.
+///
+/// It holds the function itself rather than its name, so it keeps
+/// calling the same one when the script later rebinds the name.
+/// The interpreter's globals stay alive as long as the handle does.
+#[derive(Clone)]
+pub struct MytonFunction {
+    callable: DynValue,
+    env: Env,
+    sources: Rc<RefCell<SourceMap>>,
+    output: Rc<RefCell<Box<dyn MyWrite>>>,
+}
+
+impl MytonFunction {
+    pub fn new(
+        callable: DynValue,
+        env: Env,
+        sources: Rc<RefCell<SourceMap>>,
+        output: Rc<RefCell<Box<dyn MyWrite>>>,
+    ) -> Self {
+        Self {
+            callable,
+            env,
+            sources,
+            output,
+        }
+    }
+
+    /// The name the function was defined with.
+    pub fn name(&self) -> String {
+        self.callable.name.clone().unwrap_or_default()
+    }
+
+    /// Calls the function with the arguments, like the script would.
+    /// A wrong number of them is a `TypeError`, and the errors come
+    /// with the code they were raised in.
+    pub fn call(&self, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+        let callee = self.callable.as_callable().unwrap();
+        if !callee.accepts(args.len()) {
+            return Err(Traceback::from_exception(
+                TracebackKind::TypeError,
+                &callee.arity_error(args.len()),
+            ));
+        }
+        let result = callee.call(&self.env, args).map_err(|mut traceback| {
+            // a native one doesn't say where it failed
+            if let Some(chunk) = traceback.chunk {
+                self.sources.borrow().locate(&mut traceback, chunk);
+            }
+            traceback
+        });
+        self.output.borrow_mut().flush().ok();
+        result
+    }
+}
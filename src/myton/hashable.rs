@@ -0,0 +1,192 @@
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use super::traceback::{Traceback, TracebackKind};
+use super::types::{DynValue, TypeKind};
+
+// what a key is compared and hashed by. Numbers go by value, with
+// booleans as 0 and 1, so 1, 1.0 and True are the same key like in python.
+// A tuple goes by its elements, all of which must be keys themselves
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Key {
+    None,
+    Number(u64),
+    Str(String),
+    Tuple(Vec<Key>),
+}
+
+// what a value usable as a dict key is known by
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct HashableKey(Key);
+
+impl HashableKey {
+    pub fn new(value: &DynValue) -> Result<Self, Traceback> {
+        Ok(Self(key_of(value)?))
+    }
+
+    // what hash() gives: integers are their own hash, like in python
+    pub fn hash_value(&self) -> f64 {
+        match self.0 {
+            Key::Number(bits) if is_integer(f64::from_bits(bits)) => f64::from_bits(bits),
+            _ => {
+                let mut hasher = DefaultHasher::new();
+                self.0.hash(&mut hasher);
+                // exactly representable by a number
+                (hasher.finish() >> 11) as f64
+            }
+        }
+    }
+}
+
+fn key_of(value: &DynValue) -> Result<Key, Traceback> {
+    let key = match value.tipe {
+        TypeKind::Nil => Key::None,
+        TypeKind::Number | TypeKind::Boolean => {
+            // -0.0 == 0.0
+            Key::Number((value.as_number() + 0.0).to_bits())
+        }
+        TypeKind::Stringue => Key::Str(value.as_string()),
+        TypeKind::Tuple => Key::Tuple(
+            value
+                .as_tuple()
+                .unwrap()
+                .iter()
+                .map(key_of)
+                .collect::<Result<_, _>>()?,
+        ),
+        _ => {
+            return Err(Traceback::from_exception(
+                TracebackKind::TypeError,
+                &format!("unhashable type: '{}'", value.tipe),
+            ))
+        }
+    };
+    Ok(key)
+}
+
+fn is_integer(number: f64) -> bool {
+    number.fract() == 0.0 && number.abs() < (1u64 << 53) as f64
+}
+
+// what a dict holds: its entries in the order they were added,
+// and where the entry of each key is among them
+#[derive(Clone, Default)]
+pub struct Dict {
+    entries: Vec<(DynValue, DynValue)>,
+    positions: HashMap<HashableKey, usize>,
+}
+
+impl Dict {
+    // a key seen again keeps its first place and spelling
+    // but takes the later value
+    pub fn new(pairs: Vec<(DynValue, DynValue)>) -> Result<Self, Traceback> {
+        let mut dict = Self::default();
+        for (key, value) in pairs {
+            dict.insert(key, value)?;
+        }
+        Ok(dict)
+    }
+
+    pub fn entries(&self) -> &[(DynValue, DynValue)] {
+        &self.entries
+    }
+
+    // the value of the entry with the key, a KeyError if there is none
+    pub fn lookup(&self, key: &DynValue) -> Result<DynValue, Traceback> {
+        match self.positions.get(&HashableKey::new(key)?) {
+            Some(position) => Ok(self.entries[*position].1.clone()),
+            None => Err(Traceback::from_exception(
+                TracebackKind::KeyError,
+                &key.repr(),
+            )),
+        }
+    }
+
+    // whether there is an entry with the key, a TypeError if it can't be one
+    pub fn contains_key(&self, key: &DynValue) -> Result<bool, Traceback> {
+        Ok(self.positions.contains_key(&HashableKey::new(key)?))
+    }
+
+    // replaces the value of the entry with the key, or adds one at the end
+    pub fn insert(&mut self, key: DynValue, value: DynValue) -> Result<(), Traceback> {
+        match self.positions.entry(HashableKey::new(&key)?) {
+            Entry::Occupied(position) => self.entries[*position.get()].1 = value,
+            Entry::Vacant(position) => {
+                position.insert(self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(value: impl Into<DynValue>) -> HashableKey {
+        HashableKey::new(&value.into()).unwrap()
+    }
+
+    #[test]
+    fn test_numbers_collide_by_value() {
+        assert!(key(1.0) == key(true));
+        assert!(key(0.0) == key(-0.0));
+        assert!(key(0.0) == key(false));
+        assert!(key(1.0) != key("1"));
+        assert!(key(1.5) != key(1.0));
+        assert!(key(DynValue::none()) != key(0.0));
+
+        assert_eq!(key(42.0).hash_value(), 42.0);
+        assert_eq!(key(true).hash_value(), 1.0);
+        assert_eq!(key("a").hash_value(), key("a").hash_value());
+        assert_ne!(key("a").hash_value(), key("b").hash_value());
+    }
+
+    #[test]
+    fn test_dict_new() {
+        let dict = Dict::new(vec![
+            (DynValue::from(1.0), DynValue::from("one")),
+            (DynValue::from("1"), DynValue::from("string")),
+            (DynValue::from(true), DynValue::from("true")),
+        ])
+        .unwrap();
+        assert_eq!(
+            dict.entries(),
+            [
+                (DynValue::from(1.0), DynValue::from("true")),
+                (DynValue::from("1"), DynValue::from("string")),
+            ]
+        );
+
+        let error = Dict::new(vec![(DynValue::from(Vec::new()), DynValue::none())])
+            .err()
+            .unwrap();
+        assert_eq!(error.message(), "unhashable type: 'list'");
+    }
+
+    #[test]
+    fn test_dict_lookup_and_insert() {
+        let mut dict = Dict::new(vec![(DynValue::from("a"), DynValue::from(1.0))]).unwrap();
+        dict.insert(DynValue::from(2.0), DynValue::from("two"))
+            .unwrap();
+        dict.insert(DynValue::from("a"), DynValue::from(3.0))
+            .unwrap();
+        assert_eq!(dict.entries().len(), 2);
+        assert_eq!(
+            dict.lookup(&DynValue::from("a")).unwrap(),
+            DynValue::from(3.0)
+        );
+        assert_eq!(
+            dict.lookup(&DynValue::from(2.0)).unwrap(),
+            DynValue::from("two")
+        );
+        // the first spelling of the key stays
+        assert_eq!(dict.entries()[1].0, DynValue::from(2.0));
+
+        let error = dict.lookup(&DynValue::from("b")).unwrap_err();
+        assert!(matches!(error.tipe, TracebackKind::KeyError));
+        assert_eq!(error.message(), "'b'");
+    }
+}
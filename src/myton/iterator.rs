@@ -0,0 +1,158 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::builtin_types::elements;
+use super::environment::Env;
+use super::traceback::{Traceback, TracebackKind};
+use super::types::DynValue;
+
+type Step = dyn FnMut(&Env) -> Result<Option<DynValue>, Traceback>;
+
+// a lazy sequence, computing each value when asked for it. It can
+// only be gone through once: its copies share where it is at, and
+// once exhausted it stays empty, like python's iterators
+#[derive(Clone)]
+pub struct LazyIterator {
+    // what it shows as, like zip or map
+    pub name: &'static str,
+    step: Rc<RefCell<Box<Step>>>,
+}
+
+impl LazyIterator {
+    pub fn new(
+        name: &'static str,
+        step: impl FnMut(&Env) -> Result<Option<DynValue>, Traceback> + 'static,
+    ) -> Self {
+        Self {
+            name,
+            step: Rc::new(RefCell::new(Box::new(step))),
+        }
+    }
+
+    pub fn from_values(values: Vec<DynValue>) -> Self {
+        let mut values = values.into_iter();
+        Self::new("iterator", move |_| Ok(values.next()))
+    }
+
+    // the env is the caller's, for the functions a map or a filter calls
+    pub fn next(&self, env: &Env) -> Result<Option<DynValue>, Traceback> {
+        match self.step.try_borrow_mut() {
+            Ok(mut step) => step(env),
+            // a map calling a function which reads from the map
+            Err(_) => Err(Traceback::from_exception(
+                TracebackKind::ValueError,
+                &format!("{} iterator already executing", self.name),
+            )),
+        }
+    }
+
+    pub fn collect(&self, env: &Env) -> Result<Vec<DynValue>, Traceback> {
+        let mut values = Vec::new();
+        while let Some(value) = self.next(env)? {
+            values.push(value);
+        }
+        Ok(values)
+    }
+}
+
+// what range() gives: the numbers from start, by step, up to stop
+// excluded. Unlike an iterator it can be gone through again, each
+// loop making the numbers as it gets to them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    pub start: f64,
+    pub stop: f64,
+    pub step: f64,
+}
+
+impl Range {
+    pub fn len(&self) -> usize {
+        let span = (self.stop - self.start) / self.step;
+        if span > 0.0 {
+            span.ceil() as usize
+        } else {
+            0
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // the number at a position below len
+    pub fn get(&self, position: usize) -> f64 {
+        self.start + position as f64 * self.step
+    }
+
+    // whether the number is one of those the range goes through
+    pub fn contains(&self, number: f64) -> bool {
+        let position = (number - self.start) / self.step;
+        position.fract() == 0.0 && position >= 0.0 && position < self.len() as f64
+    }
+
+    pub fn iter(&self) -> LazyIterator {
+        let (range, len) = (*self, self.len());
+        let mut position = 0;
+        LazyIterator::new("range_iterator", move |_| {
+            if position == len {
+                return Ok(None);
+            }
+            position += 1;
+            Ok(Some(DynValue::from(range.get(position - 1))))
+        })
+    }
+}
+
+// what a for loop goes through: iterators themselves,
+// the elements of the other iterables
+pub fn iterate(env: &Env, value: &DynValue) -> Result<LazyIterator, Traceback> {
+    if let Some(range) = value.as_range() {
+        return Ok(range.iter());
+    }
+    match value.as_iterator() {
+        Some(iterator) => Ok(iterator),
+        None => Ok(LazyIterator::from_values(elements(env, value)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::environment::make_env;
+    use super::*;
+
+    #[test]
+    fn test_consumed_once() {
+        let env = make_env();
+        let iterator = LazyIterator::from_values(vec![DynValue::from(1.0), DynValue::from(2.0)]);
+        let copy = iterator.clone();
+
+        assert_eq!(iterator.next(&env).unwrap(), Some(DynValue::from(1.0)));
+        assert_eq!(copy.collect(&env).unwrap(), vec![DynValue::from(2.0)]);
+        assert_eq!(iterator.next(&env).unwrap(), None);
+        assert!(iterator.collect(&env).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_range_len() {
+        let range = |start, stop, step| Range { start, stop, step };
+        let cases = [
+            (range(0.0, 5.0, 1.0), 5),
+            (range(0.0, 10.0, 3.0), 4),
+            (range(5.0, 0.0, -2.0), 3),
+            (range(3.0, 3.0, 1.0), 0),
+            (range(5.0, 0.0, 1.0), 0),
+            (range(0.0, 5.0, -1.0), 0),
+            (range(0.0, 1e12, 1.0), 1_000_000_000_000),
+        ];
+        let env = make_env();
+        for (range, len) in cases {
+            assert_eq!(range.len(), len, "{:?}", range);
+            if len < 100 {
+                assert_eq!(range.iter().collect(&env).unwrap().len(), len);
+                // and again
+                assert_eq!(range.iter().collect(&env).unwrap().len(), len);
+            }
+        }
+        assert_eq!(range(5.0, 0.0, -2.0).get(2), 1.0);
+    }
+}
@@ -1,6 +1,6 @@
 pub mod token;
 
-use super::traceback::Traceback;
+use super::traceback::{Traceback, TracebackKind};
 use regex::Regex;
 use strum::IntoEnumIterator;
 use token::*;
@@ -11,8 +11,15 @@ pub struct Lexer {
     pub idx: usize,
     ignored_tokens: Vec<TokenKind>,
     regexs: Vec<Regex>,
-    tokens: Vec<Token>,
     cur_indent: usize,
+    // leading whitespace of every open block, outermost first
+    indents: Vec<String>,
+    // kind of the last token given out, None once Eof was
+    last_kind: Option<TokenKind>,
+    // the Eof token, held back behind a final Newline
+    pending_eof: Option<Token>,
+    done: bool,
+    chunk: Option<usize>,
 }
 
 impl Lexer {
@@ -21,67 +28,103 @@ impl Lexer {
             input,
             position: (0, 0),
             idx: 0,
-            ignored_tokens: vec![TokenKind::Space, TokenKind::Comment, TokenKind::Indent],
+            ignored_tokens: vec![TokenKind::Space, TokenKind::Comment],
             regexs: TokenKind::iter()
                 .map(|kind| Regex::new(format!(r"^{}", kind.regex()).as_str()).unwrap())
                 .collect(),
-            tokens: Vec::new(),
             cur_indent: 0,
+            indents: vec![String::new()],
+            last_kind: None,
+            pending_eof: None,
+            done: false,
+            chunk: None,
         };
         lexer
     }
 
+    // stamps the tokens with the chunk the input is registered as
+    pub fn chunk(mut self, chunk: usize) -> Lexer {
+        self.chunk = Some(chunk);
+        self
+    }
+
     pub fn tokenize(&mut self) -> Result<Vec<Token>, Traceback> {
-        while self.tokens.last().map(|t| t.kind) != Some(TokenKind::Eof) {
-            let res = self.step();
-            if let Some(mut token) = res {
-                token.pos = Some(self.position);
-                token.indent = self.cur_indent;
-                if !self.ignored_tokens.contains(&token.kind) {
-                    self.tokens.push(token.clone());
-                }
-                match token.kind {
-                    TokenKind::Newline => {
-                        self.cur_indent = 0;
-                    }
-                    TokenKind::Indent => {
-                        self.cur_indent += 1;
-                    }
-                    _ => {}
-                }
-            } else {
-                return Err(Traceback {
-                    pos: self.position,
-                    message: Some("invalid syntax".to_string()),
-                    ..Default::default()
-                });
-            }
+        self.by_ref().collect()
+    }
+
+    // consumes the leading whitespace of a line and sets the
+    // indentation level of its tokens, blank lines keep the previous one
+    fn indentation(&mut self) -> Result<(), Traceback> {
+        let found: String = self.input[self.idx..]
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect();
+        self.idx += found.len();
+        self.position.0 += found.len();
+
+        if matches!(
+            self.input[self.idx..].chars().next(),
+            None | Some('\n') | Some('\r') | Some('#')
+        ) {
+            return Ok(());
         }
 
-        if self.tokens.len() > 1 && self.tokens[self.tokens.len() - 2].kind != TokenKind::Newline {
-            self.tokens.insert(
-                self.tokens.len() - 1,
-                Token::from_token_kind(TokenKind::Newline),
-            )
+        let expected = self.indents.last().cloned().unwrap_or_default();
+        if found == expected {
+            return Ok(());
         }
 
-        return Ok(self.tokens.clone());
+        // the block's first indented line decides between tabs and spaces
+        let block_char = expected.chars().next().or(found.chars().next());
+        if found.chars().any(|c| Some(c) != block_char) {
+            return Err(self.indentation_error(
+                TracebackKind::TabError,
+                format!(
+                    "inconsistent use of tabs and spaces in indentation, expected {} but found {}",
+                    describe_indent(&expected),
+                    describe_indent(&found)
+                ),
+            ));
+        }
+
+        if found.starts_with(&expected) {
+            self.indents.push(found);
+        } else if let Some(level) = self.indents.iter().position(|indent| *indent == found) {
+            self.indents.truncate(level + 1);
+        } else {
+            return Err(self.indentation_error(
+                TracebackKind::IndentationError,
+                format!(
+                    "unindent does not match any outer indentation level, expected {} but found {}",
+                    self.indents
+                        .iter()
+                        .map(|indent| describe_indent(indent))
+                        .collect::<Vec<_>>()
+                        .join(" or "),
+                    describe_indent(&found)
+                ),
+            ));
+        }
+
+        self.cur_indent = self.indents.len() - 1;
+        Ok(())
+    }
+
+    fn indentation_error(&self, tipe: TracebackKind, message: String) -> Traceback {
+        Traceback {
+            pos: self.position,
+            message: Some(message),
+            tipe,
+            ..Default::default()
+        }
     }
 
     fn step(&mut self) -> Option<Token> {
         let mut matches: Vec<(TokenKind, String)> = Vec::new();
 
         for (kind, re) in TokenKind::iter().zip(self.regexs.iter()) {
-            if re.is_match(&self.input[self.idx..]) {
-                let value = re
-                    .captures(&self.input[self.idx..])
-                    .unwrap()
-                    .get(0)
-                    .unwrap()
-                    .as_str()
-                    .to_string();
-
-                matches.push((kind, value));
+            if let Some(found) = re.find(&self.input[self.idx..]) {
+                matches.push((kind, found.as_str().to_string()));
             }
         }
 
@@ -89,32 +132,22 @@ impl Lexer {
             matches.retain(|(kind, _)| *kind != TokenKind::Space);
         }
 
-        if matches.len() == 0 {
-            if self.idx == self.input.len() {
+        let max_match = match matches.iter().map(|(_, v)| v.len()).max() {
+            Some(max_match) => max_match,
+            None if self.idx == self.input.len() => {
                 return Some(Token::from_token_kind(TokenKind::Eof));
-            } else {
-                return None;
             }
-        }
-
-        let max_match = matches.iter().map(|(_, v)| v.len()).max().unwrap();
+            None => return None,
+        };
 
-        matches = matches
-            .iter()
-            .cloned()
-            .filter(|(_, value)| value.len() == max_match)
-            .collect::<Vec<_>>();
+        matches.retain(|(_, value)| value.len() == max_match);
 
         if matches.len() > 1 {
             // If there is an identifier, it should be the only match
             matches.retain(|(kind, _)| kind != &TokenKind::Identifier);
         }
 
-        let (kind, mut value) = matches
-            .iter()
-            .max_by_key(|(_, value)| value.len())
-            .unwrap()
-            .clone();
+        let (kind, mut value) = matches.pop()?;
 
         self.idx += value.len();
         self.position.0 += value.len();
@@ -136,23 +169,95 @@ impl Lexer {
     }
 }
 
+fn describe_indent(indent: &str) -> String {
+    let plural = |count: usize, name: &str| {
+        format!("{} {}{}", count, name, if count > 1 { "s" } else { "" })
+    };
+    let tabs = indent.chars().filter(|c| *c == '\t').count();
+    let spaces = indent.len() - tabs;
+    match (tabs, spaces) {
+        (0, 0) => "no indentation".to_string(),
+        (0, spaces) => plural(spaces, "space"),
+        (tabs, 0) => plural(tabs, "tab"),
+        (tabs, spaces) => format!("{} and {}", plural(tabs, "tab"), plural(spaces, "space")),
+    }
+}
+
+impl Iterator for Lexer {
+    type Item = Result<Token, Traceback>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(eof) = self.pending_eof.take() {
+            self.done = true;
+            return Some(Ok(eof));
+        }
+
+        while !self.done {
+            if self.position.0 == 0 {
+                if let Err(traceback) = self.indentation() {
+                    self.done = true;
+                    return Some(Err(traceback));
+                }
+            }
+
+            let res = self.step();
+            if let Some(mut token) = res {
+                token.pos = Some(self.position);
+                token.indent = self.cur_indent;
+                token.chunk = self.chunk;
+
+                if self.ignored_tokens.contains(&token.kind) {
+                    continue;
+                }
+
+                if token.kind == TokenKind::Eof {
+                    // every program ends with a newline
+                    if self.last_kind.is_some() && self.last_kind != Some(TokenKind::Newline) {
+                        self.pending_eof = Some(token);
+                        self.last_kind = Some(TokenKind::Newline);
+                        let mut newline = Token::from_token_kind(TokenKind::Newline);
+                        newline.pos = Some(self.position);
+                        newline.chunk = self.chunk;
+                        return Some(Ok(newline));
+                    }
+                    self.done = true;
+                }
+
+                self.last_kind = Some(token.kind);
+                return Some(Ok(token));
+            } else {
+                self.done = true;
+                return Some(Err(Traceback {
+                    pos: self.position,
+                    message: Some("invalid syntax".to_string()),
+                    tipe: TracebackKind::SyntaxError,
+                    ..Default::default()
+                }));
+            }
+        }
+
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::myton::errors::report_trace;
+    use crate::myton::errors::{report_trace, Style};
 
     use super::*;
     use TokenKind::*;
 
     fn test_lexer_case(input: &str, expected: Vec<TokenKind>) {
-        let mut lexer = Lexer::new(input.to_string());
-        let lex_res = lexer.tokenize();
+        let lexer = Lexer::new(input.to_string());
+        let lex_res = lexer.collect::<Result<Vec<_>, _>>();
         assert!(
             lex_res.is_ok(),
             "Lexer failed to tokenize {}, \nerror: {:?}",
             input,
-            report_trace(lex_res.err().unwrap())
+            report_trace(lex_res.err().unwrap(), Style::PLAIN)
         );
         let tokens = lex_res.ok().unwrap();
+        assert_eq!(tokens.len(), expected.len(), "{:?}", tokens);
 
         let diff = tokens
             .iter()
@@ -174,6 +279,12 @@ mod tests {
     #[test]
     fn test_lexer() {
         test_lexer_case("1+2", vec![Number, Plus, Number, Newline, Eof]);
+        test_lexer_case(
+            "2**-x*3",
+            vec![
+                Number, StarStar, Minus, Identifier, Star, Number, Newline, Eof,
+            ],
+        );
 
         test_lexer_case(
             "(1*2) + 3 - 4/1.2",
@@ -256,6 +367,157 @@ mod tests {
                 Newline,
                 Eof,
             ],
-        )
+        );
+
+        test_lexer_case(
+            "{\"a\": {}}",
+            vec![
+                LeftBrace, Stringue, Colon, LeftBrace, RightBrace, RightBrace, Newline, Eof,
+            ],
+        );
+
+        test_lexer_case(
+            "if (n := 10) > 5:",
+            vec![
+                If, LeftParen, Identifier, ColonEqual, Number, RightParen, Greater, Number, Colon,
+                Newline, Eof,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_keywords_next_to_punctuation() {
+        // a keyword ends where the word does, whatever follows it
+        test_lexer_case(
+            "a and(b)",
+            vec![
+                Identifier, And, LeftParen, Identifier, RightParen, Newline, Eof,
+            ],
+        );
+        test_lexer_case(
+            "a or[b]",
+            vec![
+                Identifier,
+                Or,
+                LeftBracket,
+                Identifier,
+                RightBracket,
+                Newline,
+                Eof,
+            ],
+        );
+        test_lexer_case(
+            "not(x)",
+            vec![Not, LeftParen, Identifier, RightParen, Newline, Eof],
+        );
+        test_lexer_case("not-1", vec![Not, Minus, Number, Newline, Eof]);
+        test_lexer_case(
+            "x in[1]",
+            vec![
+                Identifier,
+                In,
+                LeftBracket,
+                Number,
+                RightBracket,
+                Newline,
+                Eof,
+            ],
+        );
+        test_lexer_case(
+            "print\"a\"or\"b\"",
+            vec![Print, Stringue, Or, Stringue, Newline, Eof],
+        );
+        test_lexer_case("return-x", vec![Return, Minus, Identifier, Newline, Eof]);
+        test_lexer_case("1and 2", vec![Number, And, Number, Newline, Eof]);
+        test_lexer_case(
+            "is(None)",
+            vec![Is, LeftParen, Nil, RightParen, Newline, Eof],
+        );
+        test_lexer_case(
+            "x not  in(y)",
+            vec![
+                Identifier, NotIn, LeftParen, Identifier, RightParen, Newline, Eof,
+            ],
+        );
+        test_lexer_case("not inside", vec![Not, Identifier, Newline, Eof]);
+
+        // and a word only containing a keyword is an identifier
+        for word in [
+            "xin", "inx", "android", "order", "nothing", "not_x", "island", "self_ish", "Nonesuch",
+            "Truer", "if_", "_in",
+        ] {
+            test_lexer_case(word, vec![Identifier, Newline, Eof]);
+        }
+        test_lexer_case(
+            "xin [1]",
+            vec![Identifier, LeftBracket, Number, RightBracket, Newline, Eof],
+        );
+    }
+
+    fn indents(input: &str) -> Vec<usize> {
+        Lexer::new(input.to_string())
+            .map(|token| token.unwrap())
+            .filter(|token| token.kind == Identifier)
+            .map(|token| token.indent)
+            .collect()
+    }
+
+    #[test]
+    fn test_indentation_levels() {
+        assert_eq!(
+            indents("a\n    b\n        c\n    d\ne"),
+            vec![0, 1, 2, 1, 0]
+        );
+        assert_eq!(indents("a\n\tb\n\t\tc\n\n\td"), vec![0, 1, 2, 1]);
+        // blank and comment lines don't count
+        assert_eq!(indents("a\n  b\n\n# x\n\t\n  c"), vec![0, 1, 1]);
+    }
+
+    #[test]
+    fn test_mixed_indentation() {
+        let error = |input: &str| {
+            Lexer::new(input.to_string())
+                .find_map(|token| token.err())
+                .unwrap()
+        };
+
+        let tab_error = error("a\n  b\n\tc");
+        assert!(matches!(tab_error.tipe, TracebackKind::TabError));
+        assert_eq!(tab_error.pos, (1, 2));
+
+        let tab_error = error("a\n\tb\n\t  c");
+        assert!(matches!(tab_error.tipe, TracebackKind::TabError));
+
+        let indentation_error = error("a\n    b\n  c");
+        assert!(matches!(
+            indentation_error.tipe,
+            TracebackKind::IndentationError
+        ));
+    }
+
+    #[test]
+    fn test_lexer_is_lazy() {
+        // the invalid character is never reached
+        let mut lexer = Lexer::new("a = 1\nb = °".to_string());
+        let kinds = lexer
+            .by_ref()
+            .take(3)
+            .map(|token| token.unwrap().kind)
+            .collect::<Vec<_>>();
+        assert_eq!(kinds, vec![Identifier, Equal, Number]);
+
+        let rest = lexer.collect::<Vec<_>>();
+        assert!(rest.last().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_lexer_stops_after_eof_or_error() {
+        let mut lexer = Lexer::new("1".to_string());
+        assert_eq!(lexer.by_ref().count(), 3);
+        assert!(lexer.next().is_none());
+
+        let mut lexer = Lexer::new("°".to_string());
+        assert!(lexer.next().unwrap().is_err());
+        assert!(lexer.next().is_none());
     }
 }
@@ -6,6 +6,8 @@ pub struct Token {
     pub value: String,
     pub pos: Option<(usize, usize)>,
     pub indent: usize,
+    // the source it was read from, see SourceMap
+    pub chunk: Option<usize>,
 }
 
 #[derive(Debug, EnumIter, Clone, Copy, PartialEq, Eq)]
@@ -14,13 +16,17 @@ pub enum TokenKind {
     RightParen,
     LeftBracket,
     RightBracket,
+    LeftBrace,
+    RightBrace,
     Comma,
     Dot,
     Plus,
     Minus,
     Slash,
     Star,
+    StarStar,
     Colon,
+    ColonEqual,
     Identifier,
     Stringue,
     Number,
@@ -38,6 +44,7 @@ pub enum TokenKind {
 
     And,
     Class,
+    Elif,
     Else,
     False,
     Def,
@@ -57,10 +64,16 @@ pub enum TokenKind {
     Continue,
     Global,
     Nonlocal,
+    Try,
+    Except,
+    As,
+    Is,
+    Raise,
+    Not,
+    NotIn,
 
     Comment,
     Space,
-    Indent,
     Newline,
 
     Eof,
@@ -79,6 +92,7 @@ impl Default for Token {
             value: "".to_string(),
             pos: None,
             indent: 0,
+            chunk: None,
         }
     }
 }
@@ -90,12 +104,16 @@ impl TokenKind {
             TokenKind::Plus => r"\+",
             TokenKind::Minus => r"-",
             TokenKind::Star => r"\*",
+            TokenKind::StarStar => r"\*\*",
             TokenKind::Slash => r"/",
             TokenKind::LeftParen => r"\(",
             TokenKind::RightParen => r"\)",
             TokenKind::LeftBracket => r"\[",
             TokenKind::RightBracket => r"\]",
+            TokenKind::LeftBrace => r"\{",
+            TokenKind::RightBrace => r"\}",
             TokenKind::Colon => r":",
+            TokenKind::ColonEqual => r":=",
             TokenKind::Comment => r"(?m)#.*$",
             TokenKind::Eof => r"^$",
             TokenKind::Bang => r"!",
@@ -113,11 +131,11 @@ impl TokenKind {
             TokenKind::Identifier => r"[a-zA-Z_][a-zA-Z0-9_]*",
             TokenKind::Stringue => r#""[^"]*""#,
             TokenKind::Space => r"[ \t]+",
-            TokenKind::Indent => r"[ ]{2}",
             TokenKind::Percent => r"%",
 
             TokenKind::And => r"and",
             TokenKind::Class => r"class",
+            TokenKind::Elif => r"elif",
             TokenKind::Else => r"else",
             TokenKind::False => r"False",
             TokenKind::Def => r"def",
@@ -137,6 +155,13 @@ impl TokenKind {
             TokenKind::Continue => r"continue",
             TokenKind::Global => r"global",
             TokenKind::Nonlocal => r"nonlocal",
+            TokenKind::Try => r"try",
+            TokenKind::Except => r"except",
+            TokenKind::As => r"as",
+            TokenKind::Is => r"is",
+            TokenKind::Raise => r"raise",
+            TokenKind::Not => r"not",
+            TokenKind::NotIn => r"not[ \t]+in\b",
         }
     }
 }
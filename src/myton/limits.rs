@@ -0,0 +1,57 @@
+use std::cell::Cell;
+use std::mem::size_of;
+use std::rc::Rc;
+
+use super::environment::Env;
+use super::traceback::{Traceback, TracebackKind};
+use super::types::DynValue;
+
+// the largest string or list a script can make, in bytes,
+// see Interpreter::set_max_object_size
+pub const DEFAULT_MAX_SIZE: usize = 256 * 1024 * 1024;
+
+// how many calls a script can nest, see Interpreter::set_max_recursion
+pub const DEFAULT_MAX_RECURSION: usize = 200;
+
+// what a list of len elements takes, not counting what they point to
+pub fn list_size(len: usize) -> Option<usize> {
+    len.checked_mul(size_of::<DynValue>())
+}
+
+// a MemoryError unless the size is known and within the limit,
+// raised before anything is allocated
+pub fn check_size(env: &Env, size: Option<usize>, message: &str) -> Result<(), Traceback> {
+    match size {
+        Some(size) if size <= env.borrow().max_size() => Ok(()),
+        _ => Err(Traceback::from_exception(
+            TracebackKind::MemoryError,
+            message,
+        )),
+    }
+}
+
+// a call being run, counted in the depth until it returns
+pub struct Call {
+    depth: Rc<Cell<usize>>,
+}
+
+impl Drop for Call {
+    fn drop(&mut self) {
+        self.depth.set(self.depth.get() - 1);
+    }
+}
+
+// a RecursionError instead of running one call too many,
+// before the stack of the host runs out
+pub fn enter_call(env: &Env) -> Result<Call, Traceback> {
+    let env = env.borrow();
+    let depth = env.call_depth();
+    if depth.get() >= env.max_recursion() {
+        return Err(Traceback::from_exception(
+            TracebackKind::RecursionError,
+            "maximum recursion depth exceeded",
+        ));
+    }
+    depth.set(depth.get() + 1);
+    Ok(Call { depth })
+}
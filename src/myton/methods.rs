@@ -0,0 +1,905 @@
+use std::ops::RangeInclusive;
+
+use super::class::get_from_instance;
+use super::environment::Env;
+use super::format::format_template;
+use super::functions::{Kwargs, MethodFn, NativeFunction, NativeMethod, UnboundMethod};
+use super::hashable::Dict;
+use super::limits::{check_size, list_size};
+use super::traceback::{Exception, Traceback, TracebackKind};
+use super::types::{DynValue, TypeKind};
+
+type MethodResult = Result<DynValue, Traceback>;
+type KeywordMethodFn = fn(&Env, &DynValue, Vec<DynValue>, Kwargs) -> MethodResult;
+
+// built-in methods of the primitive types, by receiver type
+fn methods_of(tipe: &TypeKind) -> Vec<(&'static str, MethodFn, RangeInclusive<usize>)> {
+    match tipe {
+        TypeKind::Stringue => vec![
+            ("upper", str_upper, 0..=0),
+            ("lower", str_lower, 0..=0),
+            ("strip", str_strip, 0..=1),
+            ("lstrip", str_lstrip, 0..=1),
+            ("rstrip", str_rstrip, 0..=1),
+            ("split", str_split, 1..=1),
+            ("replace", str_replace, 2..=2),
+            ("startswith", str_startswith, 1..=1),
+            ("endswith", str_endswith, 1..=1),
+            ("join", str_join, 1..=1),
+            ("isdigit", str_isdigit, 0..=0),
+            ("isalpha", str_isalpha, 0..=0),
+            ("isspace", str_isspace, 0..=0),
+            ("count", str_count, 1..=1),
+            ("index", str_index, 1..=1),
+            ("find", str_find, 1..=1),
+            ("rfind", str_rfind, 1..=1),
+            ("partition", str_partition, 1..=1),
+            ("rpartition", str_rpartition, 1..=1),
+            ("ljust", str_ljust, 1..=1),
+            ("rjust", str_rjust, 1..=1),
+            ("zfill", str_zfill, 1..=1),
+            ("splitlines", str_splitlines, 0..=0),
+            ("title", str_title, 0..=0),
+            ("capitalize", str_capitalize, 0..=0),
+        ],
+        TypeKind::List => vec![
+            ("append", list_append, 1..=1),
+            ("pop", list_pop, 0..=0),
+            ("extend", list_extend, 1..=1),
+            ("insert", list_insert, 2..=2),
+            ("remove", list_remove, 1..=1),
+            ("index", list_index, 1..=1),
+            ("count", list_count, 1..=1),
+            ("reverse", list_reverse, 0..=0),
+            ("copy", list_copy, 0..=0),
+            ("clear", list_clear, 0..=0),
+        ],
+        _ => vec![],
+    }
+}
+
+// the built-in methods taking any number of arguments and keywords
+fn keyword_methods_of(tipe: &TypeKind) -> Vec<(&'static str, KeywordMethodFn)> {
+    match tipe {
+        TypeKind::Stringue => vec![("format", str_format)],
+        _ => vec![],
+    }
+}
+
+fn method_names(tipe: &TypeKind) -> Vec<String> {
+    let names = methods_of(tipe).into_iter().map(|(name, _, _)| name);
+    let keyword_names = keyword_methods_of(tipe).into_iter().map(|(name, _)| name);
+    names
+        .chain(keyword_names)
+        .map(|name| name.to_string())
+        .collect()
+}
+
+// a keyword method bound to its receiver
+fn keyword_method(object: &DynValue, name: &str) -> Option<DynValue> {
+    let (method_name, func) = keyword_methods_of(&object.tipe)
+        .into_iter()
+        .find(|(method_name, _)| *method_name == name)?;
+    let receiver = object.clone();
+    let method = NativeFunction {
+        max_args: usize::MAX,
+        ..NativeFunction::with_keywords(
+            move |env, args, kwargs| func(env, &receiver, args, kwargs),
+            0,
+        )
+    };
+    Some(DynValue::from_native_function(
+        method,
+        method_name.to_string(),
+    ))
+}
+
+// looks up an attribute on any value: fields and methods of
+// instances, methods of classes, and built-in methods otherwise
+pub fn get_attribute(object: &DynValue, name: &str) -> Option<DynValue> {
+    match object.tipe {
+        TypeKind::Instance if name == "__dict__" => {
+            Some(object.as_instance()?.borrow().fields_dict())
+        }
+        TypeKind::Instance => get_from_instance(&object.as_instance()?, name),
+        TypeKind::Class => {
+            let class = object.as_class()?;
+            if let Some(value) = class.find_attribute(name) {
+                return Some(value);
+            }
+            let method = class.find_method(name)?.clone();
+            let unbound = UnboundMethod { class, method };
+            Some(DynValue::from_unbound_method(unbound, name.to_string()))
+        }
+        TypeKind::Exception => exception_attribute(&object.as_exception()?, name),
+        TypeKind::Module => object.as_module()?.get(name),
+        _ => methods_of(&object.tipe)
+            .into_iter()
+            .find(|(method_name, _, _)| *method_name == name)
+            .map(|(method_name, func, arity)| {
+                let method = NativeMethod {
+                    receiver: object.clone(),
+                    func,
+                    nb_args: *arity.start(),
+                    max_args: *arity.end(),
+                };
+                DynValue::from_native_method(method, method_name.to_string())
+            })
+            .or_else(|| keyword_method(object, name)),
+    }
+}
+
+const EXCEPTION_ATTRIBUTES: [&str; 3] = ["line", "message", "traceback"];
+
+fn exception_attribute(exception: &Exception, name: &str) -> Option<DynValue> {
+    match name {
+        "message" => Some(DynValue::from(
+            exception.traceback.message.clone().unwrap_or_default(),
+        )),
+        "line" => Some(DynValue::from(exception.traceback.line() as f64)),
+        "traceback" => Some(
+            exception
+                .frames()
+                .iter()
+                .map(|frame| DynValue::from(frame.to_string()))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+// what dir() lists: the fields and methods of instances, the
+// methods of classes, and the built-in methods otherwise
+pub fn attribute_names(object: &DynValue) -> Vec<String> {
+    let mut names = match object.tipe {
+        TypeKind::Instance => {
+            let instance = object.as_instance().unwrap();
+            let instance = instance.borrow();
+            let mut names: Vec<String> = instance.fields.keys().cloned().collect();
+            names.push("__dict__".to_string());
+            names.extend(instance.class.member_names());
+            names
+        }
+        TypeKind::Class => object.as_class().unwrap().member_names(),
+        TypeKind::Module => object.as_module().unwrap().attribute_names(),
+        TypeKind::Exception => EXCEPTION_ATTRIBUTES
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+        _ => method_names(&object.tipe),
+    };
+    names.sort();
+    names.dedup();
+    names
+}
+
+// only instances can get new attributes
+pub fn set_attribute(object: &DynValue, name: &str, value: DynValue) -> bool {
+    match object.as_instance() {
+        Some(instance) => {
+            instance.borrow_mut().set(name.to_string(), value);
+            true
+        }
+        None => false,
+    }
+}
+
+pub fn attribute_error(object: &DynValue, name: &str) -> String {
+    match object.tipe {
+        TypeKind::Instance => format!(
+            "'{}' object has no attribute '{}'",
+            object.as_instance().unwrap().borrow().class.name,
+            name
+        ),
+        TypeKind::Class => format!(
+            "type object '{}' has no attribute '{}'",
+            object.as_class().unwrap().name,
+            name
+        ),
+        TypeKind::Module => format!(
+            "module '{}' has no attribute '{}'",
+            object.as_module().unwrap().name,
+            name
+        ),
+        _ => format!("'{}' object has no attribute '{}'", object.tipe, name),
+    }
+}
+
+// the usual reason for poking at None, hinted by every
+// error about an attribute or an element of it
+pub const NONE_HINT: &str = "a function may have returned None";
+
+// the message of an error about using the object, hinted if it is None
+pub fn none_hinted(object: &DynValue, message: String) -> String {
+    if object.is_nil() {
+        format!("{} — {}", message, NONE_HINT)
+    } else {
+        message
+    }
+}
+
+// a hint for a missing attribute: the nearby method names of
+// strings and lists, or the usual reason for poking at None
+pub fn attribute_hint(object: &DynValue, name: &str) -> Option<String> {
+    if object.is_nil() {
+        return Some(NONE_HINT.to_string());
+    }
+    let known: Vec<String> = match object.as_module() {
+        Some(module) => module.attribute_names(),
+        None => method_names(&object.tipe),
+    };
+    let mut candidates: Vec<(usize, &str)> = known
+        .iter()
+        .map(|method_name| (levenshtein(name, method_name), method_name.as_str()))
+        .filter(|(distance, _)| *distance <= 2)
+        .collect();
+    candidates.sort();
+    let names: Vec<String> = candidates
+        .iter()
+        .map(|(_, method_name)| format!("'{}'", method_name))
+        .collect();
+    match names.as_slice() {
+        [] => None,
+        [single] => Some(format!("did you mean {}?", single)),
+        [rest @ .., last] => Some(format!("did you mean {} or {}?", rest.join(", "), last)),
+    }
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn expect_type(method: &str, value: &DynValue, tipe: TypeKind) -> Result<(), Traceback> {
+    if value.tipe == tipe {
+        Ok(())
+    } else {
+        Err(Traceback::from_exception(
+            TracebackKind::TypeError,
+            &format!("{}: expected a {}, got '{}'", method, tipe, value.tipe),
+        ))
+    }
+}
+
+fn str_upper(_: &Env, receiver: &DynValue, _: Vec<DynValue>) -> MethodResult {
+    Ok(DynValue::from(receiver.as_string().to_uppercase()))
+}
+
+fn str_format(env: &Env, receiver: &DynValue, args: Vec<DynValue>, kwargs: Kwargs) -> MethodResult {
+    Ok(DynValue::from(format_template(
+        env,
+        &receiver.as_string(),
+        &args,
+        &kwargs,
+    )?))
+}
+
+fn str_lower(_: &Env, receiver: &DynValue, _: Vec<DynValue>) -> MethodResult {
+    Ok(DynValue::from(receiver.as_string().to_lowercase()))
+}
+
+// the characters strip removes: whitespace, or the ones given
+fn strip_chars(method: &str, args: &[DynValue]) -> Result<Option<Vec<char>>, Traceback> {
+    match args.first().filter(|chars| chars.tipe != TypeKind::Nil) {
+        Some(chars) => {
+            expect_type(method, chars, TypeKind::Stringue)?;
+            Ok(Some(chars.as_string().chars().collect()))
+        }
+        None => Ok(None),
+    }
+}
+
+fn strip(
+    method: &str,
+    receiver: &DynValue,
+    args: &[DynValue],
+    start: bool,
+    end: bool,
+) -> MethodResult {
+    let chars = strip_chars(method, args)?;
+    let stripped = |c: char| match &chars {
+        Some(chars) => chars.contains(&c),
+        None => c.is_whitespace(),
+    };
+    let string = receiver.as_string();
+    let mut result = string.as_str();
+    if start {
+        result = result.trim_start_matches(stripped);
+    }
+    if end {
+        result = result.trim_end_matches(stripped);
+    }
+    Ok(DynValue::from(result.to_string()))
+}
+
+fn str_strip(_: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    strip("strip", receiver, &args, true, true)
+}
+
+fn str_lstrip(_: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    strip("lstrip", receiver, &args, true, false)
+}
+
+fn str_rstrip(_: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    strip("rstrip", receiver, &args, false, true)
+}
+
+fn str_split(_: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    expect_type("split", &args[0], TypeKind::Stringue)?;
+    let separator = args[0].as_string();
+    if separator.is_empty() {
+        return Err(Traceback::from_exception(
+            TracebackKind::ValueError,
+            "split: empty separator",
+        ));
+    }
+    Ok(DynValue::from(
+        receiver
+            .as_string()
+            .split(separator.as_str())
+            .map(|part| DynValue::from(part.to_string()))
+            .collect::<Vec<_>>(),
+    ))
+}
+
+fn str_replace(_: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    expect_type("replace", &args[0], TypeKind::Stringue)?;
+    expect_type("replace", &args[1], TypeKind::Stringue)?;
+    Ok(DynValue::from(
+        receiver
+            .as_string()
+            .replace(&args[0].as_string(), &args[1].as_string()),
+    ))
+}
+
+// a string, or a tuple (or list) of strings any of which may match
+fn affixes(method: &str, value: &DynValue) -> Result<Vec<String>, Traceback> {
+    match value.tipe {
+        TypeKind::Stringue => Ok(vec![value.as_string()]),
+        TypeKind::Tuple | TypeKind::List => {
+            let items = value.as_sequence().unwrap_or_default();
+            for item in &items {
+                expect_type(method, item, TypeKind::Stringue)?;
+            }
+            Ok(items.iter().map(DynValue::as_string).collect())
+        }
+        _ => Err(Traceback::from_exception(
+            TracebackKind::TypeError,
+            &format!(
+                "{}: expected a str or a tuple of str, got '{}'",
+                method, value.tipe
+            ),
+        )),
+    }
+}
+
+fn str_startswith(_: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    let string = receiver.as_string();
+    Ok(DynValue::from(
+        affixes("startswith", &args[0])?
+            .iter()
+            .any(|prefix| string.starts_with(prefix.as_str())),
+    ))
+}
+
+fn str_endswith(_: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    let string = receiver.as_string();
+    Ok(DynValue::from(
+        affixes("endswith", &args[0])?
+            .iter()
+            .any(|suffix| string.ends_with(suffix.as_str())),
+    ))
+}
+
+fn str_join(_: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    expect_type("join", &args[0], TypeKind::List)?;
+    let mut parts = Vec::new();
+    for (i, item) in args[0].as_list().unwrap_or_default().iter().enumerate() {
+        if item.tipe != TypeKind::Stringue {
+            return Err(Traceback::from_exception(
+                TracebackKind::TypeError,
+                &format!(
+                    "join: sequence item {}: expected a str, got '{}'",
+                    i, item.tipe
+                ),
+            ));
+        }
+        parts.push(item.as_string());
+    }
+    Ok(DynValue::from(parts.join(&receiver.as_string())))
+}
+
+// python's str.is* are all false on the empty string
+fn all_chars(receiver: &DynValue, predicate: impl Fn(char) -> bool) -> bool {
+    let string = receiver.as_string();
+    !string.is_empty() && string.chars().all(predicate)
+}
+
+fn str_isdigit(_: &Env, receiver: &DynValue, _: Vec<DynValue>) -> MethodResult {
+    Ok(DynValue::from(all_chars(receiver, char::is_numeric)))
+}
+
+fn str_isalpha(_: &Env, receiver: &DynValue, _: Vec<DynValue>) -> MethodResult {
+    Ok(DynValue::from(all_chars(receiver, char::is_alphabetic)))
+}
+
+fn str_isspace(_: &Env, receiver: &DynValue, _: Vec<DynValue>) -> MethodResult {
+    Ok(DynValue::from(all_chars(receiver, char::is_whitespace)))
+}
+
+fn str_count(_: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    expect_type("count", &args[0], TypeKind::Stringue)?;
+    let string = receiver.as_string();
+    let sub = args[0].as_string();
+    // the empty string is found between every character
+    let count = if sub.is_empty() {
+        string.chars().count() + 1
+    } else {
+        string.matches(sub.as_str()).count()
+    };
+    Ok(DynValue::from(count))
+}
+
+fn str_index(_: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    expect_type("index", &args[0], TypeKind::Stringue)?;
+    let string = receiver.as_string();
+    match string.find(args[0].as_string().as_str()) {
+        // in characters, not bytes
+        Some(byte_index) => Ok(DynValue::from(string[..byte_index].chars().count())),
+        None => Err(Traceback::from_exception(
+            TracebackKind::ValueError,
+            "substring not found",
+        )),
+    }
+}
+
+// in characters, -1 when not found
+fn find(
+    method: &str,
+    receiver: &DynValue,
+    args: &[DynValue],
+    search: fn(&str, &str) -> Option<usize>,
+) -> MethodResult {
+    expect_type(method, &args[0], TypeKind::Stringue)?;
+    let string = receiver.as_string();
+    Ok(DynValue::from(
+        match search(&string, &args[0].as_string()) {
+            Some(byte_index) => string[..byte_index].chars().count() as f64,
+            None => -1.0,
+        },
+    ))
+}
+
+fn str_find(_: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    find("find", receiver, &args, |string, sub| string.find(sub))
+}
+
+fn str_rfind(_: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    find("rfind", receiver, &args, |string, sub| string.rfind(sub))
+}
+
+// [before, separator, after], split at the first separator found by
+// search, or else the string whole on the side given by missing
+fn partition(
+    method: &str,
+    receiver: &DynValue,
+    args: &[DynValue],
+    search: fn(&str, &str) -> Option<usize>,
+    missing: fn(String) -> [String; 3],
+) -> MethodResult {
+    expect_type(method, &args[0], TypeKind::Stringue)?;
+    let separator = args[0].as_string();
+    if separator.is_empty() {
+        return Err(Traceback::from_exception(
+            TracebackKind::ValueError,
+            &format!("{}: empty separator", method),
+        ));
+    }
+    let string = receiver.as_string();
+    let parts = match search(&string, &separator) {
+        Some(index) => [
+            string[..index].to_string(),
+            separator.clone(),
+            string[index + separator.len()..].to_string(),
+        ],
+        None => missing(string),
+    };
+    Ok(DynValue::from(
+        parts.into_iter().map(DynValue::from).collect::<Vec<_>>(),
+    ))
+}
+
+fn str_partition(_: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    partition(
+        "partition",
+        receiver,
+        &args,
+        |string, separator| string.find(separator),
+        |string| [string, String::new(), String::new()],
+    )
+}
+
+fn str_rpartition(_: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    partition(
+        "rpartition",
+        receiver,
+        &args,
+        |string, separator| string.rfind(separator),
+        |string| [String::new(), String::new(), string],
+    )
+}
+
+// the missing characters to reach the given width, a MemoryError
+// when the padded string wouldn't fit in the size limit
+fn padding(
+    env: &Env,
+    method: &str,
+    receiver: &DynValue,
+    width: &DynValue,
+) -> Result<usize, Traceback> {
+    expect_type(method, width, TypeKind::Number)?;
+    let string = receiver.as_string();
+    let padding = (width.as_number().max(0.0) as usize).saturating_sub(string.chars().count());
+    check_size(env, string.len().checked_add(padding), "str too large")?;
+    Ok(padding)
+}
+
+fn str_ljust(env: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    let padding = padding(env, "ljust", receiver, &args[0])?;
+    Ok(DynValue::from(receiver.as_string() + &" ".repeat(padding)))
+}
+
+fn str_rjust(env: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    let padding = padding(env, "rjust", receiver, &args[0])?;
+    Ok(DynValue::from(" ".repeat(padding) + &receiver.as_string()))
+}
+
+fn str_zfill(env: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    let padding = padding(env, "zfill", receiver, &args[0])?;
+    let string = receiver.as_string();
+    // the zeros go after the sign
+    let (sign, digits) = match string.chars().next() {
+        Some(sign @ ('+' | '-')) => string.split_at(sign.len_utf8()),
+        _ => ("", string.as_str()),
+    };
+    Ok(DynValue::from(format!(
+        "{}{}{}",
+        sign,
+        "0".repeat(padding),
+        digits
+    )))
+}
+
+fn str_splitlines(_: &Env, receiver: &DynValue, _: Vec<DynValue>) -> MethodResult {
+    let string = receiver.as_string();
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut chars = string.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' if chars.peek() == Some(&'\n') => {
+                chars.next();
+                lines.push(std::mem::take(&mut line));
+            }
+            '\n' | '\r' | '\x0b' | '\x0c' | '\x1c' | '\x1d' | '\x1e' | '\u{85}' | '\u{2028}'
+            | '\u{2029}' => lines.push(std::mem::take(&mut line)),
+            _ => line.push(c),
+        }
+    }
+    // a last line break doesn't start a new line
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    Ok(lines.into_iter().map(DynValue::from).collect())
+}
+
+fn str_title(_: &Env, receiver: &DynValue, _: Vec<DynValue>) -> MethodResult {
+    let mut title = String::new();
+    let mut in_word = false;
+    for c in receiver.as_string().chars() {
+        if in_word {
+            title.extend(c.to_lowercase());
+        } else {
+            title.extend(c.to_uppercase());
+        }
+        in_word = c.is_alphabetic();
+    }
+    Ok(DynValue::from(title))
+}
+
+fn str_capitalize(_: &Env, receiver: &DynValue, _: Vec<DynValue>) -> MethodResult {
+    let string = receiver.as_string();
+    let mut chars = string.chars();
+    let capitalized = match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.as_str().to_lowercase().chars())
+            .collect(),
+        None => String::new(),
+    };
+    Ok(DynValue::from(capitalized))
+}
+
+// the list behind the receiver, shared with every other reference to it
+pub fn with_list<T>(list: &DynValue, f: impl FnOnce(&mut Vec<DynValue>) -> T) -> T {
+    let mut value = list.value.borrow_mut();
+    f(value.downcast_mut::<Vec<DynValue>>().unwrap())
+}
+
+// the dict behind the receiver, shared like with_list's
+pub fn with_dict<T>(dict: &DynValue, f: impl FnOnce(&mut Dict) -> T) -> T {
+    let mut value = dict.value.borrow_mut();
+    f(value.downcast_mut::<Dict>().unwrap())
+}
+
+fn list_append(_: &Env, receiver: &DynValue, mut args: Vec<DynValue>) -> MethodResult {
+    let item = args.remove(0);
+    with_list(receiver, |list| list.push(item));
+    Ok(DynValue::none())
+}
+
+fn list_pop(_: &Env, receiver: &DynValue, _: Vec<DynValue>) -> MethodResult {
+    with_list(receiver, |list| list.pop()).ok_or(Traceback::from_exception(
+        TracebackKind::IndexError,
+        "pop from empty list",
+    ))
+}
+
+fn list_extend(env: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    expect_type("extend", &args[0], TypeKind::List)?;
+    let items = args[0].as_list().unwrap_or_default();
+    let len = with_list(receiver, |list| list.len());
+    check_size(env, list_size(len + items.len()), "list too large")?;
+    with_list(receiver, |list| list.extend(items));
+    Ok(DynValue::none())
+}
+
+fn list_insert(_: &Env, receiver: &DynValue, mut args: Vec<DynValue>) -> MethodResult {
+    expect_type("insert", &args[0], TypeKind::Number)?;
+    let index = args[0].as_number();
+    let item = args.remove(1);
+    with_list(receiver, |list| {
+        // out of range indices insert at the ends, like python
+        let len = list.len() as f64;
+        let index = if index < 0.0 { index + len } else { index };
+        list.insert(index.clamp(0.0, len) as usize, item)
+    });
+    Ok(DynValue::none())
+}
+
+// compared out of with_list's borrow, the elements may hold the list itself
+fn list_position(list: &DynValue, item: &DynValue) -> Result<Option<usize>, Traceback> {
+    let elements = list.as_list().unwrap_or_default();
+    for (i, element) in elements.iter().enumerate() {
+        if element.try_eq(item)? {
+            return Ok(Some(i));
+        }
+    }
+    Ok(None)
+}
+
+fn list_remove(_: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    match list_position(receiver, &args[0])? {
+        Some(i) => {
+            with_list(receiver, |list| list.remove(i));
+            Ok(DynValue::none())
+        }
+        None => Err(Traceback::from_exception(
+            TracebackKind::ValueError,
+            "list.remove(x): x not in list",
+        )),
+    }
+}
+
+fn list_index(_: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    list_position(receiver, &args[0])?
+        .map(|i| DynValue::from(i as f64))
+        .ok_or(Traceback::from_exception(
+            TracebackKind::ValueError,
+            &format!("{} is not in list", args[0].as_string()),
+        ))
+}
+
+fn list_count(_: &Env, receiver: &DynValue, args: Vec<DynValue>) -> MethodResult {
+    let elements = receiver.as_list().unwrap_or_default();
+    let mut count = 0;
+    for item in elements.iter() {
+        if item.try_eq(&args[0])? {
+            count += 1;
+        }
+    }
+    Ok(DynValue::from(count as f64))
+}
+
+fn list_reverse(_: &Env, receiver: &DynValue, _: Vec<DynValue>) -> MethodResult {
+    with_list(receiver, |list| list.reverse());
+    Ok(DynValue::none())
+}
+
+fn list_copy(_: &Env, receiver: &DynValue, _: Vec<DynValue>) -> MethodResult {
+    Ok(DynValue::from(receiver.as_list().unwrap_or_default()))
+}
+
+fn list_clear(_: &Env, receiver: &DynValue, _: Vec<DynValue>) -> MethodResult {
+    with_list(receiver, |list| list.clear());
+    Ok(DynValue::none())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::myton::environment::make_env;
+
+    fn call(receiver: &str, method: &str, args: Vec<DynValue>) -> MethodResult {
+        let method = get_attribute(&DynValue::from(receiver), method).unwrap();
+        method.as_callable().unwrap().call(&make_env(), args)
+    }
+
+    fn strings(values: &[&str]) -> DynValue {
+        values.iter().map(|value| DynValue::from(*value)).collect()
+    }
+
+    // the expected values come from cpython
+    #[test]
+    fn test_str_predicates() {
+        let inputs = [
+            "", "123", "12a", "abc", "Ab c", "  ", "\t\n", "éà", "٣٤", "x", "-42", "ß",
+        ];
+        let expected = [
+            ("isdigit", [0, 1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0]),
+            ("isalpha", [0, 0, 0, 1, 0, 0, 0, 1, 0, 1, 0, 1]),
+            ("isspace", [0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0]),
+        ];
+        for (method, results) in expected {
+            for (input, result) in inputs.iter().zip(results) {
+                let value = call(input, method, vec![]).unwrap();
+                assert_eq!(value.as_bool(), result == 1, "{:?}.{}()", input, method);
+            }
+        }
+    }
+
+    #[test]
+    fn test_str_case() {
+        let cases = [
+            ("", "", ""),
+            ("123", "123", "123"),
+            ("12a", "12A", "12a"),
+            ("abc", "Abc", "Abc"),
+            ("Ab c", "Ab C", "Ab c"),
+            ("éà", "Éà", "Éà"),
+            ("hello world", "Hello World", "Hello world"),
+            ("hELLO wORLD", "Hello World", "Hello world"),
+            ("they're bill's", "They'Re Bill'S", "They're bill's"),
+            ("hello2world", "Hello2World", "Hello2world"),
+            ("  x", "  X", "  x"),
+            ("-42", "-42", "-42"),
+        ];
+        for (input, title, capitalize) in cases {
+            assert_eq!(call(input, "title", vec![]).unwrap().as_string(), title);
+            assert_eq!(
+                call(input, "capitalize", vec![]).unwrap().as_string(),
+                capitalize
+            );
+        }
+    }
+
+    #[test]
+    fn test_str_count_and_index() {
+        let cases = [
+            ("", "l", 0),
+            ("", "", 1),
+            ("hello", "l", 2),
+            ("hello", "", 6),
+            ("lll", "l", 3),
+            ("aaaa", "aa", 2),
+            ("éél", "l", 1),
+            ("éél", "", 4),
+            ("abcabc", "abc", 2),
+            ("abc", "d", 0),
+        ];
+        for (input, sub, count) in cases {
+            let value = call(input, "count", vec![DynValue::from(sub)]).unwrap();
+            assert_eq!(
+                value.as_number(),
+                count as f64,
+                "{:?}.count({:?})",
+                input,
+                sub
+            );
+        }
+
+        let cases = [
+            ("hello", "l", 2),
+            ("héllo", "l", 2),
+            ("hello", "", 0),
+            ("hello", "lo", 3),
+            ("ééé", "é", 0),
+        ];
+        for (input, sub, index) in cases {
+            let value = call(input, "index", vec![DynValue::from(sub)]).unwrap();
+            assert_eq!(
+                value.as_number(),
+                index as f64,
+                "{:?}.index({:?})",
+                input,
+                sub
+            );
+        }
+        let error = call("hello", "index", vec![DynValue::from("z")]).unwrap_err();
+        assert!(matches!(error.kind(), TracebackKind::ValueError));
+    }
+
+    #[test]
+    fn test_str_padding() {
+        let cases = [
+            ("", "     ", "     ", "00000"),
+            ("ab", "ab   ", "   ab", "000ab"),
+            ("-42", "-42  ", "  -42", "-0042"),
+            ("+7", "+7   ", "   +7", "+0007"),
+            ("éé", "éé   ", "   éé", "000éé"),
+            ("toolong", "toolong", "toolong", "toolong"),
+        ];
+        for (input, ljust, rjust, zfill) in cases {
+            let width = || vec![DynValue::from(5)];
+            assert_eq!(call(input, "ljust", width()).unwrap().as_string(), ljust);
+            assert_eq!(call(input, "rjust", width()).unwrap().as_string(), rjust);
+            assert_eq!(call(input, "zfill", width()).unwrap().as_string(), zfill);
+        }
+    }
+
+    #[test]
+    fn test_str_splitlines() {
+        let cases: [(&str, &[&str]); 8] = [
+            ("", &[]),
+            ("a", &["a"]),
+            ("a\n", &["a"]),
+            ("a\nb", &["a", "b"]),
+            ("a\r\nb\rc", &["a", "b", "c"]),
+            ("\n\n", &["", ""]),
+            ("a\n\nb\n", &["a", "", "b"]),
+            ("a\u{2028}b", &["a", "b"]),
+        ];
+        for (input, lines) in cases {
+            let value = call(input, "splitlines", vec![]).unwrap();
+            assert_eq!(value.as_string(), strings(lines).as_string(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("append", "append"), 0);
+        assert_eq!(levenshtein("apend", "append"), 1);
+        assert_eq!(levenshtein("", "pop"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_attribute_hint() {
+        let list = DynValue::from_vec(vec![]);
+        assert_eq!(
+            attribute_hint(&list, "apend"),
+            Some("did you mean 'append'?".to_string())
+        );
+        assert_eq!(attribute_hint(&list, "frobnicate"), None);
+        let string = DynValue::from("abc");
+        assert_eq!(
+            attribute_hint(&string, "ltrip"),
+            Some("did you mean 'lstrip', 'strip' or 'rstrip'?".to_string())
+        );
+        assert_eq!(
+            attribute_hint(&DynValue::none(), "x"),
+            Some(NONE_HINT.to_string())
+        );
+        assert_eq!(attribute_hint(&DynValue::from(1.0), "x"), None);
+    }
+}
@@ -1,146 +1,881 @@
+mod builtin_types;
 mod class;
+mod debugger;
+mod dynamic;
 mod environment;
 mod errors;
 mod expression;
+mod format;
 mod functions;
+mod handle;
+mod hashable;
+mod iterator;
 mod lexer;
+mod limits;
+mod methods;
+mod module;
 mod native_functions;
+mod numeric;
+mod output;
 mod parser;
+mod pipeline;
 mod repl;
+mod report;
 mod resolver;
+mod send;
+mod session;
+mod snapshot;
+mod sources;
 mod statement;
+mod stats;
+#[doc(hidden)]
+pub mod test_support;
+mod timestamp;
+mod trace;
 mod traceback;
 mod types;
+mod unparse;
+mod watch;
 
-pub use errors::had_error;
-
-use environment::{make_env, Env, EnvVariable};
-use errors::report_trace;
+use environment::{make_env, Env};
+use errors::{report_trace, report_warning, ColorChoice, Style};
+use functions::NativeFunction;
+pub use handle::MytonFunction;
 use lexer::*;
-use native_functions::define_globals;
-use parser::Parser;
-use repl::Repl;
+use native_functions::{define_globals, is_dunder};
+use output::LineTracker;
+use parser::{Parser, MAX_NESTING};
+pub use pipeline::{lex, parse, resolve, Ast, ResolvedProgram};
+use repl::{goodbye, Repl, DEFAULT_PROMPT};
+use report::{report, Finding};
 use resolver::Resolver;
-use std::cell::RefCell;
-use std::io::prelude::*;
-use std::io::{stdout, Stdout, Write};
+pub use send::SendInterpreter;
+use session::{value_to_json, Json, Session};
+use snapshot::whos_table;
+pub use snapshot::{diff_snapshots, Global, GlobalChange};
+use sources::SourceMap;
+use statement::{ClassStatement, FunctionStatement, STMT};
+pub use stats::RunStats;
+use stats::{count_statement, Measure};
+use std::cell::{Cell, RefCell};
+use std::io::{
+    stderr, stdin, stdout, BufRead, BufWriter, Cursor, Read, Stderr, Stdin, Stdout, Write,
+};
 use std::rc::Rc;
-use traceback::Traceback;
-use types::DynValue;
+pub use token::{Token, TokenKind};
+pub use trace::TraceMode;
+use trace::Tracer;
+pub use traceback::Traceback;
+pub use traceback::TracebackKind;
+use traceback::{EX_DATAERR, EX_NOINPUT, EX_USAGE};
+pub use types::{DynValue, TypeKind};
+use watch::{timestamp, ChangeSource, MtimePoller, Watcher};
 
 const DEBUG_LEXER: bool = false;
 
 pub struct Interpreter {
     environment: Env,
     output: Rc<RefCell<Box<dyn MyWrite>>>,
+    error_output: Rc<RefCell<Box<dyn MyWrite>>>,
+    // what input() reads from
+    input: Rc<RefCell<Box<dyn MyRead>>>,
+    // lines printed since the last reset, counted by the output
+    lines_written: Rc<Cell<usize>>,
     resolver: Resolver,
+    // source of the top-level functions and classes, for %save
+    definitions: Vec<(String, String)>,
+    show_warnings: bool,
+    // how errors and warnings are rendered
+    style: Style,
+    // what is reported on the error output while running
+    trace: TraceMode,
+    // everything run so far, for the errors to show the right code
+    sources: Rc<RefCell<SourceMap>>,
+    // how long the values %vars and %whos show get, ellipsis included
+    repr_width: usize,
+    // how deep expressions can nest before being a syntax error
+    max_nesting: usize,
+    last_run_stats: RunStats,
 }
 
 impl Interpreter {
     pub fn new() -> Interpreter {
-        Self::new_with_output(Rc::new(RefCell::new(Box::new(stdout()))))
+        Self::new_with_output(Rc::new(RefCell::new(stdout_writer())))
     }
 
     pub fn new_with_output(output: Rc<RefCell<Box<dyn MyWrite>>>) -> Interpreter {
+        Self::new_with_outputs(output, Rc::new(RefCell::new(Box::new(stderr()))))
+    }
+
+    pub fn new_with_outputs(
+        output: Rc<RefCell<Box<dyn MyWrite>>>,
+        error_output: Rc<RefCell<Box<dyn MyWrite>>>,
+    ) -> Interpreter {
         let resolver = Resolver::new();
         let env = make_env();
-        define_globals(&env);
+        let input = Rc::new(RefCell::new(Box::new(stdin()) as Box<dyn MyRead>));
+        let lines_written = Rc::new(Cell::new(0));
+        let output: Rc<RefCell<Box<dyn MyWrite>>> = Rc::new(RefCell::new(Box::new(
+            LineTracker::new(output, lines_written.clone()),
+        )));
+        let sources = Rc::new(RefCell::new(SourceMap::default()));
+        env.borrow_mut().set_output(output.clone());
+        define_globals(
+            &env,
+            input.clone(),
+            output.clone(),
+            error_output.clone(),
+            sources.clone(),
+        );
 
         let res = Interpreter {
             environment: env,
             output,
+            error_output,
+            input,
+            lines_written,
             resolver,
+            definitions: Vec::new(),
+            show_warnings: true,
+            style: Style::PLAIN,
+            trace: TraceMode::Off,
+            sources,
+            repr_width: 40,
+            max_nesting: MAX_NESTING,
+            last_run_stats: RunStats::default(),
         };
 
         return res;
     }
 
-    pub fn run_file(&mut self, path: &str) {
-        if let Ok(mut file) = std::fs::File::open(path) {
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).unwrap();
-
-            self.debug_lexer(contents.to_string());
+    // a new interpreter writing where this one does, with its settings
+    // and limits but none of what was run in it, for --watch's runs
+    fn fresh(&self) -> Interpreter {
+        let mut fresh = Self::new_with_outputs(self.output.clone(), self.error_output.clone());
+        fresh.show_warnings = self.show_warnings;
+        fresh.style = self.style;
+        fresh.trace = self.trace;
+        fresh.repr_width = self.repr_width;
+        fresh.max_nesting = self.max_nesting;
+        let env = self.environment.borrow();
+        fresh.set_max_object_size(env.max_size());
+        fresh.set_max_recursion(env.max_recursion());
+        fresh
+    }
 
-            if let Err(e) = self.run(contents) {
-                print!("{}", e);
+    pub fn run_file(&mut self, path: &str) -> i32 {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(_) => {
+                self.report_error(format!("Could not open file {}\n", path));
+                return EX_NOINPUT;
             }
-        } else {
-            println!("Could not open file {}", path);
+        };
+
+        self.debug_lexer(contents.to_string());
+
+        match self.run(contents) {
+            Ok(()) => 0,
+            Err(traceback) => self.report_traceback(traceback),
         }
     }
 
-    pub fn run_repl(&mut self) {
+    fn report_error(&mut self, error: String) {
+        write!(self.error_output.borrow_mut(), "{}", error).unwrap();
+    }
+
+    // reports the traceback unless the program simply
+    // called exit(), and returns the matching exit code
+    fn report_traceback(&mut self, traceback: Traceback) -> i32 {
+        let code = traceback.tipe.exit_code();
+        if !matches!(traceback.tipe, TracebackKind::Exit(_)) {
+            self.report_error(report_trace(traceback, self.style));
+        }
+        code
+    }
+
+    pub fn run_repl(&mut self) -> i32 {
+        if !termion::is_tty(&stdin()) {
+            return self.run_piped();
+        }
+
         let mut repl = Repl::new();
+        repl.welcome_prompt();
+        let mut entries = 0;
 
-        while let Some(source) = repl.next() {
-            self.environment
-                .borrow_mut()
-                .set_env_var(EnvVariable::NewLines, DynValue::from(0));
+        loop {
+            repl.set_prompt(self.prompt());
+            let source = match repl.next() {
+                Some(source) => source,
+                None => break,
+            };
+            self.reset_lines_written();
 
-            if let Err(result) = self.run(source.clone()) {
-                repl.printerr(result);
-            } else {
-                let skip = self
-                    .environment
-                    .borrow()
-                    .get_env_var(EnvVariable::NewLines)
-                    .as_number() as u16;
-                repl.skiplines(skip);
+            if source.trim_start().starts_with('%') {
+                match self.command(&source) {
+                    Ok(output) => repl.println(output),
+                    Err(traceback) => repl.printerr(report_trace(traceback, self.style)),
+                }
+                continue;
+            }
+
+            entries += 1;
+            match self.run_named(source.clone(), &format!("stdin-{}", entries)) {
+                Err(Traceback {
+                    tipe: TracebackKind::Exit(code),
+                    ..
+                }) => return code,
+                Err(traceback) => repl.printerr(report_trace(traceback, self.style)),
+                Ok(()) => repl.skiplines(self.lines_written() as u16),
             }
         }
+
+        repl.println(goodbye(entries));
+        0
     }
 
-    fn run(&mut self, source: String) -> Result<(), String> {
-        if let Err(mut traceback) = self.run_with_traceback(source.clone()) {
-            traceback.code = Some(source);
-            Err(report_trace(traceback))
-        } else {
-            Ok(())
+    // PS1 when the user set it, called before each prompt when it
+    // is a function so that it can change. Any error falls back
+    // to the default prompt rather than stopping the session
+    fn prompt(&self) -> String {
+        let value = match self.environment.borrow().get("PS1".to_string()) {
+            Some(value) => value,
+            None => return DEFAULT_PROMPT.to_string(),
+        };
+        match value.as_callable() {
+            Some(callable) => callable
+                .call(&self.environment, Vec::new())
+                .map(|prompt| prompt.as_string())
+                .unwrap_or_else(|_| DEFAULT_PROMPT.to_string()),
+            None => value.as_string(),
+        }
+    }
+
+    // without a terminal to edit lines in, what is piped
+    // in is run as a whole, like a script
+    fn run_piped(&mut self) -> i32 {
+        let mut source = String::new();
+        if stdin().read_to_string(&mut source).is_err() {
+            self.report_error("Could not read stdin\n".to_string());
+            return EX_NOINPUT;
+        }
+
+        match self.run_named(source, "stdin") {
+            Ok(()) => 0,
+            Err(traceback) => self.report_traceback(traceback),
+        }
+    }
+
+    // handles the REPL's %-commands, returns what should be shown
+    fn command(&mut self, command: &str) -> Result<String, Traceback> {
+        let mut words = command.trim()[1..].split_whitespace();
+        match (words.next(), words.next(), words.next()) {
+            (Some("load"), Some(path), None) => {
+                self.load_file(path)?;
+                Ok(String::new())
+            }
+            (Some("vars"), None, None) => Ok(self.vars()),
+            (Some("whos"), None, None) => Ok(whos_table(&self.shown_globals())),
+            (Some("save"), Some(path), None) => {
+                let skipped = self.save_session(path)?;
+                if skipped.is_empty() {
+                    Ok(format!("Saved session to {}\n", path))
+                } else {
+                    Ok(format!(
+                        "Saved session to {}, skipped: {}\n",
+                        path,
+                        skipped.join(", ")
+                    ))
+                }
+            }
+            (Some("restore"), Some(path), None) => {
+                self.restore_session(path)?;
+                Ok(String::new())
+            }
+            _ => Err(Traceback::from_message(&format!(
+                "Unknown command '{}', expected %load <path>, %vars, %whos, %save <path> or %restore <path>",
+                command.trim()
+            ))),
         }
     }
 
-    fn run_with_traceback(&mut self, source: String) -> Result<(), Traceback> {
+    // makes a host callback callable from scripts as a global
+    pub fn register_native(
+        &mut self,
+        name: &str,
+        nb_args: usize,
+        func: impl Fn(&Env, Vec<DynValue>) -> Result<DynValue, Traceback> + 'static,
+    ) {
+        let native = NativeFunction::new(func, nb_args);
+        self.environment.borrow_mut().set(
+            name.to_string(),
+            DynValue::from_native_function(native, name.to_string()),
+        );
+    }
+
+    /// The script function, or any other callable, bound to the global
+    /// name, as a handle the host can call as many times as it needs.
+    pub fn get_function(&self, name: &str) -> Option<MytonFunction> {
+        let value = self.environment.borrow().get(name.to_string())?;
+        value.as_callable()?;
+        Some(MytonFunction::new(
+            value,
+            self.environment.clone(),
+            self.sources.clone(),
+            self.output.clone(),
+        ))
+    }
+
+    // runs the file in the current environment, so that
+    // its definitions stay available afterwards
+    pub fn load_file(&mut self, path: &str) -> Result<(), Traceback> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|_| Traceback::from(format!("Could not open file {}", path)))?;
+
+        self.run_named(contents, path)
+    }
+
+    // one line per global binding (natives excepted)
+    // with its type and a short representation
+    pub fn vars(&self) -> String {
+        self.shown_globals()
+            .iter()
+            .map(|(name, tipe, repr)| format!("{}: {} = {}\n", name, tipe, repr))
+            .collect()
+    }
+
+    /// The globals the scripts defined, sorted by name, as their
+    /// name, type and whole representation, so that `diff_snapshots`
+    /// sees any change. The natives, modules, types and `__name__`
+    /// are left out.
+    pub fn snapshot_globals(&self) -> Vec<Global> {
+        self.environment
+            .borrow()
+            .variables()
+            .into_iter()
+            .filter(|(name, value)| {
+                !is_dunder(name)
+                    && !matches!(
+                        value.tipe,
+                        TypeKind::NativeFunction | TypeKind::Type | TypeKind::Module
+                    )
+            })
+            .map(|(name, value)| (name, value.tipe.to_string(), value.as_string()))
+            .collect()
+    }
+
+    // the snapshot as %vars and %whos show it, cut at the repr width
+    fn shown_globals(&self) -> Vec<Global> {
+        self.snapshot_globals()
+            .into_iter()
+            .map(|(name, tipe, repr)| (name, tipe, short_repr(repr, self.repr_width)))
+            .collect()
+    }
+
+    /// Sets how many characters the values in %vars and %whos are
+    /// cut to, the "..." ending them included.
+    pub fn set_repr_width(&mut self, width: usize) {
+        self.repr_width = width;
+    }
+
+    // writes the globals that are plain data and the source of the
+    // functions and classes, returns the names that were skipped
+    pub fn save_session(&self, path: &str) -> Result<Vec<String>, Traceback> {
+        let mut session = Session::default();
+        let mut skipped = Vec::new();
+
+        for (name, value) in self.environment.borrow().variables() {
+            match value.tipe {
+                // builtins and __name__ are defined again by the new interpreter
+                _ if is_dunder(&name) => {}
+                TypeKind::NativeFunction | TypeKind::Type | TypeKind::Module
+                    if value.name.as_ref() == Some(&name) => {}
+                TypeKind::Function | TypeKind::Class
+                    if value.name.as_ref() == Some(&name)
+                        && self.definitions.iter().any(|(n, _)| n == &name) => {}
+                _ if value_to_json(&value).is_some() => session.globals.push((name, value)),
+                _ => skipped.push(name),
+            }
+        }
+
+        let bound = |name: &String| match self.environment.borrow().get(name.clone()) {
+            Some(value) => {
+                matches!(value.tipe, TypeKind::Function | TypeKind::Class)
+                    && value.name.as_ref() == Some(name)
+            }
+            None => false,
+        };
+        session.definitions = self
+            .definitions
+            .iter()
+            .filter(|(name, _)| bound(name))
+            .map(|(_, source)| source.clone())
+            .collect();
+
+        std::fs::write(path, session.to_json())
+            .map_err(|_| Traceback::from(format!("Could not write file {}", path)))?;
+        Ok(skipped)
+    }
+
+    // replays the saved definitions then sets the saved globals
+    pub fn restore_session(&mut self, path: &str) -> Result<(), Traceback> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|_| Traceback::from(format!("Could not open file {}", path)))?;
+        let session = Session::from_json(&contents).map_err(|message| {
+            let mut traceback = Traceback::from(message);
+            traceback.filename = Some(path.to_string());
+            traceback
+        })?;
+
+        for definition in session.definitions {
+            self.run(definition)?;
+        }
+        for (name, value) in session.globals {
+            self.environment.borrow_mut().set(name, value);
+        }
+        Ok(())
+    }
+
+    // lexes, parses and resolves every file without executing
+    // anything, returns the number of errors found
+    pub fn check_files(&mut self, paths: &[String]) -> usize {
+        self.check_each(paths, |_, _| ())
+    }
+
+    // like check_files, also giving what the report finds
+    // in each file without errors, see report::report
+    fn report_files(&mut self, paths: &[String]) -> (usize, Vec<(String, Finding)>) {
+        let mut findings = Vec::new();
+        let nb_errors = self.check_each(paths, |path, program| {
+            findings.extend(
+                report(program)
+                    .into_iter()
+                    .map(|finding| (path.to_string(), finding)),
+            )
+        });
+        (nb_errors, findings)
+    }
+
+    fn check_each(&mut self, paths: &[String], mut checked: impl FnMut(&str, &[STMT])) -> usize {
+        let mut nb_errors = 0;
+        for path in paths {
+            let contents = match std::fs::read_to_string(path) {
+                Ok(contents) => contents,
+                Err(_) => {
+                    self.report_error(format!("Could not open file {}\n", path));
+                    nb_errors += 1;
+                    continue;
+                }
+            };
+
+            let (program, errors, warnings) = self.check(contents.clone());
+            if errors.is_empty() {
+                checked(path, &program);
+            }
+            if self.show_warnings {
+                for mut warning in warnings {
+                    warning.code = Some(contents.clone());
+                    warning.filename = Some(path.clone());
+                    self.report_error(report_warning(warning, self.style));
+                }
+            }
+            for mut traceback in errors {
+                traceback.code = Some(contents.clone());
+                traceback.filename = Some(path.clone());
+                self.report_error(report_trace(traceback, self.style));
+                nb_errors += 1;
+            }
+        }
+        nb_errors
+    }
+
+    // the program with its errors and the resolver's warnings
+    fn check(&mut self, source: String) -> (Vec<STMT>, Vec<Traceback>, Vec<Traceback>) {
         let mut lexer = Lexer::new(source);
-        let mut parser = Parser::new(lexer.tokenize()?, self.output.clone());
+        let tokens = match lexer.tokenize() {
+            Ok(tokens) => tokens,
+            Err(traceback) => return (Vec::new(), vec![traceback], Vec::new()),
+        };
 
-        let program = parser.parse()?;
+        let mut parser = Parser::new(tokens).max_nesting(self.max_nesting);
+        let (program, mut errors) = parser.parse_recovering();
 
+        let mut resolver = Resolver::new();
+        let mut warnings = Vec::new();
         for stmt in &program {
-            stmt.resolve(&mut self.resolver)?;
+            if let Err(traceback) = stmt.resolve(&mut resolver) {
+                errors.push(traceback);
+                // the resolver may be left mid-scope
+                warnings.append(&mut resolver.warnings);
+                resolver = Resolver::new();
+            }
         }
+        warnings.append(&mut resolver.warnings);
+        (program, errors, warnings)
+    }
+
+    pub fn run(&mut self, source: String) -> Result<(), Traceback> {
+        self.run_chunk(source, None)
+    }
 
+    /// Like run, with errors reported under the given name, such as `stdin-2`,
+    /// even those raised later by the functions it defines.
+    pub fn run_named(&mut self, source: String, name: &str) -> Result<(), Traceback> {
+        self.run_chunk(source, Some(name.to_string()))
+    }
+
+    /// Runs a program made with `lex`, `parse` and `resolve`. The
+    /// errors have no source to show, the one it was lexed from can
+    /// be given to them as their `code`.
+    pub fn execute(&mut self, program: ResolvedProgram) -> Result<(), Traceback> {
+        let measure = Measure::start();
         self.environment
-            .borrow_mut()
-            .set_resolved_locals(self.resolver.locals.clone());
+            .borrow()
+            .add_resolved_locals(program.locals);
+        let res = program.statements.iter().try_for_each(|stmt| {
+            count_statement();
+            stmt.execute(&self.environment)
+        });
+        self.last_run_stats = measure.finish();
+        self.flush();
+        res
+    }
+
+    fn run_chunk(&mut self, source: String, name: Option<String>) -> Result<(), Traceback> {
+        let chunk = self.sources.borrow_mut().add(source.clone(), name);
+        let measure = Measure::start();
+        let res = self.run_with_traceback(source, chunk);
+        self.last_run_stats = measure.finish();
+        let res = res.map_err(|mut traceback| {
+            self.sources.borrow().locate(&mut traceback, chunk);
+            traceback
+        });
+        self.flush();
+        res
+    }
+
+    /// Sets the largest string or list, in bytes, the scripts can make
+    /// by repeating, concatenating or extending. Going over raises a
+    /// MemoryError, the default is 256 MB.
+    pub fn set_max_object_size(&mut self, bytes: usize) {
+        self.environment.borrow().set_max_size(bytes);
+    }
+
+    /// Sets how many calls, of functions or of exec() and eval(), can
+    /// be running at once before raising a RecursionError. The default,
+    /// 200, fits in the main thread's stack: going higher may need
+    /// running the interpreter in a thread with a bigger one.
+    pub fn set_max_recursion(&mut self, depth: usize) {
+        self.environment.borrow().set_max_recursion(depth);
+    }
+
+    /// Sets how deeply expressions can nest, in parentheses, calls or
+    /// unary operators, before being a syntax error. The default,
+    /// 200, fits in the main thread's stack: going higher may need
+    /// running the interpreter in a thread with a bigger one.
+    pub fn set_max_nesting(&mut self, depth: usize) {
+        self.max_nesting = depth;
+    }
+
+    /// What the last run, or REPL input, did and how long it took.
+    pub fn last_run_stats(&self) -> RunStats {
+        self.last_run_stats
+    }
+
+    /// Makes input() read from the given source instead of stdin.
+    pub fn set_input(&mut self, input: Box<dyn MyRead>) {
+        *self.input.borrow_mut() = input;
+    }
+
+    /// Reports every statement and function call on the error output while running.
+    pub fn set_trace(&mut self, enabled: bool) {
+        self.set_trace_mode(if enabled {
+            TraceMode::Lines
+        } else {
+            TraceMode::Off
+        });
+    }
+
+    pub fn set_trace_mode(&mut self, mode: TraceMode) {
+        self.trace = mode;
+    }
+
+    /// The number of lines printed since the interpreter was created,
+    /// or since the REPL started reading the last input.
+    pub fn lines_written(&self) -> usize {
+        self.lines_written.get()
+    }
+
+    fn reset_lines_written(&mut self) {
+        self.lines_written.set(0);
+    }
+
+    // the output may be buffered, this makes sure everything
+    // printed so far is written before anything else shows up
+    fn flush(&mut self) {
+        self.output.borrow_mut().flush().ok();
+    }
+
+    fn run_with_traceback(&mut self, source: String, chunk: usize) -> Result<(), Traceback> {
+        let mut lexer = Lexer::new(source.clone()).chunk(chunk);
+        let mut parser = Parser::new(lexer.tokenize()?)
+            .fresh_uuids()
+            .max_nesting(self.max_nesting);
+        if self.trace != TraceMode::Off {
+            let tracer = Tracer::new(self.trace, source.clone(), self.error_output.clone());
+            parser.trace(Rc::new(tracer));
+        }
+
+        let program = parser.parse()?;
+
+        let resolved = program
+            .iter()
+            .try_for_each(|stmt| stmt.resolve(&mut self.resolver));
+        self.report_warnings(&source);
+        resolved?;
+
+        self.environment
+            .borrow()
+            .add_resolved_locals(std::mem::take(&mut self.resolver.locals));
 
         for stmt in &program {
+            count_statement();
             stmt.execute(&self.environment)?;
+            self.record_definition(stmt, &source);
         }
 
         Ok(())
     }
 
+    fn report_warnings(&mut self, source: &str) {
+        let warnings = std::mem::take(&mut self.resolver.warnings);
+        if !self.show_warnings {
+            return;
+        }
+        for mut warning in warnings {
+            warning.code = Some(source.to_string());
+            self.report_error(report_warning(warning, self.style));
+        }
+    }
+
+    fn record_definition(&mut self, stmt: &STMT, source: &str) {
+        let name = if let Some(function) = stmt.as_any().downcast_ref::<FunctionStatement>() {
+            function.inner.borrow().name.clone()
+        } else if let Some(class) = stmt.as_any().downcast_ref::<ClassStatement>() {
+            class.name.clone()
+        } else {
+            return;
+        };
+
+        if let Some((_, line)) = name.pos {
+            self.definitions.retain(|(n, _)| n != &name.value);
+            self.definitions
+                .push((name.value.clone(), block_source(source, line)));
+        }
+    }
+
     fn debug_lexer(&mut self, source: String) {
         if DEBUG_LEXER {
-            let mut lexer = Lexer::new(source);
-            let tokens = lexer.tokenize().unwrap();
-
-            for token in tokens {
+            for token in Lexer::new(source) {
                 println!("{:?}", token);
             }
         }
     }
 }
 
+const USAGE: &str = "Usage: myton [--no-warnings] [--stats] [--color=always|never|auto] [--trace[=calls|lines]] [script | check [--report | --report-json] file... | --watch script]";
+
+// entry point of the myton binary, returns the process exit code
+pub fn run_main(mut args: Vec<String>) -> i32 {
+    let mut myton = Interpreter::new();
+    // only the command line colors its errors, when they go to a terminal
+    myton.style = Style::for_stderr(ColorChoice::Auto);
+
+    if let Some(i) = args.iter().position(|arg| arg == "--no-warnings") {
+        args.remove(i);
+        myton.show_warnings = false;
+    }
+    let show_stats = args
+        .iter()
+        .position(|arg| arg == "--stats")
+        .map(|i| args.remove(i));
+    if let Some(i) = args.iter().position(|arg| arg.starts_with("--trace")) {
+        let flag = args.remove(i);
+        match flag.strip_prefix("--trace=").map(TraceMode::from_flag) {
+            None if flag == "--trace" => myton.set_trace(true),
+            Some(Some(mode)) => myton.set_trace_mode(mode),
+            _ => {
+                eprintln!("--trace expects calls or lines");
+                return EX_USAGE;
+            }
+        }
+    }
+    if let Some(i) = args.iter().position(|arg| arg.starts_with("--color=")) {
+        match ColorChoice::from_flag(&args.remove(i)["--color=".len()..]) {
+            Some(choice) => myton.style = Style::for_stderr(choice),
+            None => {
+                eprintln!("--color expects always, never or auto");
+                return EX_USAGE;
+            }
+        }
+    }
+
+    if args.len() > 1 && (args[1] == "check" || args[1] == "--check") {
+        let report_flag = args[2..]
+            .iter()
+            .position(|arg| arg == "--report" || arg == "--report-json")
+            .map(|i| args.remove(i + 2));
+        if args.len() == 2 {
+            eprintln!("check expects files\n{}", USAGE);
+            return EX_USAGE;
+        }
+        let (nb_errors, findings) = match report_flag {
+            Some(_) => myton.report_files(&args[2..]),
+            None => (myton.check_files(&args[2..]), Vec::new()),
+        };
+        if report_flag.as_deref() == Some("--report-json") {
+            let findings = findings
+                .iter()
+                .map(|(path, finding)| finding.to_json(path))
+                .collect();
+            println!("{}", Json::Array(findings).render());
+        } else {
+            for (path, finding) in &findings {
+                println!("{}", finding.render(path));
+            }
+        }
+        match nb_errors {
+            0 => {
+                if report_flag.is_none() {
+                    println!("OK");
+                }
+                0
+            }
+            nb_errors => {
+                eprintln!("Found {} error(s)", nb_errors);
+                EX_DATAERR
+            }
+        }
+    } else if args.len() == 3 && args[1] == "--watch" {
+        watch_file(&args[2], &myton);
+        0
+    } else if args.len() > 2 {
+        eprintln!("{}", USAGE);
+        EX_USAGE
+    } else if args.len() == 2 {
+        let code = myton.run_file(&args[1]);
+        if show_stats.is_some() {
+            let stats = myton.last_run_stats();
+            myton.report_error(format!("{}\n", stats));
+        }
+        code
+    } else {
+        myton.run_repl()
+    }
+}
+
+fn short_repr(repr: String, width: usize) -> String {
+    if repr.chars().count() > width {
+        repr.chars()
+            .take(width.saturating_sub(3))
+            .collect::<String>()
+            + "..."
+    } else {
+        repr
+    }
+}
+
+// the lines of the block starting at the given line,
+// that is until the indentation goes back to its level
+fn block_source(source: &str, start: usize) -> String {
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let mut lines = source.lines().skip(start);
+    let first = match lines.next() {
+        Some(first) => first,
+        None => return String::new(),
+    };
+    let base = indent(first);
+
+    let mut block = vec![&first[base..]];
+    for line in lines {
+        if !line.trim().is_empty() && indent(line) <= base {
+            break;
+        }
+        block.push(line.get(base..).unwrap_or(""));
+    }
+    while block.last().is_some_and(|line| line.trim().is_empty()) {
+        block.pop();
+    }
+    block.join("\n") + "\n"
+}
+
+// each print is shown right away on a terminal, otherwise
+// the output is buffered and flushed at the end of every run
+fn stdout_writer() -> Box<dyn MyWrite> {
+    if termion::is_tty(&stdout()) {
+        Box::new(stdout())
+    } else {
+        Box::new(BufWriter::new(stdout()))
+    }
+}
+
+// re-runs the script in a fresh interpreter every time it changes,
+// every run is configured like the given one
+fn watch_file(path: &str, settings: &Interpreter) {
+    watch_with(
+        Watcher::new(MtimePoller::new(vec![path.into()])),
+        path,
+        settings,
+    );
+}
+
+// runs the file each time the watcher says, in a fresh interpreter
+fn watch_with<S: ChangeSource>(mut watcher: Watcher<S>, path: &str, settings: &Interpreter) {
+    watcher.run(|| {
+        print!("{}{}", termion::clear::All, termion::cursor::Goto(1, 1));
+        println!("----- {} [{}] -----", path, timestamp());
+        settings.fresh().run_file(path);
+        stdout().flush().ok();
+    });
+}
+
 // used in tests
 pub fn run_to_string(source: String) -> String {
     let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
     let mut interpreter = Interpreter::new_with_output(output.clone());
-    if let Err(errors) = interpreter.run(source.to_string()) {
-        return errors;
+    match interpreter.run(source.to_string()) {
+        Err(Traceback {
+            tipe: TracebackKind::Exit(_),
+            ..
+        })
+        | Ok(()) => output.borrow().get_string().unwrap(),
+        Err(traceback) => report_trace(traceback, Style::PLAIN),
+    }
+}
+
+// used in tests, returns what was written to (stdout, stderr)
+pub fn run_to_strings(source: String) -> (String, String) {
+    run_to_strings_with_input(source, String::new())
+}
+
+// same, with the lines input() reads
+pub fn run_to_strings_with_input(source: String, input: String) -> (String, String) {
+    let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+    let error_output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+    let mut interpreter = Interpreter::new_with_outputs(output.clone(), error_output.clone());
+    interpreter.set_input(Box::new(Cursor::new(input)));
+    if let Err(traceback) = interpreter.run(source) {
+        interpreter.report_traceback(traceback);
+    }
+    let stdout = output.borrow().get_string().unwrap();
+    let stderr = error_output.borrow().get_string().unwrap();
+    (stdout, stderr)
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
     }
-    return output.borrow().get_string().unwrap();
 }
 
 pub trait MyWrite: Write {
@@ -159,6 +894,60 @@ impl MyWrite for Stdout {
     }
 }
 
+impl MyWrite for Stderr {
+    fn get_string(&self) -> Option<String> {
+        None
+    }
+}
+
+// only sees what has already been flushed
+impl<W: MyWrite> MyWrite for BufWriter<W> {
+    fn get_string(&self) -> Option<String> {
+        self.get_ref().get_string()
+    }
+}
+
+pub trait MyRead {
+    // the next line without its line break, None once the input is over
+    fn next_line(&mut self) -> std::io::Result<Option<String>>;
+
+    // whether someone can answer the debugger, rather than a pipe or a file
+    fn is_interactive(&self) -> bool {
+        true
+    }
+}
+
+fn next_line_from(reader: &mut impl BufRead) -> std::io::Result<Option<String>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(Some(line))
+}
+
+// locks stdin only while reading, the REPL reads from it too
+impl MyRead for Stdin {
+    fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        next_line_from(&mut self.lock())
+    }
+
+    fn is_interactive(&self) -> bool {
+        termion::is_tty(self)
+    }
+}
+
+impl<T: AsRef<[u8]>> MyRead for Cursor<T> {
+    fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        next_line_from(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -166,12 +955,575 @@ mod tests {
     fn test_run_case(test_case_name: &str, source: &str, expected: &str) {
         let output = run_to_string(source.to_string());
 
+        if let Some(diff) = test_support::line_diff(expected, &output) {
+            panic!("Test case \"{}\" failed, {}", test_case_name, diff);
+        }
+    }
+
+    #[test]
+    fn test_run_to_strings() {
+        let (stdout, stderr) = run_to_strings("print 1\nprint(undefined)\nprint 2".to_string());
+        assert_eq!(stdout, "1\n");
+        assert!(
+            stderr.contains("Undefined variable 'undefined'"),
+            "{}",
+            stderr
+        );
+
+        let (stdout, stderr) = run_to_strings("print 1".to_string());
+        assert_eq!(stdout, "1\n");
+        assert_eq!(stderr, "");
+    }
+
+    struct CountingWriter {
+        data: Vec<u8>,
+        nb_writes: Rc<RefCell<usize>>,
+    }
+
+    impl Write for CountingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            *self.nb_writes.borrow_mut() += 1;
+            self.data.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl MyWrite for CountingWriter {
+        fn get_string(&self) -> Option<String> {
+            self.data.get_string()
+        }
+    }
+
+    #[test]
+    fn test_buffered_output() {
+        let source = "i = 0\nwhile i < 10000:\n  print(i)\n  i = i + 1\nprint(\"done\")";
+        let run = |buffered: bool| {
+            let nb_writes = Rc::new(RefCell::new(0));
+            let writer = CountingWriter {
+                data: Vec::new(),
+                nb_writes: nb_writes.clone(),
+            };
+            let output: Box<dyn MyWrite> = if buffered {
+                Box::new(BufWriter::new(writer))
+            } else {
+                Box::new(writer)
+            };
+            let output = Rc::new(RefCell::new(output));
+            let mut interpreter = Interpreter::new_with_output(output.clone());
+            interpreter.run(source.to_string()).unwrap();
+            let res = output.borrow().get_string().unwrap();
+            let nb_writes = *nb_writes.borrow();
+            (res, nb_writes)
+        };
+
+        let (unbuffered, unbuffered_writes) = run(false);
+        let (buffered, buffered_writes) = run(true);
+
+        assert_eq!(buffered, unbuffered);
+        assert!(buffered.ends_with("9998\n9999\ndone\n"));
+        assert!(unbuffered_writes >= 10000);
+        assert!(buffered_writes * 100 < unbuffered_writes);
+    }
+
+    // what the two streams got, in the order it got there
+    struct StreamLog {
+        stream: &'static str,
+        log: Rc<RefCell<Vec<(&'static str, String)>>>,
+    }
+
+    impl Write for StreamLog {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let text = String::from_utf8(buf.to_vec()).unwrap();
+            let mut log = self.log.borrow_mut();
+            match log.last_mut() {
+                Some((stream, logged)) if *stream == self.stream => logged.push_str(&text),
+                _ => log.push((self.stream, text)),
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl MyWrite for StreamLog {
+        fn get_string(&self) -> Option<String> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_eprint_and_flush() {
+        let (stdout, stderr) = run_to_strings("print 1\neprint(2)\nprint 3".to_string());
+        assert_eq!(stdout, "1\n3\n");
+        assert_eq!(stderr, "2\n");
+
+        let (stdout, stderr) =
+            run_to_strings("print 1\nflush()\nprint 2\nflush()\nflush()".to_string());
+        assert_eq!(stdout, "1\n2\n");
+        assert_eq!(stderr, "");
+
+        // buffered, what was printed still comes before what eprint writes
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let output = StreamLog {
+            stream: "out",
+            log: log.clone(),
+        };
+        let error_output = StreamLog {
+            stream: "err",
+            log: log.clone(),
+        };
+        let mut interpreter = Interpreter::new_with_outputs(
+            Rc::new(RefCell::new(Box::new(BufWriter::new(output)))),
+            Rc::new(RefCell::new(Box::new(error_output))),
+        );
+        interpreter
+            .run("print 1\nprint 2\neprint(\"oops\")\nprint 3".to_string())
+            .unwrap();
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                ("out", "1\n2\n".to_string()),
+                ("err", "oops\n".to_string()),
+                ("out", "3\n".to_string()),
+            ]
+        );
+        // only the lines printed on the output count
+        assert_eq!(interpreter.lines_written(), 3);
+    }
+
+    #[test]
+    fn test_print_end_counts_lines() {
+        let mut interpreter =
+            Interpreter::new_with_output(Rc::new(RefCell::new(Box::new(Vec::new()))));
+        interpreter
+            .run("print(1, end=\"\")\nprint(2, end=\"\")\nprint()\nprint(3)".to_string())
+            .unwrap();
+        assert_eq!(interpreter.lines_written(), 2);
+    }
+
+    #[test]
+    fn test_builtin_shadowing_warning() {
+        let source = "def clock():\n  return 42\nprint(clock())";
+        let (stdout, stderr) = run_to_strings(source.to_string());
+        assert_eq!(stdout, "42\n");
+        assert!(
+            stderr.contains("warning: 'clock' shadows a built-in function"),
+            "{}",
+            stderr
+        );
+        assert!(stderr.contains("1 | def clock():"), "{}", stderr);
+
+        let error_output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+        let mut interpreter = Interpreter::new_with_outputs(
+            Rc::new(RefCell::new(Box::new(Vec::new()))),
+            error_output.clone(),
+        );
+        interpreter.show_warnings = false;
+        interpreter.run("exit = 5".to_string()).unwrap();
+        assert_eq!(error_output.borrow().get_string().unwrap(), "");
+    }
+
+    #[test]
+    fn test_register_stateful_native() {
+        let calls = Rc::new(RefCell::new(Vec::<String>::new()));
+        let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+        let mut interpreter = Interpreter::new_with_output(output.clone());
+
+        let log = calls.clone();
+        interpreter.register_native("record", 1, move |_, args| {
+            log.borrow_mut().push(args[0].as_string());
+            Ok(DynValue::from(log.borrow().len()))
+        });
+
+        interpreter
+            .run("record(\"a\")\nfor x in [1, 2]:\n  print record(x)".to_string())
+            .unwrap();
+
+        assert_eq!(*calls.borrow(), ["a", "1", "2"]);
+        assert_eq!(output.borrow().get_string().unwrap(), "2\n3\n");
+    }
+
+    #[test]
+    fn test_redefinition_keeps_closures() {
+        // the same text parsed again, by run or exec, has ids of its own:
+        // the closures of each definition keep their captured scope
+        let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+        let mut interpreter = Interpreter::new_with_output(output.clone());
+        let definition = "x = 0\ndef make(v):\n  x = v\n  def get():\n    return x\n  return get\n";
+
+        interpreter
+            .run(definition.to_string() + "first = make(1)")
+            .unwrap();
+        interpreter
+            .run(definition.to_string() + "second = make(2)")
+            .unwrap();
+        interpreter
+            .run(format!("exec(\"{}\")\nthird = make(3)", definition))
+            .unwrap();
+        interpreter
+            .run("print(first(), second(), third(), x)".to_string())
+            .unwrap();
+
+        assert_eq!(output.borrow().get_string().unwrap(), "1 2 3 0\n");
+    }
+
+    #[test]
+    fn test_load_file() {
+        let path = std::env::temp_dir().join("myton_test_load_file.my");
+        std::fs::write(&path, "def f(x):\n  return x * 10\nname = \"loaded\"").unwrap();
+
+        let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+        let mut interpreter = Interpreter::new_with_output(output.clone());
+        interpreter
+            .command(&format!("%load {}", path.to_str().unwrap()))
+            .unwrap();
+        interpreter.run("print(f(2))".to_string()).unwrap();
+
+        assert_eq!(output.borrow().get_string().unwrap(), "20\n");
+        assert_eq!(
+            interpreter.command("%vars").unwrap(),
+            "f: function = <function f>\nname: str = loaded\n"
+        );
+    }
+
+    #[test]
+    fn test_prompt() {
+        let mut interpreter =
+            Interpreter::new_with_output(Rc::new(RefCell::new(Box::new(Vec::new()))));
+        assert_eq!(interpreter.prompt(), ">>> ");
+
+        interpreter.run("PS1 = \"in: \"".to_string()).unwrap();
+        assert_eq!(interpreter.prompt(), "in: ");
+        interpreter.run("PS1 = 42".to_string()).unwrap();
+        assert_eq!(interpreter.prompt(), "42");
+
+        // called again before each prompt
+        interpreter
+            .run(
+                "count = 0
+def PS1():
+  global count
+  count = count + 1
+  return \"[\" + str(count) + \"] \""
+                    .to_string(),
+            )
+            .unwrap();
+        assert_eq!(interpreter.prompt(), "[1] ");
+        assert_eq!(interpreter.prompt(), "[2] ");
+
+        interpreter
+            .run("def PS1():\n  return [][0]".to_string())
+            .unwrap();
+        assert_eq!(interpreter.prompt(), ">>> ");
+        interpreter
+            .run("def PS1(a):\n  return a".to_string())
+            .unwrap();
+        assert_eq!(interpreter.prompt(), ">>> ");
+    }
+
+    #[test]
+    fn test_snapshot_globals() {
+        let global = |name: &str, tipe: &str, repr: &str| {
+            (name.to_string(), tipe.to_string(), repr.to_string())
+        };
+        let mut interpreter =
+            Interpreter::new_with_output(Rc::new(RefCell::new(Box::new(Vec::new()))));
+        interpreter
+            .run("n = 1\nl = [1, 2]\ndef f():\n  pass\nclass A:\n  pass\n".to_string())
+            .unwrap();
+        let before = interpreter.snapshot_globals();
+        assert_eq!(
+            before,
+            vec![
+                global("A", "class", "<class 'A'>"),
+                global("f", "function", "<function f>"),
+                global("l", "list", "[1, 2]"),
+                global("n", "number", "1"),
+            ]
+        );
+
+        interpreter
+            .run("l.append(\"a long string\")".to_string())
+            .unwrap();
+        assert_eq!(
+            diff_snapshots(&before, &interpreter.snapshot_globals()),
+            vec![GlobalChange::Changed(
+                global("l", "list", "[1, 2]"),
+                global("l", "list", "[1, 2, 'a long string']"),
+            )]
+        );
+
+        // only %vars and %whos cut the values
+        interpreter.set_repr_width(12);
+        assert!(interpreter.vars().contains("l: list = [1, 2, 'a...\n"));
+        assert!(interpreter.snapshot_globals().contains(&global(
+            "l",
+            "list",
+            "[1, 2, 'a long string']"
+        )));
+    }
+
+    #[test]
+    fn test_snapshots_see_changes_past_the_repr_width() {
+        let mut interpreter =
+            Interpreter::new_with_output(Rc::new(RefCell::new(Box::new(Vec::new()))));
+        interpreter.run("l = list(range(100))".to_string()).unwrap();
+        let before = interpreter.snapshot_globals();
+        interpreter.run("l[99] = -1".to_string()).unwrap();
+        let after = interpreter.snapshot_globals();
+
+        let changes = diff_snapshots(&before, &after);
+        assert_eq!(changes.len(), 1, "{:?}", changes);
+        assert!(matches!(
+            &changes[0],
+            GlobalChange::Changed(old, new) if old.2.ends_with("99]") && new.2.ends_with("-1]")
+        ));
+        // while %vars shows both the same
+        assert_eq!(
+            interpreter.vars(),
+            format!("l: list = {}\n", short_repr(before[0].2.clone(), 40))
+        );
+    }
+
+    #[test]
+    fn test_embedded_interpreters_report_plain_text() {
+        // whether stderr is a terminal or not, only run_main colors
+        assert_eq!(Interpreter::new().style, Style::PLAIN);
+        let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+        assert_eq!(Interpreter::new_with_output(output).style, Style::PLAIN);
+    }
+
+    #[test]
+    fn test_internal_state_is_not_in_globals() {
+        // what the interpreter keeps for itself lives outside the
+        // variables, so a script can neither see nor overwrite it
+        let mut interpreter =
+            Interpreter::new_with_output(Rc::new(RefCell::new(Box::new(Vec::new()))));
+        interpreter
+            .run("new_lines = 100\nmax_size = 1\nprint [1, 2, 3]\nprint 2\n".to_string())
+            .unwrap();
+        assert_eq!(interpreter.lines_written(), 2);
+
+        let names = interpreter
+            .snapshot_globals()
+            .into_iter()
+            .map(|(name, _, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["max_size", "new_lines"]);
+        assert!(interpreter
+            .environment
+            .borrow()
+            .variables()
+            .iter()
+            .all(|(name, _)| !name.contains('.')));
+    }
+
+    #[test]
+    fn test_save_and_restore_session() {
+        let path = std::env::temp_dir().join("myton_test_session.mys");
+        let path = path.to_str().unwrap();
+
+        let mut interpreter =
+            Interpreter::new_with_output(Rc::new(RefCell::new(Box::new(Vec::new()))));
+        interpreter
+            .run(
+                "def f(x):\n  return x * 10\n\nclass A:\n  def get():\n    return \"a\"\n\nn = 4\nl = [1, \"two\", True, None]\na = A()\n"
+                    .to_string(),
+            )
+            .unwrap();
+        assert_eq!(
+            interpreter.command(&format!("%save {}", path)).unwrap(),
+            format!("Saved session to {}, skipped: a\n", path)
+        );
+
+        let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+        let mut restored = Interpreter::new_with_output(output.clone());
+        restored.command(&format!("%restore {}", path)).unwrap();
+        restored
+            .run("print(f(n))\nprint(l)\nprint(A().get())".to_string())
+            .unwrap();
+
         assert_eq!(
-            output.as_str(),
-            expected,
-            "Test case \"{}\" failed",
-            test_case_name
+            output.borrow().get_string().unwrap(),
+            "40\n[1, 'two', True, None]\na\n"
+        );
+    }
+
+    #[test]
+    fn test_load_file_error_reports_filename() {
+        let path = std::env::temp_dir().join("myton_test_load_file_error.my");
+        std::fs::write(&path, "a = 1\nprint(b)").unwrap();
+
+        let mut interpreter =
+            Interpreter::new_with_output(Rc::new(RefCell::new(Box::new(Vec::new()))));
+        let traceback = interpreter.load_file(path.to_str().unwrap()).unwrap_err();
+
+        let report = report_trace(traceback, Style::PLAIN);
+        assert!(
+            report.contains("myton_test_load_file_error.my>:2:"),
+            "{}",
+            report
+        );
+        assert!(report.contains("print(b)"), "{}", report);
+    }
+
+    #[test]
+    fn test_errors_show_the_chunk_they_are_in() {
+        let mut interpreter =
+            Interpreter::new_with_output(Rc::new(RefCell::new(Box::new(Vec::new()))));
+        interpreter
+            .run_named("def half(n):\n  return n.upper()\n".to_string(), "stdin-1")
+            .unwrap();
+        interpreter
+            .run_named("x = 2\n".to_string(), "stdin-2")
+            .unwrap();
+
+        let traceback = interpreter
+            .run_named("y = 1\nprint(x + z)\n".to_string(), "stdin-3")
+            .unwrap_err();
+        let report = report_trace(traceback, Style::PLAIN);
+        assert!(report.contains("<stdin-3>:2:"), "{}", report);
+        assert!(report.contains("2 | print(x + z)"), "{}", report);
+        assert!(!report.contains("def half"), "{}", report);
+
+        // raised in the first chunk, from a call in the third
+        let traceback = interpreter
+            .run_named("y = 1\nhalf(x)\n".to_string(), "stdin-3")
+            .unwrap_err();
+        let report = report_trace(traceback, Style::PLAIN);
+        assert!(report.contains("<stdin-1>:2:"), "{}", report);
+        assert!(report.contains("2 |   return n.upper()"), "{}", report);
+    }
+
+    #[test]
+    fn test_listings_do_not_depend_on_hashing() {
+        let source = "class Shape:
+  def area():
+    return 0
+  def perimeter():
+    return 0
+class Square(Shape):
+  def __init__(side):
+    self.side = side
+    self.name = \"square\"
+    self.corners = 4
+  def area():
+    return self.side * self.side
+zeta = 1
+alpha = [dir(Square(2)), dir(Square), Square(2).__dict__, dir(math)]
+print alpha
+";
+        let run = || {
+            let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+            let mut interpreter = Interpreter::new_with_output(output.clone());
+            interpreter.run(source.to_string()).unwrap();
+            let printed = output.borrow().get_string().unwrap();
+            (printed, interpreter.vars())
+        };
+
+        let first = run();
+        for _ in 0..5 {
+            assert_eq!(run(), first);
+        }
+        assert!(first.1.starts_with("Shape: "), "{}", first.1);
+    }
+
+    #[test]
+    fn test_assignment_targets_fail_alike() {
+        for (target, message) in [
+            (
+                "n.attribute",
+                "'number' object has no attribute 'attribute'",
+            ),
+            ("xs[1]", "list index out of range (index 1, length 1)"),
+            ("s[0]", "'str' object does not support item assignment"),
+        ] {
+            for statement in [
+                format!("{} = 1", target),
+                format!("print {} = 1", target),
+                format!("for {} in [1]:\n  pass", target),
+            ] {
+                let source = format!("n = 1\nxs = [1]\ns = \"a\"\n{}\n", statement);
+                let (stdout, stderr) = run_to_strings(source);
+                assert_eq!(stdout, "", "{:?}", statement);
+                assert!(stderr.contains(message), "{:?}: {}", statement, stderr);
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_nesting() {
+        let source = format!("print {}1{}", "(".repeat(50), ")".repeat(50));
+        let mut interpreter =
+            Interpreter::new_with_output(Rc::new(RefCell::new(Box::new(Vec::new()))));
+        assert!(interpreter.run(source.clone()).is_ok());
+
+        interpreter.set_max_nesting(20);
+        match interpreter.run(source) {
+            Err(traceback) => {
+                assert!(matches!(traceback.tipe, TracebackKind::SyntaxError));
+                assert_eq!(traceback.message(), "expression too deeply nested");
+            }
+            Ok(()) => panic!("50 parentheses are deeper than 20"),
+        }
+    }
+
+    #[test]
+    fn test_max_recursion() {
+        let source = "def down(n):\n  if n > 0:\n    down(n - 1)\ndown(50)".to_string();
+        let mut interpreter =
+            Interpreter::new_with_output(Rc::new(RefCell::new(Box::new(Vec::new()))));
+        assert!(interpreter.run(source.clone()).is_ok());
+
+        interpreter.set_max_recursion(20);
+        match interpreter.run(source) {
+            Err(traceback) => {
+                assert!(matches!(traceback.tipe, TracebackKind::RecursionError));
+                assert_eq!(traceback.message(), "maximum recursion depth exceeded");
+            }
+            Ok(()) => panic!("51 calls are more than 20"),
+        }
+
+        // every call that errored has returned
+        assert!(interpreter.run("down(19)".to_string()).is_ok());
+    }
+
+    #[test]
+    fn test_conversions() {
+        test_run_case("str of a number", "print str(12) + str(2.5)", "122.5\n");
+        test_run_case("int of a float", "print int(-7.9)", "-7\n");
+        test_run_case("int of a string", "print int(\"12\") + 1", "13\n");
+        test_run_case("float of a string", "print float(\"1.5\") * 2", "3\n");
+        test_run_case(
+            "round trip",
+            "x = 42\nprint int(str(x)) == x and float(str(x)) == x",
+            "True\n",
         );
+
+        // garbage raises, it never panics
+        for (source, message) in [
+            (
+                "int(\"12abc\")",
+                "invalid literal for int() with base 10: '12abc'",
+            ),
+            ("int(\"\")", "invalid literal for int() with base 10: ''"),
+            (
+                "int(\"1.5\")",
+                "invalid literal for int() with base 10: '1.5'",
+            ),
+            ("float(\"abc\")", "could not convert string to float: 'abc'"),
+        ] {
+            test_run_case(
+                source,
+                &format!("try:\n  {}\nexcept ValueError as e:\n  print e", source),
+                &format!("{}\n", message),
+            );
+        }
     }
 
     #[test]
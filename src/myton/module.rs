@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use super::environment::Env;
+use super::functions::NativeFunction;
+use super::traceback::Traceback;
+use super::types::DynValue;
+
+// a named, read-only bag of values reached with `.`,
+// which groups the natives like math.sqrt
+#[derive(Clone)]
+pub struct Module {
+    pub name: String,
+    attributes: Rc<BTreeMap<String, DynValue>>,
+}
+
+impl Module {
+    pub fn get(&self, name: &str) -> Option<DynValue> {
+        self.attributes.get(name).cloned()
+    }
+
+    // sorted
+    pub fn attribute_names(&self) -> Vec<String> {
+        self.attributes.keys().cloned().collect()
+    }
+}
+
+pub struct ModuleBuilder {
+    name: String,
+    attributes: BTreeMap<String, DynValue>,
+}
+
+impl ModuleBuilder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    pub fn value(mut self, name: &str, value: impl Into<DynValue>) -> Self {
+        self.attributes.insert(name.to_string(), value.into());
+        self
+    }
+
+    pub fn function(
+        self,
+        name: &str,
+        nb_args: usize,
+        func: impl Fn(&Env, Vec<DynValue>) -> Result<DynValue, Traceback> + 'static,
+    ) -> Self {
+        let native = NativeFunction::new(func, nb_args);
+        self.value(
+            name,
+            DynValue::from_native_function(native, name.to_string()),
+        )
+    }
+
+    // taking from nb_args to max_args arguments
+    pub fn optional_function(
+        self,
+        name: &str,
+        nb_args: usize,
+        max_args: usize,
+        func: impl Fn(&Env, Vec<DynValue>) -> Result<DynValue, Traceback> + 'static,
+    ) -> Self {
+        let native = NativeFunction::optional(func, nb_args, max_args);
+        self.value(
+            name,
+            DynValue::from_native_function(native, name.to_string()),
+        )
+    }
+
+    pub fn build(self) -> Module {
+        let name = self.name.clone();
+        let builder = self.value("__name__", name.as_str());
+        Module {
+            name,
+            attributes: Rc::new(builder.attributes),
+        }
+    }
+}
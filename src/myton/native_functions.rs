@@ -1,26 +1,210 @@
-use super::environment::{Env, EnvVariable};
-use super::functions::NativeFunction;
-use super::traceback::Traceback;
-use super::types::DynValue;
+use super::builtin_types::{elements, isinstance, GLOBAL_TYPES};
+use super::debugger::breakpoint;
+use super::dynamic::Dynamic;
+use super::environment::Env;
+use super::functions::{Callable, Kwargs, NativeFunction};
+use super::hashable::HashableKey;
+use super::iterator::{iterate, LazyIterator, Range};
+use super::methods::{attribute_names, with_dict, with_list};
+use super::module::{Module, ModuleBuilder};
+use super::session::{json_to_value, value_to_json, Json};
+use super::sources::SourceMap;
+use super::timestamp;
+use super::traceback::{Traceback, TracebackKind};
+use super::types::{CompareError, DynValue, TypeKind};
+use super::{MyRead, MyWrite};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::io::Write;
+use std::rc::Rc;
 
-pub fn define_globals(env: &Env) {
+fn native_functions() -> Vec<(&'static str, NativeFunction)> {
+    vec![
+        ("clock", NativeFunction::new(native_clock, 0)),
+        ("exit", NativeFunction::new(native_exit, 1)),
+        ("id", NativeFunction::new(native_id, 1)),
+        ("hash", NativeFunction::new(native_hash, 1)),
+        ("len", NativeFunction::new(native_len, 1)),
+        ("isinstance", NativeFunction::new(native_isinstance, 2)),
+        ("dir", NativeFunction::new(native_dir, 1)),
+        ("vars", NativeFunction::new(native_vars, 1)),
+        ("sorted", NativeFunction::with_keywords(native_sorted, 1)),
+        ("min", NativeFunction::with_keywords(native_min, 1)),
+        ("max", NativeFunction::with_keywords(native_max, 1)),
+        ("range", NativeFunction::optional(native_range, 1, 3)),
+        ("zip", NativeFunction::variadic(native_zip, 0)),
+        (
+            "enumerate",
+            NativeFunction::with_keywords(native_enumerate, 1),
+        ),
+        ("map", NativeFunction::new(native_map, 2)),
+        ("filter", NativeFunction::new(native_filter, 2)),
+        ("sum", NativeFunction::with_keywords(native_sum, 1)),
+        ("any", NativeFunction::new(native_any, 1)),
+        ("all", NativeFunction::new(native_all, 1)),
+        ("round", NativeFunction::optional(native_round, 1, 2)),
+        (
+            "format_number",
+            NativeFunction::new(native_format_number, 2),
+        ),
+    ]
+}
+
+// the natives grouped by theme, reached like math.sqrt
+fn modules() -> Vec<Module> {
+    vec![
+        ModuleBuilder::new("math")
+            .value("pi", std::f64::consts::PI)
+            .value("e", std::f64::consts::E)
+            .value("inf", f64::INFINITY)
+            .function("sqrt", 1, math_sqrt)
+            .function("floor", 1, |_, args| math_unary("floor", &args, f64::floor))
+            .function("ceil", 1, |_, args| math_unary("ceil", &args, f64::ceil))
+            .function("fabs", 1, |_, args| math_unary("fabs", &args, f64::abs))
+            .function("sin", 1, |_, args| math_unary("sin", &args, f64::sin))
+            .function("cos", 1, |_, args| math_unary("cos", &args, f64::cos))
+            .function("log", 1, math_log)
+            .function("pow", 2, math_pow)
+            .build(),
+        ModuleBuilder::new("json")
+            .function("dumps", 1, json_dumps)
+            .function("loads", 1, json_loads)
+            .build(),
+        ModuleBuilder::new("os")
+            .function("getenv", 1, os_getenv)
+            .function("getcwd", 0, os_getcwd)
+            .build(),
+        ModuleBuilder::new("time")
+            .function("time", 0, native_clock)
+            .function("sleep", 1, time_sleep)
+            .function("utcnow_iso", 0, time_utcnow_iso)
+            .optional_function("format_timestamp", 2, 3, time_format_timestamp)
+            .function("parse_timestamp", 2, time_parse_timestamp)
+            .build(),
+    ]
+}
+
+const MODULES: [&str; 4] = ["math", "json", "os", "time"];
+
+// the names of native_functions(), to look them up without building them
+const NATIVE_NAMES: [&str; 21] = [
+    "clock",
+    "exit",
+    "id",
+    "hash",
+    "len",
+    "isinstance",
+    "dir",
+    "vars",
+    "sorted",
+    "min",
+    "max",
+    "range",
+    "zip",
+    "enumerate",
+    "map",
+    "filter",
+    "sum",
+    "any",
+    "all",
+    "round",
+    "format_number",
+];
+
+// the natives using the interpreter's streams, defined apart
+const STREAM_NATIVES: [&str; 4] = ["input", "breakpoint", "flush", "eprint"];
+// set up with what they need from the interpreter
+const DYNAMIC_NATIVES: [&str; 2] = ["exec", "eval"];
+
+pub const MAIN_MODULE: &str = "__main__";
+
+// what the interpreter defines for the scripts, like __name__
+pub fn is_dunder(name: &str) -> bool {
+    name.len() > 4 && name.starts_with("__") && name.ends_with("__")
+}
+
+pub fn define_globals(
+    env: &Env,
+    input: Rc<RefCell<Box<dyn MyRead>>>,
+    output: Rc<RefCell<Box<dyn MyWrite>>>,
+    error_output: Rc<RefCell<Box<dyn MyWrite>>>,
+    sources: Rc<RefCell<SourceMap>>,
+) {
     let mut env = env.borrow_mut();
-    let native_functions: Vec<(&str, NativeFunction)> = vec![(
-        "clock",
-        NativeFunction {
-            func: native_clock,
-            nb_args: 0,
-        },
-    )];
 
-    for (name, func) in native_functions {
+    for (name, func) in native_functions() {
         env.set(
             name.to_string(),
             DynValue::from_native_function(func, name.to_string()),
         );
     }
+    let input_native = {
+        let input = input.clone();
+        let output = output.clone();
+        NativeFunction::optional(
+            move |_, args| native_input(&input, &output, args.first()),
+            0,
+            1,
+        )
+    };
+    env.set(
+        "input".to_string(),
+        DynValue::from_native_function(input_native, "input".to_string()),
+    );
+    let flush = {
+        let output = output.clone();
+        NativeFunction::new(move |_, _| native_flush(&output), 0)
+    };
+    env.set(
+        "flush".to_string(),
+        DynValue::from_native_function(flush, "flush".to_string()),
+    );
+    let eprint = {
+        let output = output.clone();
+        NativeFunction::new(
+            move |_, args| native_eprint(&output, &error_output, &args[0]),
+            1,
+        )
+    };
+    env.set(
+        "eprint".to_string(),
+        DynValue::from_native_function(eprint, "eprint".to_string()),
+    );
+    let dynamic = Dynamic { sources };
+    let exec = {
+        let dynamic = dynamic.clone();
+        NativeFunction::new(move |env, args| dynamic.exec(env, args), 1)
+    };
+    env.set(
+        "exec".to_string(),
+        DynValue::from_native_function(exec, "exec".to_string()),
+    );
+    let eval = NativeFunction::new(move |env, args| dynamic.eval(env, args), 1);
+    env.set(
+        "eval".to_string(),
+        DynValue::from_native_function(eval, "eval".to_string()),
+    );
+    let breakpoint = NativeFunction::new(move |env, _| breakpoint(env, &input, &output), 0);
+    env.set(
+        "breakpoint".to_string(),
+        DynValue::from_native_function(breakpoint, "breakpoint".to_string()),
+    );
+    // scripts, -c and the REPL are all run as the main module
+    env.set("__name__".to_string(), DynValue::from(MAIN_MODULE));
+    for tipe in GLOBAL_TYPES {
+        env.set(tipe.name().to_string(), DynValue::from(tipe));
+    }
+    for module in modules() {
+        env.set(module.name.clone(), DynValue::from(module));
+    }
+}
 
-    env.set_env_var(EnvVariable::NewLines, DynValue::from(0));
+pub fn is_builtin(name: &str) -> bool {
+    NATIVE_NAMES.contains(&name)
+        || STREAM_NATIVES.contains(&name)
+        || DYNAMIC_NATIVES.contains(&name)
+        || GLOBAL_TYPES.iter().any(|tipe| tipe.name() == name)
+        || MODULES.contains(&name)
 }
 
 pub fn native_clock(_: &Env, _: Vec<DynValue>) -> Result<DynValue, Traceback> {
@@ -33,3 +217,694 @@ pub fn native_clock(_: &Env, _: Vec<DynValue>) -> Result<DynValue, Traceback> {
         Err(Traceback::from_message("clock: time went backwards??"))
     }
 }
+
+pub fn native_exit(_: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    if args[0].tipe != TypeKind::Number {
+        return Err(Traceback::from_exception(
+            TracebackKind::TypeError,
+            &format!("exit: expected a number, got '{}'", args[0].tipe),
+        ));
+    }
+    Err(Traceback::from_exit_code(args[0].as_number() as i32))
+}
+
+pub fn native_id(_: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    Ok(DynValue::from(args[0].identity()))
+}
+
+pub fn native_hash(_: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    Ok(DynValue::from(HashableKey::new(&args[0])?.hash_value()))
+}
+
+// in characters for a string, not bytes
+pub fn native_len(_: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    let value = &args[0];
+    let len = match value.tipe {
+        TypeKind::Stringue => value.as_string().chars().count(),
+        // counted where they are, without cloning the elements
+        TypeKind::List | TypeKind::Tuple => value.with_elements(<[DynValue]>::len).unwrap(),
+        TypeKind::Dict => with_dict(value, |dict| dict.entries().len()),
+        TypeKind::Range => value.as_range().unwrap().len(),
+        _ => {
+            return Err(Traceback::from_exception(
+                TracebackKind::TypeError,
+                &format!("object of type '{}' has no len()", value.tipe),
+            ))
+        }
+    };
+    Ok(DynValue::from(len))
+}
+
+// rounds like python: a tie goes to the even neighbour, and what is
+// rounded is the number as stored. 2.675 is really 2.67499999...,
+// so round(2.675, 2) is 2.67 while round(2.5) is 2 and round(3.5) is 4.
+// A negative ndigits rounds to tens, hundreds...
+fn round_digits(number: f64, ndigits: i32) -> f64 {
+    if !number.is_finite() {
+        return number;
+    }
+    if ndigits >= 0 {
+        // rust's formatting rounds the exact value, ties to even
+        format!("{:.*}", ndigits as usize, number)
+            .parse()
+            .unwrap_or(number)
+    } else if -ndigits > f64::MAX_10_EXP {
+        // the scale would be inf, and 0 * inf nan
+        0f64.copysign(number)
+    } else {
+        let scale = 10f64.powi(-ndigits);
+        (number / scale).round_ties_even() * scale
+    }
+}
+
+fn ndigits_arg(function: &str, value: &DynValue) -> Result<i32, Traceback> {
+    let ndigits = number_arg(function, value)?;
+    if ndigits.fract() != 0.0 {
+        return Err(Traceback::from_exception(
+            TracebackKind::TypeError,
+            &format!("{}: ndigits must be an integer, not {}", function, ndigits),
+        ));
+    }
+    // past what a number can hold, it is unchanged or 0
+    Ok(ndigits.clamp(-400.0, 400.0) as i32)
+}
+
+pub fn native_round(_: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    let number = number_arg("round", &args[0])?;
+    match args.get(1).filter(|ndigits| ndigits.tipe != TypeKind::Nil) {
+        Some(ndigits) => Ok(DynValue::from(round_digits(
+            number,
+            ndigits_arg("round", ndigits)?,
+        ))),
+        None if number.is_nan() => Err(Traceback::from_exception(
+            TracebackKind::ValueError,
+            "cannot convert float NaN to integer",
+        )),
+        None if number.is_infinite() => Err(Traceback::from_exception(
+            TracebackKind::ValueError,
+            "cannot convert float infinity to integer",
+        )),
+        // an integer, which has no -0
+        None => Ok(DynValue::from(number.round_ties_even() + 0.0)),
+    }
+}
+
+// the number with exactly ndigits decimals, rounded like round()
+pub fn native_format_number(_: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    let number = number_arg("format_number", &args[0])?;
+    let ndigits = ndigits_arg("format_number", &args[1])?;
+    let rounded = round_digits(number, ndigits);
+    Ok(DynValue::from(format!(
+        "{:.*}",
+        ndigits.max(0) as usize,
+        rounded
+    )))
+}
+
+pub fn native_isinstance(_: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    Ok(DynValue::from(isinstance(&args[0], &args[1])?))
+}
+
+pub fn native_dir(_: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    Ok(attribute_names(&args[0])
+        .into_iter()
+        .map(DynValue::from)
+        .collect())
+}
+
+pub fn native_vars(_: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    match args[0].as_instance() {
+        Some(instance) => Ok(instance.borrow().fields_dict()),
+        None => Err(Traceback::from_exception(
+            TracebackKind::TypeError,
+            "vars() argument must have __dict__ attribute",
+        )),
+    }
+}
+
+// what sorted, min and max compare: the elements themselves or what the
+// key function gives for each, checked once to be comparable together
+fn comparison_keys(
+    env: &Env,
+    elements: &[DynValue],
+    key: Option<&DynValue>,
+) -> Result<Vec<DynValue>, Traceback> {
+    let keys = match key {
+        None => elements.to_vec(),
+        Some(key) => {
+            let callable = unary_callable(key)?;
+            elements
+                .iter()
+                .map(|element| callable.call(env, vec![element.clone()]))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+    };
+
+    if let Some(first) = keys.first() {
+        if let Some(error) = keys.iter().find_map(|other| first.try_cmp(other).err()) {
+            return Err(incomparable(error));
+        }
+    }
+    Ok(keys)
+}
+
+// a function given to a native, to be called with one value at a time
+fn unary_callable(function: &DynValue) -> Result<Box<dyn Callable>, Traceback> {
+    let callable = function.as_callable().ok_or_else(|| {
+        Traceback::from_exception(
+            TracebackKind::TypeError,
+            &format!("'{}' object is not callable", function.tipe),
+        )
+    })?;
+    if !callable.accepts(1) {
+        return Err(Traceback::from_exception(
+            TracebackKind::TypeError,
+            &callable.arity_error(1),
+        ));
+    }
+    Ok(callable)
+}
+
+fn incomparable(error: CompareError) -> Traceback {
+    error.into_traceback(|a, b| {
+        Traceback::from_exception(
+            TracebackKind::TypeError,
+            &format!("'<' not supported between instances of '{}' and '{}'", a, b),
+        )
+    })
+}
+
+// keys comparable with the first one can still hold elements that
+// aren't comparable together, the first ones met are kept to be raised
+fn compare(a: &DynValue, b: &DynValue, failed: &RefCell<Option<CompareError>>) -> Ordering {
+    match a.try_cmp(b) {
+        Ok(ordering) => ordering.unwrap_or(Ordering::Equal),
+        Err(error) => {
+            failed.borrow_mut().get_or_insert(error);
+            Ordering::Equal
+        }
+    }
+}
+
+// a list of numbers without a key is compared as plain numbers, which
+// orders them the same as their values do. They are read under one
+// borrow of the list, before any of its elements is cloned
+fn plain_numbers(iterable: &DynValue, key: Option<&DynValue>) -> Option<Vec<f64>> {
+    match key {
+        None => iterable.as_numbers(),
+        Some(_) => None,
+    }
+}
+
+fn compare_numbers(a: f64, b: f64) -> Ordering {
+    a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+}
+
+// key=None is the same as no key
+fn key_kwarg(kwargs: &mut Kwargs) -> Option<DynValue> {
+    kwargs.pop("key").filter(|key| !key.is_nil())
+}
+
+// stable, so elements with equal keys keep their order, reversed or not
+pub fn native_sorted(
+    env: &Env,
+    args: Vec<DynValue>,
+    mut kwargs: Kwargs,
+) -> Result<DynValue, Traceback> {
+    let key = key_kwarg(&mut kwargs);
+    let reverse = kwargs.pop_or("reverse", false).as_bool();
+    kwargs.finish()?;
+
+    if let Some(numbers) = plain_numbers(&args[0], key.as_ref()) {
+        let mut order: Vec<usize> = (0..numbers.len()).collect();
+        sort_order(&mut order, reverse, |a, b| {
+            compare_numbers(numbers[a], numbers[b])
+        });
+        return Ok(with_list(&args[0], |list| {
+            order.into_iter().map(|i| list[i].clone()).collect()
+        }));
+    }
+
+    let elements = elements(env, &args[0])?;
+    let keys = comparison_keys(env, &elements, key.as_ref())?;
+    let mut order: Vec<usize> = (0..elements.len()).collect();
+    let failed = RefCell::new(None);
+    sort_order(&mut order, reverse, |a, b| {
+        compare(&keys[a], &keys[b], &failed)
+    });
+    if let Some(error) = failed.take() {
+        return Err(incomparable(error));
+    }
+    // moved out of the elements rather than cloned again
+    let mut elements: Vec<Option<DynValue>> = elements.into_iter().map(Some).collect();
+    Ok(order
+        .into_iter()
+        .map(|i| elements[i].take().unwrap())
+        .collect())
+}
+
+fn sort_order(order: &mut [usize], reverse: bool, compare: impl Fn(usize, usize) -> Ordering) {
+    if reverse {
+        order.sort_by(|&a, &b| compare(b, a));
+    } else {
+        order.sort_by(|&a, &b| compare(a, b));
+    }
+}
+
+pub fn native_min(env: &Env, args: Vec<DynValue>, kwargs: Kwargs) -> Result<DynValue, Traceback> {
+    extreme("min", Ordering::Less, env, args, kwargs)
+}
+
+pub fn native_max(env: &Env, args: Vec<DynValue>, kwargs: Kwargs) -> Result<DynValue, Traceback> {
+    extreme("max", Ordering::Greater, env, args, kwargs)
+}
+
+// the first element whose key is the furthest in the wanted direction
+fn extreme(
+    name: &str,
+    wanted: Ordering,
+    env: &Env,
+    args: Vec<DynValue>,
+    mut kwargs: Kwargs,
+) -> Result<DynValue, Traceback> {
+    let key = key_kwarg(&mut kwargs);
+    kwargs.finish()?;
+
+    if let Some(numbers) = plain_numbers(&args[0], key.as_ref()) {
+        if numbers.is_empty() {
+            return Err(empty_sequence(name));
+        }
+        let best = furthest(numbers.len(), wanted, |a, b| {
+            compare_numbers(numbers[a], numbers[b])
+        });
+        return Ok(with_list(&args[0], |list| list[best].clone()));
+    }
+
+    let mut elements = elements(env, &args[0])?;
+    if elements.is_empty() {
+        return Err(empty_sequence(name));
+    }
+    let keys = comparison_keys(env, &elements, key.as_ref())?;
+    let failed = RefCell::new(None);
+    let best = furthest(keys.len(), wanted, |a, b| {
+        compare(&keys[a], &keys[b], &failed)
+    });
+    if let Some(error) = failed.take() {
+        return Err(incomparable(error));
+    }
+    Ok(elements.swap_remove(best))
+}
+
+fn empty_sequence(name: &str) -> Traceback {
+    Traceback::from_exception(
+        TracebackKind::ValueError,
+        &format!("{}() arg is an empty sequence", name),
+    )
+}
+
+fn furthest(len: usize, wanted: Ordering, compare: impl Fn(usize, usize) -> Ordering) -> usize {
+    let mut best = 0;
+    for i in 1..len {
+        if compare(i, best) == wanted {
+            best = i;
+        }
+    }
+    best
+}
+
+// the whole numbers of range(), past which floats skip some
+const MAX_RANGE: f64 = 9_007_199_254_740_992.0;
+
+fn range_arg(value: &DynValue) -> Result<f64, Traceback> {
+    let number = number_arg("range", value)?;
+    if number.fract() != 0.0 {
+        return Err(Traceback::from_exception(
+            TracebackKind::TypeError,
+            "'float' object cannot be interpreted as an integer",
+        ));
+    }
+    if number.abs() > MAX_RANGE {
+        return Err(Traceback::from_exception(
+            TracebackKind::ValueError,
+            "range() argument too large",
+        ));
+    }
+    Ok(number)
+}
+
+// range(stop), range(start, stop) or range(start, stop, step), each
+// number made when a loop gets to it rather than all up front
+pub fn native_range(_: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    let numbers = args.iter().map(range_arg).collect::<Result<Vec<_>, _>>()?;
+    let (start, stop, step) = match numbers[..] {
+        [stop] => (0.0, stop, 1.0),
+        [start, stop] => (start, stop, 1.0),
+        [start, stop, step] => (start, stop, step),
+        _ => unreachable!(),
+    };
+    if step == 0.0 {
+        return Err(Traceback::from_exception(
+            TracebackKind::ValueError,
+            "range() arg 3 must not be zero",
+        ));
+    }
+    Ok(DynValue::from(Range { start, stop, step }))
+}
+
+// tuples of the values taken together, up to the shortest iterable
+pub fn native_zip(env: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    let iterators = args
+        .iter()
+        .map(|arg| iterate(env, arg))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(DynValue::from(LazyIterator::new("zip", move |env| {
+        let mut values = Vec::new();
+        for iterator in &iterators {
+            match iterator.next(env)? {
+                Some(value) => values.push(value),
+                None => return Ok(None),
+            }
+        }
+        Ok((!values.is_empty()).then(|| DynValue::from_tuple(values)))
+    })))
+}
+
+pub fn native_enumerate(
+    env: &Env,
+    args: Vec<DynValue>,
+    mut kwargs: Kwargs,
+) -> Result<DynValue, Traceback> {
+    let mut index = number_arg("enumerate", &kwargs.pop_or("start", 0.0))?;
+    kwargs.finish()?;
+
+    let iterator = iterate(env, &args[0])?;
+    Ok(DynValue::from(LazyIterator::new("enumerate", move |env| {
+        Ok(iterator.next(env)?.map(|value| {
+            let pair = DynValue::from_tuple(vec![DynValue::from(index), value]);
+            index += 1.0;
+            pair
+        }))
+    })))
+}
+
+pub fn native_map(env: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    let function = unary_callable(&args[0])?;
+    let iterator = iterate(env, &args[1])?;
+    Ok(DynValue::from(LazyIterator::new(
+        "map",
+        move |env| match iterator.next(env)? {
+            Some(value) => Ok(Some(function.call(env, vec![value])?)),
+            None => Ok(None),
+        },
+    )))
+}
+
+// the values the function is true for, or the true values with None
+pub fn native_filter(env: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    let function = if args[0].is_nil() {
+        None
+    } else {
+        Some(unary_callable(&args[0])?)
+    };
+    let iterator = iterate(env, &args[1])?;
+    Ok(DynValue::from(LazyIterator::new("filter", move |env| {
+        while let Some(value) = iterator.next(env)? {
+            let keep = match &function {
+                Some(function) => function.call(env, vec![value.clone()])?.as_bool(),
+                None => value.as_bool(),
+            };
+            if keep {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    })))
+}
+
+pub fn native_sum(
+    env: &Env,
+    args: Vec<DynValue>,
+    mut kwargs: Kwargs,
+) -> Result<DynValue, Traceback> {
+    let mut total = number_arg("sum", &kwargs.pop_or("start", 0.0))?;
+    kwargs.finish()?;
+
+    if let Some(numbers) = args[0].as_numbers() {
+        for number in numbers {
+            total += number;
+        }
+        return Ok(DynValue::from(total));
+    }
+    let iterator = iterate(env, &args[0])?;
+    while let Some(value) = iterator.next(env)? {
+        if !matches!(value.tipe, TypeKind::Number | TypeKind::Boolean) {
+            return Err(Traceback::from_exception(
+                TracebackKind::TypeError,
+                &format!(
+                    "unsupported operand type(s) for +: 'number' and '{}'",
+                    value.tipe
+                ),
+            ));
+        }
+        total += value.as_number();
+    }
+    Ok(DynValue::from(total))
+}
+
+// both stop at the first value deciding the answer
+pub fn native_any(env: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    let iterator = iterate(env, &args[0])?;
+    while let Some(value) = iterator.next(env)? {
+        if value.as_bool() {
+            return Ok(DynValue::from(true));
+        }
+    }
+    Ok(DynValue::from(false))
+}
+
+pub fn native_all(env: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    let iterator = iterate(env, &args[0])?;
+    while let Some(value) = iterator.next(env)? {
+        if !value.as_bool() {
+            return Ok(DynValue::from(false));
+        }
+    }
+    Ok(DynValue::from(true))
+}
+
+fn number_arg(function: &str, value: &DynValue) -> Result<f64, Traceback> {
+    match value.tipe {
+        TypeKind::Number | TypeKind::Boolean => Ok(value.as_number()),
+        _ => Err(Traceback::from_exception(
+            TracebackKind::TypeError,
+            &format!("{}: must be a number, not '{}'", function, value.tipe),
+        )),
+    }
+}
+
+fn string_arg(function: &str, value: &DynValue) -> Result<String, Traceback> {
+    match value.tipe {
+        TypeKind::Stringue => Ok(value.as_string()),
+        _ => Err(Traceback::from_exception(
+            TracebackKind::TypeError,
+            &format!("{}: must be a string, not '{}'", function, value.tipe),
+        )),
+    }
+}
+
+fn math_domain_error() -> Traceback {
+    Traceback::from_exception(TracebackKind::ValueError, "math domain error")
+}
+
+fn math_unary(name: &str, args: &[DynValue], f: fn(f64) -> f64) -> Result<DynValue, Traceback> {
+    Ok(DynValue::from(f(number_arg(name, &args[0])?)))
+}
+
+fn math_sqrt(_: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    match number_arg("sqrt", &args[0])? {
+        x if x < 0.0 => Err(math_domain_error()),
+        x => Ok(DynValue::from(x.sqrt())),
+    }
+}
+
+fn math_log(_: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    match number_arg("log", &args[0])? {
+        x if x <= 0.0 => Err(math_domain_error()),
+        x => Ok(DynValue::from(x.ln())),
+    }
+}
+
+fn math_pow(_: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    let base = number_arg("pow", &args[0])?;
+    let exponent = number_arg("pow", &args[1])?;
+    Ok(DynValue::from(base.powf(exponent)))
+}
+
+fn json_dumps(_: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    match value_to_json(&args[0]) {
+        Some(json) => Ok(DynValue::from(json.render())),
+        None => Err(Traceback::from_exception(
+            TracebackKind::TypeError,
+            &format!("Object of type {} is not JSON serializable", args[0].tipe),
+        )),
+    }
+}
+
+fn json_loads(_: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    if args[0].tipe != TypeKind::Stringue {
+        return Err(Traceback::from_exception(
+            TracebackKind::TypeError,
+            &format!("the JSON object must be str, not '{}'", args[0].tipe),
+        ));
+    }
+    match Json::parse(&args[0].as_string()) {
+        Ok(json) => Ok(json_to_value(&json)),
+        Err(message) => Err(Traceback::from_exception(
+            TracebackKind::ValueError,
+            &message,
+        )),
+    }
+}
+
+fn os_getenv(_: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    match std::env::var(args[0].as_string()) {
+        Ok(value) => Ok(DynValue::from(value)),
+        Err(_) => Ok(DynValue::none()),
+    }
+}
+
+fn os_getcwd(_: &Env, _: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    std::env::current_dir()
+        .map(|dir| DynValue::from(dir.to_string_lossy().to_string()))
+        .map_err(|err| Traceback::from(format!("getcwd: {}", err)))
+}
+
+fn time_sleep(_: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    let seconds = number_arg("sleep", &args[0])?;
+    let duration = std::time::Duration::try_from_secs_f64(seconds).map_err(|_| {
+        Traceback::from_exception(
+            TracebackKind::ValueError,
+            "sleep length must be a non-negative finite number",
+        )
+    })?;
+    std::thread::sleep(duration);
+    Ok(DynValue::none())
+}
+
+fn time_value_error(message: String) -> Traceback {
+    Traceback::from_exception(TracebackKind::ValueError, &message)
+}
+
+fn time_utcnow_iso(env: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    let now = native_clock(env, args)?.as_number();
+    let iso = timestamp::iso_utc(now).map_err(time_value_error)?;
+    Ok(DynValue::from(iso))
+}
+
+fn time_format_timestamp(_: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    let seconds = number_arg("format_timestamp", &args[0])?;
+    let format = string_arg("format_timestamp", &args[1])?;
+    let offset = match args.get(2) {
+        Some(offset) => Some(string_arg("format_timestamp", offset)?),
+        None => None,
+    };
+    let formatted = timestamp::format_timestamp(seconds, &format, offset.as_deref())
+        .map_err(time_value_error)?;
+    Ok(DynValue::from(formatted))
+}
+
+fn time_parse_timestamp(_: &Env, args: Vec<DynValue>) -> Result<DynValue, Traceback> {
+    let text = string_arg("parse_timestamp", &args[0])?;
+    let format = string_arg("parse_timestamp", &args[1])?;
+    let seconds = timestamp::parse_timestamp(&text, &format).map_err(time_value_error)?;
+    Ok(DynValue::from(seconds))
+}
+
+// the prompt is written without a newline, the answer
+// being typed on the same line
+pub fn native_input(
+    input: &RefCell<Box<dyn MyRead>>,
+    output: &RefCell<Box<dyn MyWrite>>,
+    prompt: Option<&DynValue>,
+) -> Result<DynValue, Traceback> {
+    if let Some(prompt) = prompt {
+        write!(output.borrow_mut(), "{}", prompt.as_string())
+            .map_err(|err| Traceback::from(format!("could not print: {}", err)))?;
+        native_flush(output)?;
+    }
+    match input.borrow_mut().next_line() {
+        Ok(Some(line)) => Ok(DynValue::from(line)),
+        Ok(None) => Err(Traceback::from_exception(
+            TracebackKind::EOFError,
+            "EOF when reading a line",
+        )),
+        Err(err) => Err(Traceback::from(format!("input: {}", err))),
+    }
+}
+
+pub fn native_flush(output: &RefCell<Box<dyn MyWrite>>) -> Result<DynValue, Traceback> {
+    output
+        .borrow_mut()
+        .flush()
+        .map_err(|err| Traceback::from(format!("could not flush: {}", err)))?;
+    Ok(DynValue::none())
+}
+
+// what was printed before is flushed first, for the two
+// streams to show up in order when they go to the same place
+pub fn native_eprint(
+    output: &RefCell<Box<dyn MyWrite>>,
+    error_output: &RefCell<Box<dyn MyWrite>>,
+    value: &DynValue,
+) -> Result<DynValue, Traceback> {
+    native_flush(output)?;
+    writeln!(error_output.borrow_mut(), "{}", value.as_string())
+        .map_err(|err| Traceback::from(format!("could not print: {}", err)))?;
+    Ok(DynValue::none())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::environment::make_env;
+    use super::*;
+
+    fn len(value: DynValue) -> Result<DynValue, Traceback> {
+        native_len(&make_env(), vec![value])
+    }
+
+    #[test]
+    fn test_native_names() {
+        let names: Vec<&str> = native_functions().iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, NATIVE_NAMES);
+    }
+
+    #[test]
+    fn test_len_of_containers() {
+        assert_eq!(len(DynValue::from("")).unwrap(), DynValue::from(0.0));
+        assert_eq!(len(DynValue::from("héllo")).unwrap(), DynValue::from(5.0));
+        assert_eq!(
+            len(DynValue::from(vec![DynValue::from(1.0), DynValue::none()])).unwrap(),
+            DynValue::from(2.0)
+        );
+        assert_eq!(
+            len(DynValue::from_tuple(vec![DynValue::from("a")])).unwrap(),
+            DynValue::from(1.0)
+        );
+        let dict = DynValue::from_dict(vec![
+            (DynValue::from("a"), DynValue::from(1.0)),
+            (DynValue::from("b"), DynValue::from(2.0)),
+        ]);
+        assert_eq!(len(dict).unwrap(), DynValue::from(2.0));
+    }
+
+    #[test]
+    fn test_len_of_others() {
+        let error = len(DynValue::from(3.0)).unwrap_err();
+        assert!(matches!(error.tipe, TracebackKind::TypeError));
+        assert_eq!(
+            error.message.as_deref(),
+            Some("object of type 'number' has no len()")
+        );
+        assert!(len(DynValue::none()).is_err());
+    }
+}
@@ -0,0 +1,221 @@
+// parsing numbers out of strings by python's rules, for int(), float()
+// and the strings used as numbers, so that they all agree
+
+// the digits with single underscores between them, like 1_000
+fn without_underscores(digits: &str) -> Option<String> {
+    if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+        return None;
+    }
+    Some(digits.replace('_', ""))
+}
+
+fn split_sign(text: &str) -> (f64, &str) {
+    match text.strip_prefix('-') {
+        Some(rest) => (-1.0, rest),
+        None => (1.0, text.strip_prefix('+').unwrap_or(text)),
+    }
+}
+
+fn prefix_base(digits: &str) -> Option<u32> {
+    match digits.get(..2)?.to_ascii_lowercase().as_str() {
+        "0x" => Some(16),
+        "0o" => Some(8),
+        "0b" => Some(2),
+        _ => None,
+    }
+}
+
+// int(text, base): surrounding whitespace, a sign, underscores between
+// digits, the 0x/0o/0b prefix matching the base, or any with base 0
+pub fn parse_int(text: &str, base: u32) -> Option<f64> {
+    let (sign, digits) = split_sign(text.trim());
+    let (base, digits) = match prefix_base(digits) {
+        Some(prefixed) if base == 0 || base == prefixed => {
+            // an underscore may follow the prefix: 0x_ff
+            let rest = &digits[2..];
+            (prefixed, rest.strip_prefix('_').unwrap_or(rest))
+        }
+        _ if base == 0 => {
+            // 010 would be ambiguous, only zeros can lead
+            let decimal = digits.trim_start_matches(['0', '_']);
+            if digits.starts_with('0') && !decimal.is_empty() {
+                return None;
+            }
+            (10, digits)
+        }
+        _ => (base, digits),
+    };
+
+    let digits = without_underscores(digits)?;
+    if digits.is_empty() {
+        return None;
+    }
+    let values = digits
+        .chars()
+        .map(|c| c.to_digit(base))
+        .collect::<Option<Vec<_>>>()?;
+    let value = if base == 10 {
+        // rounded once, like the literal and float() of the same digits
+        digits.parse::<f64>().ok()?
+    } else {
+        exact_or_rounded(&values, base)
+    };
+    // -0 is 0 for an int
+    Some(sign * value + 0.0)
+}
+
+// exact up to u128, only longer numbers round at every digit
+fn exact_or_rounded(values: &[u32], base: u32) -> f64 {
+    let mut exact: u128 = 0;
+    for (i, &digit) in values.iter().enumerate() {
+        match exact
+            .checked_mul(base as u128)
+            .and_then(|shifted| shifted.checked_add(digit as u128))
+        {
+            Some(next) => exact = next,
+            None => {
+                return values[i..].iter().fold(exact as f64, |value, &digit| {
+                    value * base as f64 + digit as f64
+                })
+            }
+        }
+    }
+    exact as f64
+}
+
+// float(text): surrounding whitespace, a sign, underscores between
+// digits, an exponent, and inf, infinity or nan in any case
+pub fn parse_float(text: &str) -> Option<f64> {
+    let (sign, number) = split_sign(text.trim());
+    match number.to_ascii_lowercase().as_str() {
+        "inf" | "infinity" => return Some(sign * f64::INFINITY),
+        "nan" => return Some(f64::NAN),
+        _ => {}
+    }
+
+    let (mantissa, exponent) = match number.find(['e', 'E']) {
+        Some(index) => (&number[..index], Some(&number[index + 1..])),
+        None => (number, None),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let integer = without_underscores(integer)?;
+    let fraction = without_underscores(fraction)?;
+    let digits = format!("{}{}", integer, fraction);
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let zero_if_empty = |part: String| {
+        if part.is_empty() {
+            "0".to_string()
+        } else {
+            part
+        }
+    };
+    let mut cleaned = format!("{}.{}", zero_if_empty(integer), zero_if_empty(fraction));
+    if let Some(exponent) = exponent {
+        let (exponent_sign, digits) = match exponent.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", exponent.strip_prefix('+').unwrap_or(exponent)),
+        };
+        let digits = without_underscores(digits)?;
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        cleaned = format!("{}e{}{}", cleaned, exponent_sign, digits);
+    }
+    cleaned.parse::<f64>().ok().map(|value| sign * value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_int() {
+        let accepted = [
+            (" -42 ", 10, -42.0),
+            ("+7", 10, 7.0),
+            ("1_000", 10, 1000.0),
+            ("0x1A", 16, 26.0),
+            ("1a", 16, 26.0),
+            ("0b_101", 2, 5.0),
+            ("0o17", 0, 15.0),
+            ("0XfF", 0, 255.0),
+            ("000", 0, 0.0),
+            ("z", 36, 35.0),
+            ("\t12\n", 10, 12.0),
+            ("-0", 10, 0.0),
+            ("0012", 10, 12.0),
+            // rounded once, as the literals are
+            ("99999999999999999999", 10, 99999999999999999999.0),
+            ("-1_2345678901234567890123", 10, -12345678901234567890123.0),
+            ("9007199254740993", 10, 9007199254740993.0),
+            ("0x1fffffffffffff", 0, 9007199254740991.0),
+            ("0x20000000000001", 16, 9007199254740993.0),
+            (
+                "ffffffffffffffffffffffffffffffffffffffff",
+                16,
+                2f64.powi(160),
+            ),
+        ];
+        for (text, base, expected) in accepted {
+            assert_eq!(
+                parse_int(text, base),
+                Some(expected),
+                "int({:?}, {})",
+                text,
+                base
+            );
+        }
+
+        let rejected = [
+            ("1e3", 10),
+            ("1.5", 10),
+            ("", 10),
+            (" ", 10),
+            ("+", 10),
+            ("--1", 10),
+            ("_1", 10),
+            ("1_", 10),
+            ("1__0", 10),
+            ("0x1A", 10),
+            ("0x", 16),
+            ("0b2", 2),
+            ("012", 0),
+            ("1 2", 10),
+            ("inf", 10),
+            ("z", 35),
+        ];
+        for (text, base) in rejected {
+            assert_eq!(parse_int(text, base), None, "int({:?}, {})", text, base);
+        }
+    }
+
+    #[test]
+    fn test_parse_float() {
+        let accepted = [
+            ("1_000.5", 1000.5),
+            (" -2.5 ", -2.5),
+            ("+.5", 0.5),
+            ("5.", 5.0),
+            ("1e3", 1000.0),
+            ("1E-2", 0.01),
+            ("1_0e1_0", 1e11),
+            ("inf", f64::INFINITY),
+            ("-Infinity", f64::NEG_INFINITY),
+            ("007", 7.0),
+        ];
+        for (text, expected) in accepted {
+            assert_eq!(parse_float(text), Some(expected), "float({:?})", text);
+        }
+        assert!(parse_float("NaN").unwrap().is_nan());
+
+        let rejected = [
+            "", ".", "e3", "1e", "1e+", "1_.5", "1._5", "_1.5", "1.5_", "0x10", "1,5", "in f",
+            "infinit", "1.2.3", "--1", "1 e3",
+        ];
+        for text in rejected {
+            assert_eq!(parse_float(text), None, "float({:?})", text);
+        }
+    }
+}
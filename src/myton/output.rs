@@ -0,0 +1,89 @@
+use std::cell::{Cell, RefCell};
+use std::io::{Result, Write};
+use std::rc::Rc;
+
+use super::MyWrite;
+
+// passes the output through while counting the lines written,
+// so the REPL knows how far the cursor moved during a run
+pub struct LineTracker {
+    inner: Rc<RefCell<Box<dyn MyWrite>>>,
+    lines: Rc<Cell<usize>>,
+}
+
+impl LineTracker {
+    pub fn new(inner: Rc<RefCell<Box<dyn MyWrite>>>, lines: Rc<Cell<usize>>) -> Self {
+        Self { inner, lines }
+    }
+}
+
+impl Write for LineTracker {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let written = self.inner.borrow_mut().write(buf)?;
+        let new_lines = buf[..written].iter().filter(|&&b| b == b'\n').count();
+        self.lines.set(self.lines.get() + new_lines);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.borrow_mut().flush()
+    }
+}
+
+impl MyWrite for LineTracker {
+    fn get_string(&self) -> Option<String> {
+        self.inner.borrow().get_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_tracker() {
+        let inner = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+        let lines = Rc::new(Cell::new(0));
+        let mut tracker = LineTracker::new(inner.clone(), lines.clone());
+
+        writeln!(tracker, "one").unwrap();
+        assert_eq!(lines.get(), 1);
+
+        writeln!(tracker, "two\nthree\nfour").unwrap();
+        assert_eq!(lines.get(), 4);
+
+        // a line is only counted once it is finished
+        write!(tracker, "fi").unwrap();
+        assert_eq!(lines.get(), 4);
+        writeln!(tracker, "ve").unwrap();
+        assert_eq!(lines.get(), 5);
+
+        writeln!(tracker).unwrap();
+        assert_eq!(lines.get(), 6);
+
+        lines.set(0);
+        write!(tracker, "no newline").unwrap();
+        assert_eq!(lines.get(), 0);
+
+        assert_eq!(
+            tracker.get_string().unwrap(),
+            "one\ntwo\nthree\nfour\nfive\n\nno newline"
+        );
+        assert_eq!(inner.borrow().get_string(), tracker.get_string());
+    }
+
+    #[test]
+    fn test_lines_written_by_print() {
+        let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+        let mut interpreter = super::super::Interpreter::new_with_output(output.clone());
+
+        interpreter
+            .run("print 1\nprint \"\"\nprint [1, 2]".to_string())
+            .unwrap();
+        assert_eq!(interpreter.lines_written(), 3);
+
+        interpreter.reset_lines_written();
+        interpreter.run("x = 1".to_string()).unwrap();
+        assert_eq!(interpreter.lines_written(), 0);
+    }
+}
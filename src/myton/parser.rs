@@ -1,41 +1,140 @@
 use super::expression::*;
+use super::resolver::UUID;
 use super::statement::*;
 use super::token::{Token, TokenKind};
-use super::traceback::Traceback;
-use super::MyWrite;
-use std::cell::RefCell;
+use super::trace::{TraceMode, TracedStatement, Tracer};
+use super::traceback::{Traceback, TracebackKind};
 use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// deeper expressions and blocks would overflow the main thread's
+// stack, see Interpreter::set_max_nesting
+pub const MAX_NESTING: usize = 200;
+
+// the first id not taken by any parse yet, see fresh_uuids
+static NEXT_UUID: AtomicUsize = AtomicUsize::new(0);
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
-    output: Rc<RefCell<Box<dyn MyWrite>>>,
+    depth: usize,
+    // past it, expressions are too deeply nested
+    max_nesting: usize,
+    tracer: Option<Rc<Tracer>>,
+    // the ids of the expressions are this plus their token's index
+    first_uuid: UUID,
+    // while parsing recovering, the errors skipped over so far
+    recovered: Option<Vec<Traceback>>,
 }
 
 type ParseResult = Result<Vec<STMT>, Traceback>;
+// the positional arguments, the keyword ones and the closing ')'
+type ArgumentsResult = Result<(Vec<Argument>, Vec<(Token, EXPR)>, Token), Traceback>;
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>, output: Rc<RefCell<Box<dyn MyWrite>>>) -> Parser {
+    pub fn new(tokens: Vec<Token>) -> Parser {
         Parser {
             tokens,
             current: 0,
-            output,
+            depth: 0,
+            max_nesting: MAX_NESTING,
+            tracer: None,
+            first_uuid: 0,
+            recovered: None,
         }
     }
 
+    // ids no other parse with fresh ids has, so that code parsed apart
+    // (REPL entries, exec'd strings) never gets the distances resolved
+    // for the rest of the program
+    pub fn fresh_uuids(mut self) -> Parser {
+        self.first_uuid = NEXT_UUID.fetch_add(self.tokens.len() + 1, Ordering::Relaxed);
+        self
+    }
+
+    pub fn max_nesting(mut self, max_nesting: usize) -> Parser {
+        self.max_nesting = max_nesting;
+        self
+    }
+
+    fn uuid(&self) -> UUID {
+        self.first_uuid + self.current
+    }
+
+    // the statements and functions parsed from now on
+    // report to the tracer, as much as its mode asks
+    pub fn trace(&mut self, tracer: Rc<Tracer>) {
+        self.tracer = Some(tracer);
+    }
+
     pub fn parse(&mut self) -> ParseResult {
         let mut statements = Vec::new();
         while !self.is_at_end() {
-            statements.push(self.declaration()?);
+            statements.push(self.declaration().map_err(syntax_error)?);
         }
         Ok(statements)
     }
 
+    // what eval() runs: a single expression, alone in the source
+    pub fn parse_expression(&mut self) -> Result<EXPR, Traceback> {
+        // an assignment there is a statement, like "x = 1"
+        let expression = self.nested(|parser| parser.or()).map_err(syntax_error)?;
+        while self.match_token(vec![TokenKind::Newline]) {}
+        if !self.is_at_end() {
+            return Err(Traceback {
+                message: Some("invalid syntax".to_string()),
+                pos: self.peek().pos.unwrap_or_default(),
+                tipe: TracebackKind::SyntaxError,
+                ..Default::default()
+            });
+        }
+        Ok(expression)
+    }
+
+    // like parse, but keeps going after an error to report as many
+    // errors as possible in one go. The statements are what could be
+    // parsed, the blocks missing some lines can't be run
+    pub fn parse_recovering(&mut self) -> (Vec<STMT>, Vec<Traceback>) {
+        self.recovered = Some(Vec::new());
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            let indent_level = self.peek().indent;
+            match self.declaration() {
+                Ok(statement) => statements.push(statement),
+                Err(traceback) => self.recover(traceback, indent_level),
+            }
+        }
+        (statements, self.recovered.take().unwrap_or_default())
+    }
+
+    // skips to the next statement at the level of the one which failed
+    fn recover(&mut self, traceback: Traceback, indent_level: usize) {
+        if let Some(errors) = &mut self.recovered {
+            errors.push(syntax_error(traceback));
+        }
+        self.synchronize(indent_level);
+    }
+
     fn declaration(&mut self) -> Result<STMT, Traceback> {
+        let start = self.peek();
+        let statement = self.untraced_declaration()?;
+        match (&self.tracer, start.pos) {
+            (Some(tracer), Some((_, line)))
+                if tracer.mode >= TraceMode::Lines && start.kind != TokenKind::Newline =>
+            {
+                Ok(Box::new(TracedStatement {
+                    line,
+                    statement,
+                    tracer: tracer.clone(),
+                }))
+            }
+            _ => Ok(statement),
+        }
+    }
+
+    fn untraced_declaration(&mut self) -> Result<STMT, Traceback> {
         if self.match_token(vec![TokenKind::Def]) {
             self.function()
-        } else if self.check_sequence(vec![TokenKind::Identifier, TokenKind::Equal]) {
-            self.var_declaration()
         } else if self.match_token(vec![TokenKind::Class]) {
             self.class()
         } else {
@@ -44,14 +143,15 @@ impl Parser {
     }
 
     fn class(&mut self) -> Result<STMT, Traceback> {
-        let indent_level = self.previous().indent; // should be 0 right?
+        let keyword = self.previous();
+        let indent_level = keyword.indent; // should be 0 right?
         let name = self.consume(TokenKind::Identifier, "Expect class name.")?;
 
         let superclass = if self.match_token(vec![TokenKind::LeftParen]) {
             self.consume(TokenKind::Identifier, "Expect superclass name.")?;
             let name = self.previous().clone();
             self.consume(TokenKind::RightParen, "Expect ')' after superclass name.")?;
-            Some(Variable::new(name, self.current))
+            Some(Variable::new(name, self.uuid()))
         } else {
             None
         };
@@ -59,12 +159,36 @@ impl Parser {
         self.consume(TokenKind::Colon, "Expect ':' after class name.")?;
         self.consume(TokenKind::Newline, "Expect newline after class name.")?;
 
+        self.indented_block(&keyword, indent_level)?;
+
         let mut methods = Vec::new();
+        let mut attributes = Vec::new();
         while !self.is_at_end() && self.peek().indent > indent_level {
-            self.consume(TokenKind::Def, "Expect 'def' before class method.")?;
-            methods.push(self.function_inner()?);
+            if self.match_token(vec![TokenKind::Def]) {
+                methods.push(self.function_inner()?);
+            } else if self.check_sequence(vec![TokenKind::Identifier, TokenKind::Equal]) {
+                let name = self.advance();
+                self.advance();
+                attributes.push((name, self.expression()?));
+                self.consume(TokenKind::Newline, "Expect newline after class attribute.")?;
+            } else if self.match_token(vec![TokenKind::Stringue, TokenKind::Pass]) {
+                // docstrings are only there to be read
+                self.consume(TokenKind::Newline, "Expect newline after class member.")?;
+            } else if !self.match_token(vec![TokenKind::Newline]) {
+                return Err(Traceback {
+                    message: Some(
+                        "only methods, attributes and docstrings are allowed directly in a class body"
+                            .to_string(),
+                    ),
+                    pos: self.peek().pos.unwrap_or_default(),
+                    tipe: TracebackKind::SyntaxError,
+                    ..Default::default()
+                });
+            }
         }
-        Ok(Box::new(ClassStatement::new(name, methods, superclass)))
+        Ok(Box::new(ClassStatement::new(
+            name, methods, attributes, superclass,
+        )))
     }
 
     fn function(&mut self) -> Result<STMT, Traceback> {
@@ -72,31 +196,48 @@ impl Parser {
     }
 
     fn function_inner(&mut self) -> Result<FunctionStatement, Traceback> {
+        let keyword = self.previous();
         let name = self.consume(TokenKind::Identifier, "Expect function name.")?;
         self.consume(TokenKind::LeftParen, "Expect '(' after function name.")?;
-        let mut parameters = Vec::new();
-        if !self.check(TokenKind::RightParen) {
-            while {
-                parameters.push(self.consume(TokenKind::Identifier, "Expect parameter name.")?);
-                self.match_token(vec![TokenKind::Comma])
-            } {}
-        }
+        let parameters = self.comma_separated(TokenKind::RightParen, "parameter", |parser| {
+            parser.consume(TokenKind::Identifier, "Expect parameter name.")
+        })?;
         self.consume(TokenKind::RightParen, "Expect ')' after parameters.")?;
         self.consume(TokenKind::Colon, "Expect ':' before function body.")?;
-        let body = self.block_statement()?;
-        Ok(FunctionStatement::new(name, parameters, body))
+        let body = self.block_statement(&keyword)?;
+        let function = FunctionStatement::new(name, parameters, body);
+        if let Some(tracer) = &self.tracer {
+            if tracer.mode >= TraceMode::Calls {
+                function.inner.borrow_mut().tracer = Some(tracer.clone());
+            }
+        }
+        Ok(function)
     }
 
-    fn var_declaration(&mut self) -> Result<STMT, Traceback> {
-        let name = self.consume(TokenKind::Identifier, "Expect variable name.")?;
-        self.consume(TokenKind::Equal, "Expect '=' after variable name.")?;
-        let initializer = self.expression()?;
+    // an assignment on its own line binds a local,
+    // which the resolver needs to know from the start
+    fn var_declaration(&mut self, assign: EXPR) -> Result<STMT, Traceback> {
+        let assign = assign.into_any().downcast::<Assign>().unwrap();
 
         self.consume(
             TokenKind::Newline,
             "Expect newline after variable declaration.",
         )?;
-        Ok(Box::new(VarStatement { name, initializer }))
+        Ok(Box::new(VarStatement {
+            target: assign.target,
+            initializer: assign.value,
+        }))
+    }
+
+    // what an assignment-like statement stores into, which
+    // started at the given token, see AssignTarget
+    fn target(&mut self, target: EXPR, start: &Token) -> Result<AssignTarget, Traceback> {
+        AssignTarget::from_expr(target).map_err(|what| Traceback {
+            message: Some(format!("cannot assign to {}", what)),
+            pos: start.pos.unwrap_or_default(),
+            tipe: TracebackKind::SyntaxError,
+            ..Default::default()
+        })
     }
 
     fn statement(&mut self) -> Result<STMT, Traceback> {
@@ -114,6 +255,13 @@ impl Parser {
             self.global_statement()
         } else if self.match_token(vec![TokenKind::Nonlocal]) {
             self.nonlocal_statement()
+        } else if self.at_match_statement() {
+            self.advance();
+            self.match_statement()
+        } else if self.match_token(vec![TokenKind::Try]) {
+            self.try_statement()
+        } else if self.match_token(vec![TokenKind::Raise]) {
+            self.raise_statement()
         } else if self.match_token(vec![TokenKind::Newline]) {
             self.empty_statement()
         } else {
@@ -126,116 +274,389 @@ impl Parser {
         let value = if self.check(TokenKind::Newline) {
             None
         } else {
-            Some(self.expression()?)
+            Some(self.expression_list()?)
         };
         self.consume(TokenKind::Newline, "Expect newline after return value.")?;
         Ok(Box::new(ReturnStatement { keyword, value }))
     }
 
+    fn raise_statement(&mut self) -> Result<STMT, Traceback> {
+        let keyword = self.previous();
+        let exception = if self.check(TokenKind::Newline) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(TokenKind::Newline, "Expect newline after raise.")?;
+        Ok(Box::new(RaiseStatement { keyword, exception }))
+    }
+
     fn while_statement(&mut self) -> Result<STMT, Traceback> {
+        let keyword = self.previous();
         let condition = self.expression()?;
         self.consume(TokenKind::Colon, "Expect ':' after while condition.")?;
-        let body = self.block_statement()?;
+        let body = self.block_statement(&keyword)?;
 
         Ok(Box::new(WhileStatement { condition, body }))
     }
 
     fn for_statement(&mut self) -> Result<STMT, Traceback> {
-        let variable = self.consume(TokenKind::Identifier, "Expect variable name.")?;
+        let keyword = self.previous();
+        let start = self.peek();
+        // below comparisons, which would take the 'in'
+        let mut target = self.call()?;
+        if self.check(TokenKind::Comma) {
+            let mut elements = vec![target];
+            while self.match_token(vec![TokenKind::Comma]) {
+                elements.push(self.call()?);
+            }
+            target = Box::new(Tuple::new(start.clone(), elements, self.uuid()));
+        }
+        let target = self.target(target, &start)?;
         self.consume(TokenKind::In, "Expect 'in' after variable name.")?;
         let collection = self.expression()?;
         self.consume(TokenKind::Colon, "Expect ':' after for collection.")?;
-        let body = self.block_statement()?;
+        let body = self.block_statement(&keyword)?;
 
         Ok(Box::new(ForeachStatement {
-            variable,
+            target,
             collection,
             body,
         }))
     }
 
     fn if_statement(&mut self) -> Result<STMT, Traceback> {
+        let keyword = self.previous();
         let condition = self.expression()?;
         self.consume(TokenKind::Colon, "Expect ':' after if condition.")?;
-        let then_branch = self.block_statement()?;
+        let mut branches = vec![(condition, self.block_statement(&keyword)?)];
 
-        let else_branch = if self.match_token(vec![TokenKind::Else]) {
-            self.consume(TokenKind::Colon, "Expect ':' after else.")?;
-            Some(self.block_statement()?)
-        } else {
-            None
-        };
+        // the elifs and else at the if's own indentation, the ones
+        // less indented belong to an if this one is in
+        let mut else_branch = None;
+        while self.peek().indent == keyword.indent {
+            if self.match_token(vec![TokenKind::Elif]) {
+                let keyword = self.previous();
+                let condition = self.expression()?;
+                self.consume(TokenKind::Colon, "Expect ':' after elif condition.")?;
+                branches.push((condition, self.block_statement(&keyword)?));
+            } else if self.match_token(vec![TokenKind::Else]) {
+                let keyword = self.previous();
+                self.consume(TokenKind::Colon, "Expect ':' after else.")?;
+                else_branch = Some(self.block_statement(&keyword)?);
+                break;
+            } else {
+                break;
+            }
+        }
 
-        Ok(Box::new(IfStatement {
-            condition,
-            then_branch,
-            else_branch,
-        }))
+        // an elif is an if in the else branch of the one before
+        for (condition, then_branch) in branches.into_iter().rev() {
+            else_branch = Some(Box::new(IfStatement {
+                condition,
+                then_branch,
+                else_branch,
+            }));
+        }
+        Ok(else_branch.unwrap())
+    }
+
+    // match is a soft keyword: a statement only starts with it when
+    // a subject and ':' follow, otherwise it is a plain name
+    fn at_match_statement(&mut self) -> bool {
+        if !self.check_soft_keyword("match") {
+            return false;
+        }
+        let (start, depth) = (self.current, self.depth);
+        self.advance();
+        let is_match = self.expression().is_ok() && self.check(TokenKind::Colon);
+        (self.current, self.depth) = (start, depth);
+        is_match
+    }
+
+    fn match_statement(&mut self) -> Result<STMT, Traceback> {
+        let indent_level = self.previous().indent;
+        let subject = self.expression()?;
+        self.consume(TokenKind::Colon, "Expect ':' after match subject.")?;
+        self.consume(TokenKind::Newline, "Expect newline after match subject.")?;
+
+        let mut cases = Vec::new();
+        while !self.is_at_end() && self.peek().indent > indent_level {
+            if !self.check_soft_keyword("case") {
+                return Err(Traceback {
+                    message: Some("Expect 'case' inside match block.".to_string()),
+                    pos: self.previous().pos.unwrap_or_default(),
+                    ..Default::default()
+                });
+            }
+            let keyword = self.advance();
+            let pattern = self.pattern()?;
+            self.consume(TokenKind::Colon, "Expect ':' after case pattern.")?;
+            let body = self.block_statement(&keyword)?;
+            cases.push(MatchCase { pattern, body });
+        }
+
+        Ok(Box::new(MatchStatement { subject, cases }))
+    }
+
+    fn try_statement(&mut self) -> Result<STMT, Traceback> {
+        let keyword = self.previous();
+        let indent_level = keyword.indent;
+        self.consume(TokenKind::Colon, "Expect ':' after try.")?;
+        let body = self.block_statement(&keyword)?;
+
+        let mut handlers = Vec::new();
+        // the handlers of a nested try are more indented
+        while self.peek().indent == indent_level && self.match_token(vec![TokenKind::Except]) {
+            let keyword = self.previous();
+            let mut name = None;
+            let kind = if self.check(TokenKind::Identifier) {
+                let kind = self.advance();
+                if self.match_token(vec![TokenKind::As]) {
+                    name = Some(self.consume(TokenKind::Identifier, "Expect name after 'as'.")?);
+                }
+                Some(kind)
+            } else {
+                None
+            };
+            self.consume(TokenKind::Colon, "Expect ':' after except clause.")?;
+            let body = self.block_statement(&keyword)?;
+            handlers.push(ExceptHandler { kind, name, body });
+        }
+
+        if handlers.is_empty() {
+            return Err(Traceback {
+                pos: self.peek().pos.unwrap_or_default(),
+                message: Some("Expect 'except' after try block.".to_string()),
+                ..Default::default()
+            });
+        }
+
+        Ok(Box::new(TryStatement { body, handlers }))
     }
 
-    fn block_statement(&mut self) -> Result<STMT, Traceback> {
+    fn pattern(&mut self) -> Result<Pattern, Traceback> {
+        if self.match_token(vec![
+            TokenKind::Number,
+            TokenKind::Stringue,
+            TokenKind::False,
+            TokenKind::True,
+            TokenKind::Nil,
+        ]) {
+            return Ok(Pattern::Literal(self.previous()));
+        }
+        if self.match_token(vec![TokenKind::Minus]) {
+            let mut number = self.consume(TokenKind::Number, "Expect number after '-'.")?;
+            number.value = format!("-{}", number.value);
+            return Ok(Pattern::Literal(number));
+        }
+        if self.match_token(vec![TokenKind::Identifier]) {
+            let name = self.previous();
+            return Ok(if name.value == "_" {
+                Pattern::Wildcard
+            } else {
+                Pattern::Capture(name)
+            });
+        }
+        if self.match_token(vec![TokenKind::LeftBracket]) {
+            let patterns = self.comma_separated(TokenKind::RightBracket, "pattern", |parser| {
+                parser.pattern()
+            })?;
+            self.consume(TokenKind::RightBracket, "Expect ']' after patterns.")?;
+            return Ok(Pattern::Sequence(patterns));
+        }
+
+        Err(Traceback {
+            pos: self.peek().pos.unwrap_or_default(),
+            message: Some("Expect pattern.".to_string()),
+            ..Default::default()
+        })
+    }
+
+    // the body of the statement the keyword opened
+    fn block_statement(&mut self, keyword: &Token) -> Result<STMT, Traceback> {
         self.consume(TokenKind::Newline, "Expect newline before code block")?;
         let indent_level = self.previous().indent;
+        self.indented_block(keyword, indent_level)?;
         let mut statements = Vec::new();
+        let mut erroneous = false;
         while !self.is_at_end() && self.peek().indent > indent_level {
-            statements.push(self.declaration()?);
+            let statement_indent = self.peek().indent;
+            match self.nested(|parser| parser.declaration()) {
+                Ok(statement) => statements.push(statement),
+                // its siblings may still be checked
+                Err(traceback) if self.recovered.is_some() => {
+                    self.recover(traceback, statement_indent);
+                    erroneous = true;
+                }
+                Err(traceback) => return Err(traceback),
+            }
         }
-        Ok(Box::new(BlockStatement { statements }))
+        Ok(Box::new(BlockStatement {
+            statements,
+            erroneous,
+        }))
+    }
+
+    // a block whose first line isn't indented would be empty,
+    // its lines silently running after the statement instead
+    fn indented_block(&self, keyword: &Token, indent_level: usize) -> Result<(), Traceback> {
+        if !self.is_at_end() && self.peek().indent > indent_level {
+            return Ok(());
+        }
+        let opener = match keyword.kind {
+            TokenKind::Def => "function definition".to_string(),
+            TokenKind::Class => "class definition".to_string(),
+            _ => format!("'{}' statement", keyword.value),
+        };
+        let (_, line) = keyword.pos.unwrap_or_default();
+        Err(Traceback {
+            message: Some(format!(
+                "expected an indented block after {} on line {}",
+                opener,
+                line + 1
+            )),
+            pos: self.peek().pos.or(self.previous().pos).unwrap_or_default(),
+            tipe: TracebackKind::IndentationError,
+            ..Default::default()
+        })
     }
 
+    // print(a, b, sep="") takes arguments like a call does, when the
+    // line ends with its ')'. Otherwise, as in print (a + b) * 2, the
+    // parentheses are part of the one expression printed
     fn print_statement(&mut self) -> Result<STMT, Traceback> {
-        let expression = self.expression()?;
+        if self.check(TokenKind::LeftParen) {
+            let start = self.current;
+            self.advance();
+            if let Ok((arguments, keywords, _)) = self.arguments() {
+                if self.match_token(vec![TokenKind::Newline]) {
+                    return Ok(self.print(arguments, keywords));
+                }
+            }
+            self.current = start;
+        }
+        let value = self.expression()?;
         self.consume(TokenKind::Newline, "Expect newline after expression.")?;
-        Ok(Box::new(PrintStatement {
-            expression,
-            output: self.output.clone(),
-        }))
+        Ok(self.print(vec![Argument { star: None, value }], Vec::new()))
+    }
+
+    fn print(&self, arguments: Vec<Argument>, keywords: Vec<(Token, EXPR)>) -> STMT {
+        Box::new(PrintStatement {
+            arguments,
+            keywords,
+        })
     }
 
     fn expression_statement(&mut self) -> Result<STMT, Traceback> {
-        let expression = self.expression()?;
+        let expression = self.expression_list()?;
+        if let Some(assign) = expression.as_any().downcast_ref::<AssignExpr>() {
+            return Err(Traceback {
+                message: Some("invalid syntax. Maybe you meant '=' instead of ':='?".to_string()),
+                pos: assign.name.pos.unwrap_or_default(),
+                ..Default::default()
+            });
+        }
+        if expression.as_any().is::<Assign>() {
+            return self.var_declaration(expression);
+        }
         self.consume(TokenKind::Newline, "Expect newline after expression.")?;
         Ok(Box::new(ExpressionStatement { expression }))
     }
 
     fn expression(&mut self) -> Result<EXPR, Traceback> {
-        self.assignment()
+        self.nested(|parser| parser.assignment(false))
+    }
+
+    // where the elements of a tuple needn't be in parentheses:
+    // statements, the values they assign and the ones returned
+    fn expression_list(&mut self) -> Result<EXPR, Traceback> {
+        self.nested(|parser| parser.assignment(true))
+    }
+
+    fn nested<T>(
+        &mut self,
+        rule: impl FnOnce(&mut Self) -> Result<T, Traceback>,
+    ) -> Result<T, Traceback> {
+        if self.depth >= self.max_nesting {
+            return Err(self.too_deeply_nested());
+        }
+        self.depth += 1;
+        let res = rule(self);
+        self.depth -= 1;
+        res
     }
 
-    fn assignment(&mut self) -> Result<EXPR, Traceback> {
-        let expr = self.or()?;
+    // every link of a call chain nests the expression
+    // built so far a bit deeper
+    fn link(&self, links: &mut usize) -> Result<(), Traceback> {
+        *links += 1;
+        if self.depth + *links > self.max_nesting {
+            return Err(self.too_deeply_nested());
+        }
+        Ok(())
+    }
+
+    fn too_deeply_nested(&self) -> Traceback {
+        Traceback {
+            pos: self.peek().pos.unwrap_or_default(),
+            message: Some("expression too deeply nested".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn assignment(&mut self, tuple: bool) -> Result<EXPR, Traceback> {
+        let start = self.peek();
+        let expr = if tuple {
+            self.bare_tuple()?
+        } else {
+            self.or()?
+        };
+
+        if self.match_token(vec![TokenKind::ColonEqual]) {
+            return if let Ok(AssignTarget::Name(name)) = self.target(expr, &start) {
+                let value = self.assignment(false)?;
+                Ok(Box::new(AssignExpr::new(name, value, self.uuid())))
+            } else {
+                Err(Traceback {
+                    message: Some("Expect variable name before ':='.".to_string()),
+                    pos: self.previous().pos.unwrap_or_default(),
+                    ..Default::default()
+                })
+            };
+        }
 
         if self.match_token(vec![TokenKind::Equal]) {
-            if let Some(get) = expr.as_any().downcast_ref::<Get>() {
-                let value = self.assignment()?;
-
-                return if let Some(var) = get.object.as_any().downcast_ref::<Variable>().cloned() {
-                    Ok(Box::new(Set::new(
-                        Box::new(var),
-                        get.name.clone(),
-                        value,
-                        self.current,
-                    )))
-                } else if let Some(this) = get.object.as_any().downcast_ref::<This>().cloned() {
-                    Ok(Box::new(Set::new(
-                        Box::new(this.clone()),
-                        this.keyword.clone(),
-                        value,
-                        self.current,
-                    )))
-                } else {
-                    Err(Traceback {
-                        message: Some("Only instances have fields".to_string()),
-                        pos: self.previous().pos.unwrap(),
-                        ..Default::default()
-                    })
-                };
-            }
+            let target = self.target(expr, &start)?;
+            let value = self.assignment(tuple)?;
+            return Ok(Box::new(Assign::new(target, value, self.uuid())));
         }
 
         Ok(expr)
     }
 
+    // `a, b`, its elements below assignments so that in `a, b = pair`
+    // the `=` is the statement's. A trailing comma is allowed: `a, = xs`
+    fn bare_tuple(&mut self) -> Result<EXPR, Traceback> {
+        let start = self.peek();
+        let first = self.or()?;
+        if !self.check(TokenKind::Comma) {
+            return Ok(first);
+        }
+
+        let mut elements = vec![first];
+        while self.match_token(vec![TokenKind::Comma]) {
+            if matches!(
+                self.peektype(),
+                TokenKind::Newline | TokenKind::Eof | TokenKind::Equal
+            ) {
+                break;
+            }
+            elements.push(self.or()?);
+        }
+        Ok(Box::new(Tuple::new(start, elements, self.uuid())))
+    }
+
     fn global_statement(&mut self) -> Result<STMT, Traceback> {
         let mut names = Vec::new();
         while {
@@ -260,74 +681,90 @@ impl Parser {
     }
 
     fn or(&mut self) -> Result<EXPR, Traceback> {
-        let mut expr = self.and()?;
-
-        while self.match_token(vec![TokenKind::Or]) {
-            let operator = self.previous();
-            let right = self.and()?;
-            expr = Box::new(Logical::new(expr, operator, right, self.current));
-        }
-
-        Ok(expr)
+        let expr = self.and()?;
+        self.chain(
+            expr,
+            vec![TokenKind::Or],
+            Self::and,
+            |left, operator, right, uuid| Box::new(Logical::new(left, operator, right, uuid)),
+        )
     }
 
     fn and(&mut self) -> Result<EXPR, Traceback> {
-        let mut expr = self.equality()?;
+        let expr = self.not()?;
+        self.chain(
+            expr,
+            vec![TokenKind::And],
+            Self::not,
+            |left, operator, right, uuid| Box::new(Logical::new(left, operator, right, uuid)),
+        )
+    }
 
-        while self.match_token(vec![TokenKind::And]) {
+    // like python's, looser than comparisons: not a == b is not (a == b)
+    fn not(&mut self) -> Result<EXPR, Traceback> {
+        if self.match_token(vec![TokenKind::Not]) {
             let operator = self.previous();
-            let right = self.equality()?;
-            expr = Box::new(Logical::new(expr, operator, right, self.current));
+            let right = self.nested(|parser| parser.not())?;
+            return Ok(Box::new(Unary::new(operator, right, self.uuid())));
         }
-
-        Ok(expr)
+        self.equality()
     }
 
     fn equality(&mut self) -> Result<EXPR, Traceback> {
-        let mut expr = self.comparison()?;
-        while self.match_token(vec![
+        let expr = self.comparison()?;
+        let operators = vec![
             TokenKind::BangEqual,
             TokenKind::EqualEqual,
             TokenKind::EqualEqualEqual,
-        ]) {
-            let operator = self.previous();
-            let right = self.comparison()?;
-            expr = Box::new(Binary::new(expr, operator, right, self.current));
-        }
-        Ok(expr)
+            TokenKind::Is,
+            TokenKind::In,
+            TokenKind::NotIn,
+        ];
+        self.chain(expr, operators, Self::comparison, binary)
     }
 
     fn comparison(&mut self) -> Result<EXPR, Traceback> {
-        let mut expr = self.term()?;
-        while self.match_token(vec![
+        let expr = self.term()?;
+        let operators = vec![
             TokenKind::Greater,
             TokenKind::GreaterEqual,
             TokenKind::Less,
             TokenKind::LessEqual,
-        ]) {
-            let operator = self.previous();
-            let right = self.term()?;
-            expr = Box::new(Binary::new(expr, operator, right, self.current));
-        }
-        Ok(expr)
+        ];
+        self.chain(expr, operators, Self::term, binary)
     }
 
     fn term(&mut self) -> Result<EXPR, Traceback> {
-        let mut expr = self.factor()?;
-        while self.match_token(vec![TokenKind::Plus, TokenKind::Minus]) {
-            let operator = self.previous();
-            let right = self.factor()?;
-            expr = Box::new(Binary::new(expr, operator, right, self.current));
-        }
-        Ok(expr)
+        let expr = self.factor()?;
+        self.chain(
+            expr,
+            vec![TokenKind::Plus, TokenKind::Minus],
+            Self::factor,
+            binary,
+        )
     }
 
     fn factor(&mut self) -> Result<EXPR, Traceback> {
-        let mut expr = self.unary()?;
-        while self.match_token(vec![TokenKind::Star, TokenKind::Slash, TokenKind::Percent]) {
+        let expr = self.unary()?;
+        let operators = vec![TokenKind::Star, TokenKind::Slash, TokenKind::Percent];
+        self.chain(expr, operators, Self::unary, binary)
+    }
+
+    // the operators of one precedence level following its first operand,
+    // grouping from the left. The chain is not nesting: however long,
+    // what goes through it loops over it, see Binary::chain. And the
+    // levels of nesting in that first operand don't pay for this frame
+    fn chain(
+        &mut self,
+        mut expr: EXPR,
+        operators: Vec<TokenKind>,
+        operand: fn(&mut Self) -> Result<EXPR, Traceback>,
+        node: fn(EXPR, Token, EXPR, UUID) -> EXPR,
+    ) -> Result<EXPR, Traceback> {
+        while self.match_token(operators.clone()) {
             let operator = self.previous();
-            let right = self.unary()?;
-            expr = Box::new(Binary::new(expr, operator, right, self.current));
+            let right = operand(self)?;
+            expr = node(expr, operator, right, self.uuid());
         }
         Ok(expr)
     }
@@ -335,21 +772,47 @@ impl Parser {
     fn unary(&mut self) -> Result<EXPR, Traceback> {
         if self.match_token(vec![TokenKind::Bang, TokenKind::Minus]) {
             let operator = self.previous();
-            let right = self.unary()?;
-            return Ok(Box::new(Unary::new(operator, right, self.current)));
+            let right = self.nested(|parser| parser.unary())?;
+            return Ok(Box::new(Unary::new(operator, right, self.uuid())));
         }
-        self.call()
+        let expr = self.call()?;
+        if self.check(TokenKind::StarStar) {
+            return self.power(expr);
+        }
+        Ok(expr)
+    }
+
+    // binds tighter than a unary on its left, -2 ** 2 is -(2 ** 2),
+    // but takes one on its right: 2 ** -1, and groups from the right.
+    // Called from unary once the '**' is seen, so that the levels of
+    // nesting without one don't pay for its frame
+    fn power(&mut self, base: EXPR) -> Result<EXPR, Traceback> {
+        let operator = self.advance();
+        let exponent = self.nested(|parser| parser.unary())?;
+        Ok(Box::new(Binary::new(base, operator, exponent, self.uuid())))
     }
 
     fn call(&mut self) -> Result<EXPR, Traceback> {
         let mut expr = self.primary()?;
+        let mut links = 0;
         loop {
+            if self.check(TokenKind::LeftParen)
+                || self.check(TokenKind::Dot)
+                || self.check(TokenKind::LeftBracket)
+            {
+                self.link(&mut links)?;
+            }
             if self.match_token(vec![TokenKind::LeftParen]) {
                 expr = self.finish_call(expr)?;
             } else if self.match_token(vec![TokenKind::Dot]) {
                 let name =
                     self.consume(TokenKind::Identifier, "Expect property name after '.'.")?;
-                expr = Box::new(Get::new(expr, name, self.current));
+                expr = Box::new(Get::new(expr, name, self.uuid()));
+            } else if self.match_token(vec![TokenKind::LeftBracket]) {
+                let bracket = self.previous();
+                let index = self.expression()?;
+                self.consume_closing(&bracket, TokenKind::RightBracket, "Expect ']' after index.")?;
+                expr = Box::new(Subscript::new(expr, bracket, index, self.uuid()));
             } else {
                 break;
             }
@@ -358,15 +821,110 @@ impl Parser {
     }
 
     fn finish_call(&mut self, callee: EXPR) -> Result<EXPR, Traceback> {
+        let (arguments, keywords, paren) = self.arguments()?;
+        Ok(Box::new(Call::new(
+            callee,
+            paren,
+            arguments,
+            keywords,
+            self.uuid(),
+        )))
+    }
+
+    // after the '(', the positional arguments then the keyword ones,
+    // up to the ')' returned with them
+    fn arguments(&mut self) -> ArgumentsResult {
+        let opening = self.previous();
         let mut arguments = Vec::new();
+        let mut keywords: Vec<(Token, EXPR)> = Vec::new();
+        self.comma_separated(TokenKind::RightParen, "expression", |parser| {
+            let start = parser.peek();
+            if parser.check_sequence(vec![TokenKind::Identifier, TokenKind::Equal]) {
+                let name = parser.advance();
+                parser.advance();
+                if keywords.iter().any(|(other, _)| other.value == name.value) {
+                    return Err(Traceback {
+                        message: Some(format!("keyword argument repeated: '{}'", name.value)),
+                        pos: name.pos.unwrap_or_default(),
+                        tipe: TracebackKind::SyntaxError,
+                        ..Default::default()
+                    });
+                }
+                keywords.push((name, parser.expression()?));
+            } else if keywords.is_empty() {
+                let star = if parser.match_token(vec![TokenKind::Star]) {
+                    Some(parser.previous())
+                } else {
+                    None
+                };
+                let value = parser.expression()?;
+                arguments.push(Argument { star, value });
+            } else {
+                return Err(Traceback {
+                    message: Some("positional argument follows keyword argument".to_string()),
+                    pos: start.pos.unwrap_or_default(),
+                    tipe: TracebackKind::SyntaxError,
+                    ..Default::default()
+                });
+            }
+            Ok(())
+        })?;
+        let paren = self.consume_closing(
+            &opening,
+            TokenKind::RightParen,
+            "Expect ')' after arguments.",
+        )?;
+        Ok((arguments, keywords, paren))
+    }
+
+    // after the '{'. Apart from primary, whose frame every level of
+    // nesting pays for, and an entry counting as a level of its own
+    // since a dict takes about twice the stack of a list
+    fn dict_literal(&mut self) -> Result<EXPR, Traceback> {
+        let brace = self.previous();
+        let entries = self.comma_separated(TokenKind::RightBrace, "entry", |parser| {
+            parser.nested(|parser| {
+                let key = parser.expression()?;
+                parser.consume(TokenKind::Colon, "Expect ':' after dict key.")?;
+                Ok((key, parser.expression()?))
+            })
+        })?;
+
+        self.consume_closing(&brace, TokenKind::RightBrace, "Expect '}' after entries.")?;
+        Ok(Box::new(DictLiteral::new(brace, entries, self.uuid())))
+    }
+
+    // after the '(', a grouping unless a comma makes it a tuple:
+    // `(1,)` has one element and `()` none. The first element is
+    // parsed directly, deep groupings paying for no more frames
+    fn parenthesized(&mut self) -> Result<EXPR, Traceback> {
+        let paren = self.previous();
+        let mut expr = None;
+        let mut elements = Vec::new();
         if !self.check(TokenKind::RightParen) {
-            while {
-                arguments.push(self.expression()?);
-                self.match_token(vec![TokenKind::Comma])
-            } {}
+            let first = self.expression()?;
+            if self.match_token(vec![TokenKind::Comma]) {
+                elements.push(first);
+                elements.extend(self.comma_separated(
+                    TokenKind::RightParen,
+                    "expression",
+                    |parser| parser.expression(),
+                )?);
+            } else {
+                expr = Some(first);
+            }
         }
-        let paren = self.consume(TokenKind::RightParen, "Expect ')' after arguments.")?;
-        Ok(Box::new(Call::new(callee, paren, arguments, self.current)))
+        self.consume_closing(
+            &paren,
+            TokenKind::RightParen,
+            "Expect ')' after expression.",
+        )?;
+
+        let expr = match expr {
+            Some(expr) => expr,
+            None => Box::new(Tuple::new(paren, elements, self.uuid())),
+        };
+        Ok(Box::new(Grouping::new(expr, self.uuid())))
     }
 
     fn primary(&mut self) -> Result<EXPR, Traceback> {
@@ -377,41 +935,51 @@ impl Parser {
             TokenKind::True,
             TokenKind::Nil,
         ]) {
-            return Ok(Box::new(Literal::new(self.previous(), self.current)));
+            return Ok(Box::new(Literal::new(self.previous(), self.uuid())));
         }
         if self.match_token(vec![TokenKind::Pass]) {
             return self.empty_expression();
         }
         if self.match_token(vec![TokenKind::LeftParen]) {
-            let expr = self.expression()?;
-            self.consume(TokenKind::RightParen, "Expect ')' after expression.")?;
-            return Ok(Box::new(Grouping::new(expr, self.current)));
+            return self.parenthesized();
         }
         if self.match_token(vec![TokenKind::Identifier]) {
-            return Ok(Box::new(Variable::new(self.previous(), self.current)));
+            return Ok(Box::new(Variable::new(self.previous(), self.uuid())));
         }
         if self.match_token(vec![TokenKind::LeftBracket]) {
-            let mut elements = Vec::new();
-            if !self.check(TokenKind::RightBracket) {
-                while {
-                    elements.push(self.expression()?);
-                    self.match_token(vec![TokenKind::Comma])
-                } {}
-            }
+            let bracket = self.previous();
+            let elements =
+                self.comma_separated(TokenKind::RightBracket, "expression", |parser| {
+                    parser.expression()
+                })?;
 
-            self.consume(TokenKind::RightBracket, "Expect ']' after expression.")?;
-            return Ok(Box::new(List::new(elements, self.current)));
+            self.consume_closing(
+                &bracket,
+                TokenKind::RightBracket,
+                "Expect ']' after expression.",
+            )?;
+            return Ok(Box::new(List::new(elements, self.uuid())));
+        }
+        if self.match_token(vec![TokenKind::LeftBrace]) {
+            return self.dict_literal();
         }
         if self.match_token(vec![TokenKind::Selph]) {
-            return Ok(Box::new(This::new(self.previous(), self.current)));
+            return Ok(Box::new(This::new(self.previous(), self.uuid())));
         }
         if self.match_token(vec![TokenKind::Super]) {
             let keyword = self.previous();
             self.consume(TokenKind::Dot, "Expect '.' after 'super'.")?;
             let method = self.consume(TokenKind::Identifier, "Expect superclass method name.")?;
-            return Ok(Box::new(Super::new(keyword, method, self.current)));
+            return Ok(Box::new(Super::new(keyword, method, self.uuid())));
         }
 
+        // the line ended before the expression, inside brackets
+        // that is the actual mistake
+        if matches!(self.peektype(), TokenKind::Newline | TokenKind::Eof) {
+            if let Some(opening) = self.unclosed_bracket() {
+                return Err(never_closed(&opening));
+            }
+        }
         Err(Traceback {
             pos: self.peek().pos.unwrap_or_default(),
             message: Some("Expect expression.".to_string()),
@@ -419,10 +987,62 @@ impl Parser {
         })
     }
 
+    // the innermost bracket still open on the line so far
+    fn unclosed_bracket(&self) -> Option<Token> {
+        let mut closed = 0;
+        for token in self.tokens[..self.current.min(self.tokens.len())]
+            .iter()
+            .rev()
+        {
+            match token.kind {
+                TokenKind::Newline => break,
+                TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace => {
+                    closed += 1
+                }
+                TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::LeftBrace => {
+                    if closed == 0 {
+                        return Some(token.clone());
+                    }
+                    closed -= 1;
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    // items up to the closing token, which is left to the caller,
+    // with an optional trailing comma. What an item is only
+    // shows in the error for a comma with nothing before it
+    fn comma_separated<T>(
+        &mut self,
+        closing: TokenKind,
+        what: &str,
+        mut item: impl FnMut(&mut Self) -> Result<T, Traceback>,
+    ) -> Result<Vec<T>, Traceback> {
+        let mut items = Vec::new();
+        while !self.check(closing) {
+            if self.check(TokenKind::Comma) {
+                let place = if items.is_empty() { "before" } else { "after" };
+                return Err(Traceback {
+                    pos: self.peek().pos.unwrap_or_default(),
+                    message: Some(format!("expected {} {} ','", what, place)),
+                    tipe: TracebackKind::SyntaxError,
+                    ..Default::default()
+                });
+            }
+            items.push(item(self)?);
+            if !self.match_token(vec![TokenKind::Comma]) {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
     fn empty_expression(&mut self) -> Result<EXPR, Traceback> {
         let mut token = self.previous();
         token.kind = TokenKind::Nil;
-        Ok(Box::new(Literal::new(token, self.current)))
+        Ok(Box::new(Literal::new(token, self.uuid())))
     }
 
     fn empty_statement(&mut self) -> Result<STMT, Traceback> {
@@ -448,6 +1068,11 @@ impl Parser {
         self.peektype() == token_type
     }
 
+    // the names which are keywords only in some places
+    fn check_soft_keyword(&self, keyword: &str) -> bool {
+        self.check(TokenKind::Identifier) && self.peek().value == keyword
+    }
+
     fn check_sequence(&self, token_types: Vec<TokenKind>) -> bool {
         if self.is_at_end() {
             return false;
@@ -470,19 +1095,36 @@ impl Parser {
     }
 
     fn peektype(&self) -> TokenKind {
-        self.tokens[self.current].kind.clone()
+        self.peek().kind
     }
 
+    // past the last token (or without any), behaves as if at Eof
     fn peek(&self) -> Token {
-        self.tokens[self.current].clone()
+        self.tokens
+            .get(self.current)
+            .cloned()
+            .unwrap_or_else(|| Token::from_token_kind(TokenKind::Eof))
     }
 
     fn previous(&self) -> Token {
-        if self.current == 0 {
-            self.tokens[0].clone()
-        } else {
-            self.tokens[self.current - 1].clone()
+        self.tokens
+            .get(self.current.saturating_sub(1))
+            .cloned()
+            .unwrap_or_else(|| Token::from_token_kind(TokenKind::Eof))
+    }
+
+    // like consume, but when the line ends first the error points
+    // at the opening token, which is where the fix usually goes
+    fn consume_closing(
+        &mut self,
+        opening: &Token,
+        closing: TokenKind,
+        message: &str,
+    ) -> Result<Token, Traceback> {
+        if matches!(self.peektype(), TokenKind::Newline | TokenKind::Eof) {
+            return Err(never_closed(opening));
         }
+        self.consume(closing, message)
     }
 
     fn consume(&mut self, token_type: TokenKind, message: &str) -> Result<Token, Traceback> {
@@ -499,29 +1141,228 @@ impl Parser {
         }
         Err(Traceback {
             message: Some(format!("{}", message)),
-            pos: self.previous().pos.unwrap(),
+            pos: self.previous().pos.unwrap_or_default(),
             ..Default::default()
         })
     }
 
-    #[allow(dead_code)] // #TODO: remove this
-    fn synchronize(&mut self) {
+    // skips the rest of the broken statement, including
+    // any block indented below it
+    fn synchronize(&mut self, indent_level: usize) {
         self.advance();
         while !self.is_at_end() {
-            if self.previous().kind == TokenKind::Newline {
+            if self.previous().kind == TokenKind::Newline && self.peek().indent <= indent_level {
                 return;
             }
-            match self.peektype() {
-                TokenKind::Class => return,
-                TokenKind::Def => return,
-                TokenKind::For => return,
-                TokenKind::If => return,
-                TokenKind::While => return,
-                TokenKind::Print => return,
-                TokenKind::Return => return,
-                _ => (),
-            }
             self.advance();
         }
     }
 }
+
+fn binary(left: EXPR, operator: Token, right: EXPR, uuid: UUID) -> EXPR {
+    Box::new(Binary::new(left, operator, right, uuid))
+}
+
+// the line ended inside the brackets this token opened
+fn never_closed(opening: &Token) -> Traceback {
+    Traceback {
+        message: Some(format!("'{}' was never closed", opening.value)),
+        pos: opening.pos.unwrap_or_default(),
+        ..Default::default()
+    }
+}
+
+// errors of the parser are syntax errors, unless they have a more precise kind
+fn syntax_error(mut traceback: Traceback) -> Traceback {
+    if matches!(traceback.tipe, TracebackKind::Error) {
+        traceback.tipe = TracebackKind::SyntaxError;
+    }
+    traceback
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::myton::{lexer::Lexer, parser::Parser, Interpreter};
+
+    fn parse_error(code: &str) -> Option<String> {
+        let interpreter = Interpreter::new();
+        let tokens = Lexer::new(code.to_string()).tokenize().unwrap();
+        Parser::new(tokens)
+            .parse()
+            .err()
+            .map(|traceback| traceback.message().to_string())
+    }
+
+    fn parses(code: &str) -> bool {
+        parse_error(code).is_none()
+    }
+
+    #[test]
+    fn test_max_nesting() {
+        let nesting_error = |code: &str, max_nesting: usize| {
+            let interpreter = Interpreter::new();
+            let tokens = Lexer::new(code.to_string()).tokenize().unwrap();
+            Parser::new(tokens)
+                .max_nesting(max_nesting)
+                .parse()
+                .err()
+                .map(|traceback| traceback.message().to_string())
+        };
+        let too_deep = Some("expression too deeply nested".to_string());
+
+        let parens = format!("{}1{}", "(".repeat(20), ")".repeat(20));
+        let calls = "f".to_string() + &"()".repeat(20);
+        let unary = "-".repeat(20) + "1";
+        for code in [&parens, &calls, &unary] {
+            assert_eq!(nesting_error(code, 10), too_deep, "{}", code);
+            assert_eq!(nesting_error(code, 30), None, "{}", code);
+        }
+
+        // a chain of operators isn't nesting, however long
+        let chain = ["1"; 300].join(" - ");
+        assert_eq!(nesting_error(&chain, 10), None);
+        assert!(parses(&["True"; 300].join(" and ")));
+    }
+
+    #[test]
+    fn test_trailing_commas() {
+        for code in [
+            "f(1, 2,)",
+            "f(1,)",
+            "[1, 2, 3,]",
+            "[1,]",
+            "def f(a, b,):\n  pass",
+            "def f(a,):\n  pass",
+            "match x:\n  case [1, 2,]:\n    pass",
+            "f()",
+            "[]",
+        ] {
+            assert!(parses(code), "{:?} should parse", code);
+        }
+
+        for code in [
+            "f(1,,2)",
+            "f(1, 2,,)",
+            "f(,)",
+            "[1,,2]",
+            "[,]",
+            "def f(a,,b):\n  pass",
+            "def f(,):\n  pass",
+        ] {
+            assert!(!parses(code), "{:?} should not parse", code);
+        }
+    }
+
+    #[test]
+    fn test_list_literals() {
+        let deep = "[".repeat(30) + &"]".repeat(30);
+        for code in ["[]", "[[]]", "[1, [2, [3]]]", "[[], [[]],]", &deep] {
+            assert!(parses(code), "{:?} should parse", code);
+        }
+
+        for (code, message) in [
+            ("[,]", "expected expression before ','"),
+            ("[, 1]", "expected expression before ','"),
+            ("[1,,2]", "expected expression after ','"),
+            ("[[1], [2,,]]", "expected expression after ','"),
+            ("f(1, ,)", "expected expression after ','"),
+            ("def f(a,,b):\n  pass", "expected parameter after ','"),
+            (
+                "match x:\n  case [,]:\n    pass",
+                "expected pattern before ','",
+            ),
+        ] {
+            assert_eq!(parse_error(code).as_deref(), Some(message), "{:?}", code);
+        }
+    }
+
+    #[test]
+    fn test_keyword_arguments() {
+        for code in ["f(a=1)", "f(1, b=2, c=3)", "f(1, key=g,)", "f(a=b == c)"] {
+            assert!(parses(code), "{:?} should parse", code);
+        }
+
+        for (code, message) in [
+            ("f(a=1, 2)", "positional argument follows keyword argument"),
+            ("f(a=1, a=2)", "keyword argument repeated: 'a'"),
+            ("f(a=)", "Expect expression."),
+        ] {
+            assert_eq!(parse_error(code).as_deref(), Some(message), "{:?}", code);
+        }
+    }
+
+    #[test]
+    fn test_line_ends_inside_brackets() {
+        for (code, message) in [
+            ("x = (1 +", "'(' was never closed"),
+            ("x = (1 +\n", "'(' was never closed"),
+            ("f(a=", "'(' was never closed"),
+            ("[1, (2)] + [3, -", "'[' was never closed"),
+            ("{1: [2, (3 *", "'(' was never closed"),
+            ("x = 1 +", "Expect expression."),
+            ("(1) +", "Expect expression."),
+        ] {
+            assert_eq!(parse_error(code).as_deref(), Some(message), "{:?}", code);
+        }
+    }
+
+    #[test]
+    fn test_unindented_blocks() {
+        for (code, message) in [
+            ("if x:\nprint 1", "'if' statement on line 1"),
+            (
+                "if x:\n  pass\nelse:\nprint 1",
+                "'else' statement on line 3",
+            ),
+            ("x = 1\nwhile x:\nprint 1", "'while' statement on line 2"),
+            ("for x in y:\nprint x", "'for' statement on line 1"),
+            ("def f():\nreturn 1", "function definition on line 1"),
+            ("class A:\n\ndef f():\n  pass", "class definition on line 1"),
+            ("try:\npass", "'try' statement on line 1"),
+            (
+                "try:\n  pass\nexcept:\npass",
+                "'except' statement on line 3",
+            ),
+            ("match x:\n  case 1:\n  pass", "'case' statement on line 2"),
+            ("def f():\n  if x:\n  return 1", "'if' statement on line 2"),
+            ("if x:", "'if' statement on line 1"),
+        ] {
+            let message = format!("expected an indented block after {}", message);
+            assert_eq!(
+                parse_error(code).as_deref(),
+                Some(message.as_str()),
+                "{:?}",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn test_assignment_targets() {
+        for code in [
+            "a = 1",
+            "a.b = 1",
+            "a[0] = 1",
+            "(a) = 1",
+            "a = b.c = d[0] = 1",
+            "print a.b = 1",
+            "for a.b in c:\n  pass",
+            "for a[0] in c:\n  pass",
+        ] {
+            assert!(parses(code), "{:?} should parse", code);
+        }
+
+        for (code, message) in [
+            ("f() = 1", "cannot assign to function call"),
+            ("1 = a", "cannot assign to literal"),
+            ("[a] = 1", "cannot assign to literal"),
+            ("a + b = 1", "cannot assign to expression"),
+            ("a.b() = 1", "cannot assign to function call"),
+            ("for f() in c:\n  pass", "cannot assign to function call"),
+            ("for 1 in c:\n  pass", "cannot assign to literal"),
+            ("a.b := 1", "Expect variable name before ':='."),
+        ] {
+            assert_eq!(parse_error(code).as_deref(), Some(message), "{:?}", code);
+        }
+    }
+}
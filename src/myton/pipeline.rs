@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use super::lexer::token::Token;
+use super::lexer::Lexer;
+use super::parser::Parser;
+use super::resolver::{Resolver, UUID};
+use super::statement::STMT;
+use super::traceback::Traceback;
+
+// the stages Interpreter::run goes through, one at a time, for
+// tools which stop half-way (a formatter, a linter) or look at
+// what is between them
+
+/// The statements parsed from some tokens, see `parse`.
+pub struct Ast {
+    statements: Rc<Vec<STMT>>,
+}
+
+/// A parsed program with its variables resolved, ready for
+/// `Interpreter::execute`. See `resolve`.
+pub struct ResolvedProgram {
+    pub(crate) statements: Rc<Vec<STMT>>,
+    // how many scopes up each local variable is, by expression id
+    pub(crate) locals: HashMap<UUID, usize>,
+    warnings: Vec<Traceback>,
+}
+
+impl ResolvedProgram {
+    /// What the resolver noticed without it being an error, like
+    /// a builtin being shadowed. Running the program doesn't report them.
+    pub fn warnings(&self) -> &[Traceback] {
+        &self.warnings
+    }
+}
+
+impl fmt::Debug for Ast {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Ast")
+            .field("statements", &self.statements.len())
+            .finish()
+    }
+}
+
+impl fmt::Debug for ResolvedProgram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ResolvedProgram")
+            .field("statements", &self.statements.len())
+            .field("locals", &self.locals.len())
+            .field("warnings", &self.warnings.len())
+            .finish()
+    }
+}
+
+/// Splits the source into tokens, the first step of running it.
+pub fn lex(source: &str) -> Result<Vec<Token>, Traceback> {
+    Lexer::new(source.to_string())
+        .tokenize()
+        .map_err(|mut traceback| {
+            traceback.code = Some(source.to_string());
+            traceback
+        })
+}
+
+/// Parses the tokens of a whole program, with all the syntax errors
+/// found if any. Nothing is run or written while parsing.
+pub fn parse(tokens: Vec<Token>) -> Result<Ast, Vec<Traceback>> {
+    // ids of its own, so that the program can join others in one interpreter
+    let (statements, errors) = Parser::new(tokens).fresh_uuids().parse_recovering();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(Ast {
+        statements: Rc::new(statements),
+    })
+}
+
+/// Works out which scope each variable of the program is in,
+/// with the errors found in every statement if any.
+pub fn resolve(ast: &Ast) -> Result<ResolvedProgram, Vec<Traceback>> {
+    let mut resolver = Resolver::new();
+    let mut errors = Vec::new();
+    for statement in ast.statements.iter() {
+        if let Err(traceback) = statement.resolve(&mut resolver) {
+            errors.push(traceback);
+            // the resolver may be left mid-scope
+            resolver = Resolver::new();
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(ResolvedProgram {
+        statements: ast.statements.clone(),
+        locals: resolver.locals,
+        warnings: resolver.warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reports_every_error() {
+        let errors = parse(lex("x = (\ny = 1\nz = )\n").unwrap()).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].line(), 1);
+        assert_eq!(errors[1].line(), 3);
+    }
+
+    #[test]
+    fn test_reparsing_gives_new_ids() {
+        let source = "def f(x):\n  def g():\n    return x\n  return g\n";
+        let first = resolve(&parse(lex(source).unwrap()).unwrap()).unwrap();
+        let second = resolve(&parse(lex(source).unwrap()).unwrap()).unwrap();
+        assert!(!first.locals.is_empty());
+        assert!(first
+            .locals
+            .keys()
+            .all(|uuid| !second.locals.contains_key(uuid)));
+    }
+}
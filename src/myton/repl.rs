@@ -4,10 +4,11 @@ use termion::raw::IntoRawMode;
 use termion::{event::Key, raw::RawTerminal};
 
 const FORBIDENT_REPL_CHARS: &str = "°éèçàù²µù£¤§¨¹̣̣̣̣̣·´¡⅛£$⅜⅝⅞™±°¬¿°¯ˇ˘˙÷×˝";
-const PROMPT: &str = ">>> ";
+pub const DEFAULT_PROMPT: &str = ">>> ";
 
 pub struct Repl {
     buffer: Buffer,
+    prompt: String,
     cursor: (u16, u16),
     term_size: (u16, u16),
     input_history: History,
@@ -16,24 +17,33 @@ pub struct Repl {
 
 impl Repl {
     pub fn new() -> Repl {
-        let mut res = Repl {
+        Repl {
             buffer: Buffer::new(),
+            prompt: DEFAULT_PROMPT.to_string(),
             cursor: (1, 1),
             term_size: termion::terminal_size().unwrap(),
             input_history: History::new(),
             stdout: stdout().into_raw_mode().unwrap(),
-        };
-        res.welcome_prompt();
-        res
+        }
     }
 
     pub fn welcome_prompt(&mut self) {
         self.clear_all();
-        self.println("Myton 0.0.1 (main) [Rust 1.65.0] on linux".to_string());
+        self.println(banner());
+    }
+
+    // shown before the next lines read
+    pub fn set_prompt(&mut self, prompt: String) {
+        self.prompt = prompt;
+    }
+
+    // in columns, the cursor and the line being edited start after it
+    fn prompt_width(&self) -> usize {
+        self.prompt.chars().count()
     }
 
     fn update_cursor(&mut self) {
-        self.cursor.0 = (self.buffer.cursor + PROMPT.len() + 1) as u16;
+        self.cursor.0 = (self.buffer.cursor + self.prompt_width() + 1) as u16;
         self.update_cursor_pos();
     }
 
@@ -45,8 +55,6 @@ impl Repl {
     fn exit(&mut self) {
         self.newline();
         self.update_cursor_pos();
-        // self.print("Byebye!".to_string());
-        return;
     }
 
     fn update_buffer(&mut self) {
@@ -55,7 +63,7 @@ impl Repl {
     }
 
     fn clear_line(&mut self) {
-        let prompt_len = PROMPT.len() as u16;
+        let prompt_len = self.prompt_width() as u16;
         print!(
             "{}{}{}",
             termion::cursor::Goto(prompt_len, self.cursor.1),
@@ -76,7 +84,7 @@ impl Repl {
 
     fn prompt(&mut self) {
         self.cursor.0 = 1;
-        self.print(PROMPT.to_string());
+        self.print(self.prompt.clone());
     }
 
     fn newline(&mut self) {
@@ -98,7 +106,7 @@ impl Repl {
             termion::cursor::Goto(self.cursor.0, self.cursor.1),
             s
         );
-        self.cursor.0 += s.len() as u16;
+        self.cursor.0 += s.chars().count() as u16;
         self.flush();
     }
 
@@ -109,10 +117,9 @@ impl Repl {
         }
     }
 
+    // errors come already styled by the error reporting
     pub fn printerr(&mut self, s: String) {
-        print!("{}", termion::color::Fg(termion::color::Red));
         self.println(s);
-        print!("{}", termion::color::Fg(termion::color::Reset));
     }
 
     pub fn skiplines(&mut self, n: u16) {
@@ -121,6 +128,23 @@ impl Repl {
     }
 }
 
+// shown when an interactive session starts
+pub fn banner() -> String {
+    format!(
+        "Myton {} on {}\nType exit() or press Ctrl+D to leave, %vars to list the variables.",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS
+    )
+}
+
+// shown when the session is left with Ctrl+D
+pub fn goodbye(entries: usize) -> String {
+    match entries {
+        1 => "Bye! 1 entry run.".to_string(),
+        _ => format!("Bye! {} entries run.", entries),
+    }
+}
+
 impl Iterator for Repl {
     type Item = String;
 
@@ -285,3 +309,22 @@ impl History {
         self.index = self.history.len();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_banner() {
+        let banner = banner();
+        assert!(banner.starts_with(&format!("Myton {} on ", env!("CARGO_PKG_VERSION"))));
+        assert!(banner.contains("exit()"), "{}", banner);
+    }
+
+    #[test]
+    fn test_goodbye() {
+        assert_eq!(goodbye(0), "Bye! 0 entries run.");
+        assert_eq!(goodbye(1), "Bye! 1 entry run.");
+        assert_eq!(goodbye(3), "Bye! 3 entries run.");
+    }
+}
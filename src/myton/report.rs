@@ -0,0 +1,427 @@
+use std::collections::{HashMap, HashSet};
+
+use super::expression::*;
+use super::session::Json;
+use super::statement::*;
+use super::token::{Token, TokenKind};
+
+// a string found more often than this is reported
+pub const MAX_DUPLICATES: usize = 3;
+
+// what `myton check --report` finds in a file it does not run. Names are
+// looked at file-wide, whatever their scope, so a function is only
+// reported if nothing anywhere mentions its name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindingKind {
+    UnusedFunction,
+    DeadBranch,
+    Constant,
+    DuplicateString,
+}
+
+impl FindingKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::UnusedFunction => "unused function",
+            Self::DeadBranch => "dead branch",
+            Self::Constant => "constant",
+            Self::DuplicateString => "duplicate string",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    pub kind: FindingKind,
+    pub pos: (usize, usize),
+    pub message: String,
+}
+
+impl Finding {
+    fn new(kind: FindingKind, token: &Token, message: String) -> Self {
+        Self {
+            kind,
+            pos: token.pos.unwrap_or_default(),
+            message,
+        }
+    }
+
+    // path:line:column: kind: message, placed like the errors are
+    pub fn render(&self, path: &str) -> String {
+        format!(
+            "{}:{}:{}: {}: {}",
+            path,
+            self.pos.1 + 1,
+            self.pos.0,
+            self.kind.name(),
+            self.message
+        )
+    }
+
+    pub fn to_json(&self, path: &str) -> Json {
+        Json::Object(vec![
+            ("file".to_string(), Json::Str(path.to_string())),
+            ("line".to_string(), Json::Number((self.pos.1 + 1) as f64)),
+            ("column".to_string(), Json::Number(self.pos.0 as f64)),
+            ("kind".to_string(), Json::Str(self.kind.name().to_string())),
+            ("message".to_string(), Json::Str(self.message.clone())),
+        ])
+    }
+}
+
+// a name bound by an assignment, a loop, a parameter..., with the
+// literal it was given when it is one
+struct Binding {
+    name: Token,
+    constant: Option<String>,
+}
+
+#[derive(Default)]
+struct Collector {
+    functions: Vec<Token>,
+    mentioned: HashSet<String>,
+    bindings: HashMap<String, Vec<Binding>>,
+    strings: HashMap<String, Vec<Token>>,
+    dead: Vec<Finding>,
+}
+
+/// What the program has that never matters when it runs: functions never
+/// called, branches that cannot be taken, variables only ever given one
+/// literal, and strings written out more than `MAX_DUPLICATES` times.
+/// Sorted by position, and empty for a clean program.
+pub fn report(program: &[STMT]) -> Vec<Finding> {
+    let mut collector = Collector::default();
+    for stmt in program {
+        collector.stmt(stmt);
+    }
+
+    let mut findings = collector.dead;
+    for function in &collector.functions {
+        if !collector.mentioned.contains(&function.value) {
+            findings.push(Finding::new(
+                FindingKind::UnusedFunction,
+                function,
+                format!("function '{}' is never called", function.value),
+            ));
+        }
+    }
+    for bindings in collector.bindings.values() {
+        if let [Binding {
+            name,
+            constant: Some(literal),
+        }] = &bindings[..]
+        {
+            findings.push(Finding::new(
+                FindingKind::Constant,
+                name,
+                format!("'{}' is always {}", name.value, literal),
+            ));
+        }
+    }
+    for tokens in collector.strings.values() {
+        if tokens.len() > MAX_DUPLICATES {
+            findings.push(Finding::new(
+                FindingKind::DuplicateString,
+                &tokens[0],
+                format!(
+                    "the string \"{}\" is written {} times",
+                    tokens[0].value,
+                    tokens.len()
+                ),
+            ));
+        }
+    }
+    findings.sort_by_key(|finding| (finding.pos.1, finding.pos.0));
+    findings
+}
+
+// the literal an expression always is, for a constant or a condition
+fn literal(expr: &EXPR) -> Option<&Token> {
+    let any = expr.as_any();
+    if let Some(grouping) = any.downcast_ref::<Grouping>() {
+        return literal(&grouping.expression);
+    }
+    any.downcast_ref::<Literal>().map(|literal| &literal.token)
+}
+
+fn render_literal(token: &Token) -> String {
+    match token.kind {
+        TokenKind::Stringue => format!("\"{}\"", token.value),
+        _ => token.value.clone(),
+    }
+}
+
+// whether a condition always goes the same way, and which
+fn always(condition: &EXPR) -> Option<bool> {
+    let token = literal(condition)?;
+    match token.kind {
+        TokenKind::True => Some(true),
+        TokenKind::False | TokenKind::Nil => Some(false),
+        TokenKind::Number => token.value.parse::<f64>().ok().map(|n| n != 0.0),
+        TokenKind::Stringue => Some(!token.value.is_empty()),
+        _ => None,
+    }
+}
+
+impl Collector {
+    fn bind(&mut self, name: &Token, constant: Option<String>) {
+        self.bindings
+            .entry(name.value.clone())
+            .or_default()
+            .push(Binding {
+                name: name.clone(),
+                constant,
+            });
+    }
+
+    fn dead_branch(&mut self, condition: &EXPR, message: &str) {
+        if let Some(token) = literal(condition) {
+            self.dead.push(Finding::new(
+                FindingKind::DeadBranch,
+                token,
+                format!("{}: the condition is always {}", message, token.value),
+            ));
+        }
+    }
+
+    fn stmt(&mut self, stmt: &STMT) {
+        let any = stmt.as_any();
+        if let Some(stmt) = any.downcast_ref::<ExpressionStatement>() {
+            self.expr(&stmt.expression);
+        } else if let Some(stmt) = any.downcast_ref::<PrintStatement>() {
+            for argument in &stmt.arguments {
+                self.expr(&argument.value);
+            }
+            for (_, value) in &stmt.keywords {
+                self.expr(value);
+            }
+        } else if let Some(stmt) = any.downcast_ref::<VarStatement>() {
+            self.assignment(&stmt.target, &stmt.initializer);
+        } else if let Some(stmt) = any.downcast_ref::<BlockStatement>() {
+            for stmt in &stmt.statements {
+                self.stmt(stmt);
+            }
+        } else if let Some(stmt) = any.downcast_ref::<IfStatement>() {
+            match always(&stmt.condition) {
+                Some(false) => self.dead_branch(&stmt.condition, "this branch never runs"),
+                Some(true) if stmt.else_branch.is_some() => {
+                    self.dead_branch(&stmt.condition, "the else branch never runs")
+                }
+                _ => (),
+            }
+            self.expr(&stmt.condition);
+            self.stmt(&stmt.then_branch);
+            if let Some(else_branch) = &stmt.else_branch {
+                self.stmt(else_branch);
+            }
+        } else if let Some(stmt) = any.downcast_ref::<WhileStatement>() {
+            if always(&stmt.condition) == Some(false) {
+                self.dead_branch(&stmt.condition, "this loop never runs");
+            }
+            self.expr(&stmt.condition);
+            self.stmt(&stmt.body);
+        } else if let Some(stmt) = any.downcast_ref::<ForeachStatement>() {
+            self.target(&stmt.target, None);
+            self.expr(&stmt.collection);
+            self.stmt(&stmt.body);
+        } else if let Some(stmt) = any.downcast_ref::<FunctionStatement>() {
+            self.functions.push(stmt.inner.borrow().name.clone());
+            self.function(stmt);
+        } else if let Some(stmt) = any.downcast_ref::<ReturnStatement>() {
+            if let Some(value) = &stmt.value {
+                self.expr(value);
+            }
+        } else if let Some(stmt) = any.downcast_ref::<RaiseStatement>() {
+            if let Some(exception) = &stmt.exception {
+                self.expr(exception);
+            }
+        } else if let Some(stmt) = any.downcast_ref::<MatchStatement>() {
+            self.expr(&stmt.subject);
+            for case in &stmt.cases {
+                self.pattern(&case.pattern);
+                self.stmt(&case.body);
+            }
+        } else if let Some(stmt) = any.downcast_ref::<TryStatement>() {
+            self.stmt(&stmt.body);
+            for handler in &stmt.handlers {
+                if let Some(kind) = &handler.kind {
+                    self.mentioned.insert(kind.value.clone());
+                }
+                if let Some(name) = &handler.name {
+                    self.bind(name, None);
+                }
+                self.stmt(&handler.body);
+            }
+        } else if let Some(stmt) = any.downcast_ref::<ClassStatement>() {
+            self.bind(&stmt.name, None);
+            if let Some(superclass) = &stmt.superclass {
+                self.mentioned.insert(superclass.name.value.clone());
+            }
+            for (_, value) in &stmt.attributes {
+                self.expr(value);
+            }
+            // called through their instances
+            for method in &stmt.methods {
+                self.function(method);
+            }
+        }
+    }
+
+    fn function(&mut self, function: &FunctionStatement) {
+        let inner = function.inner.borrow();
+        self.bind(&inner.name, None);
+        for parameter in &inner.parameters {
+            self.bind(parameter, None);
+        }
+        self.stmt(&inner.body);
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Capture(name) => self.bind(name, None),
+            Pattern::Sequence(patterns) => {
+                for pattern in patterns {
+                    self.pattern(pattern);
+                }
+            }
+            Pattern::Literal(_) | Pattern::Wildcard => (),
+        }
+    }
+
+    fn assignment(&mut self, target: &AssignTarget, value: &EXPR) {
+        self.target(target, literal(value).map(render_literal));
+        self.expr(value);
+    }
+
+    fn target(&mut self, target: &AssignTarget, constant: Option<String>) {
+        match target {
+            AssignTarget::Name(name) => self.bind(name, constant),
+            AssignTarget::Attribute { object, .. } => self.expr(object),
+            AssignTarget::Index { object, index, .. } => {
+                self.expr(object);
+                self.expr(index);
+            }
+            AssignTarget::Unpack { targets, .. } => {
+                for target in targets {
+                    self.target(target, None);
+                }
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &EXPR) {
+        let any = expr.as_any();
+        if let Some(literal) = any.downcast_ref::<Literal>() {
+            if literal.token.kind == TokenKind::Stringue {
+                self.strings
+                    .entry(literal.token.value.clone())
+                    .or_default()
+                    .push(literal.token.clone());
+            }
+        } else if let Some(variable) = any.downcast_ref::<Variable>() {
+            self.mentioned.insert(variable.name.value.clone());
+        } else if let Some(assign) = any.downcast_ref::<Assign>() {
+            self.assignment(&assign.target, &assign.value);
+        } else if let Some(assign) = any.downcast_ref::<AssignExpr>() {
+            self.bind(&assign.name, literal(&assign.value).map(render_literal));
+            self.expr(&assign.value);
+        } else if let Some(list) = any.downcast_ref::<List>() {
+            self.exprs(&list.elements);
+        } else if let Some(tuple) = any.downcast_ref::<Tuple>() {
+            self.exprs(&tuple.elements);
+        } else if let Some(dict) = any.downcast_ref::<DictLiteral>() {
+            for (key, value) in &dict.entries {
+                self.expr(key);
+                self.expr(value);
+            }
+        } else if let Some(binary) = any.downcast_ref::<Binary>() {
+            let (first, chain) = binary.chain();
+            self.expr(first);
+            for binary in chain {
+                self.expr(&binary.right);
+            }
+        } else if let Some(logical) = any.downcast_ref::<Logical>() {
+            let (first, chain) = logical.chain();
+            self.expr(first);
+            for logical in chain {
+                self.expr(&logical.right);
+            }
+        } else if let Some(unary) = any.downcast_ref::<Unary>() {
+            self.expr(&unary.right);
+        } else if let Some(grouping) = any.downcast_ref::<Grouping>() {
+            self.expr(&grouping.expression);
+        } else if let Some(call) = any.downcast_ref::<Call>() {
+            self.expr(&call.callee);
+            for argument in &call.arguments {
+                self.expr(&argument.value);
+            }
+            for (_, value) in &call.keywords {
+                self.expr(value);
+            }
+        } else if let Some(get) = any.downcast_ref::<Get>() {
+            self.expr(&get.object);
+        } else if let Some(subscript) = any.downcast_ref::<Subscript>() {
+            self.expr(&subscript.object);
+            self.expr(&subscript.index);
+        }
+    }
+
+    fn exprs(&mut self, exprs: &[EXPR]) {
+        for expr in exprs {
+            self.expr(expr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::lexer::Lexer;
+    use super::super::parser::Parser;
+    use super::*;
+
+    fn findings(code: &str) -> Vec<String> {
+        let tokens = Lexer::new(code.to_string()).tokenize().unwrap();
+        let (program, errors) = Parser::new(tokens).parse_recovering();
+        assert!(errors.is_empty());
+        report(&program)
+            .iter()
+            .map(|finding| finding.render("f.my"))
+            .collect()
+    }
+
+    #[test]
+    fn test_report_of_each_finding() {
+        assert_eq!(
+            findings(
+                "def unused(x):\n  return x\n\ndef used():\n  return \"a\" + \"a\" + \"a\" + \"a\"\n\nlimit = 10\nif False:\n  print(used())\nprint(limit)\n"
+            ),
+            vec![
+                "f.my:1:10: unused function: function 'unused' is never called",
+                "f.my:5:12: duplicate string: the string \"a\" is written 4 times",
+                "f.my:7:5: constant: 'limit' is always 10",
+                "f.my:8:8: dead branch: this branch never runs: the condition is always False",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_report_of_clean_code() {
+        assert!(findings(
+            "def double(x):\n  return x * 2\n\ntotal = 0\nfor n in [1, 2]:\n  total = total + double(n)\nwhile total > 100:\n  total = total - 1\nprint(total)\n"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_report_of_branches_always_taken() {
+        assert_eq!(
+            findings("if True:\n  print(1)\nelse:\n  print(2)\nwhile 0:\n  print(3)\n"),
+            vec![
+                "f.my:1:7: dead branch: the else branch never runs: the condition is always True",
+                "f.my:5:7: dead branch: this loop never runs: the condition is always 0",
+            ]
+        );
+        // without an else, nothing is dead
+        assert!(findings("while True:\n  print(1)\n").is_empty());
+    }
+}
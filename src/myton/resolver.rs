@@ -1,6 +1,8 @@
 use super::expression::*;
+use super::native_functions::is_builtin;
 use super::statement::*;
 use super::token::Token;
+use super::trace::TracedStatement;
 use super::traceback::Traceback;
 use super::traceback::TracebackKind;
 use std::collections::HashMap;
@@ -13,6 +15,8 @@ pub struct Resolver {
     pub locals: HashMap<UUID, usize>, // UUID -> depth
     current_function: FunctionType,
     current_class: ClassType,
+    // not errors, the program still runs
+    pub warnings: Vec<Traceback>,
 }
 
 #[derive(Clone, Copy)]
@@ -40,6 +44,7 @@ impl Resolver {
             locals: HashMap::new(),
             current_function: FunctionType::None,
             current_class: ClassType::None,
+            warnings: Vec::new(),
         }
     }
     // STATEMENTS
@@ -75,15 +80,45 @@ impl Resolver {
     }
 
     fn var(&mut self, stmt: &VarStatement) -> ResolveResult {
-        self.declare(&stmt.name)?;
+        if let AssignTarget::Name(name) = &stmt.target {
+            self.declare(name)?;
+        }
         stmt.initializer.resolve(self)?;
-        self.define(&stmt.name)?;
+        self.target(&stmt.target)
+    }
 
-        Ok(())
+    // binds a name in the current scope, or resolves
+    // the expressions of what is stored into
+    fn target(&mut self, target: &AssignTarget) -> ResolveResult {
+        match target {
+            AssignTarget::Name(name) => {
+                self.declare(name)?;
+                self.define(name)
+            }
+            AssignTarget::Attribute { object, .. } => object.resolve(self),
+            AssignTarget::Index { object, index, .. } => {
+                object.resolve(self)?;
+                index.resolve(self)
+            }
+            AssignTarget::Unpack { targets, .. } => {
+                for target in targets {
+                    self.target(target)?;
+                }
+                Ok(())
+            }
+        }
     }
 
     fn declare(&mut self, name: &Token) -> ResolveResult {
         if let Some(scope) = self.scopes.last_mut() {
+            if !scope.contains_key(&name.value) && is_builtin(&name.value) {
+                self.warnings.push(Traceback {
+                    message: Some(format!("'{}' shadows a built-in function", name.value)),
+                    pos: name.pos.unwrap_or_default(),
+                    tipe: TracebackKind::Warning,
+                    ..Default::default()
+                });
+            }
             scope.insert(name.value.clone(), false);
         }
 
@@ -158,14 +193,20 @@ impl Resolver {
     }
 
     fn print(&mut self, stmt: &PrintStatement) -> ResolveResult {
-        stmt.expression.resolve(self)
+        for arg in &stmt.arguments {
+            arg.value.resolve(self)?;
+        }
+        for (_, value) in &stmt.keywords {
+            value.resolve(self)?;
+        }
+        Ok(())
     }
 
     fn reteurn(&mut self, stmt: &ReturnStatement) -> ResolveResult {
         if matches!(self.current_function, FunctionType::None) {
             return Err(Traceback {
                 message: Some(format!("'return' outside function")),
-                pos: stmt.keyword.pos.unwrap(),
+                pos: stmt.keyword.pos.unwrap_or_default(),
                 tipe: TracebackKind::ResolveError,
                 ..Default::default()
             });
@@ -177,6 +218,13 @@ impl Resolver {
         Ok(())
     }
 
+    fn raise(&mut self, stmt: &RaiseStatement) -> ResolveResult {
+        if let Some(exception) = &stmt.exception {
+            exception.resolve(self)?;
+        }
+        Ok(())
+    }
+
     fn whyle(&mut self, stmt: &WhileStatement) -> ResolveResult {
         stmt.condition.resolve(self)?;
         stmt.body.resolve(self)?;
@@ -184,13 +232,38 @@ impl Resolver {
     }
 
     fn foreach(&mut self, stmt: &ForeachStatement) -> ResolveResult {
-        self.declare(&stmt.variable)?;
-        self.define(&stmt.variable)?;
+        // like an assignment: the collection is evaluated before the
+        // target is bound, in the current function (or module) scope
         stmt.collection.resolve(self)?;
+        self.target(&stmt.target)?;
         stmt.body.resolve(self)?;
         Ok(())
     }
 
+    fn match_stmt(&mut self, stmt: &MatchStatement) -> ResolveResult {
+        stmt.subject.resolve(self)?;
+        for case in &stmt.cases {
+            for name in case.pattern.captures() {
+                self.declare(name)?;
+                self.define(name)?;
+            }
+            case.body.resolve(self)?;
+        }
+        Ok(())
+    }
+
+    fn try_stmt(&mut self, stmt: &TryStatement) -> ResolveResult {
+        stmt.body.resolve(self)?;
+        for handler in &stmt.handlers {
+            if let Some(name) = &handler.name {
+                self.declare(name)?;
+                self.define(name)?;
+            }
+            handler.body.resolve(self)?;
+        }
+        Ok(())
+    }
+
     fn global(&mut self, _: &GlobalStatement) -> ResolveResult {
         Ok(())
     }
@@ -206,11 +279,15 @@ impl Resolver {
         self.declare(&class.name)?;
         self.define(&class.name)?;
 
+        for (_, value) in &class.attributes {
+            value.resolve(self)?;
+        }
+
         if let Some(superclass) = &class.superclass {
             if superclass.name.value == class.name.value {
                 return Err(Traceback {
                     message: Some(format!("A class cannot inherit from itself.")),
-                    pos: class.name.pos.unwrap(),
+                    pos: class.name.pos.unwrap_or_default(),
                     tipe: TracebackKind::ResolveError,
                     ..Default::default()
                 });
@@ -250,15 +327,20 @@ impl Resolver {
 
     // EXPRESSIONS
     fn binary(&mut self, expr: &Binary) -> ResolveResult {
-        expr.left.resolve(self)?;
-        expr.right.resolve(self)?;
-        Ok(())
+        let (first, chain) = expr.chain();
+        first.resolve(self)?;
+        chain
+            .iter()
+            .try_for_each(|binary| binary.right.resolve(self))
     }
 
     fn call(&mut self, expr: &Call) -> ResolveResult {
         expr.callee.resolve(self)?;
         for arg in &expr.arguments {
-            arg.resolve(self)?;
+            arg.value.resolve(self)?;
+        }
+        for (_, value) in &expr.keywords {
+            value.resolve(self)?;
         }
         Ok(())
     }
@@ -272,9 +354,11 @@ impl Resolver {
     }
 
     fn logical(&mut self, expr: &Logical) -> ResolveResult {
-        expr.left.resolve(self)?;
-        expr.right.resolve(self)?;
-        Ok(())
+        let (first, chain) = expr.chain();
+        first.resolve(self)?;
+        chain
+            .iter()
+            .try_for_each(|logical| logical.right.resolve(self))
     }
 
     fn unary(&mut self, expr: &Unary) -> ResolveResult {
@@ -288,20 +372,48 @@ impl Resolver {
         Ok(())
     }
 
+    fn tuple(&mut self, expr: &Tuple) -> ResolveResult {
+        for element in &expr.elements {
+            element.resolve(self)?;
+        }
+        Ok(())
+    }
+
+    fn dict_literal(&mut self, expr: &DictLiteral) -> ResolveResult {
+        for (key, value) in &expr.entries {
+            key.resolve(self)?;
+            value.resolve(self)?;
+        }
+        Ok(())
+    }
+
     fn get(&mut self, expr: &Get) -> ResolveResult {
         expr.object.resolve(self)
     }
 
-    fn set(&mut self, expr: &Set) -> ResolveResult {
+    fn subscript(&mut self, expr: &Subscript) -> ResolveResult {
         expr.object.resolve(self)?;
-        expr.value.resolve(self)
+        expr.index.resolve(self)
+    }
+
+    fn assign(&mut self, expr: &Assign) -> ResolveResult {
+        expr.value.resolve(self)?;
+        self.target(&expr.target)
+    }
+
+    fn assign_expr(&mut self, expr: &AssignExpr) -> ResolveResult {
+        // the target is bound in the current function (or module)
+        // scope, blocks never open a scope of their own
+        expr.value.resolve(self)?;
+        self.declare(&expr.name)?;
+        self.define(&expr.name)
     }
 
     fn this(&mut self, expr: &This) -> ResolveResult {
         if matches!(self.current_class, ClassType::None) {
             return Err(Traceback {
                 message: Some(format!("Cannot use 'this' outside of a class.")),
-                pos: expr.keyword.pos.unwrap(),
+                pos: expr.keyword.pos.unwrap_or_default(),
                 tipe: TracebackKind::ResolveError,
                 ..Default::default()
             });
@@ -323,14 +435,14 @@ impl Resolver {
         if matches!(self.current_class, ClassType::None) {
             return Err(Traceback {
                 message: Some(format!("Cannot use 'super' outside of a class.")),
-                pos: expr.keyword.pos.unwrap(),
+                pos: expr.keyword.pos.unwrap_or_default(),
                 tipe: TracebackKind::ResolveError,
                 ..Default::default()
             });
         } else if !matches!(self.current_class, ClassType::Subclass) {
             return Err(Traceback {
                 message: Some(format!("Cannot use 'super' in a class with no superclass.")),
-                pos: expr.keyword.pos.unwrap(),
+                pos: expr.keyword.pos.unwrap_or_default(),
                 tipe: TracebackKind::ResolveError,
                 ..Default::default()
             });
@@ -361,6 +473,12 @@ impl Resolvable for VarStatement {
     }
 }
 
+impl Resolvable for TracedStatement {
+    fn resolve(&self, resolver: &mut Resolver) -> ResolveResult {
+        self.statement.resolve(resolver)
+    }
+}
+
 impl Resolvable for BlockStatement {
     fn resolve(&self, resolver: &mut Resolver) -> ResolveResult {
         resolver.block(self)
@@ -397,6 +515,24 @@ impl Resolvable for IfStatement {
     }
 }
 
+impl Resolvable for MatchStatement {
+    fn resolve(&self, resolver: &mut Resolver) -> ResolveResult {
+        resolver.match_stmt(self)
+    }
+}
+
+impl Resolvable for RaiseStatement {
+    fn resolve(&self, resolver: &mut Resolver) -> ResolveResult {
+        resolver.raise(self)
+    }
+}
+
+impl Resolvable for TryStatement {
+    fn resolve(&self, resolver: &mut Resolver) -> ResolveResult {
+        resolver.try_stmt(self)
+    }
+}
+
 impl Resolvable for GlobalStatement {
     fn resolve(&self, resolver: &mut Resolver) -> ResolveResult {
         resolver.global(self)
@@ -454,6 +590,18 @@ impl Resolvable for List {
     }
 }
 
+impl Resolvable for Tuple {
+    fn resolve(&self, resolver: &mut Resolver) -> ResolveResult {
+        resolver.tuple(self)
+    }
+}
+
+impl Resolvable for DictLiteral {
+    fn resolve(&self, resolver: &mut Resolver) -> ResolveResult {
+        resolver.dict_literal(self)
+    }
+}
+
 impl Resolvable for Unary {
     fn resolve(&self, resolver: &mut Resolver) -> ResolveResult {
         resolver.unary(self)
@@ -466,9 +614,21 @@ impl Resolvable for Get {
     }
 }
 
-impl Resolvable for Set {
+impl Resolvable for Subscript {
+    fn resolve(&self, resolver: &mut Resolver) -> ResolveResult {
+        resolver.subscript(self)
+    }
+}
+
+impl Resolvable for Assign {
+    fn resolve(&self, resolver: &mut Resolver) -> ResolveResult {
+        resolver.assign(self)
+    }
+}
+
+impl Resolvable for AssignExpr {
     fn resolve(&self, resolver: &mut Resolver) -> ResolveResult {
-        resolver.set(self)
+        resolver.assign_expr(self)
     }
 }
 
@@ -503,7 +663,7 @@ f()"
         let mut interpreter = Interpreter::new();
         let mut lexer = Lexer::new(code);
         let tokens = lexer.tokenize().unwrap();
-        let mut parser = Parser::new(tokens.clone(), interpreter.output.clone());
+        let mut parser = Parser::new(tokens.clone());
         let program = parser.parse().unwrap();
 
         for stmt in &program {
@@ -514,7 +674,10 @@ f()"
 
         let mut message = String::new();
 
-        for (u, d) in &locals {
+        // by uuid, the map's own order changes from run to run
+        let mut sorted: Vec<_> = locals.iter().collect();
+        sorted.sort();
+        for (u, d) in sorted {
             message += &format!("{:?} {:?}\n", u, d).to_string();
         }
 
@@ -525,4 +688,35 @@ f()"
         assert_eq!(locals[&30], 0);
         assert_eq!(locals[&34], 0);
     }
+
+    fn resolve(code: &str) -> (Interpreter, Result<(), String>) {
+        let mut interpreter = Interpreter::new();
+        let tokens = Lexer::new(code.to_string()).tokenize().unwrap();
+        let program = Parser::new(tokens).parse().unwrap();
+        let result = program
+            .iter()
+            .try_for_each(|stmt| stmt.resolve(&mut interpreter.resolver))
+            .map_err(|traceback| traceback.message.unwrap_or_default());
+        (interpreter, result)
+    }
+
+    #[test]
+    fn test_elif_branches_are_resolved() {
+        let (_, result) = resolve("if 0:\n  pass\nelif 0:\n  pass\nelif 1:\n  return 1\n");
+        assert_eq!(result.unwrap_err(), "'return' outside function");
+
+        let (_, result) = resolve("if 0:\n  pass\nelif 1:\n  pass\nelse:\n  print(this)\n");
+        assert_eq!(result.unwrap_err(), "Cannot use 'this' outside of a class.");
+
+        // a name bound in an elif is declared in the function's scope
+        let (interpreter, result) =
+            resolve("def f(n):\n  if n:\n    pass\n  elif 1:\n    max = 2\n  return max\n");
+        result.unwrap();
+        let warnings = &interpreter.resolver.warnings;
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0].message.as_deref(),
+            Some("'max' shadows a built-in function")
+        );
+    }
 }
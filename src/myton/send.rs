@@ -0,0 +1,85 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::{Builder, JoinHandle};
+
+use super::{Interpreter, MyWrite};
+
+// same as the main thread, deep recursion needs it
+const STACK_SIZE: usize = 8 * 1024 * 1024;
+
+// An interpreter that can be moved between threads or shared
+// in server state. The real interpreter is built on Rc and lives
+// on its own thread, this only sends it sources to run.
+pub struct SendInterpreter {
+    sources: Option<Sender<String>>,
+    outputs: Receiver<(String, String)>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl SendInterpreter {
+    pub fn new() -> SendInterpreter {
+        let (sources, received_sources) = channel::<String>();
+        let (sent_outputs, outputs) = channel();
+
+        let worker = Builder::new()
+            .name("myton".to_string())
+            .stack_size(STACK_SIZE)
+            .spawn(move || {
+                let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+                let error_output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+                let mut interpreter =
+                    Interpreter::new_with_outputs(output.clone(), error_output.clone());
+
+                for source in received_sources {
+                    if let Err(traceback) = interpreter.run(source) {
+                        interpreter.report_traceback(traceback);
+                    }
+                    let result = (take_output(&output), take_output(&error_output));
+                    if sent_outputs.send(result).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("could not spawn the interpreter thread");
+
+        SendInterpreter {
+            sources: Some(sources),
+            outputs,
+            worker: Some(worker),
+        }
+    }
+
+    // runs the source with the globals left by the previous
+    // runs, returns what it wrote to (stdout, stderr)
+    pub fn run(&self, source: &str) -> (String, String) {
+        self.sources
+            .as_ref()
+            .and_then(|sources| sources.send(source.to_string()).ok())
+            .and_then(|_| self.outputs.recv().ok())
+            .expect("the interpreter thread panicked")
+    }
+}
+
+// empties the buffer so each run only gives back what it wrote
+// itself, and a long lived interpreter doesn't keep every output
+fn take_output(buffer: &Rc<RefCell<Box<dyn MyWrite>>>) -> String {
+    let written = std::mem::replace(&mut *buffer.borrow_mut(), Box::new(Vec::new()));
+    written.get_string().unwrap_or_default()
+}
+
+impl Default for SendInterpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for SendInterpreter {
+    fn drop(&mut self) {
+        // closing the channel stops the worker
+        self.sources.take();
+        if let Some(worker) = self.worker.take() {
+            worker.join().ok();
+        }
+    }
+}
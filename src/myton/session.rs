@@ -0,0 +1,356 @@
+// the file %save writes and %restore reads, a json object like
+// {"version": 1, "globals": {"x": [1, 2]}, "definitions": ["def f():..."]}
+
+use super::types::{DynValue, TypeKind};
+use std::iter::Peekable;
+use std::str::Chars;
+
+// bumped whenever the session format changes
+pub const SESSION_VERSION: f64 = 1.0;
+
+// how many arrays and objects can be open at once when parsing,
+// the parser recursing once for each
+pub const MAX_JSON_DEPTH: usize = 200;
+
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+// what %save writes: the plain data globals, and the source
+// of the functions and classes, replayed in order by %restore
+#[derive(Default)]
+pub struct Session {
+    pub globals: Vec<(String, DynValue)>,
+    pub definitions: Vec<String>,
+}
+
+impl Session {
+    pub fn to_json(&self) -> String {
+        Json::Object(vec![
+            ("version".to_string(), Json::Number(SESSION_VERSION)),
+            (
+                "globals".to_string(),
+                Json::Object(
+                    self.globals
+                        .iter()
+                        .filter_map(|(name, value)| Some((name.clone(), value_to_json(value)?)))
+                        .collect(),
+                ),
+            ),
+            (
+                "definitions".to_string(),
+                Json::Array(
+                    self.definitions
+                        .iter()
+                        .map(|source| Json::Str(source.clone()))
+                        .collect(),
+                ),
+            ),
+        ])
+        .render()
+    }
+
+    pub fn from_json(text: &str) -> Result<Session, String> {
+        let fields = match Json::parse(text)? {
+            Json::Object(fields) => fields,
+            _ => return Err("session file should contain an object".to_string()),
+        };
+        let field = |name: &str| fields.iter().find(|(key, _)| key == name).map(|(_, v)| v);
+
+        match field("version") {
+            Some(Json::Number(version)) if *version == SESSION_VERSION => {}
+            Some(Json::Number(version)) => {
+                return Err(format!("unsupported session version {}", version))
+            }
+            _ => return Err("session file has no version".to_string()),
+        }
+
+        let mut session = Session::default();
+        if let Some(Json::Object(globals)) = field("globals") {
+            for (name, value) in globals {
+                session.globals.push((name.clone(), json_to_value(value)));
+            }
+        }
+        if let Some(Json::Array(definitions)) = field("definitions") {
+            for definition in definitions {
+                if let Json::Str(source) = definition {
+                    session.definitions.push(source.clone());
+                }
+            }
+        }
+        Ok(session)
+    }
+}
+
+// None for values that can't be saved as plain data
+pub fn value_to_json(value: &DynValue) -> Option<Json> {
+    match value.tipe {
+        TypeKind::Nil => Some(Json::Null),
+        TypeKind::Boolean => Some(Json::Bool(value.as_bool())),
+        TypeKind::Number if value.as_number().is_finite() => Some(Json::Number(value.as_number())),
+        TypeKind::Stringue => Some(Json::Str(value.as_string())),
+        TypeKind::List | TypeKind::Tuple => value
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .map(value_to_json)
+            .collect::<Option<Vec<_>>>()
+            .map(Json::Array),
+        // json keys can only be strings
+        TypeKind::Dict => value
+            .as_dict()
+            .unwrap()
+            .iter()
+            .map(|(key, value)| match key.tipe {
+                TypeKind::Stringue => Some((key.as_string(), value_to_json(value)?)),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(Json::Object),
+        _ => None,
+    }
+}
+
+pub fn json_to_value(json: &Json) -> DynValue {
+    match json {
+        Json::Null => DynValue::none(),
+        Json::Bool(b) => DynValue::from(*b),
+        Json::Number(n) => DynValue::from(*n),
+        Json::Str(s) => DynValue::from(s.clone()),
+        Json::Array(elements) => {
+            DynValue::from(elements.iter().map(json_to_value).collect::<Vec<_>>())
+        }
+        Json::Object(entries) => DynValue::from_dict(
+            entries
+                .iter()
+                .map(|(key, value)| (DynValue::from(key.clone()), json_to_value(value)))
+                .collect(),
+        ),
+    }
+}
+
+impl Json {
+    pub fn render(&self) -> String {
+        self.render_indented(0)
+    }
+
+    fn render_indented(&self, depth: usize) -> String {
+        match self {
+            Json::Null => "null".to_string(),
+            Json::Bool(b) => b.to_string(),
+            Json::Number(n) => n.to_string(),
+            Json::Str(s) => render_string(s),
+            Json::Array(elements) => format!(
+                "[{}]",
+                elements
+                    .iter()
+                    .map(|e| e.render_indented(depth))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Json::Object(fields) => format!(
+                "{{\n{}\n{}}}",
+                fields
+                    .iter()
+                    .map(|(key, value)| format!(
+                        "{}{}: {}",
+                        "  ".repeat(depth + 1),
+                        render_string(key),
+                        value.render_indented(depth + 1)
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(",\n"),
+                "  ".repeat(depth)
+            ),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut chars = text.chars().peekable();
+        let json = parse_value(&mut chars, 0)?;
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            None => Ok(json),
+            Some(c) => Err(format!("unexpected '{}' after the end of the json", c)),
+        }
+    }
+}
+
+fn render_string(s: &str) -> String {
+    let mut res = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn expect(chars: &mut Peekable<Chars>, word: &str) -> Result<(), String> {
+    for expected in word.chars() {
+        if chars.next() != Some(expected) {
+            return Err(format!("invalid json, expected '{}'", word));
+        }
+    }
+    Ok(())
+}
+
+fn parse_value(chars: &mut Peekable<Chars>, depth: usize) -> Result<Json, String> {
+    skip_whitespace(chars);
+    if depth >= MAX_JSON_DEPTH && matches!(chars.peek(), Some('[' | '{')) {
+        return Err("invalid json, nested too deeply".to_string());
+    }
+    match chars.peek() {
+        Some('n') => expect(chars, "null").map(|_| Json::Null),
+        Some('t') => expect(chars, "true").map(|_| Json::Bool(true)),
+        Some('f') => expect(chars, "false").map(|_| Json::Bool(false)),
+        Some('"') => parse_string(chars).map(Json::Str),
+        Some('[') => {
+            chars.next();
+            let mut elements = Vec::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&']') {
+                chars.next();
+                return Ok(Json::Array(elements));
+            }
+            loop {
+                elements.push(parse_value(chars, depth + 1)?);
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some(']') => return Ok(Json::Array(elements)),
+                    _ => return Err("invalid json, expected ',' or ']'".to_string()),
+                }
+            }
+        }
+        Some('{') => {
+            chars.next();
+            let mut fields = Vec::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&'}') {
+                chars.next();
+                return Ok(Json::Object(fields));
+            }
+            loop {
+                skip_whitespace(chars);
+                let key = parse_string(chars)?;
+                skip_whitespace(chars);
+                expect(chars, ":")?;
+                fields.push((key, parse_value(chars, depth + 1)?));
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some('}') => return Ok(Json::Object(fields)),
+                    _ => return Err("invalid json, expected ',' or '}'".to_string()),
+                }
+            }
+        }
+        Some(c) if *c == '-' || c.is_ascii_digit() => {
+            let mut number = String::new();
+            while let Some(c) = chars.peek() {
+                if c.is_ascii_digit() || "+-.eE".contains(*c) {
+                    number.push(*c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            number
+                .parse::<f64>()
+                .map(Json::Number)
+                .map_err(|_| format!("invalid json number '{}'", number))
+        }
+        Some(c) => Err(format!("invalid json, unexpected '{}'", c)),
+        None => Err("invalid json, unexpected end of input".to_string()),
+    }
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    expect(chars, "\"")?;
+    let mut res = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(res),
+            Some('\\') => match chars.next() {
+                Some('n') => res.push('\n'),
+                Some('r') => res.push('\r'),
+                Some('t') => res.push('\t'),
+                Some('u') => {
+                    let code: String = chars.by_ref().take(4).collect();
+                    let c = u32::from_str_radix(&code, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or(format!("invalid json escape '\\u{}'", code))?;
+                    res.push(c);
+                }
+                Some(c) => res.push(c),
+                None => return Err("invalid json, unterminated string".to_string()),
+            },
+            Some(c) => res.push(c),
+            None => return Err("invalid json, unterminated string".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_round_trip() {
+        let text = Json::Object(vec![
+            ("a".to_string(), Json::Number(1.5)),
+            (
+                "b".to_string(),
+                Json::Array(vec![
+                    Json::Null,
+                    Json::Bool(true),
+                    Json::Str("x\"\n".to_string()),
+                ]),
+            ),
+        ])
+        .render();
+
+        let rendered_again = Json::parse(&text).unwrap().render();
+        assert_eq!(text, rendered_again);
+        assert!(Json::parse("[1, 2").is_err());
+        assert!(Json::parse("{\"a\": 1} x").is_err());
+    }
+
+    #[test]
+    fn test_json_depth() {
+        let nested = |depth| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(Json::parse(&nested(MAX_JSON_DEPTH)).is_ok());
+        assert_eq!(
+            Json::parse(&nested(MAX_JSON_DEPTH + 1)).err().unwrap(),
+            "invalid json, nested too deeply"
+        );
+        assert!(Json::parse(&"{\"a\": ".repeat(100_000)).is_err());
+        assert!(Json::parse(&"[".repeat(100_000)).is_err());
+    }
+
+    #[test]
+    fn test_rejects_other_versions() {
+        assert!(Session::from_json("{\"version\": 1}").is_ok());
+        assert!(Session::from_json("{\"version\": 2}").is_err());
+        assert!(Session::from_json("{}").is_err());
+    }
+}
@@ -0,0 +1,118 @@
+// a global in a snapshot: its name, type and repr, which %whos cuts short
+pub type Global = (String, String, String);
+
+// how a global differs between two snapshots
+#[derive(Debug, PartialEq)]
+pub enum GlobalChange {
+    Added(Global),
+    Removed(Global),
+    // before and after
+    Changed(Global, Global),
+}
+
+// the changes from one snapshot to the next, by name. A value
+// counts as changed when its type or repr did
+pub fn diff_snapshots(before: &[Global], after: &[Global]) -> Vec<GlobalChange> {
+    let find = |globals: &[Global], name: &str| {
+        globals.iter().find(|(other, _, _)| other == name).cloned()
+    };
+    let mut changes: Vec<GlobalChange> = after
+        .iter()
+        .filter_map(|global| match find(before, &global.0) {
+            None => Some(GlobalChange::Added(global.clone())),
+            Some(old) if old != *global => Some(GlobalChange::Changed(old, global.clone())),
+            Some(_) => None,
+        })
+        .collect();
+    changes.extend(
+        before
+            .iter()
+            .filter(|global| find(after, &global.0).is_none())
+            .map(|global| GlobalChange::Removed(global.clone())),
+    );
+    changes.sort_by(|a, b| name_of(a).cmp(name_of(b)));
+    changes
+}
+
+fn name_of(change: &GlobalChange) -> &str {
+    match change {
+        GlobalChange::Added(global)
+        | GlobalChange::Removed(global)
+        | GlobalChange::Changed(_, global) => &global.0,
+    }
+}
+
+// the globals in aligned columns, like ipython's %whos
+pub fn whos_table(globals: &[Global]) -> String {
+    if globals.is_empty() {
+        return "Interactive namespace is empty.\n".to_string();
+    }
+    let header = ("Variable", "Type", "Data/Info");
+    let width = |column: fn(&Global) -> &str, title: &str| {
+        globals
+            .iter()
+            .map(|global| column(global).chars().count())
+            .chain([title.chars().count()])
+            .max()
+            .unwrap_or(0)
+    };
+    let name_width = width(|global| &global.0, header.0);
+    let type_width = width(|global| &global.1, header.1);
+    let row = |name: &str, tipe: &str, repr: &str| {
+        format!(
+            "{:name_width$}   {:type_width$}   {}\n",
+            name,
+            tipe,
+            repr,
+            name_width = name_width,
+            type_width = type_width
+        )
+    };
+
+    let mut table = row(header.0, header.1, header.2);
+    let rule_width = table.chars().count() - 1;
+    table.push_str(&"-".repeat(rule_width));
+    table.push('\n');
+    for (name, tipe, repr) in globals {
+        table.push_str(&row(name, tipe, repr));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn global(name: &str, tipe: &str, repr: &str) -> Global {
+        (name.to_string(), tipe.to_string(), repr.to_string())
+    }
+
+    #[test]
+    fn test_whos_table() {
+        assert_eq!(whos_table(&[]), "Interactive namespace is empty.\n");
+        assert_eq!(
+            whos_table(&[
+                global("n", "number", "1"),
+                global("numbers", "list", "[1, 2]")
+            ]),
+            "Variable   Type     Data/Info\n\
+             -----------------------------\n\
+             n          number   1\n\
+             numbers    list     [1, 2]\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_order() {
+        let before = [global("a", "number", "1"), global("c", "number", "3")];
+        let after = [global("b", "number", "2"), global("c", "str", "3")];
+        assert_eq!(
+            diff_snapshots(&before, &after),
+            vec![
+                GlobalChange::Removed(global("a", "number", "1")),
+                GlobalChange::Added(global("b", "number", "2")),
+                GlobalChange::Changed(global("c", "number", "3"), global("c", "str", "3")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use super::traceback::Traceback;
+
+// a piece of source given to one run: a file, a REPL entry...
+struct Chunk {
+    // shown in reports instead of <unknown>
+    name: Option<String>,
+    source: String,
+}
+
+// every source the interpreter ran, by chunk id. Functions outlive
+// the run that defined them, so an error can point into any of them
+#[derive(Default)]
+pub struct SourceMap {
+    chunks: Vec<Chunk>,
+}
+
+impl SourceMap {
+    // returns the id the lexer stamps on the tokens of this source
+    pub fn add(&mut self, source: String, name: Option<String>) -> usize {
+        self.chunks.push(Chunk { name, source });
+        self.chunks.len() - 1
+    }
+
+    // gives the traceback the source (and name) of the chunk its
+    // position is in, the given one if it doesn't know better
+    pub fn locate(&self, traceback: &mut Traceback, current: usize) {
+        if let Some(cause) = &mut traceback.cause {
+            self.locate(cause, current);
+        }
+        let id = *traceback.chunk.get_or_insert(current);
+        if let Some(chunk) = self.chunks.get(id) {
+            traceback.code = Some(chunk.source.clone());
+            if traceback.filename.is_none() {
+                traceback.filename = chunk.name.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate() {
+        let mut sources = SourceMap::default();
+        let first = sources.add("a = 1\n".to_string(), Some("stdin-1".to_string()));
+        let second = sources.add("b = 2\n".to_string(), None);
+
+        let mut traceback = Traceback::default();
+        sources.locate(&mut traceback, second);
+        assert_eq!(traceback.code.as_deref(), Some("b = 2\n"));
+        assert_eq!(traceback.filename, None);
+
+        let mut traceback = Traceback {
+            chunk: Some(first),
+            ..Default::default()
+        };
+        sources.locate(&mut traceback, second);
+        assert_eq!(traceback.code.as_deref(), Some("a = 1\n"));
+        assert_eq!(traceback.filename.as_deref(), Some("stdin-1"));
+    }
+}
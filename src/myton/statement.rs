@@ -1,23 +1,28 @@
+use std::any::Any;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::io::Write;
 use std::rc::Rc;
 
 use super::class::Class;
-use super::environment::{make_env_enclosed, Env, EnvVariable};
-use super::expression::{Evaluable, Variable, EXPR};
+use super::environment::{call_stack, make_env_enclosed, Env};
+use super::expression::{eval_arguments, Argument, AssignTarget, Evaluable, Variable, EXPR};
 use super::functions::Function;
+use super::iterator::iterate;
 use super::resolver::Resolvable;
+use super::stats::count_statement;
 use super::token::Token;
-use super::traceback::Traceback;
-use super::types::DynValue;
-use super::MyWrite;
+use super::trace::Tracer;
+use super::traceback::{Traceback, TracebackKind};
+use super::types::{DynValue, TypeKind};
 
 pub trait Executable {
     fn execute(&self, env: &Env) -> Result<(), Traceback>;
 }
 
-pub trait Statement: Executable + Resolvable {}
+pub trait Statement: Executable + Resolvable + Any {
+    fn as_any(&self) -> &dyn Any;
+}
 
 pub type STMT = Box<dyn Statement>;
 
@@ -37,29 +42,34 @@ pub struct WhileStatement {
 }
 
 pub struct ForeachStatement {
-    pub variable: Token,
+    pub target: AssignTarget,
     pub collection: EXPR,
     pub body: STMT,
 }
 
 pub struct PrintStatement {
-    pub expression: EXPR,
-    pub output: Rc<RefCell<Box<dyn MyWrite>>>,
+    pub arguments: Vec<Argument>,
+    // sep and end, checked when printing
+    pub keywords: Vec<(Token, EXPR)>,
 }
 
 pub struct VarStatement {
-    pub name: Token,
+    pub target: AssignTarget,
     pub initializer: EXPR,
 }
 
 pub struct BlockStatement {
     pub statements: Vec<STMT>,
+    // some of its lines had syntax errors and were left out
+    pub erroneous: bool,
 }
 
 pub struct FunctionStatementInner {
     pub name: Token,
     pub parameters: Vec<Token>,
     pub body: STMT,
+    // set by the parser when calls are traced
+    pub tracer: Option<Rc<Tracer>>,
 }
 
 pub struct FunctionStatement {
@@ -79,9 +89,47 @@ pub struct NonlocalStatement {
     pub names: Vec<Token>,
 }
 
+pub struct MatchStatement {
+    pub subject: EXPR,
+    pub cases: Vec<MatchCase>,
+}
+
+pub struct MatchCase {
+    pub pattern: Pattern,
+    pub body: STMT,
+}
+
+pub enum Pattern {
+    Literal(Token),
+    Capture(Token),
+    Wildcard,
+    Sequence(Vec<Pattern>),
+}
+
+// a bare raise re-raises the error being handled
+pub struct RaiseStatement {
+    pub keyword: Token,
+    pub exception: Option<EXPR>,
+}
+
+pub struct TryStatement {
+    pub body: STMT,
+    pub handlers: Vec<ExceptHandler>,
+}
+
+pub struct ExceptHandler {
+    // a bare except catches everything
+    pub kind: Option<Token>,
+    // bound to the exception with `except Kind as name`
+    pub name: Option<Token>,
+    pub body: STMT,
+}
+
 pub struct ClassStatement {
     pub name: Token,
     pub methods: Vec<FunctionStatement>,
+    // `name = value` lines of its body
+    pub attributes: Vec<(Token, EXPR)>,
     pub superclass: Option<Variable>,
 }
 
@@ -106,32 +154,77 @@ impl Executable for IfStatement {
 
 impl Executable for PrintStatement {
     fn execute(&self, env: &Env) -> Result<(), Traceback> {
-        let value = self.expression.eval(env)?.as_string();
-
-        let line_nb = value.lines().count();
-        env.borrow()
-            .get_env_var(EnvVariable::NewLines)
-            .increment_by(line_nb as f64);
+        let values: Vec<String> = eval_arguments(env, &self.arguments)?
+            .iter()
+            .map(DynValue::as_string)
+            .collect();
+        let (mut sep, mut end) = (" ".to_string(), "\n".to_string());
+        for (name, value) in &self.keywords {
+            let setting = match name.value.as_str() {
+                "sep" => &mut sep,
+                "end" => &mut end,
+                _ => {
+                    return Err(print_error(
+                        name,
+                        format!(
+                            "'{}' is an invalid keyword argument for print()",
+                            name.value
+                        ),
+                    ))
+                }
+            };
+            // None keeps the default
+            let value = value.eval(env)?;
+            match value.tipe {
+                TypeKind::Nil => {}
+                TypeKind::Stringue => *setting = value.as_string(),
+                _ => {
+                    return Err(print_error(
+                        name,
+                        format!(
+                            "{} must be None or a string, not {}",
+                            name.value, value.tipe
+                        ),
+                    ))
+                }
+            }
+        }
 
-        writeln!(self.output.borrow_mut(), "{}", value).unwrap();
+        let output = env.borrow().output();
+        write!(output.borrow_mut(), "{}{}", values.join(&sep), end)
+            .map_err(|err| Traceback::from(format!("could not print: {}", err)))?;
 
         Ok(())
     }
 }
 
+fn print_error(keyword: &Token, message: String) -> Traceback {
+    Traceback {
+        message: Some(message),
+        pos: keyword.pos.unwrap_or_default(),
+        tipe: TracebackKind::TypeError,
+        ..Default::default()
+    }
+}
+
 impl Executable for VarStatement {
     fn execute(&self, env: &Env) -> Result<(), Traceback> {
+        let place = self.target.place(env)?;
         let value = self.initializer.eval(env)?;
-
-        env.borrow_mut().set(self.name.value.clone(), value);
-
-        Ok(())
+        place.store(env, value)
     }
 }
 
 impl Executable for BlockStatement {
     fn execute(&self, env: &Env) -> Result<(), Traceback> {
+        if self.erroneous {
+            return Err(Traceback::from_exception(
+                TracebackKind::SyntaxError,
+                "cannot run a block with syntax errors",
+            ));
+        }
         for statement in &self.statements {
+            count_statement();
             statement.execute(env)?;
         }
         Ok(())
@@ -149,19 +242,13 @@ impl Executable for WhileStatement {
 
 impl Executable for ForeachStatement {
     fn execute(&self, env: &Env) -> Result<(), Traceback> {
-        let list = self.collection.eval(env)?;
-        if let Some(array) = list.as_list() {
-            for value in array {
-                env.borrow_mut().set(self.variable.value.clone(), value);
-                self.body.execute(env)?;
-            }
-            Ok(())
-        } else {
-            Err(Traceback {
-                message: Some(format!("'{}' object is not iterable", list.tipe)),
-                ..Default::default()
-            })
+        let collection = self.collection.eval(env)?;
+        let iterator = iterate(env, &collection)?;
+        while let Some(value) = iterator.next(env)? {
+            self.target.store(env, value)?;
+            self.body.execute(env)?;
         }
+        Ok(())
     }
 }
 
@@ -185,6 +272,7 @@ impl FunctionStatement {
                 name,
                 parameters,
                 body,
+                tracer: None,
             })),
         }
     }
@@ -228,9 +316,133 @@ impl Executable for NonlocalStatement {
     }
 }
 
+impl Executable for MatchStatement {
+    fn execute(&self, env: &Env) -> Result<(), Traceback> {
+        let subject = self.subject.eval(env)?;
+
+        for case in &self.cases {
+            let mut bindings = Vec::new();
+            if case.pattern.matches(&subject, &mut bindings) {
+                for (name, value) in bindings {
+                    env.borrow_mut().set(name, value);
+                }
+                return case.body.execute(env);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Executable for TryStatement {
+    fn execute(&self, env: &Env) -> Result<(), Traceback> {
+        match self.body.execute(env) {
+            Err(traceback) if traceback.tipe.exception_name().is_some() => {
+                for handler in &self.handlers {
+                    let caught = match &handler.kind {
+                        Some(kind) => traceback.tipe.is_caught_by(&kind.value),
+                        None => true,
+                    };
+                    if caught {
+                        if let Some(name) = &handler.name {
+                            let function = call_stack(env).pop().unwrap();
+                            env.borrow_mut()
+                                .set(name.value.clone(), traceback.exception_value(function));
+                        }
+                        return match handler.body.execute(env) {
+                            Err(Traceback {
+                                tipe: TracebackKind::Reraise,
+                                ..
+                            }) => Err(traceback),
+                            Err(mut error) if error.tipe.exception_name().is_some() => {
+                                error.chain(traceback);
+                                Err(error)
+                            }
+                            result => result,
+                        };
+                    }
+                }
+                Err(traceback)
+            }
+            result => result,
+        }
+    }
+}
+
+impl Executable for RaiseStatement {
+    fn execute(&self, env: &Env) -> Result<(), Traceback> {
+        let pos = self.keyword.pos.unwrap_or_default();
+        let exception = match &self.exception {
+            Some(exception) => exception.eval(env)?,
+            None => {
+                return Err(Traceback {
+                    pos,
+                    message: Some("No active exception to reraise".to_string()),
+                    tipe: TracebackKind::Reraise,
+                    ..Default::default()
+                })
+            }
+        };
+        match exception.as_exception() {
+            // with the position and frames it had when it was caught
+            Some(exception) => Err(exception.traceback),
+            None => Err(Traceback {
+                pos,
+                message: Some(format!(
+                    "can only raise a caught exception, not '{}'",
+                    exception.tipe
+                )),
+                tipe: TracebackKind::TypeError,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+impl Pattern {
+    // collects the captured names into bindings, which
+    // should be discarded if the pattern doesn't match
+    fn matches(&self, value: &DynValue, bindings: &mut Vec<(String, DynValue)>) -> bool {
+        match self {
+            Pattern::Literal(token) => {
+                let literal = DynValue::from_token(token);
+                literal.tipe == value.tipe && literal == *value
+            }
+            Pattern::Capture(name) => {
+                bindings.push((name.value.clone(), value.clone()));
+                true
+            }
+            Pattern::Wildcard => true,
+            Pattern::Sequence(patterns) => match value.as_sequence() {
+                Some(elements) => {
+                    elements.len() == patterns.len()
+                        && patterns
+                            .iter()
+                            .zip(elements.iter())
+                            .all(|(pattern, element)| pattern.matches(element, bindings))
+                }
+                None => false,
+            },
+        }
+    }
+
+    pub fn captures(&self) -> Vec<&Token> {
+        match self {
+            Pattern::Capture(name) => vec![name],
+            Pattern::Sequence(patterns) => patterns.iter().flat_map(|p| p.captures()).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
 impl Executable for ClassStatement {
     fn execute(&self, env: &Env) -> Result<(), Traceback> {
         let mut env = env.clone();
+        let mut attributes = HashMap::new();
+        for (name, value) in &self.attributes {
+            attributes.insert(name.value.clone(), value.eval(&env)?);
+        }
+
         let superclass = if let Some(superclass_stmt) = &self.superclass {
             let superclass = superclass_stmt.eval(&env)?;
             if let Some(superclass) = superclass.as_class() {
@@ -244,7 +456,7 @@ impl Executable for ClassStatement {
                         "class cannot inherit from non-class '{}'",
                         superclass.tipe
                     )),
-                    pos: superclass_stmt.name.pos.unwrap(),
+                    pos: superclass_stmt.name.pos.unwrap_or_default(),
                     ..Default::default()
                 });
             }
@@ -267,7 +479,8 @@ impl Executable for ClassStatement {
             env = enclosing;
         }
 
-        let class = Class::new(self.name.value.clone(), methods, superclass);
+        let mut class = Class::new(self.name.value.clone(), methods, superclass);
+        class.attributes = attributes;
 
         env.borrow_mut()
             .set(self.name.value.clone(), DynValue::from(class));
@@ -276,24 +489,46 @@ impl Executable for ClassStatement {
 }
 
 impl ClassStatement {
-    pub fn new(name: Token, methods: Vec<FunctionStatement>, superclass: Option<Variable>) -> Self {
+    pub fn new(
+        name: Token,
+        methods: Vec<FunctionStatement>,
+        attributes: Vec<(Token, EXPR)>,
+        superclass: Option<Variable>,
+    ) -> Self {
         Self {
             name,
             methods,
+            attributes,
             superclass,
         }
     }
 }
 
-impl Statement for FunctionStatement {}
-impl Statement for ExpressionStatement {}
-impl Statement for IfStatement {}
-impl Statement for PrintStatement {}
-impl Statement for VarStatement {}
-impl Statement for BlockStatement {}
-impl Statement for WhileStatement {}
-impl Statement for ForeachStatement {}
-impl Statement for ReturnStatement {}
-impl Statement for GlobalStatement {}
-impl Statement for NonlocalStatement {}
-impl Statement for ClassStatement {}
+macro_rules! impl_stmt {
+    ($($t:ty),*) => {
+        $(
+            impl Statement for $t {
+                fn as_any(&self) -> &dyn Any {
+                    self
+                }
+            }
+        )*
+    }
+}
+impl_stmt!(
+    FunctionStatement,
+    ExpressionStatement,
+    IfStatement,
+    PrintStatement,
+    VarStatement,
+    BlockStatement,
+    WhileStatement,
+    ForeachStatement,
+    ReturnStatement,
+    GlobalStatement,
+    NonlocalStatement,
+    ClassStatement,
+    MatchStatement,
+    RaiseStatement,
+    TryStatement
+);
@@ -0,0 +1,115 @@
+use std::cell::Cell;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// What a run did, see `Interpreter::last_run_stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunStats {
+    /// Statements executed, those in a loop once per iteration.
+    pub statements: u64,
+    /// Calls made to functions, methods, classes and builtins.
+    pub calls: u64,
+    /// How deep the calls to script functions nested.
+    pub peak_depth: usize,
+    /// Lists made, by literals as well as by builtins and methods.
+    pub lists: u64,
+    /// Instances of the script's classes made.
+    pub instances: u64,
+    /// Wall-clock time of the run, parsing included.
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for RunStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "statements: {}, calls: {}, peak depth: {}, lists: {}, instances: {}, time: {:?}",
+            self.statements, self.calls, self.peak_depth, self.lists, self.instances, self.elapsed
+        )
+    }
+}
+
+// counted on the thread since it started, a run takes the difference.
+// Not kept per interpreter so that counting needs no environment
+#[derive(Clone, Copy, Default)]
+struct Counters {
+    statements: u64,
+    calls: u64,
+    lists: u64,
+    instances: u64,
+    depth: usize,
+    peak_depth: usize,
+}
+
+thread_local! {
+    static COUNTERS: Cell<Counters> = Cell::new(Counters::default());
+}
+
+fn update(change: impl FnOnce(&mut Counters)) {
+    COUNTERS.with(|counters| {
+        let mut updated = counters.get();
+        change(&mut updated);
+        counters.set(updated);
+    });
+}
+
+pub fn count_statement() {
+    update(|counters| counters.statements += 1);
+}
+
+pub fn count_call() {
+    update(|counters| counters.calls += 1);
+}
+
+pub fn count_list() {
+    update(|counters| counters.lists += 1);
+}
+
+pub fn count_instance() {
+    update(|counters| counters.instances += 1);
+}
+
+// around the body of a script function
+pub fn enter_function() {
+    update(|counters| {
+        counters.depth += 1;
+        counters.peak_depth = counters.peak_depth.max(counters.depth);
+    });
+}
+
+pub fn leave_function() {
+    update(|counters| counters.depth -= 1);
+}
+
+// the counting for a run, which may itself be inside another
+// (a host function running a second interpreter)
+pub struct Measure {
+    start: Counters,
+    outer_peak: usize,
+    started: Instant,
+}
+
+impl Measure {
+    pub fn start() -> Self {
+        let start = COUNTERS.with(Cell::get);
+        update(|counters| counters.peak_depth = counters.depth);
+        Self {
+            start,
+            outer_peak: start.peak_depth,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn finish(self) -> RunStats {
+        let end = COUNTERS.with(Cell::get);
+        update(|counters| counters.peak_depth = counters.peak_depth.max(self.outer_peak));
+        RunStats {
+            statements: end.statements - self.start.statements,
+            calls: end.calls - self.start.calls,
+            peak_depth: end.peak_depth - self.start.depth,
+            lists: end.lists - self.start.lists,
+            instances: end.instances - self.start.instances,
+            elapsed: self.started.elapsed(),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+// helpers for the tests, the crate's own and the ones in tests/
+
+// lines shown around the first difference
+const CONTEXT: usize = 2;
+// lines shown from the first difference on, the others only counted
+const SHOWN: usize = 8;
+
+/// Where `got` differs from `expected`, line by line, or `None` when
+/// they are the same: the first differing line with a few around it,
+/// marked `-` for the expected and `+` for what was got.
+///
+/// ```
+/// use myton::test_support::line_diff;
+///
+/// assert_eq!(line_diff("a\nb\n", "a\nb\n"), None);
+/// assert!(line_diff("a\nb\n", "a\nc\n").unwrap().contains("- 2 | b\n+ 2 | c"));
+/// ```
+pub fn line_diff(expected: &str, got: &str) -> Option<String> {
+    if expected == got {
+        return None;
+    }
+    let expected_lines = expected.lines().collect::<Vec<_>>();
+    let got_lines = got.lines().collect::<Vec<_>>();
+    let len = expected_lines.len().max(got_lines.len());
+    let differs = |i: usize| expected_lines.get(i) != got_lines.get(i);
+
+    // the same lines, so only what ends the last one is different
+    let first = match (0..len).find(|&i| differs(i)) {
+        Some(first) => first,
+        None => {
+            return Some(match (expected.ends_with('\n'), got.ends_with('\n')) {
+                (true, false) => "the newline at the end is missing".to_string(),
+                (false, true) => "there is an extra newline at the end".to_string(),
+                _ => "the line endings differ".to_string(),
+            })
+        }
+    };
+
+    let differing = (0..len).filter(|&i| differs(i)).count();
+    let mut diff = format!(
+        "first difference at line {}, {} of {} lines differ:\n",
+        first + 1,
+        differing,
+        len
+    );
+    let end = len.min(first + SHOWN);
+    for i in first.saturating_sub(CONTEXT)..end {
+        if !differs(i) {
+            diff += &format!("  {} | {}\n", i + 1, expected_lines[i]);
+            continue;
+        }
+        if let Some(line) = expected_lines.get(i) {
+            diff += &format!("- {} | {}\n", i + 1, line);
+        }
+        if let Some(line) = got_lines.get(i) {
+            diff += &format!("+ {} | {}\n", i + 1, line);
+        }
+    }
+    let hidden = (end..len).filter(|&i| differs(i)).count();
+    if hidden > 0 {
+        diff += &format!("... and {} more differing lines\n", hidden);
+    }
+    Some(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_diff() {
+        assert_eq!(line_diff("", ""), None);
+
+        assert_eq!(
+            line_diff("1\n2\n3\n4\n5\n", "1\n2\n3\nfour\n5\n").unwrap(),
+            "first difference at line 4, 1 of 5 lines differ:\n  2 | 2\n  3 | 3\n- 4 | 4\n+ 4 | four\n  5 | 5\n"
+        );
+
+        // lines only one of them has
+        assert_eq!(
+            line_diff("a\n", "a\nb\n").unwrap(),
+            "first difference at line 2, 1 of 2 lines differ:\n  1 | a\n+ 2 | b\n"
+        );
+        assert_eq!(
+            line_diff("a\nb\n", "").unwrap(),
+            "first difference at line 1, 2 of 2 lines differ:\n- 1 | a\n- 2 | b\n"
+        );
+
+        // far from the first difference, only counted
+        let expected = (0..20).map(|i| format!("{}\n", i)).collect::<String>();
+        let diff = line_diff(&expected, &expected.replace('1', "x")).unwrap();
+        assert!(diff.starts_with(
+            "first difference at line 2, 11 of 20 lines differ:\n  1 | 0\n- 2 | 1\n+ 2 | x\n"
+        ));
+        assert!(diff.ends_with("  9 | 8\n... and 10 more differing lines\n"));
+    }
+
+    #[test]
+    fn test_line_diff_of_line_endings() {
+        assert_eq!(
+            line_diff("done\n", "done").unwrap(),
+            "the newline at the end is missing"
+        );
+        assert_eq!(
+            line_diff("done", "done\n").unwrap(),
+            "there is an extra newline at the end"
+        );
+        assert_eq!(
+            line_diff("a\nb\n", "a\r\nb\n").unwrap(),
+            "the line endings differ"
+        );
+    }
+}
@@ -0,0 +1,484 @@
+// the dates and times of the time module, in UTC unless an offset like
+// +02:00 is given. The errors are the ValueError messages. Like python's
+// datetime, only the years 1 to 9999 of the gregorian calendar
+
+const SECONDS_PER_DAY: i64 = 86_400;
+// 0001-01-01T00:00:00 and 9999-12-31T23:59:59
+const MIN_SECONDS: i64 = -62_135_596_800;
+const MAX_SECONDS: i64 = 253_402_300_799;
+
+// a point in time as read on a clock set `offset` seconds east of UTC
+#[derive(Debug, PartialEq)]
+struct DateTime {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    micros: u32,
+    offset: i64,
+}
+
+// the days from 1970-01-01 to the date and back, after
+// http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i64;
+    let shifted_month = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * shifted_month + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn out_of_range() -> String {
+    "timestamp out of range".to_string()
+}
+
+impl DateTime {
+    fn from_timestamp(seconds: f64, offset: i64) -> Result<Self, String> {
+        // whole microseconds, which a day of margin keeps within an i64
+        let margin = SECONDS_PER_DAY as f64;
+        if !(MIN_SECONDS as f64 - margin..=MAX_SECONDS as f64 + margin).contains(&seconds) {
+            return Err(out_of_range());
+        }
+        let total = (seconds * 1e6).round() as i64;
+        let local = total.div_euclid(1_000_000) + offset;
+        if !(MIN_SECONDS..=MAX_SECONDS).contains(&local) {
+            return Err(out_of_range());
+        }
+
+        let (year, month, day) = civil_from_days(local.div_euclid(SECONDS_PER_DAY));
+        let time = local.rem_euclid(SECONDS_PER_DAY) as u32;
+        Ok(Self {
+            year,
+            month,
+            day,
+            hour: time / 3600,
+            minute: time / 60 % 60,
+            second: time % 60,
+            micros: total.rem_euclid(1_000_000) as u32,
+            offset,
+        })
+    }
+
+    fn timestamp(&self) -> f64 {
+        let local = days_from_civil(self.year, self.month, self.day) * SECONDS_PER_DAY
+            + (self.hour * 3600 + self.minute * 60 + self.second) as i64;
+        // one division, so that -0.000001 doesn't come back as -1 + 0.999999
+        ((local - self.offset) * 1_000_000 + self.micros as i64) as f64 / 1e6
+    }
+
+    // the strftime directives %Y %m %d %H %M %S %f %z and %%
+    fn format(&self, format: &str) -> Result<String, String> {
+        let mut formatted = String::new();
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                formatted.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('Y') => formatted += &format!("{:04}", self.year),
+                Some('m') => formatted += &format!("{:02}", self.month),
+                Some('d') => formatted += &format!("{:02}", self.day),
+                Some('H') => formatted += &format!("{:02}", self.hour),
+                Some('M') => formatted += &format!("{:02}", self.minute),
+                Some('S') => formatted += &format!("{:02}", self.second),
+                Some('f') => formatted += &format!("{:06}", self.micros),
+                Some('z') => formatted += &format_offset(self.offset),
+                Some('%') => formatted.push('%'),
+                Some(other) => return Err(format!("unsupported directive '%{}'", other)),
+                None => return Err("stray '%' at the end of the format".to_string()),
+            }
+        }
+        Ok(formatted)
+    }
+}
+
+// +0200, like %z
+fn format_offset(offset: i64) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let minutes = offset.abs() / 60;
+    format!("{}{:02}{:02}", sign, minutes / 60, minutes % 60)
+}
+
+// Z, +02, +0200 or +02:00 at the start of the text, in
+// seconds east of UTC, with the length it was written in
+fn parse_offset(text: &str) -> Option<(i64, usize)> {
+    if text.starts_with('Z') {
+        return Some((0, 1));
+    }
+    let sign = match text.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let (hours, mut len) = digits(&text[1..], 2, 2)?;
+    len += 1;
+    let rest = &text[len..];
+    let (minutes, minutes_len) = match digits(rest.strip_prefix(':').unwrap_or(rest), 2, 2) {
+        Some((minutes, minutes_len)) if rest.starts_with(':') => (minutes, minutes_len + 1),
+        Some(found) => found,
+        None => (0, 0),
+    };
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+    Some((sign * (hours * 3600 + minutes * 60), len + minutes_len))
+}
+
+// from min to max ascii digits at the start of the text, and how many
+fn digits(text: &str, min: usize, max: usize) -> Option<(i64, usize)> {
+    let len = text
+        .bytes()
+        .take(max)
+        .take_while(u8::is_ascii_digit)
+        .count();
+    if len < min {
+        return None;
+    }
+    Some((text[..len].parse().ok()?, len))
+}
+
+/// The time, in seconds since the epoch, formatted with the strftime
+/// directives %Y %m %d %H %M %S %f %z and %%, in UTC or at the offset.
+pub fn format_timestamp(
+    seconds: f64,
+    format: &str,
+    offset: Option<&str>,
+) -> Result<String, String> {
+    let offset = match offset {
+        Some(text) => match parse_offset(text) {
+            Some((offset, len)) if len == text.len() => offset,
+            _ => {
+                return Err(format!(
+                    "invalid offset '{}', expected one like +02:00",
+                    text
+                ))
+            }
+        },
+        None => 0,
+    };
+    DateTime::from_timestamp(seconds, offset)?.format(format)
+}
+
+/// The seconds since the epoch written in the text as the format says,
+/// the fields it lacks being those of 1900-01-01T00:00:00 UTC.
+pub fn parse_timestamp(text: &str, format: &str) -> Result<f64, String> {
+    let mismatch = |rest: &str| {
+        format!(
+            "time data '{}' does not match format '{}' at '{}'",
+            text, format, rest
+        )
+    };
+    let mut parsed = DateTime {
+        year: 1900,
+        month: 1,
+        day: 1,
+        hour: 0,
+        minute: 0,
+        second: 0,
+        micros: 0,
+        offset: 0,
+    };
+
+    let mut rest = text;
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            rest = rest.strip_prefix(c).ok_or_else(|| mismatch(rest))?;
+            continue;
+        }
+        let directive = chars.next();
+        let len = match directive {
+            Some('Y') => {
+                let (year, len) = digits(rest, 4, 4).ok_or_else(|| mismatch(rest))?;
+                parsed.year = year;
+                len
+            }
+            Some(field @ ('m' | 'd' | 'H' | 'M' | 'S')) => {
+                let (value, len) = digits(rest, 1, 2).ok_or_else(|| mismatch(rest))?;
+                let value = value as u32;
+                match field {
+                    'm' => parsed.month = value,
+                    'd' => parsed.day = value,
+                    'H' => parsed.hour = value,
+                    'M' => parsed.minute = value,
+                    _ => parsed.second = value,
+                }
+                len
+            }
+            Some('f') => {
+                let (micros, len) = digits(rest, 1, 6).ok_or_else(|| mismatch(rest))?;
+                parsed.micros = (micros * 10_i64.pow(6 - len as u32)) as u32;
+                len
+            }
+            Some('z') => {
+                let (offset, len) = parse_offset(rest).ok_or_else(|| mismatch(rest))?;
+                parsed.offset = offset;
+                len
+            }
+            Some('%') => {
+                rest.strip_prefix('%').ok_or_else(|| mismatch(rest))?;
+                1
+            }
+            Some(other) => return Err(format!("unsupported directive '%{}'", other)),
+            None => return Err("stray '%' at the end of the format".to_string()),
+        };
+        rest = &rest[len..];
+    }
+    if !rest.is_empty() {
+        return Err(format!("unconverted data remains: '{}'", rest));
+    }
+
+    if !(1..=12).contains(&parsed.month) {
+        return Err(format!("month {} is out of range", parsed.month));
+    }
+    if parsed.day < 1 || parsed.day > days_in_month(parsed.year, parsed.month) {
+        return Err(format!("day {} is out of range for month", parsed.day));
+    }
+    if parsed.year < 1 || parsed.hour > 23 || parsed.minute > 59 || parsed.second > 59 {
+        return Err(format!("time data '{}' is not a valid time", text));
+    }
+    Ok(parsed.timestamp())
+}
+
+/// Like 2024-05-04T12:34:56.789Z, to the millisecond.
+pub fn iso_utc(seconds: f64) -> Result<String, String> {
+    let time = DateTime::from_timestamp(seconds, 0)?;
+    Ok(format!(
+        "{}.{:03}Z",
+        time.format("%Y-%m-%dT%H:%M:%S")?,
+        time.micros / 1000
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_timestamp() {
+        let format = |seconds, format| format_timestamp(seconds, format, None).unwrap();
+        assert_eq!(format(0.0, "%Y-%m-%d %H:%M:%S"), "1970-01-01 00:00:00");
+        assert_eq!(
+            format(1714826096.789, "%Y-%m-%dT%H:%M:%S.%f%z"),
+            "2024-05-04T12:34:56.789000+0000"
+        );
+        assert_eq!(format(951782400.0, "%d/%m/%Y"), "29/02/2000");
+        assert_eq!(format(-1.5, "%Y %H:%M:%S.%f"), "1969 23:59:58.500000");
+        assert_eq!(format(0.0, "100%% at %H"), "100% at 00");
+
+        assert_eq!(
+            format_timestamp(0.0, "%H:%M%z", Some("+05:30")).unwrap(),
+            "05:30+0530"
+        );
+        assert_eq!(
+            format_timestamp(0.0, "%Y-%m-%d %H%z", Some("-0800")).unwrap(),
+            "1969-12-31 16-0800"
+        );
+
+        assert_eq!(iso_utc(1714826096.789).unwrap(), "2024-05-04T12:34:56.789Z");
+    }
+
+    #[test]
+    fn test_leap_years() {
+        for (year, leap) in [
+            (4, true),
+            (1600, true),
+            (1900, false),
+            (2000, true),
+            (2023, false),
+            (2024, true),
+            (2100, false),
+            (9996, true),
+        ] {
+            let text = format!("{:04}-02-29", year);
+            let parsed = parse_timestamp(&text, "%Y-%m-%d");
+            assert_eq!(parsed.is_ok(), leap, "{}", text);
+            // the day after the 28th is the 29th or the 1st of March
+            let before = parse_timestamp(&format!("{:04}-02-28", year), "%Y-%m-%d").unwrap();
+            let after = format_timestamp(before + 86_400.0, "%m-%d", None).unwrap();
+            assert_eq!(after, if leap { "02-29" } else { "03-01" }, "{}", year);
+        }
+    }
+
+    #[test]
+    fn test_before_the_epoch() {
+        let format = |seconds| format_timestamp(seconds, "%Y-%m-%d %H:%M:%S.%f", None).unwrap();
+        for (seconds, expected) in [
+            (-0.000001, "1969-12-31 23:59:59.999999"),
+            (-1.0, "1969-12-31 23:59:59.000000"),
+            (-86_400.0, "1969-12-31 00:00:00.000000"),
+            (-86_400.5, "1969-12-30 23:59:59.500000"),
+            (-2_208_988_800.0, "1900-01-01 00:00:00.000000"),
+            (-5_364_662_400.0, "1800-01-01 00:00:00.000000"),
+        ] {
+            assert_eq!(format(seconds), expected, "{}", seconds);
+            assert_eq!(
+                parse_timestamp(expected, "%Y-%m-%d %H:%M:%S.%f"),
+                Ok(seconds),
+                "{}",
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_microseconds_are_rounded() {
+        let format = |seconds| format_timestamp(seconds, "%S.%f", None).unwrap();
+        for (seconds, expected) in [
+            (0.123456789, "00.123457"),
+            (0.1234564, "00.123456"),
+            (1.9999996, "02.000000"),
+            (59.9999999, "00.000000"),
+            (-0.0000004, "00.000000"),
+            (-0.0000006, "59.999999"),
+        ] {
+            assert_eq!(format(seconds), expected, "{}", seconds);
+        }
+        // rounding up can carry into the next day
+        assert_eq!(
+            format_timestamp(86_399.9999999, "%Y-%m-%d %H:%M:%S", None).unwrap(),
+            "1970-01-02 00:00:00"
+        );
+        // a parsed %f of fewer digits is the start of the fraction
+        assert_eq!(
+            parse_timestamp("1.5", "%S.%f"),
+            parse_timestamp("1.500000", "%S.%f")
+        );
+    }
+
+    #[test]
+    fn test_first_and_last_years() {
+        let first = MIN_SECONDS as f64;
+        let last = MAX_SECONDS as f64;
+        let format = "%Y-%m-%d %H:%M:%S.%f";
+        for (seconds, offset, expected) in [
+            (first, None, Ok("0001-01-01 00:00:00.000000")),
+            (first, Some("+01:00"), Ok("0001-01-01 01:00:00.000000")),
+            (last + 0.5, None, Ok("9999-12-31 23:59:59.500000")),
+            (last, Some("-01:00"), Ok("9999-12-31 22:59:59.000000")),
+            (first - 0.5, None, Err("timestamp out of range")),
+            (first, Some("-00:01"), Err("timestamp out of range")),
+            (last + 1.0, None, Err("timestamp out of range")),
+            (last, Some("+00:01"), Err("timestamp out of range")),
+        ] {
+            assert_eq!(
+                format_timestamp(seconds, format, offset),
+                expected.map(str::to_string).map_err(str::to_string),
+                "{} {:?}",
+                seconds,
+                offset
+            );
+        }
+
+        assert_eq!(parse_timestamp("0001-01-01", "%Y-%m-%d"), Ok(first));
+        assert_eq!(
+            parse_timestamp("9999-12-31 23:59:59", "%Y-%m-%d %H:%M:%S"),
+            Ok(last)
+        );
+        assert_eq!(
+            parse_timestamp("0000-12-31", "%Y-%m-%d"),
+            Err("time data '0000-12-31' is not a valid time".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format_errors() {
+        let error =
+            |seconds, format, offset| format_timestamp(seconds, format, offset).unwrap_err();
+        assert_eq!(error(0.0, "%A", None), "unsupported directive '%A'");
+        assert_eq!(error(0.0, "%", None), "stray '%' at the end of the format");
+        assert_eq!(error(1e20, "%Y", None), "timestamp out of range");
+        assert_eq!(error(f64::NAN, "%Y", None), "timestamp out of range");
+        assert_eq!(
+            error(0.0, "%Y", Some("2 hours")),
+            "invalid offset '2 hours', expected one like +02:00"
+        );
+    }
+
+    #[test]
+    fn test_round_trips() {
+        for (seconds, format) in [
+            (0.0, "%Y-%m-%d %H:%M:%S"),
+            (1714826096.0, "%Y-%m-%dT%H:%M:%S%z"),
+            (1714826096.25, "%Y%m%d%H%M%S.%f"),
+            (951782400.0, "%d/%m/%Y"),
+            (-62135596800.0, "%Y-%m-%d"),
+            (253402300799.0, "%Y-%m-%d %H:%M:%S"),
+            (-86400.0 * 365.0, "%H:%M:%S %d.%m.%Y"),
+        ] {
+            let text = format_timestamp(seconds, format, None).unwrap();
+            assert_eq!(parse_timestamp(&text, format), Ok(seconds), "{}", text);
+        }
+
+        // at an offset, the same instant
+        let text = format_timestamp(1714826040.0, "%Y-%m-%d %H:%M%z", Some("+02:00")).unwrap();
+        assert_eq!(text, "2024-05-04 14:34+0200");
+        assert_eq!(parse_timestamp(&text, "%Y-%m-%d %H:%M%z"), Ok(1714826040.0));
+        assert_eq!(
+            parse_timestamp("2024-05-04T12:34:56Z", "%Y-%m-%dT%H:%M:%S%z"),
+            Ok(1714826096.0)
+        );
+        assert_eq!(parse_timestamp("12:00", "%H:%M"), Ok(-2208945600.0));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |text, format| parse_timestamp(text, format).unwrap_err();
+        assert_eq!(
+            error("2024/05/04", "%Y-%m-%d"),
+            "time data '2024/05/04' does not match format '%Y-%m-%d' at '/05/04'"
+        );
+        assert_eq!(
+            error("24-05-04", "%Y-%m-%d"),
+            "time data '24-05-04' does not match format '%Y-%m-%d' at '24-05-04'"
+        );
+        assert_eq!(
+            error("2024-05-04 12:00", "%Y-%m-%d"),
+            "unconverted data remains: ' 12:00'"
+        );
+        assert_eq!(error("2024-13-01", "%Y-%m-%d"), "month 13 is out of range");
+        assert_eq!(
+            error("2023-02-29", "%Y-%m-%d"),
+            "day 29 is out of range for month"
+        );
+        assert_eq!(
+            error("25:00", "%H:%M"),
+            "time data '25:00' is not a valid time"
+        );
+        assert_eq!(error("x", "%q"), "unsupported directive '%q'");
+    }
+}
@@ -0,0 +1,177 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+
+use super::environment::Env;
+use super::statement::{Executable, Statement, STMT};
+use super::traceback::Traceback;
+use super::types::DynValue;
+use super::MyWrite;
+
+/// How much `--trace` reports, each level includes the previous one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TraceMode {
+    Off,
+    // function entries and exits
+    Calls,
+    // every statement before it runs, and the calls
+    Lines,
+}
+
+impl TraceMode {
+    pub fn from_flag(flag: &str) -> Option<Self> {
+        match flag {
+            "calls" => Some(Self::Calls),
+            "lines" => Some(Self::Lines),
+            _ => None,
+        }
+    }
+}
+
+// writes the trace of one run, with the source
+// of that run to show the lines being executed
+pub struct Tracer {
+    pub mode: TraceMode,
+    source: String,
+    output: Rc<RefCell<Box<dyn MyWrite>>>,
+}
+
+impl Tracer {
+    pub fn new(mode: TraceMode, source: String, output: Rc<RefCell<Box<dyn MyWrite>>>) -> Self {
+        Self {
+            mode,
+            source,
+            output,
+        }
+    }
+
+    fn write(&self, message: String) {
+        writeln!(self.output.borrow_mut(), "TRACE {}", message).ok();
+    }
+
+    pub fn line(&self, line: usize) {
+        let code = self.source.lines().nth(line).unwrap_or("").trim();
+        self.write(format!("{}: {}", line + 1, code));
+    }
+
+    pub fn call(&self, name: &str, args: &[DynValue]) {
+        let args = args
+            .iter()
+            .map(DynValue::repr)
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.write(format!("call {}({})", name, args));
+    }
+
+    pub fn exit(&self, name: &str, result: &Result<DynValue, Traceback>) {
+        match result {
+            Ok(value) => self.write(format!("return {} -> {}", name, value.repr())),
+            Err(traceback) => self.write(format!(
+                "raise {}: {}: {}",
+                name,
+                traceback.tipe,
+                traceback.message()
+            )),
+        }
+    }
+}
+
+// a statement which reports its line before running,
+// only created by the parser when lines are traced
+pub struct TracedStatement {
+    pub line: usize,
+    pub statement: STMT,
+    pub tracer: Rc<Tracer>,
+}
+
+impl Executable for TracedStatement {
+    fn execute(&self, env: &Env) -> Result<(), Traceback> {
+        self.tracer.line(self.line);
+        self.statement.execute(env)
+    }
+}
+
+impl Statement for TracedStatement {
+    // looks through the wrapper, for whoever wants the actual statement
+    fn as_any(&self) -> &dyn Any {
+        self.statement.as_any()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Interpreter;
+    use super::*;
+
+    fn run_traced(mode: TraceMode, source: &str) -> (String, Vec<String>) {
+        let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+        let error_output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+        let mut interpreter = Interpreter::new_with_outputs(output.clone(), error_output.clone());
+        interpreter.set_trace_mode(mode);
+        interpreter.run(source.to_string()).unwrap();
+
+        let stdout = output.borrow().get_string().unwrap();
+        let stderr = error_output.borrow().get_string().unwrap();
+        (stdout, stderr.lines().map(str::to_string).collect())
+    }
+
+    const HALVE: &str = "def halve(n):
+  while n > 1:
+    n = n / 2
+  return n
+print halve(4)
+";
+
+    #[test]
+    fn test_trace_lines() {
+        let (stdout, trace) = run_traced(TraceMode::Lines, HALVE);
+        assert_eq!(stdout, "1\n");
+        assert_eq!(
+            trace,
+            vec![
+                "TRACE 1: def halve(n):",
+                "TRACE 5: print halve(4)",
+                "TRACE call halve(4)",
+                "TRACE 2: while n > 1:",
+                "TRACE 3: n = n / 2",
+                "TRACE 3: n = n / 2",
+                "TRACE 4: return n",
+                "TRACE return halve -> 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trace_calls() {
+        let source = "class Greeter:
+  def greet(name, times):
+    return name.upper() * times
+def shout(text):
+  return Greeter().greet(text, 2) + \"!\"
+shout(\"hey\")
+try:
+  shout(1)
+except:
+  print \"caught\"
+";
+        let (stdout, trace) = run_traced(TraceMode::Calls, source);
+        assert_eq!(stdout, "caught\n");
+        assert_eq!(trace[0], "TRACE call shout('hey')");
+        assert_eq!(trace[1], "TRACE call greet('hey', 2)");
+        assert_eq!(trace[2], "TRACE return greet -> 'HEYHEY'");
+        assert_eq!(trace[3], "TRACE return shout -> 'HEYHEY!'");
+        assert_eq!(trace[4], "TRACE call shout(1)");
+        assert_eq!(trace[5], "TRACE call greet(1, 2)");
+        assert!(trace[6].starts_with("TRACE raise greet: "));
+        assert!(trace[7].starts_with("TRACE raise shout: "));
+        assert_eq!(trace.len(), 8);
+    }
+
+    #[test]
+    fn test_no_trace_by_default() {
+        let (stdout, trace) = run_traced(TraceMode::Off, HALVE);
+        assert_eq!(stdout, "1\n");
+        assert!(trace.is_empty());
+    }
+}
@@ -1,12 +1,44 @@
+use super::errors::{report_trace, Style};
 use super::types::DynValue;
 use std::fmt::{Display, Formatter};
 
 #[derive(Debug, Clone)]
 pub enum TracebackKind {
     Error,
+    IndexError,
+    KeyError,
+    TypeError,
+    MemoryError,
+    RecursionError,
+    ValueError,
+    EOFError,
+    SyntaxError,
     ResolveError,
+    IndentationError,
+    TabError,
+    Warning,
     // Tracebacks are also a way to return values from functions
     Return,
+    // and to stop the program with exit(code)
+    Exit(i32),
+    // and for a bare raise to reach the handler it re-raises
+    Reraise,
+}
+
+// a caught error, as seen by the script
+#[derive(Debug, Clone)]
+pub struct Exception {
+    // as it was caught, to be raised again unchanged
+    pub traceback: Traceback,
+    // the function it was caught in, outside of the frames it went through
+    pub caught_in: TraceFrame,
+}
+
+// a function an error went through, and the line it was at in it
+#[derive(Debug, Clone)]
+pub struct TraceFrame {
+    pub function: String,
+    pub line: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -14,22 +46,35 @@ pub struct Traceback {
     pub pos: (usize, usize),
     pub message: Option<String>,
     pub filename: Option<String>,
-    pub function_name: Option<String>,
     pub code: Option<String>,
     pub value: Option<DynValue>,
     pub tipe: TracebackKind,
+    // the functions left on the way up, innermost first
+    pub frames: Vec<TraceFrame>,
+    // where the error came out of a call in the current function
+    pub call_line: Option<usize>,
+    // the source pos is in, None for the one being run
+    pub chunk: Option<usize>,
+    // the error being handled when this one was raised
+    pub cause: Option<Box<Traceback>>,
 }
 
+// errors kept behind the last one of a chain, the oldest are dropped
+const MAX_CHAINED: usize = 8;
+
 impl Default for Traceback {
     fn default() -> Self {
         Self {
             pos: (0, 0),
             message: None,
             filename: None,
-            function_name: None,
             code: None,
             value: None,
             tipe: TracebackKind::Error,
+            frames: Vec::new(),
+            call_line: None,
+            chunk: None,
+            cause: None,
         }
     }
 }
@@ -42,6 +87,99 @@ impl Traceback {
         }
     }
 
+    pub fn from_exit_code(code: i32) -> Self {
+        Self {
+            tipe: TracebackKind::Exit(code),
+            ..Default::default()
+        }
+    }
+
+    // an error scripts can catch by the kind's name
+    pub fn from_exception(tipe: TracebackKind, message: &str) -> Self {
+        Self {
+            message: Some(message.to_string()),
+            tipe,
+            ..Default::default()
+        }
+    }
+
+    // what an except clause binds: the payload a native put in
+    // value if there is one, otherwise an exception object
+    // what `except ... as name` binds, given the function catching it
+    pub fn exception_value(&self, function: String) -> DynValue {
+        match &self.value {
+            Some(payload) => payload.clone(),
+            None => DynValue::from(Exception {
+                traceback: self.clone(),
+                caught_in: TraceFrame {
+                    function,
+                    line: self.current_line(),
+                },
+            }),
+        }
+    }
+
+    // the line the error is at in the function it is going through:
+    // where it happened, or the call it came out of
+    pub fn current_line(&self) -> usize {
+        self.call_line.unwrap_or_else(|| self.line())
+    }
+
+    pub fn leave_function(&mut self, function: &str) {
+        self.frames.push(TraceFrame {
+            function: function.to_string(),
+            line: self.current_line(),
+        });
+        self.call_line = None;
+    }
+
+    // raised in an except handler, while handling the error
+    // it caught, which is then reported before this one
+    pub fn chain(&mut self, handled: Traceback) {
+        if self.cause.is_some() || self.is_reraise_of(&handled) {
+            return;
+        }
+        self.cause = Some(Box::new(handled));
+        let mut link = &mut self.cause;
+        for _ in 0..MAX_CHAINED {
+            match link {
+                Some(traceback) => link = &mut traceback.cause,
+                None => return,
+            }
+        }
+        *link = None;
+    }
+
+    // raise e gives back the error as it was caught
+    fn is_reraise_of(&self, other: &Traceback) -> bool {
+        self.pos == other.pos
+            && self.chunk == other.chunk
+            && self.message == other.message
+            && self.frames.len() == other.frames.len()
+    }
+
+    pub fn leave_call(&mut self, pos: (usize, usize)) {
+        self.call_line = Some(pos.1 + 1);
+    }
+
+    // 1-based, like in the reports
+    pub fn line(&self) -> usize {
+        self.pos.1 + 1
+    }
+
+    // where the offending token ends on its line
+    pub fn column(&self) -> usize {
+        self.pos.0
+    }
+
+    pub fn message(&self) -> &str {
+        self.message.as_deref().unwrap_or("no message")
+    }
+
+    pub fn kind(&self) -> &TracebackKind {
+        &self.tipe
+    }
+
     pub fn from_return_value(value: DynValue) -> Self {
         Self {
             value: Some(value),
@@ -51,12 +189,111 @@ impl Traceback {
     }
 }
 
+impl Exception {
+    // outermost first, like python prints them
+    pub fn frames(&self) -> Vec<TraceFrame> {
+        std::iter::once(self.caught_in.clone())
+            .chain(self.traceback.frames.iter().rev().cloned())
+            .collect()
+    }
+}
+
+impl Display for TraceFrame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, in {}", self.line, self.function)
+    }
+}
+
 impl Display for TracebackKind {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            TracebackKind::Error => write!(f, "runtime error"),
+            TracebackKind::Error | TracebackKind::Reraise => write!(f, "runtime error"),
+            TracebackKind::IndexError => write!(f, "index error"),
+            TracebackKind::KeyError => write!(f, "key error"),
+            TracebackKind::TypeError => write!(f, "type error"),
+            TracebackKind::MemoryError => write!(f, "memory error"),
+            TracebackKind::RecursionError => write!(f, "recursion error"),
+            TracebackKind::ValueError => write!(f, "value error"),
+            TracebackKind::EOFError => write!(f, "EOF error"),
+            TracebackKind::SyntaxError => write!(f, "syntax error"),
             TracebackKind::ResolveError => write!(f, "resolve error"),
+            TracebackKind::IndentationError => write!(f, "indentation error"),
+            TracebackKind::TabError => write!(f, "tab error"),
+            TracebackKind::Warning => write!(f, "warning"),
             TracebackKind::Return => write!(f, "return"),
+            TracebackKind::Exit(_) => write!(f, "exit"),
+        }
+    }
+}
+
+// the full report when the source is known, a one-liner otherwise
+impl Display for Traceback {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.code.is_some() {
+            write!(f, "{}", report_trace(self.clone(), Style::PLAIN).trim_end())
+        } else {
+            write!(
+                f,
+                "{}: {} at {}:{}",
+                self.tipe,
+                self.message(),
+                self.line(),
+                self.column()
+            )
+        }
+    }
+}
+
+impl std::error::Error for Traceback {}
+
+// sysexits.h style codes
+pub const EX_USAGE: i32 = 64;
+pub const EX_DATAERR: i32 = 65;
+pub const EX_NOINPUT: i32 = 66;
+pub const EX_SOFTWARE: i32 = 70;
+
+impl TracebackKind {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            TracebackKind::SyntaxError
+            | TracebackKind::ResolveError
+            | TracebackKind::IndentationError
+            | TracebackKind::TabError => EX_DATAERR,
+            TracebackKind::Error
+            | TracebackKind::IndexError
+            | TracebackKind::KeyError
+            | TracebackKind::TypeError
+            | TracebackKind::MemoryError
+            | TracebackKind::RecursionError
+            | TracebackKind::ValueError
+            | TracebackKind::EOFError
+            | TracebackKind::Return
+            | TracebackKind::Reraise => EX_SOFTWARE,
+            TracebackKind::Warning => 0,
+            TracebackKind::Exit(code) => *code,
+        }
+    }
+
+    // the name an except clause uses to catch this kind,
+    // None for the kinds scripts can't catch
+    pub fn exception_name(&self) -> Option<&'static str> {
+        match self {
+            TracebackKind::Error => Some("RuntimeError"),
+            TracebackKind::IndexError => Some("IndexError"),
+            TracebackKind::KeyError => Some("KeyError"),
+            TracebackKind::TypeError => Some("TypeError"),
+            TracebackKind::MemoryError => Some("MemoryError"),
+            TracebackKind::RecursionError => Some("RecursionError"),
+            TracebackKind::ValueError => Some("ValueError"),
+            TracebackKind::EOFError => Some("EOFError"),
+            _ => None,
+        }
+    }
+
+    pub fn is_caught_by(&self, name: &str) -> bool {
+        match self.exception_name() {
+            Some(exception) => name == "Exception" || name == exception,
+            None => false,
         }
     }
 }
@@ -78,3 +315,121 @@ impl From<&str> for Traceback {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_except_clause_matching() {
+        assert!(TracebackKind::IndexError.is_caught_by("IndexError"));
+        assert!(!TracebackKind::IndexError.is_caught_by("KeyError"));
+        assert!(TracebackKind::KeyError.is_caught_by("KeyError"));
+        assert!(!TracebackKind::KeyError.is_caught_by("IndexError"));
+        assert!(TracebackKind::KeyError.is_caught_by("Exception"));
+        assert!(TracebackKind::Error.is_caught_by("Exception"));
+
+        // control flow and compile errors are never caught
+        assert!(!TracebackKind::Return.is_caught_by("Exception"));
+        assert!(!TracebackKind::Exit(0).is_caught_by("Exception"));
+        assert!(!TracebackKind::SyntaxError.is_caught_by("Exception"));
+    }
+
+    #[test]
+    fn test_frames() {
+        // raised on line 3 of inner, called on line 7 of outer, called on line 10
+        let mut traceback = Traceback {
+            pos: (4, 2),
+            ..Default::default()
+        };
+        assert_eq!(traceback.current_line(), 3);
+        traceback.leave_function("inner");
+        traceback.leave_call((8, 6));
+        assert_eq!(traceback.current_line(), 7);
+        traceback.leave_function("outer");
+        traceback.leave_call((2, 9));
+
+        let exception = Exception {
+            caught_in: TraceFrame {
+                function: "<module>".to_string(),
+                line: traceback.current_line(),
+            },
+            traceback,
+        };
+        let frames: Vec<String> = exception.frames().iter().map(|f| f.to_string()).collect();
+        assert_eq!(
+            frames,
+            vec![
+                "line 10, in <module>",
+                "line 7, in outer",
+                "line 3, in inner"
+            ]
+        );
+        // the error itself stays where it happened
+        assert_eq!(exception.traceback.line(), 3);
+    }
+
+    #[test]
+    fn test_chain() {
+        let raised_on = |line| Traceback {
+            pos: (0, line),
+            ..Default::default()
+        };
+        let mut traceback = raised_on(0);
+        for line in 1..20 {
+            let mut error = raised_on(line);
+            error.chain(traceback);
+            traceback = error;
+        }
+        let mut lines = vec![traceback.line()];
+        let mut cause = &traceback.cause;
+        while let Some(handled) = cause {
+            lines.push(handled.line());
+            cause = &handled.cause;
+        }
+        // the last one and the 8 before it, newest first
+        assert_eq!(lines, (12..=20).rev().collect::<Vec<_>>());
+
+        // raising the caught error again doesn't chain it to itself
+        let mut reraised = traceback.clone();
+        reraised.cause = None;
+        reraised.chain(traceback);
+        assert!(reraised.cause.is_none());
+    }
+
+    #[test]
+    fn test_display() {
+        let traceback = Traceback {
+            pos: (9, 1),
+            message: Some("list index out of range".to_string()),
+            tipe: TracebackKind::IndexError,
+            ..Default::default()
+        };
+        assert_eq!(traceback.line(), 2);
+        assert_eq!(traceback.column(), 9);
+        assert_eq!(traceback.message(), "list index out of range");
+        assert!(matches!(traceback.kind(), TracebackKind::IndexError));
+        assert_eq!(
+            traceback.to_string(),
+            "index error: list index out of range at 2:9"
+        );
+
+        let traceback = Traceback {
+            code: Some("x = 1\nprint y\n".to_string()),
+            ..traceback
+        };
+        assert_eq!(
+            traceback.to_string(),
+            "error[index error]: list index out of range
+----- Traceback -----
+  ┌─ <unknown>:2:9
+1 | x = 1
+2 | print y
+  |          ^"
+        );
+
+        let traceback = Traceback::from_message("oops");
+        assert_eq!(traceback.to_string(), "runtime error: oops at 1:0");
+        assert_eq!(Traceback::default().message(), "no message");
+    }
+}
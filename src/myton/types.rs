@@ -1,6 +1,13 @@
+use super::builtin_types::BuiltinType;
 use super::class::{Class, Instance};
-use super::functions::{Callable, Function, NativeFunction};
+use super::functions::{Callable, Function, NativeFunction, NativeMethod, UnboundMethod};
+use super::hashable::Dict;
+use super::iterator::{LazyIterator, Range};
+use super::module::Module;
+use super::numeric::parse_float;
+use super::stats::count_list;
 use super::token::{Token, TokenKind};
+use super::traceback::{Exception, Traceback, TracebackKind};
 use std::any::Any;
 use std::cell::RefCell;
 use std::fmt::{Display, Formatter, Result as FmtResult};
@@ -13,10 +20,17 @@ pub enum TypeKind {
     Boolean,
     Nil,
     List,
+    Tuple,
+    Dict,
     Function,
     NativeFunction,
     Class,
+    Type,
     Instance,
+    Exception,
+    Module,
+    Iterator,
+    Range,
 }
 
 #[derive(Debug)]
@@ -45,10 +59,17 @@ impl TypeKind {
             Self::Boolean => "bool".to_string(),
             Self::Nil => "NoneType".to_string(),
             Self::List => "list".to_string(),
+            Self::Tuple => "tuple".to_string(),
+            Self::Dict => "dict".to_string(),
             Self::Function => "function".to_string(),
             Self::NativeFunction => "built-in function".to_string(),
             Self::Class => "class".to_string(),
+            Self::Type => "type".to_string(),
             Self::Instance => "object".to_string(),
+            Self::Exception => "exception".to_string(),
+            Self::Module => "module".to_string(),
+            Self::Iterator => "iterator".to_string(),
+            Self::Range => "range".to_string(),
         }
     }
 }
@@ -59,8 +80,163 @@ impl Display for TypeKind {
     }
 }
 
+// containers nested deeper are shown as [...], like the cycles
+const MAX_RENDER_DEPTH: usize = 256;
+
+// like python's repr: the shortest digits that read back as the
+// same float, in exponent notation below 1e-4 and from 1e16 on,
+// but whole numbers have no trailing .0
+pub fn format_number(n: f64) -> String {
+    if n.is_nan() {
+        return "nan".to_string();
+    }
+    if n.is_infinite() {
+        return if n > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+
+    // rust already finds the shortest round-tripping digits
+    let scientific = format!("{:e}", n);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let exponent = exponent.parse::<i32>().unwrap_or(0);
+    if n == 0.0 || (-4..16).contains(&exponent) {
+        n.to_string()
+    } else {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}e{}{:02}", mantissa, sign, exponent.abs())
+    }
+}
+
+// containers nested deeper than this can't be compared, a
+// RecursionError is raised before the host's stack runs out
+const MAX_COMPARE_DEPTH: usize = 100;
+
+// why two values couldn't be ordered
+#[derive(Debug)]
+pub enum CompareError {
+    // the types of the first values found that can't be ordered
+    Incomparable(TypeKind, TypeKind),
+    // nested deeper than MAX_COMPARE_DEPTH
+    TooDeep,
+}
+
+fn too_deep() -> Traceback {
+    Traceback::from_exception(
+        TracebackKind::RecursionError,
+        "maximum recursion depth exceeded in comparison",
+    )
+}
+
+impl CompareError {
+    // the RecursionError, or the TypeError worded by the caller
+    pub fn into_traceback(
+        self,
+        incomparable: impl FnOnce(TypeKind, TypeKind) -> Traceback,
+    ) -> Traceback {
+        match self {
+            CompareError::Incomparable(a, b) => incomparable(a, b),
+            CompareError::TooDeep => too_deep(),
+        }
+    }
+}
+
+// values nested too deep to be compared are unequal here,
+// the operators raise a RecursionError instead, see try_eq
 impl PartialEq for DynValue {
     fn eq(&self, other: &Self) -> bool {
+        self.equals(other, 0).unwrap_or(false)
+    }
+}
+
+impl PartialOrd for DynValue {
+    fn partial_cmp(&self, other: &DynValue) -> Option<std::cmp::Ordering> {
+        self.try_cmp(other).ok().flatten()
+    }
+}
+
+impl DynValue {
+    // what == gives, without an __eq__ of the script's
+    pub fn try_eq(&self, other: &DynValue) -> Result<bool, Traceback> {
+        self.equals(other, 0)
+    }
+
+    fn equals(&self, other: &DynValue, depth: usize) -> Result<bool, Traceback> {
+        // classes are only equal to themselves, whatever their name
+        if self.tipe == TypeKind::Class || other.tipe == TypeKind::Class {
+            return Ok(self.as_class().is_some() && self.as_class() == other.as_class());
+        }
+        if self.tipe == TypeKind::Type || other.tipe == TypeKind::Type {
+            return Ok(self.as_builtin_type().is_some()
+                && self.as_builtin_type() == other.as_builtin_type());
+        }
+        // None is only equal to itself, never to "None" or 0
+        if self.tipe == TypeKind::Nil || other.tipe == TypeKind::Nil {
+            return Ok(self.tipe == other.tipe);
+        }
+        // and instances, modules and iterators too, unless instances define __eq__
+        if matches!(
+            self.tipe,
+            TypeKind::Instance | TypeKind::Module | TypeKind::Iterator
+        ) || matches!(
+            other.tipe,
+            TypeKind::Instance | TypeKind::Module | TypeKind::Iterator
+        ) {
+            return Ok(self.tipe == other.tipe && self.identity() == other.identity());
+        }
+
+        let same = |a: &DynValue, b: &DynValue| -> Result<bool, Traceback> {
+            Ok(a.is(b) || a.equals(b, depth + 1)?)
+        };
+        match (&self.tipe, &other.tipe) {
+            (TypeKind::List | TypeKind::Tuple | TypeKind::Dict, _)
+                if depth >= MAX_COMPARE_DEPTH =>
+            {
+                return Err(too_deep());
+            }
+            // element by element, the ones that are the same object first
+            (TypeKind::List, TypeKind::List) | (TypeKind::Tuple, TypeKind::Tuple) => {
+                let (a, b) = (self.as_sequence().unwrap(), other.as_sequence().unwrap());
+                if a.len() != b.len() {
+                    return Ok(false);
+                }
+                for (x, y) in a.iter().zip(b.iter()) {
+                    if !same(x, y)? {
+                        return Ok(false);
+                    }
+                }
+                return Ok(true);
+            }
+            // the same keys, as the dicts know them, with the same values
+            (TypeKind::Dict, TypeKind::Dict) => {
+                let (a, b) = (self.as_dict().unwrap(), other.as_dict().unwrap());
+                if a.len() != b.len() {
+                    return Ok(false);
+                }
+                for (key, value) in a.iter() {
+                    let other_value = {
+                        let other = other.value.borrow();
+                        other.downcast_ref::<Dict>().unwrap().lookup(key).ok()
+                    };
+                    match other_value {
+                        Some(other_value) if same(value, &other_value)? => {}
+                        _ => return Ok(false),
+                    }
+                }
+                return Ok(true);
+            }
+            // the same numbers, whatever the arguments were
+            (TypeKind::Range, TypeKind::Range) => {
+                let (a, b) = (self.as_range().unwrap(), other.as_range().unwrap());
+                return Ok(a.len() == b.len()
+                    && (a.is_empty()
+                        || (a.start == b.start && (a.len() == 1 || a.step == b.step))));
+            }
+            (TypeKind::List | TypeKind::Tuple | TypeKind::Dict | TypeKind::Range, _)
+            | (_, TypeKind::List | TypeKind::Tuple | TypeKind::Dict | TypeKind::Range) => {
+                return Ok(false);
+            }
+            _ => {}
+        }
+
         let a = if self.tipe == TypeKind::Boolean {
             self.as_number().to_string()
         } else if self.tipe == TypeKind::Number && self.as_number().is_nan() {
@@ -75,32 +251,77 @@ impl PartialEq for DynValue {
             other.as_string()
         };
 
-        a == b
+        Ok(a == b)
     }
-}
 
-impl PartialOrd for DynValue {
-    fn partial_cmp(&self, other: &DynValue) -> Option<std::cmp::Ordering> {
-        match (self.tipe.clone(), other.tipe.clone()) {
-            (TypeKind::Number, TypeKind::Number)
-            | (TypeKind::Number, TypeKind::Boolean)
-            | (TypeKind::Boolean, TypeKind::Number)
-            | (TypeKind::Boolean, TypeKind::Boolean) => {
-                let a = self.as_number();
-                let b = other.as_number();
-                a.partial_cmp(&b)
+    // python's ordering: numbers with booleans, strings, and lists or
+    // tuples by their first elements that differ, or their lengths when one starts
+    // the other. None when nan makes any order false
+    pub fn try_cmp(&self, other: &DynValue) -> Result<Option<std::cmp::Ordering>, CompareError> {
+        self.ordering(other, 0)
+    }
+
+    fn ordering(
+        &self,
+        other: &DynValue,
+        depth: usize,
+    ) -> Result<Option<std::cmp::Ordering>, CompareError> {
+        match (&self.tipe, &other.tipe) {
+            (TypeKind::Number | TypeKind::Boolean, TypeKind::Number | TypeKind::Boolean) => {
+                Ok(self.as_number().partial_cmp(&other.as_number()))
             }
             (TypeKind::Stringue, TypeKind::Stringue) => {
-                let a = self.as_string();
-                let b = other.as_string();
-                a.partial_cmp(&b)
+                Ok(self.as_string().partial_cmp(&other.as_string()))
             }
-            (TypeKind::List, TypeKind::List) => {
-                let a = self.as_list();
-                let b = other.as_list();
-                a.partial_cmp(&b)
+            (TypeKind::List, TypeKind::List) | (TypeKind::Tuple, TypeKind::Tuple)
+                if depth >= MAX_COMPARE_DEPTH =>
+            {
+                Err(CompareError::TooDeep)
             }
-            _ => None,
+            (TypeKind::List, TypeKind::List) | (TypeKind::Tuple, TypeKind::Tuple) => {
+                let (a, b) = (self.as_sequence().unwrap(), other.as_sequence().unwrap());
+                for (x, y) in a.iter().zip(b.iter()) {
+                    let equal =
+                        x.is(y) || x.equals(y, depth + 1).map_err(|_| CompareError::TooDeep)?;
+                    if !equal {
+                        return x.ordering(y, depth + 1);
+                    }
+                }
+                Ok(Some(a.len().cmp(&b.len())))
+            }
+            _ => Err(CompareError::Incomparable(
+                self.tipe.clone(),
+                other.tipe.clone(),
+            )),
+        }
+    }
+}
+
+// dropping a list drops its elements in turn, a frame per level of
+// nesting. The lists and tuples nothing else holds are emptied here
+// instead, a level at a time, so that deep nesting can't overflow the stack
+impl Drop for DynValue {
+    fn drop(&mut self) {
+        let mut pending = self.take_unshared_elements();
+        while let Some(value) = pending.pop() {
+            pending.extend(value.take_unshared_elements());
+        }
+    }
+}
+
+impl DynValue {
+    fn take_unshared_elements(&self) -> Vec<DynValue> {
+        if !matches!(self.tipe, TypeKind::List | TypeKind::Tuple)
+            || Rc::strong_count(&self.value) != 1
+        {
+            return Vec::new();
+        }
+        match self.value.try_borrow_mut() {
+            Ok(mut value) => value
+                .downcast_mut::<Vec<DynValue>>()
+                .map(std::mem::take)
+                .unwrap_or_default(),
+            Err(_) => Vec::new(),
         }
     }
 }
@@ -140,7 +361,7 @@ impl DynValue {
 
     pub fn from_token(token: &Token) -> Self {
         match TypeKind::from_token(token) {
-            TypeKind::Number => Self::from_f64(token.value.parse::<f64>().unwrap()),
+            TypeKind::Number => Self::from_f64(token.value.parse::<f64>().unwrap_or(f64::NAN)),
             TypeKind::Stringue => Self::from_string(token.value.clone()),
             TypeKind::Boolean => Self::from_bool(token.kind == TokenKind::True),
             TypeKind::Nil => Self::none(),
@@ -161,25 +382,119 @@ impl DynValue {
     }
 
     pub fn from_vec(value: Vec<DynValue>) -> Self {
+        count_list();
         Self::new(Box::new(value), TypeKind::List)
     }
 
+    pub fn from_tuple(value: Vec<DynValue>) -> Self {
+        Self::new(Box::new(value), TypeKind::Tuple)
+    }
+
+    // for keys known to be hashable, such as strings
+    pub fn from_dict(entries: Vec<(DynValue, DynValue)>) -> Self {
+        Self::from(Dict::new(entries).expect("unhashable dict key"))
+    }
+
     pub fn from_function(value: Function, name: String) -> Self {
         Self::new_with_name(Box::new(value), TypeKind::Function, name)
     }
 
+    pub fn from_unbound_method(value: UnboundMethod, name: String) -> Self {
+        Self::new_with_name(Box::new(value), TypeKind::Function, name)
+    }
+
     pub fn from_native_function(value: NativeFunction, name: String) -> Self {
         Self::new_with_name(Box::new(value), TypeKind::NativeFunction, name)
     }
 
+    pub fn from_native_method(value: NativeMethod, name: String) -> Self {
+        Self::new_with_name(Box::new(value), TypeKind::NativeFunction, name)
+    }
+
     pub fn none() -> Self {
         Self::new(Box::new(()), TypeKind::Nil)
     }
 
+    /// The type of the value.
+    ///
+    /// ```
+    /// use myton::{DynValue, TypeKind};
+    ///
+    /// assert_eq!(DynValue::from("hi").kind(), TypeKind::Stringue);
+    /// ```
+    pub fn kind(&self) -> TypeKind {
+        self.tipe.clone()
+    }
+
+    fn expected(&self, tipe: TypeKind) -> Traceback {
+        Traceback::from_exception(
+            TracebackKind::TypeError,
+            &format!("expected a {}, got '{}'", tipe, self.tipe),
+        )
+    }
+
+    /// The number, without any coercion from other types.
+    ///
+    /// ```
+    /// use myton::DynValue;
+    ///
+    /// assert_eq!(DynValue::from(1.5).try_into_f64().unwrap(), 1.5);
+    /// assert!(DynValue::from("1.5").try_into_f64().is_err());
+    /// ```
+    pub fn try_into_f64(&self) -> Result<f64, Traceback> {
+        match self.tipe {
+            TypeKind::Number => Ok(self.as_number()),
+            _ => Err(self.expected(TypeKind::Number)),
+        }
+    }
+
+    /// The string, other values are not converted to their printed form.
+    ///
+    /// ```
+    /// use myton::DynValue;
+    ///
+    /// assert_eq!(DynValue::from("hi").try_into_string().unwrap(), "hi");
+    /// assert!(DynValue::from(1).try_into_string().is_err());
+    /// ```
+    pub fn try_into_string(&self) -> Result<String, Traceback> {
+        match self.tipe {
+            TypeKind::Stringue => Ok(self.as_string()),
+            _ => Err(self.expected(TypeKind::Stringue)),
+        }
+    }
+
+    /// The elements of a list.
+    ///
+    /// ```
+    /// use myton::DynValue;
+    ///
+    /// let list: DynValue = (1..=3).map(DynValue::from).collect();
+    /// assert_eq!(list.try_into_vec().unwrap().len(), 3);
+    /// assert!(DynValue::from("abc").try_into_vec().is_err());
+    /// ```
+    pub fn try_into_vec(&self) -> Result<Vec<DynValue>, Traceback> {
+        self.as_list().ok_or_else(|| self.expected(TypeKind::List))
+    }
+
+    /// The boolean, without using the truthiness of other types.
+    ///
+    /// ```
+    /// use myton::DynValue;
+    ///
+    /// assert_eq!(DynValue::from(true).try_into_bool().unwrap(), true);
+    /// assert!(DynValue::from(1).try_into_bool().is_err());
+    /// ```
+    pub fn try_into_bool(&self) -> Result<bool, Traceback> {
+        match self.tipe {
+            TypeKind::Boolean => Ok(self.as_bool()),
+            _ => Err(self.expected(TypeKind::Boolean)),
+        }
+    }
+
     pub fn as_number(&self) -> f64 {
         match self.tipe {
             TypeKind::Number => *self.value.borrow().downcast_ref::<f64>().unwrap(),
-            TypeKind::Stringue => self.as_string().parse::<f64>().unwrap(),
+            TypeKind::Stringue => parse_float(&self.as_string()).unwrap_or(f64::NAN),
             TypeKind::Boolean => {
                 if self.as_bool() {
                     1.0
@@ -188,13 +503,27 @@ impl DynValue {
                 }
             }
             TypeKind::Nil => 0.0,
-            _ => panic!("Invalid type for number"),
+            _ => f64::NAN,
         }
     }
 
     pub fn as_string(&self) -> String {
+        self.render(&mut Vec::new())
+    }
+
+    // like as_string, with the strings quoted so that "1" and 1 look different
+    pub fn repr(&self) -> String {
+        match self.tipe {
+            TypeKind::Stringue => format!("'{}'", self.as_string()),
+            _ => self.as_string(),
+        }
+    }
+
+    // `open` has the containers being rendered, meeting one of them
+    // again means it contains itself, which is shown as [...]
+    fn render(&self, open: &mut Vec<usize>) -> String {
         match self.tipe {
-            TypeKind::Number => self.as_number().to_string(),
+            TypeKind::Number => format_number(self.as_number()),
             TypeKind::Stringue => self
                 .value
                 .borrow()
@@ -203,20 +532,30 @@ impl DynValue {
                 .clone(),
             TypeKind::Boolean => if self.as_bool() { "True" } else { "False" }.to_string(),
             TypeKind::Nil => "None".to_string(),
-            TypeKind::List => format!(
-                "[{}]",
-                &self
-                    .as_list()
-                    .unwrap()
-                    .iter()
-                    .map(|x| x.as_string())
-                    .collect::<Vec<String>>()
-                    .join(", ")
-            ),
+            TypeKind::List | TypeKind::Tuple | TypeKind::Dict => self.render_container(open),
             TypeKind::Instance => format!(
-                "<{} object>",
-                self.as_instance().unwrap().borrow().class.name
+                "<{} object at {:#x}>",
+                self.as_instance().unwrap().borrow().class.name,
+                self.identity()
             ),
+            TypeKind::Class => format!("<class '{}'>", self.as_class().unwrap().name),
+            TypeKind::Type => format!("<class '{}'>", self.as_builtin_type().unwrap().name()),
+            TypeKind::Module => format!("<module '{}'>", self.as_module().unwrap().name),
+            TypeKind::Iterator => format!("<{} object>", self.as_iterator().unwrap().name),
+            TypeKind::Range => {
+                let range = self.as_range().unwrap();
+                if range.step == 1.0 {
+                    format!("range({}, {})", range.start, range.stop)
+                } else {
+                    format!("range({}, {}, {})", range.start, range.stop, range.step)
+                }
+            }
+            TypeKind::Exception => self
+                .as_exception()
+                .unwrap()
+                .traceback
+                .message
+                .unwrap_or_default(),
             _ => format!(
                 "<{} {}>",
                 self.tipe,
@@ -225,18 +564,84 @@ impl DynValue {
         }
     }
 
+    // inside a container strings are quoted, like python: ['a', 1]
+    fn render_item(&self, open: &mut Vec<usize>) -> String {
+        match self.tipe {
+            TypeKind::Stringue => self.repr(),
+            _ => self.render(open),
+        }
+    }
+
+    fn render_container(&self, open: &mut Vec<usize>) -> String {
+        let (start, end) = match self.tipe {
+            TypeKind::List => ("[", "]"),
+            TypeKind::Tuple => ("(", ")"),
+            _ => ("{", "}"),
+        };
+        if open.contains(&self.identity()) || open.len() >= MAX_RENDER_DEPTH {
+            return format!("{}...{}", start, end);
+        }
+
+        open.push(self.identity());
+        let items = match self.tipe {
+            TypeKind::List | TypeKind::Tuple => self
+                .as_sequence()
+                .unwrap()
+                .iter()
+                .map(|item| item.render_item(open))
+                .collect::<Vec<String>>(),
+            _ => self
+                .as_dict()
+                .unwrap()
+                .iter()
+                .map(|(key, value)| {
+                    format!("{}: {}", key.render_item(open), value.render_item(open))
+                })
+                .collect::<Vec<String>>(),
+        };
+        open.pop();
+
+        // (1,) and not (1), which would be the number
+        if self.tipe == TypeKind::Tuple && items.len() == 1 {
+            return format!("({},)", items[0]);
+        }
+        format!("{}{}{}", start, items.join(", "), end)
+    }
+
     pub fn as_bool(&self) -> bool {
         match self.tipe {
             TypeKind::Number => self.as_number() != 0.0,
             TypeKind::Stringue => !self.as_string().is_empty(),
             TypeKind::Boolean => *self.value.borrow().downcast_ref::<bool>().unwrap(),
             TypeKind::Nil => false,
-            TypeKind::List => !self.as_list().unwrap().is_empty(),
+            TypeKind::List | TypeKind::Tuple => !self.as_sequence().unwrap().is_empty(),
+            TypeKind::Dict => !self.as_dict().unwrap().is_empty(),
+            TypeKind::Range => !self.as_range().unwrap().is_empty(),
             TypeKind::Function
             | TypeKind::NativeFunction
             | TypeKind::Class
-            | TypeKind::Instance => true,
+            | TypeKind::Type
+            | TypeKind::Instance
+            | TypeKind::Exception
+            | TypeKind::Module
+            | TypeKind::Iterator => true,
+        }
+    }
+
+    // the values of a list holding numbers only, None for any other value
+    pub fn as_numbers(&self) -> Option<Vec<f64>> {
+        if self.tipe != TypeKind::List {
+            return None;
         }
+        let value = self.value.borrow();
+        value
+            .downcast_ref::<Vec<DynValue>>()?
+            .iter()
+            .map(|element| match element.tipe {
+                TypeKind::Number => element.value.borrow().downcast_ref::<f64>().copied(),
+                _ => None,
+            })
+            .collect()
     }
 
     pub fn as_list(&self) -> Option<Vec<DynValue>> {
@@ -253,25 +658,85 @@ impl DynValue {
         }
     }
 
-    pub fn as_callable(&self) -> Option<Box<dyn Callable>> {
-        match self.tipe {
-            TypeKind::Function => Some(Box::new(
+    pub fn as_tuple(&self) -> Option<Vec<DynValue>> {
+        if self.tipe == TypeKind::Tuple {
+            Some(
                 self.value
                     .borrow()
-                    .downcast_ref::<Function>()
+                    .downcast_ref::<Vec<DynValue>>()
                     .unwrap()
                     .clone(),
-            )),
-            TypeKind::NativeFunction => Some(Box::new(
+            )
+        } else {
+            None
+        }
+    }
+
+    // the elements of a list or a tuple, borrowed where they are
+    // rather than cloned like as_sequence's
+    pub fn with_elements<T>(&self, f: impl FnOnce(&[DynValue]) -> T) -> Option<T> {
+        match self.tipe {
+            TypeKind::List | TypeKind::Tuple => Some(f(self
+                .value
+                .borrow()
+                .downcast_ref::<Vec<DynValue>>()
+                .unwrap())),
+            _ => None,
+        }
+    }
+
+    // the elements of a list or a tuple
+    pub fn as_sequence(&self) -> Option<Vec<DynValue>> {
+        self.as_list().or_else(|| self.as_tuple())
+    }
+
+    pub fn as_dict(&self) -> Option<Vec<(DynValue, DynValue)>> {
+        if self.tipe == TypeKind::Dict {
+            Some(
                 self.value
                     .borrow()
-                    .downcast_ref::<NativeFunction>()
+                    .downcast_ref::<Dict>()
                     .unwrap()
-                    .clone(),
-            )),
+                    .entries()
+                    .to_vec(),
+            )
+        } else {
+            None
+        }
+    }
+
+    pub fn as_builtin_type(&self) -> Option<BuiltinType> {
+        if self.tipe == TypeKind::Type {
+            Some(*self.value.borrow().downcast_ref::<BuiltinType>().unwrap())
+        } else {
+            None
+        }
+    }
+
+    pub fn as_callable(&self) -> Option<Box<dyn Callable>> {
+        match self.tipe {
+            TypeKind::Function => {
+                let value = self.value.borrow();
+                if let Some(method) = value.downcast_ref::<UnboundMethod>() {
+                    Some(Box::new(method.clone()))
+                } else {
+                    Some(Box::new(value.downcast_ref::<Function>().unwrap().clone()))
+                }
+            }
+            TypeKind::NativeFunction => {
+                let value = self.value.borrow();
+                if let Some(method) = value.downcast_ref::<NativeMethod>() {
+                    Some(Box::new(method.clone()))
+                } else {
+                    Some(Box::new(
+                        value.downcast_ref::<NativeFunction>().unwrap().clone(),
+                    ))
+                }
+            }
             TypeKind::Class => Some(Box::new(
                 self.value.borrow().downcast_ref::<Class>().unwrap().clone(),
             )),
+            TypeKind::Type => Some(Box::new(self.as_builtin_type().unwrap())),
             _ => None,
         }
     }
@@ -298,25 +763,91 @@ impl DynValue {
         }
     }
 
-    pub fn is_nil(&self) -> bool {
-        self.tipe == TypeKind::Nil
+    pub fn as_module(&self) -> Option<Module> {
+        if self.tipe == TypeKind::Module {
+            Some(
+                self.value
+                    .borrow()
+                    .downcast_ref::<Module>()
+                    .unwrap()
+                    .clone(),
+            )
+        } else {
+            None
+        }
     }
 
-    pub fn is_number(&self) -> bool {
-        self.tipe == TypeKind::Number
-            || self.tipe == TypeKind::Boolean
-            || (self.tipe == TypeKind::Stringue && self.as_string().parse::<f64>().is_ok())
+    pub fn as_iterator(&self) -> Option<LazyIterator> {
+        if self.tipe == TypeKind::Iterator {
+            Some(
+                self.value
+                    .borrow()
+                    .downcast_ref::<LazyIterator>()
+                    .unwrap()
+                    .clone(),
+            )
+        } else {
+            None
+        }
+    }
+
+    pub fn as_range(&self) -> Option<Range> {
+        if self.tipe == TypeKind::Range {
+            self.value.borrow().downcast_ref::<Range>().copied()
+        } else {
+            None
+        }
     }
 
-    pub fn increment_by(&mut self, incrementation_value: f64) {
-        if self.tipe == TypeKind::Number {
-            let mut value = self.value.borrow_mut();
-            let value = value.downcast_mut::<f64>().unwrap();
-            *value += incrementation_value;
+    pub fn as_exception(&self) -> Option<Exception> {
+        if self.tipe == TypeKind::Exception {
+            Some(
+                self.value
+                    .borrow()
+                    .downcast_ref::<Exception>()
+                    .unwrap()
+                    .clone(),
+            )
         } else {
-            panic!("Invalid type for increment {}", self.tipe);
+            None
+        }
+    }
+
+    // what id() gives back: the same for all the references
+    // to an object, and unique among the living objects
+    pub fn identity(&self) -> usize {
+        match self.as_instance() {
+            Some(instance) => Rc::as_ptr(&instance) as *const () as usize,
+            None => Rc::as_ptr(&self.value) as *const () as usize,
         }
     }
+
+    // the `is` operator: mutable objects and containers must be the
+    // very same one, plain values only need to be equal
+    pub fn is(&self, other: &DynValue) -> bool {
+        match self.tipe {
+            TypeKind::List
+            | TypeKind::Tuple
+            | TypeKind::Range
+            | TypeKind::Dict
+            | TypeKind::Function
+            | TypeKind::NativeFunction
+            | TypeKind::Instance
+            | TypeKind::Exception
+            | TypeKind::Module
+            | TypeKind::Iterator => self.identity() == other.identity(),
+            _ => self.tipe == other.tipe && self == other,
+        }
+    }
+
+    pub fn is_nil(&self) -> bool {
+        self.tipe == TypeKind::Nil
+    }
+
+    // what arithmetic accepts, a string holding a number isn't one
+    pub fn is_number(&self) -> bool {
+        self.tipe == TypeKind::Number || self.tipe == TypeKind::Boolean
+    }
 }
 
 impl From<f64> for DynValue {
@@ -355,6 +886,58 @@ impl From<i32> for DynValue {
     }
 }
 
+impl From<i64> for DynValue {
+    fn from(value: i64) -> Self {
+        Self::from_f64(value as f64)
+    }
+}
+
+impl From<u64> for DynValue {
+    fn from(value: u64) -> Self {
+        Self::from_f64(value as f64)
+    }
+}
+
+impl From<usize> for DynValue {
+    fn from(value: usize) -> Self {
+        Self::from_f64(value as f64)
+    }
+}
+
+impl From<&str> for DynValue {
+    fn from(value: &str) -> Self {
+        Self::from_string(value.to_string())
+    }
+}
+
+/// `None` becomes the script's `None`.
+///
+/// ```
+/// use myton::DynValue;
+///
+/// assert!(DynValue::from(None::<f64>).is_nil());
+/// assert_eq!(DynValue::from(Some(2.0)).as_number(), 2.0);
+/// ```
+impl<T: Into<DynValue>> From<Option<T>> for DynValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or_else(Self::none, Into::into)
+    }
+}
+
+/// Collects values into a list.
+///
+/// ```
+/// use myton::DynValue;
+///
+/// let list: DynValue = vec!["a", "b"].into_iter().map(DynValue::from).collect();
+/// assert_eq!(list.as_string(), "['a', 'b']");
+/// ```
+impl FromIterator<DynValue> for DynValue {
+    fn from_iter<I: IntoIterator<Item = DynValue>>(iter: I) -> Self {
+        Self::from_vec(iter.into_iter().collect())
+    }
+}
+
 impl From<Class> for DynValue {
     fn from(value: Class) -> Self {
         let name = value.name.clone();
@@ -362,9 +945,10 @@ impl From<Class> for DynValue {
     }
 }
 
+// instances are always shared, see as_instance
 impl From<Instance> for DynValue {
     fn from(instance: Instance) -> Self {
-        Self::new(Box::new(instance), TypeKind::Instance)
+        Self::from(Rc::new(RefCell::new(instance)))
     }
 }
 
@@ -374,6 +958,13 @@ impl From<Rc<RefCell<Instance>>> for DynValue {
     }
 }
 
+impl From<BuiltinType> for DynValue {
+    fn from(value: BuiltinType) -> Self {
+        let name = value.name().to_string();
+        Self::new_with_name(Box::new(value), TypeKind::Type, name)
+    }
+}
+
 impl From<Function> for DynValue {
     fn from(value: Function) -> Self {
         let name = value.statement.inner.borrow().name.value.clone();
@@ -381,9 +972,47 @@ impl From<Function> for DynValue {
     }
 }
 
+impl From<Module> for DynValue {
+    fn from(value: Module) -> Self {
+        let name = value.name.clone();
+        Self::new_with_name(Box::new(value), TypeKind::Module, name)
+    }
+}
+
+impl From<LazyIterator> for DynValue {
+    fn from(value: LazyIterator) -> Self {
+        Self::new(Box::new(value), TypeKind::Iterator)
+    }
+}
+
+impl From<Dict> for DynValue {
+    fn from(value: Dict) -> Self {
+        Self::new(Box::new(value), TypeKind::Dict)
+    }
+}
+
+impl From<Range> for DynValue {
+    fn from(value: Range) -> Self {
+        Self::new(Box::new(value), TypeKind::Range)
+    }
+}
+
+impl From<Exception> for DynValue {
+    fn from(value: Exception) -> Self {
+        let name = value
+            .traceback
+            .tipe
+            .exception_name()
+            .unwrap_or_default()
+            .to_string();
+        Self::new_with_name(Box::new(value), TypeKind::Exception, name)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
 
     #[test]
     fn test_number() {
@@ -440,6 +1069,138 @@ mod tests {
         assert_eq!(value.is_number(), true);
     }
 
+    #[test]
+    fn test_class_identity() {
+        let dog = Class::new("Dog".to_string(), HashMap::new(), None);
+        let other_dog = Class::new("Dog".to_string(), HashMap::new(), None);
+
+        assert_eq!(DynValue::from(dog.clone()), DynValue::from(dog.clone()));
+        assert_ne!(DynValue::from(dog.clone()), DynValue::from(other_dog));
+        assert_ne!(
+            DynValue::from(dog.clone()),
+            DynValue::from("<class 'Dog'>".to_string())
+        );
+        assert_eq!(DynValue::from(dog.clone()).as_string(), "<class 'Dog'>");
+
+        // what type(instance) will give back
+        let instance = Instance::new(dog.clone());
+        assert_eq!(DynValue::from(instance.class), DynValue::from(dog));
+    }
+
+    #[test]
+    fn test_instances_are_shared() {
+        let dog = Class::new("Dog".to_string(), HashMap::new(), None);
+        let value = DynValue::from(Instance::new(dog));
+        let copy = value.clone();
+
+        value
+            .as_instance()
+            .unwrap()
+            .borrow_mut()
+            .set("name".to_string(), DynValue::from("Rex"));
+        let instance = copy.as_instance().unwrap();
+        assert_eq!(
+            instance.borrow().fields.get("name"),
+            Some(&DynValue::from("Rex"))
+        );
+    }
+
+    #[test]
+    fn test_host_conversions() {
+        assert_eq!(DynValue::from("str").as_string(), "str");
+        assert_eq!(DynValue::from(-3i64).as_number(), -3.0);
+        assert_eq!(DynValue::from(3u64).as_number(), 3.0);
+        assert_eq!(DynValue::from(3usize).as_number(), 3.0);
+        assert!(DynValue::from(None::<bool>).is_nil());
+        assert_eq!(DynValue::from(Some("x")).as_string(), "x");
+        assert_eq!(DynValue::from(Some(Some(true))).kind(), TypeKind::Boolean);
+
+        let list: DynValue = (0..3usize).map(DynValue::from).collect();
+        assert_eq!(list.kind(), TypeKind::List);
+        assert_eq!(list.as_string(), "[0, 1, 2]");
+        let empty: DynValue = std::iter::empty().collect();
+        assert_eq!(empty.as_string(), "[]");
+    }
+
+    #[test]
+    fn test_host_extraction() {
+        let values = [
+            DynValue::from(1.5),
+            DynValue::from("1.5"),
+            DynValue::from(true),
+            DynValue::from(vec![DynValue::from(1)]),
+            DynValue::none(),
+        ];
+        let f64s = values.iter().map(|v| v.try_into_f64().ok());
+        assert_eq!(
+            f64s.collect::<Vec<_>>(),
+            [Some(1.5), None, None, None, None]
+        );
+        let strings = values.iter().map(|v| v.try_into_string().ok());
+        assert_eq!(
+            strings.collect::<Vec<_>>(),
+            [None, Some("1.5".to_string()), None, None, None]
+        );
+        let bools = values.iter().map(|v| v.try_into_bool().ok());
+        assert_eq!(
+            bools.collect::<Vec<_>>(),
+            [None, None, Some(true), None, None]
+        );
+        let vecs = values
+            .iter()
+            .map(|v| v.try_into_vec().ok().map(|v| v.len()));
+        assert_eq!(vecs.collect::<Vec<_>>(), [None, None, None, Some(1), None]);
+
+        let message = |result: Result<_, Traceback>| result.err().unwrap().message.unwrap();
+        assert_eq!(
+            message(values[1].try_into_f64().map(|_| ())),
+            "expected a number, got 'str'"
+        );
+        assert_eq!(
+            message(values[0].try_into_string().map(|_| ())),
+            "expected a str, got 'number'"
+        );
+        assert_eq!(
+            message(values[4].try_into_bool().map(|_| ())),
+            "expected a bool, got 'NoneType'"
+        );
+        assert_eq!(
+            message(values[2].try_into_vec().map(|_| ())),
+            "expected a list, got 'bool'"
+        );
+        assert!(matches!(
+            values[0].try_into_vec().unwrap_err().tipe,
+            TracebackKind::TypeError
+        ));
+    }
+
+    #[test]
+    fn test_format_number() {
+        for (n, expected) in [
+            (0.1 + 0.2, "0.30000000000000004"),
+            (1.0, "1"),
+            (-2.5, "-2.5"),
+            (0.0, "0"),
+            (-0.0, "-0"),
+            (1e15, "1000000000000000"),
+            (123456789012345.6, "123456789012345.6"),
+            (1e16, "1e+16"),
+            (1.5e16, "1.5e+16"),
+            (1e21, "1e+21"),
+            (-1e100, "-1e+100"),
+            (0.0001, "0.0001"),
+            (0.00001, "1e-05"),
+            (1.25e-7, "1.25e-07"),
+            (5e-324, "5e-324"),
+            (f64::MAX, "1.7976931348623157e+308"),
+            (f64::INFINITY, "inf"),
+            (f64::NEG_INFINITY, "-inf"),
+            (f64::NAN, "nan"),
+        ] {
+            assert_eq!(format_number(n), expected, "formatting {:?}", n);
+        }
+    }
+
     #[test]
     fn test_list() {
         let value = DynValue::from(vec![DynValue::from_f64(1.0), DynValue::from_f64(2.0)]);
@@ -449,6 +1210,17 @@ mod tests {
         assert_eq!(value.is_number(), false);
     }
 
+    #[test]
+    fn test_dict() {
+        let entries = vec![(DynValue::from("a"), DynValue::from(1.0))];
+        let value = DynValue::from_dict(entries.clone());
+        assert_eq!(value.as_dict(), Some(entries));
+        assert_eq!(value.as_string(), "{'a': 1}");
+        assert_eq!(value.as_bool(), true);
+        assert_eq!(DynValue::from_dict(Vec::new()).as_bool(), false);
+        assert_eq!(DynValue::from(1.0).as_dict(), None);
+    }
+
     #[test]
     fn test_function() {
         let value = DynValue::new_with_name(
@@ -0,0 +1,143 @@
+use super::expression::{
+    Assign, AssignExpr, AssignTarget, Binary, Call, DictLiteral, Get, Grouping, List, Literal,
+    Logical, LogicalKind, Subscript, Super, This, Tuple, Unary, Variable, EXPR,
+};
+use super::token::TokenKind;
+
+// the source of an expression, as the parser saw it: spacing is
+// normalized but the parentheses written are kept
+pub fn unparse(expr: &EXPR) -> String {
+    let any = expr.as_any();
+    if let Some(variable) = any.downcast_ref::<Variable>() {
+        variable.name.value.clone()
+    } else if let Some(literal) = any.downcast_ref::<Literal>() {
+        match literal.token.kind {
+            TokenKind::Stringue => format!("\"{}\"", literal.token.value),
+            _ => literal.token.value.clone(),
+        }
+    } else if let Some(get) = any.downcast_ref::<Get>() {
+        format!("{}.{}", unparse(&get.object), get.name.value)
+    } else if let Some(call) = any.downcast_ref::<Call>() {
+        let arguments = call
+            .arguments
+            .iter()
+            .map(|argument| match argument.star {
+                Some(_) => format!("*{}", unparse(&argument.value)),
+                None => unparse(&argument.value),
+            })
+            .chain(
+                call.keywords
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name.value, unparse(value))),
+            );
+        format!("{}({})", unparse(&call.callee), join(arguments))
+    } else if let Some(subscript) = any.downcast_ref::<Subscript>() {
+        format!(
+            "{}[{}]",
+            unparse(&subscript.object),
+            unparse(&subscript.index)
+        )
+    } else if let Some(list) = any.downcast_ref::<List>() {
+        format!("[{}]", join(list.elements.iter().map(unparse)))
+    } else if let Some(tuple) = any.downcast_ref::<Tuple>() {
+        // the parentheses are a grouping around it
+        match tuple.elements.as_slice() {
+            [element] => format!("{},", unparse(element)),
+            elements => join(elements.iter().map(unparse)),
+        }
+    } else if let Some(dict) = any.downcast_ref::<DictLiteral>() {
+        let entries = dict
+            .entries
+            .iter()
+            .map(|(key, value)| format!("{}: {}", unparse(key), unparse(value)));
+        format!("{{{}}}", join(entries))
+    } else if let Some(grouping) = any.downcast_ref::<Grouping>() {
+        format!("({})", unparse(&grouping.expression))
+    } else if let Some(unary) = any.downcast_ref::<Unary>() {
+        match unary.operator.token.kind {
+            TokenKind::Not => format!("not {}", unparse(&unary.right)),
+            _ => format!("{}{}", unary.operator.token.value, unparse(&unary.right)),
+        }
+    } else if let Some(binary) = any.downcast_ref::<Binary>() {
+        let (first, chain) = binary.chain();
+        let mut source = unparse(first);
+        for binary in chain {
+            source += &format!(
+                " {} {}",
+                binary.operator.token.value,
+                unparse(&binary.right)
+            );
+        }
+        source
+    } else if let Some(logical) = any.downcast_ref::<Logical>() {
+        let (first, chain) = logical.chain();
+        let mut source = unparse(first);
+        for logical in chain {
+            let keyword = match logical.kind {
+                LogicalKind::And => "and",
+                LogicalKind::Or => "or",
+            };
+            source += &format!(" {} {}", keyword, unparse(&logical.right));
+        }
+        source
+    } else if let Some(assign) = any.downcast_ref::<Assign>() {
+        format!(
+            "{} = {}",
+            unparse_target(&assign.target),
+            unparse(&assign.value)
+        )
+    } else if let Some(assign) = any.downcast_ref::<AssignExpr>() {
+        format!("{} := {}", assign.name.value, unparse(&assign.value))
+    } else if let Some(this) = any.downcast_ref::<This>() {
+        this.keyword.value.clone()
+    } else if let Some(sup) = any.downcast_ref::<Super>() {
+        format!("{}.{}", sup.keyword.value, sup.method.value)
+    } else {
+        "...".to_string()
+    }
+}
+
+pub fn unparse_target(target: &AssignTarget) -> String {
+    match target {
+        AssignTarget::Name(name) => name.value.clone(),
+        AssignTarget::Attribute { object, name } => format!("{}.{}", unparse(object), name.value),
+        AssignTarget::Index { object, index, .. } => {
+            format!("{}[{}]", unparse(object), unparse(index))
+        }
+        AssignTarget::Unpack { targets, .. } => join(targets.iter().map(unparse_target)),
+    }
+}
+
+fn join(parts: impl Iterator<Item = String>) -> String {
+    parts.collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::lexer::Lexer;
+    use super::super::parser::Parser;
+    use super::*;
+
+    fn round_trip(source: &str) -> String {
+        let tokens = Lexer::new(source.to_string()).tokenize().unwrap();
+        unparse(&Parser::new(tokens).parse_expression().unwrap())
+    }
+
+    #[test]
+    fn test_unparse() {
+        for source in [
+            "get_user(id).profile.name",
+            "f(1, \"two\", key=[3, 4])[0]",
+            "{\"a\": [1], 2: {}}",
+            "-(a + b) * c",
+            "x and not_y or z",
+            "not a == b",
+            "(n := 10)",
+            "(1, (2,), ())",
+            "!done",
+        ] {
+            assert_eq!(round_trip(source), source);
+        }
+        assert_eq!(round_trip("f( a,b )"), "f(a, b)");
+    }
+}
@@ -0,0 +1,186 @@
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::{Duration, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// number of polls without any new change before re-running,
+// so that editors saving in several steps trigger a single run
+const QUIET_POLLS: usize = 2;
+
+pub trait ChangeSource {
+    // non-blocking check for a modification since the last poll,
+    // None once there is nothing left to watch
+    fn poll(&mut self) -> Option<bool>;
+}
+
+pub struct MtimePoller {
+    paths: Vec<PathBuf>,
+    last_modified: Vec<Option<SystemTime>>,
+}
+
+pub struct Watcher<S: ChangeSource> {
+    source: S,
+    interval: Duration,
+    quiet_polls: usize,
+    pending: Option<usize>,
+}
+
+impl MtimePoller {
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        let last_modified = paths
+            .iter()
+            .map(PathBuf::as_path)
+            .map(Self::modified)
+            .collect();
+        Self {
+            paths,
+            last_modified,
+        }
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+}
+
+impl ChangeSource for MtimePoller {
+    fn poll(&mut self) -> Option<bool> {
+        let mut changed = false;
+        for (path, last) in self.paths.iter().zip(self.last_modified.iter_mut()) {
+            let modified = Self::modified(path);
+            if modified != *last {
+                *last = modified;
+                changed = true;
+            }
+        }
+        Some(changed)
+    }
+}
+
+impl<S: ChangeSource> Watcher<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            interval: POLL_INTERVAL,
+            quiet_polls: QUIET_POLLS,
+            pending: None,
+        }
+    }
+
+    // runs once, then again after every (debounced) change,
+    // until the source has nothing left to watch
+    pub fn run(&mut self, mut run: impl FnMut()) {
+        run();
+        loop {
+            match self.tick() {
+                Some(true) => run(),
+                Some(false) => sleep(self.interval),
+                None => return,
+            }
+        }
+    }
+
+    fn tick(&mut self) -> Option<bool> {
+        if self.source.poll()? {
+            self.pending = Some(0);
+            return Some(false);
+        }
+
+        match self.pending {
+            Some(quiet) if quiet + 1 >= self.quiet_polls => {
+                self.pending = None;
+                Some(true)
+            }
+            Some(quiet) => {
+                self.pending = Some(quiet + 1);
+                Some(false)
+            }
+            None => Some(false),
+        }
+    }
+}
+
+// HH:MM:SS (UTC) of the current time
+pub fn timestamp() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600 % 24,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::myton::{watch_with, Interpreter, MyWrite};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct FakeSource {
+        events: Vec<bool>,
+    }
+
+    impl ChangeSource for FakeSource {
+        fn poll(&mut self) -> Option<bool> {
+            if self.events.is_empty() {
+                None
+            } else {
+                Some(self.events.remove(0))
+            }
+        }
+    }
+
+    fn count_runs(events: Vec<bool>) -> usize {
+        let mut watcher = Watcher::new(FakeSource { events });
+        watcher.interval = Duration::ZERO;
+        let mut runs = 0;
+        watcher.run(|| runs += 1);
+        runs
+    }
+
+    #[test]
+    fn test_runs_once_without_changes() {
+        assert_eq!(count_runs(vec![]), 1);
+        assert_eq!(count_runs(vec![false, false, false]), 1);
+    }
+
+    #[test]
+    fn test_reruns_after_change() {
+        assert_eq!(count_runs(vec![true, false, false]), 2);
+        assert_eq!(count_runs(vec![true, false, false, true, false, false]), 3);
+    }
+
+    #[test]
+    fn test_debounces_rapid_saves() {
+        assert_eq!(count_runs(vec![true, true, false, true, false, false]), 2);
+        // the last save hasn't settled yet
+        assert_eq!(count_runs(vec![true, false, false, true, false]), 2);
+    }
+
+    #[test]
+    fn test_each_run_starts_fresh() {
+        // a run seeing the global of the one before would print 2
+        let path = std::env::temp_dir().join("myton_watch_fresh.my");
+        std::fs::write(
+            &path,
+            "try:\n  runs = runs + 1\nexcept:\n  runs = 1\nprint(runs)\n",
+        )
+        .unwrap();
+        let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+        let mut settings = Interpreter::new_with_output(output.clone());
+        // nor does it see what the interpreter it takes its settings from ran
+        settings.run("runs = 10".to_string()).unwrap();
+
+        let mut watcher = Watcher::new(FakeSource {
+            events: vec![true, false, false, true, false, false],
+        });
+        watcher.interval = Duration::ZERO;
+        watch_with(watcher, path.to_str().unwrap(), &settings);
+        assert_eq!(output.borrow().get_string().unwrap(), "1\n1\n1\n");
+    }
+}
@@ -0,0 +1,203 @@
+use std::process::{Command, Output};
+
+fn write_script(name: &str, source: &str) -> String {
+    let path = std::env::temp_dir().join(format!("myton_check_{}.my", name));
+    std::fs::write(&path, source).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+fn check(paths: Vec<String>) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_myton"))
+        .arg("check")
+        .args(paths)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_check_valid_file() {
+    let output = check(vec![write_script(
+        "valid",
+        "def f(x):\n  return x\nprint(f(1))",
+    )]);
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "OK\n");
+}
+
+#[test]
+fn test_check_without_files() {
+    for args in [vec![], vec!["--report".to_string()]] {
+        let output = check(args);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+
+        // a usage error, not a file named check
+        assert_eq!(output.status.code(), Some(64), "{}", stderr);
+        assert!(
+            stderr.starts_with("check expects files\nUsage: myton"),
+            "{}",
+            stderr
+        );
+    }
+}
+
+#[test]
+fn test_check_never_executes() {
+    // only fails at runtime, and printing would show it ran
+    let output = check(vec![write_script(
+        "runtime_only",
+        "print(\"ran\")\nprint(1 - \"a\")",
+    )]);
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "OK\n");
+}
+
+#[test]
+fn test_check_reports_every_error() {
+    let output = check(vec![
+        write_script("two_errors", "x = (1 +\nprint(2)\ny = ]\nprint(3)"),
+        write_script("other_valid", "print(1)"),
+        write_script("resolve_error", "print(1)\nreturn 2"),
+    ]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(
+        stderr.matches("error[syntax error]").count(),
+        2,
+        "{}",
+        stderr
+    );
+    assert_eq!(
+        stderr.matches("error[resolve error]").count(),
+        1,
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("y = ]"), "{}", stderr);
+    assert!(
+        stderr.contains("myton_check_resolve_error.my>"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("Found 3 error(s)"), "{}", stderr);
+}
+
+#[test]
+fn test_check_recovers_inside_blocks() {
+    let output = check(vec![write_script(
+        "block_errors",
+        "def broken(x):\n  y = ]\n  print(x)\n  z = (1 +\n  return x\n\ndef sibling(x):\n  return x * 2\n\nprint(sibling(1))\nreturn 3",
+    )]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(
+        stderr.matches("error[syntax error]").count(),
+        2,
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("y = ]"), "{}", stderr);
+    assert!(stderr.contains("z = (1 +"), "{}", stderr);
+    // the valid parts are still resolved, only the last line is wrong
+    assert_eq!(
+        stderr.matches("error[resolve error]").count(),
+        1,
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("Found 3 error(s)"), "{}", stderr);
+}
+
+#[test]
+fn test_check_reports_warnings() {
+    let path = write_script("shadowed_builtin", "sum = 1\nprint(sum)");
+    let output = check(vec![path.clone()]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    // a warning isn't an error, the file is still fine
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "OK\n");
+    assert!(
+        stderr.contains("warning: 'sum' shadows a built-in function"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("myton_check_shadowed_builtin.my>"),
+        "{}",
+        stderr
+    );
+
+    let output = check(vec!["--no-warnings".to_string(), path]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+}
+
+const FIXTURE: &str = "def unused(x):
+  return x
+
+def greet(name):
+  return \"hello \" + name
+
+limit = 10
+if False:
+  print(greet(\"a\"))
+print(greet(\"b\"), limit)
+print(\"done\")
+print(\"done\")
+print(\"done\")
+print(\"done\")
+";
+
+#[test]
+fn test_check_report() {
+    let path = write_script("report", FIXTURE);
+    let output = check(vec!["--report".to_string(), path.clone()]);
+
+    assert_eq!(output.status.code(), Some(0));
+    let expected = [
+        "1:10: unused function: function 'unused' is never called",
+        "7:5: constant: 'limit' is always 10",
+        "8:8: dead branch: this branch never runs: the condition is always False",
+        "11:12: duplicate string: the string \"done\" is written 4 times",
+    ]
+    .map(|line| format!("{}:{}\n", path, line))
+    .concat();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+}
+
+#[test]
+fn test_check_report_json() {
+    let path = write_script("report_json", FIXTURE);
+    let output = check(vec!["--report-json".to_string(), path]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout.starts_with('['), "{}", stdout);
+    assert_eq!(stdout.matches("\"kind\": ").count(), 4, "{}", stdout);
+    assert!(
+        stdout.contains("\"line\": 8,\n  \"column\": 8,\n  \"kind\": \"dead branch\""),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_check_report_of_clean_file() {
+    let source = "def double(x):\n  return x * 2\n\ntotal = 0\nfor n in [1, 2]:\n  total = total + double(n)\nprint(total)";
+    let output = check(vec![
+        "--report".to_string(),
+        write_script("report_clean", source),
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "");
+
+    let output = check(vec![
+        "--report-json".to_string(),
+        write_script("report_clean_json", source),
+    ]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "[]\n");
+}
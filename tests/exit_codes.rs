@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+fn exit_code_of(name: &str, source: &str) -> i32 {
+    // each script gets its own file so tests can run in parallel
+    let path = std::env::temp_dir().join(format!("myton_exit_code_{}.my", name));
+    std::fs::write(&path, source).unwrap();
+    run_binary(path)
+}
+
+fn run_binary(path: PathBuf) -> i32 {
+    Command::new(env!("CARGO_BIN_EXE_myton"))
+        .arg(path)
+        .output()
+        .unwrap()
+        .status
+        .code()
+        .unwrap()
+}
+
+#[test]
+fn test_success() {
+    assert_eq!(exit_code_of("success", "print(1)"), 0);
+}
+
+#[test]
+fn test_compile_time_errors() {
+    assert_eq!(exit_code_of("lex_error", "print(1) $"), 65);
+    assert_eq!(exit_code_of("parse_error", "print(1"), 65);
+    assert_eq!(exit_code_of("resolve_error", "return 1"), 65);
+}
+
+#[test]
+fn test_runtime_error() {
+    assert_eq!(
+        exit_code_of("runtime_error", "print(1)\nprint(undefined)"),
+        70
+    );
+}
+
+#[test]
+fn test_unreadable_input() {
+    assert_eq!(run_binary(PathBuf::from("tests/does_not_exist.my")), 66);
+}
+
+#[test]
+fn test_exit() {
+    assert_eq!(
+        exit_code_of("exit", "def f():\n  exit(3)\nf()\nprint(1)"),
+        3
+    );
+    assert_eq!(exit_code_of("exit_zero", "exit(0)\nprint(undefined)"), 0);
+}
@@ -1,17 +1,22 @@
 import subprocess as sp
 import glob
+import os
 
 EXEC = "../target/release/myton"
 
 def generate_outputs(file_name):
     print('Generating outputs for', file_name)
-    out = None
-    try:
-        out = sp.check_output([EXEC, file_name], universal_newlines=True)
-    except sp.CalledProcessError as e:
-        out = e.output
+    in_file = file_name.removesuffix(".my") + '.in'
+    stdin = open(in_file).read() if os.path.exists(in_file) else ''
+    res = sp.run([EXEC, file_name], input=stdin, capture_output=True, universal_newlines=True)
     with open(file_name.removesuffix(".my") + '.out', 'w') as f:
-        f.write(str(out))
+        f.write(res.stdout)
+    err_file = file_name.removesuffix(".my") + '.err'
+    if res.stderr:
+        with open(err_file, 'w') as f:
+            f.write(res.stderr)
+    elif os.path.exists(err_file):
+        os.remove(err_file)
 
 def main():
     sp.call(['cargo', 'build', '--release'], stdout=sp.DEVNULL, stderr=sp.DEVNULL)
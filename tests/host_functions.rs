@@ -0,0 +1,97 @@
+use myton::{DynValue, Interpreter, MyWrite, TracebackKind};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+fn interpreter() -> (Interpreter, Rc<RefCell<Box<dyn MyWrite>>>) {
+    let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+    (Interpreter::new_with_output(output.clone()), output)
+}
+
+#[test]
+fn test_call_script_function_from_host() {
+    let (mut interpreter, output) = interpreter();
+    interpreter
+        .run(
+            "seen = []\ndef on_event(x):\n  seen.append(x)\n  print \"event \" + str(x)\n  return x * 2\n"
+                .to_string(),
+        )
+        .unwrap();
+
+    let on_event = interpreter.get_function("on_event").unwrap();
+    assert_eq!(on_event.name(), "on_event");
+    for (arg, expected) in [(1.0, 2.0), (2.5, 5.0), (-4.0, -8.0)] {
+        let result = on_event.call(vec![DynValue::from(arg)]).unwrap();
+        assert_eq!(result.as_number(), expected);
+    }
+    assert_eq!(
+        output.borrow().get_string().unwrap(),
+        "event 1\nevent 2.5\nevent -4\n"
+    );
+
+    // the calls shared the script's globals
+    interpreter.run("print(seen)".to_string()).unwrap();
+    assert!(output
+        .borrow()
+        .get_string()
+        .unwrap()
+        .ends_with("[1, 2.5, -4]\n"));
+
+    assert!(interpreter.get_function("seen").is_none());
+    assert!(interpreter.get_function("missing").is_none());
+}
+
+#[test]
+fn test_handle_outlives_later_runs() {
+    let (mut interpreter, _) = interpreter();
+    interpreter
+        .run("def hook():\n  return \"first\"\n".to_string())
+        .unwrap();
+    let first = interpreter.get_function("hook").unwrap();
+
+    interpreter.run("unrelated = 1".to_string()).unwrap();
+    assert_eq!(first.call(Vec::new()).unwrap().as_string(), "first");
+
+    // a redefinition is picked up by new handles only
+    interpreter
+        .run("def hook():\n  return \"second\"\n".to_string())
+        .unwrap();
+    assert_eq!(first.call(Vec::new()).unwrap().as_string(), "first");
+    let second = interpreter.get_function("hook").unwrap();
+    assert_eq!(second.call(Vec::new()).unwrap().as_string(), "second");
+
+    // and the handle keeps the globals alive
+    drop(interpreter);
+    assert_eq!(second.call(Vec::new()).unwrap().as_string(), "second");
+}
+
+#[test]
+fn test_call_errors() {
+    let (mut interpreter, _) = interpreter();
+    interpreter
+        .run_named("def pick(xs, i):\n  return xs[i]\n".to_string(), "hooks.my")
+        .unwrap();
+    let pick = interpreter.get_function("pick").unwrap();
+
+    let error = pick.call(vec![DynValue::from(1.0)]).unwrap_err();
+    assert!(matches!(error.kind(), TracebackKind::TypeError));
+    assert!(
+        error.to_string().contains("Expected 2 arguments but got 1"),
+        "{}",
+        error
+    );
+
+    let error = pick
+        .call(vec![
+            DynValue::from(vec![DynValue::from(1.0)]),
+            DynValue::from(3.0),
+        ])
+        .unwrap_err();
+    assert!(matches!(error.kind(), TracebackKind::IndexError));
+    assert_eq!(error.line(), 2);
+    assert!(error.to_string().contains("hooks.my"), "{}", error);
+
+    // builtins are callables too
+    let max = interpreter.get_function("max").unwrap();
+    let numbers = DynValue::from(vec![DynValue::from(2.0), DynValue::from(7.0)]);
+    assert_eq!(max.call(vec![numbers]).unwrap().as_number(), 7.0);
+}
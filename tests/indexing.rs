@@ -0,0 +1,69 @@
+use myton::run_to_strings;
+use std::time::{Duration, Instant};
+
+// the fastest of a few runs, the others being slowed by whatever
+// else the machine is doing
+fn run_time(source: &str) -> Duration {
+    (0..3)
+        .map(|_| {
+            let start = Instant::now();
+            let (_, errors) = run_to_strings(source.to_string());
+            assert_eq!(errors, "");
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+// four times the elements should take about four times as long,
+// sixteen if each step went through the whole sequence
+fn assert_linear(script: impl Fn(usize) -> String) {
+    let small = run_time(&script(5000));
+    let large = run_time(&script(20000));
+    assert!(
+        large < small * 8,
+        "4x the elements took {:?} instead of {:?}",
+        large,
+        small
+    );
+}
+
+#[test]
+fn test_indexing_takes_constant_time() {
+    for sequence in ["[1] * n", "tuple([1] * n)"] {
+        assert_linear(|n| {
+            format!(
+                "
+n = {}
+a = {}
+t = 0
+i = 0
+while i < n:
+  t = t + a[i]
+  i = i + 1
+",
+                n, sequence
+            )
+        });
+    }
+}
+
+#[test]
+fn test_len_takes_constant_time() {
+    for container in ["[1] * n", "tuple([1] * n)", "dict(zip(range(n), [1] * n))"] {
+        assert_linear(|n| {
+            format!(
+                "
+n = {}
+a = {}
+t = 0
+i = 0
+while i < len(a):
+  t = t + a[i]
+  i = i + 1
+",
+                n, container
+            )
+        });
+    }
+}
@@ -0,0 +1,87 @@
+use myton::run_to_strings;
+
+// with the spaces around a keyword left out, a program still means the same
+fn assert_same_as_spaced(spaced: &str, tight: &str) {
+    let expected = run_to_strings(spaced.to_string());
+    assert_eq!(expected.1, "", "{:?} should run", spaced);
+    assert_eq!(
+        run_to_strings(tight.to_string()),
+        expected,
+        "{:?} should run like {:?}",
+        tight,
+        spaced
+    );
+}
+
+#[test]
+fn test_keywords_without_spaces() {
+    for (spaced, tight) in [
+        ("print True and (False)", "print True and(False)"),
+        ("print False or [1]", "print False or[1]"),
+        ("print False or \"x\"", "print False or\"x\""),
+        ("print not (1 == 2)", "print not(1 == 2)"),
+        ("print not -1", "print not-1"),
+        ("print not [0]", "print not[0]"),
+        ("print 1 and -1", "print 1 and-1"),
+        ("print None is None", "print None is(None)"),
+        ("print 1 in [1]", "print 1 in[1]"),
+        ("print 1 not in (2, 3)", "print 1 not in(2,3)"),
+        ("for x in [1, 2]:\n  print x", "for x in[1,2]:\n  print x"),
+        ("for c in \"ab\":\n  print c", "for c in\"ab\":\n  print c"),
+        ("if (1):\n  print 2", "if(1):\n  print 2"),
+        (
+            "if 0:\n  pass\nelif (1):\n  print 2",
+            "if 0:\n  pass\nelif(1):\n  print 2",
+        ),
+        (
+            "while (False):\n  pass\nprint 3",
+            "while(False):\n  pass\nprint 3",
+        ),
+        (
+            "def f(x):\n  return -x\nprint f(2)",
+            "def f(x):\n  return-x\nprint f(2)",
+        ),
+        (
+            "try:\n  [][0]\nexcept IndexError:\n  print \"caught\"",
+            "try:\n  [][0]\nexcept IndexError:\n  print\"caught\"",
+        ),
+    ] {
+        assert_same_as_spaced(spaced, tight);
+    }
+}
+
+#[test]
+fn test_not_binds_like_python() {
+    let (output, errors) = run_to_strings(
+        "
+print not 1 == 2
+print not True and False
+print not not 0
+print True and not False
+print [not 0, not 1]
+"
+        .to_string(),
+    );
+    assert_eq!(errors, "");
+    assert_eq!(output, "True\nFalse\nFalse\nTrue\n[True, False]\n");
+}
+
+#[test]
+fn test_identifiers_containing_keywords() {
+    let (output, errors) = run_to_strings(
+        "
+xin = [1]
+android = 2
+order = 3
+nothing = 4
+island = 5
+print xin
+print android + order + nothing + island
+for inx in xin:
+  print inx
+"
+        .to_string(),
+    );
+    assert_eq!(errors, "");
+    assert_eq!(output, "[1]\n14\n1\n");
+}
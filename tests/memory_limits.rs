@@ -0,0 +1,115 @@
+use myton::{Interpreter, MyWrite, TracebackKind};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// with room for a 100 byte string, or a list of two elements
+fn limited() -> Interpreter {
+    let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+    let mut interpreter = Interpreter::new_with_output(output);
+    interpreter.set_max_object_size(100);
+    interpreter
+}
+
+fn memory_error(source: &str) -> String {
+    let error = limited().run(source.to_string()).unwrap_err();
+    assert!(
+        matches!(error.kind(), TracebackKind::MemoryError),
+        "{}: {}",
+        source,
+        error
+    );
+    error.message().to_string()
+}
+
+#[test]
+fn test_over_the_limit() {
+    assert_eq!(memory_error("x = \"a\" * 101"), "repeat count too large");
+    assert_eq!(memory_error("x = 101 * \"a\""), "repeat count too large");
+    assert_eq!(memory_error("x = [1] * 101"), "repeat count too large");
+    assert_eq!(memory_error("x = \"a\" * 60\ny = x + x"), "str too large");
+    assert_eq!(memory_error("x = [1] * 2\ny = x + x"), "list too large");
+    assert_eq!(
+        memory_error("x = [1] * 2\nx.extend([2, 3])"),
+        "list too large"
+    );
+}
+
+#[test]
+fn test_widths_over_the_limit() {
+    for method in ["ljust", "rjust", "zfill"] {
+        assert_eq!(
+            memory_error(&format!("x = \"a\".{}(101)", method)),
+            "str too large"
+        );
+        assert_eq!(
+            memory_error(&format!("x = \"a\".{}(10 ** 12)", method)),
+            "str too large"
+        );
+        limited().run(format!("x = \"a\".{}(100)", method)).unwrap();
+    }
+
+    for template in [
+        "{:>101}",
+        "{:>1000000000000}",
+        "{:.1000000000000f}",
+        "{:x<1000000000000}",
+    ] {
+        assert_eq!(
+            memory_error(&format!("x = \"{}\".format(1)", template)),
+            "format width too large",
+            "{}",
+            template
+        );
+    }
+    // a fill of several bytes takes as many for each character
+    assert_eq!(
+        memory_error("x = \"{:é<51}\".format(1)"),
+        "format width too large"
+    );
+    limited()
+        .run("x = \"{:>100}{:.90f}{:é<50}\".format(1, 2, 3)".to_string())
+        .unwrap();
+}
+
+#[test]
+fn test_within_the_limit() {
+    let mut interpreter = limited();
+    interpreter
+        .run(
+            "s = \"a\" * 100\nt = \"a\" * 50 + \"b\" * 50\nxs = [1]\nys = xs + xs\nxs.extend([2])"
+                .to_string(),
+        )
+        .unwrap();
+
+    // the limit applies to functions defined before it changed
+    interpreter
+        .run("def grow(s):\n  return s + s\n".to_string())
+        .unwrap();
+    interpreter.run("grow(s)".to_string()).unwrap_err();
+    interpreter.set_max_object_size(200);
+    interpreter.run("grow(s)".to_string()).unwrap();
+}
+
+#[test]
+fn test_doubling_stops_at_the_limit() {
+    let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+    let mut interpreter = Interpreter::new_with_output(output.clone());
+    interpreter.set_max_object_size(100);
+    interpreter
+        .run(
+            "
+text = \"0123456789\"
+doublings = 0
+try:
+  while True:
+    text = text + text
+    doublings = doublings + 1
+except MemoryError as error:
+  print error
+print doublings
+"
+            .to_string(),
+        )
+        .unwrap();
+    assert_eq!(output.borrow().get_string().unwrap(), "str too large\n3\n");
+}
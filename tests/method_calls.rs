@@ -0,0 +1,70 @@
+use myton::{run_to_strings, Interpreter};
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_method_calls_on_mixed_classes() {
+    let source = "
+class Counter:
+  def __init__():
+    this.count = 0
+  def bump(n):
+    this.count = this.count + n
+class Doubler(Counter):
+  def bump(n):
+    super.bump(2 * n)
+counters = [Counter(), Doubler()]
+i = 0
+while i < 50000:
+  counters[i % 2].bump(1)
+  i = i + 1
+print counters[0].count
+print counters[1].count
+";
+    let (output, errors) = run_to_strings(source.to_string());
+    assert_eq!(errors, "");
+    assert_eq!(output, "25000\n50000\n");
+}
+
+// the fastest of a few loops calling a method defined `depth`
+// classes above the instance's own
+fn call_time(depth: usize) -> Duration {
+    let mut source = "
+class C0:
+  def __init__():
+    this.count = 0
+  def bump(n):
+    this.count = this.count + n
+"
+    .to_string();
+    for class in 1..=depth {
+        source += &format!("class C{}(C{}):\n  pass\n", class, class - 1);
+    }
+    source += &format!("counter = C{}()\n", depth);
+
+    let mut interpreter = Interpreter::new();
+    interpreter.run(source).unwrap();
+    (0..3)
+        .map(|_| {
+            let start = Instant::now();
+            interpreter
+                .run("i = 0\nwhile i < 20000:\n  counter.bump(1)\n  i = i + 1".to_string())
+                .unwrap();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+#[test]
+fn test_methods_are_looked_up_once() {
+    // walking 2000 classes on every call makes it about 3.5 times
+    // slower, once for the first call it shouldn't show
+    let own = call_time(0);
+    let inherited = call_time(2000);
+    assert!(
+        inherited < own * 2,
+        "calling an inherited method took {:?}, an own one {:?}",
+        inherited,
+        own
+    );
+}
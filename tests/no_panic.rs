@@ -0,0 +1,217 @@
+use myton::run_to_string;
+use std::panic::catch_unwind;
+
+fn nasty_inputs() -> Vec<String> {
+    let mut inputs: Vec<String> = vec![
+        "",
+        " ",
+        "  ",
+        "\n\n\n",
+        "    \n  \n",
+        "  print 1",
+        "(",
+        ")",
+        "[",
+        "]",
+        "print(",
+        "print((1, 2",
+        "[1, 2",
+        "{",
+        "{1",
+        "{1:",
+        "{:}",
+        "{[]: 1}",
+        "=",
+        "= 1",
+        "a =",
+        ":=",
+        "a :=",
+        "1 := 2",
+        ".",
+        "a.",
+        ".a",
+        "a.b = ",
+        "1.x = 2",
+        "def",
+        "def f",
+        "def f(",
+        "def f(a,",
+        "def f():",
+        "def f():\n",
+        "class",
+        "class A",
+        "class A(",
+        "class A:",
+        "class A:\n  x = 1",
+        "class A(A):\n  def f():\n    pass",
+        "if",
+        "if 1",
+        "if 1:",
+        "if 1:\nelse",
+        "if 1:\n  pass\nelif",
+        "if 1:\n  pass\nelif 2",
+        "elif 1:",
+        "else:",
+        "while",
+        "for",
+        "for x",
+        "for x in",
+        "for x in 1:\n  print x",
+        "return",
+        "global",
+        "nonlocal",
+        "match",
+        "match 1:",
+        "match 1:\n  case",
+        "match 1:\n  case -",
+        "match 1:\n  case [",
+        "super",
+        "super.",
+        "self",
+        "self.x",
+        "print",
+        "print print",
+        "\"",
+        "\"unterminated",
+        "#",
+        "# only a comment",
+        "1 +",
+        "- -",
+        "!",
+        "1 / 0",
+        "1 % 0",
+        "\"a\" - 1",
+        "clock * 2",
+        "True * 3",
+        "\"ab\" * 10000000000000000000",
+        "[1] * 10000000000000000000",
+        "10000000000000000000 * \"ab\"",
+        "1e300 * [1]",
+        "[1] * 0.5",
+        "class A:\n  def f():\n    pass\nA() * 2",
+        "[] < 1",
+        "a",
+        "a()",
+        "1()",
+        "clock(1)",
+        "exit()",
+        "exit(\"a\")",
+        "exit(1e300)",
+        "1e999999",
+        "99999999999999999999999999999999999999",
+        "0.0000000000000000000000000000001",
+        "\u{0}",
+        "\u{7}\u{8}\u{1b}",
+        "print 1\u{0}",
+        "\r",
+        "\r\n",
+        "\t",
+        "\tprint 1",
+        "print 1\n\tprint 2",
+        "é",
+        "print \"é\"",
+        "🦀",
+        "a = 1\nfor a in a:\n  print a",
+        "def f():\n  return f\nf()()()",
+        "class A:\n  def init(x):\n    pass\nA()",
+        "class A:\n  def f():\n    return self\nA().f().f().x",
+    ]
+    .into_iter()
+    .map(|s| s.to_string())
+    .collect();
+
+    inputs.push("(".repeat(10_000));
+    inputs.push("[".repeat(10_000));
+    inputs.push("{".repeat(10_000));
+    inputs.push("{1: ".repeat(10_000));
+    inputs.push("-".repeat(10_000) + "1");
+    inputs.push("!".repeat(10_000) + "1");
+    inputs.push(format!("{}1{}", "(".repeat(10_000), ")".repeat(10_000)));
+    inputs.push("a.".repeat(10_000) + "a");
+    inputs.push(format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000)));
+    inputs.push("print ".to_string() + &["1"; 100_000].join(" + "));
+    inputs.push("print ".to_string() + &["1"; 100_000].join(" * "));
+    inputs.push("print ".to_string() + &["1"; 100_000].join(" < "));
+    inputs.push("print ".to_string() + &["1"; 100_000].join(" and "));
+    inputs.push("print ".to_string() + &["1"; 100_000].join(" or "));
+    inputs.push("  ".repeat(1_000) + "print 1");
+    inputs.push("def f():\n  return f()\nf()".to_string());
+    inputs.push("exec(\"def f():\n  return f()\nf()\")".to_string());
+    inputs.push("s = \"exec(s)\"\nexec(s)".to_string());
+    inputs.push((0..10_000).map(|i| format!("if {}:\n", i)).collect());
+    inputs.push(
+        (0..250)
+            .map(|i| format!("{}if 1:\n", "  ".repeat(i)))
+            .collect(),
+    );
+    inputs
+}
+
+// the nesting allowed by the parser fits in the main
+// thread's stack, not in the smaller one of test threads
+fn run_on_main_sized_stack(source: String) -> Option<String> {
+    std::thread::Builder::new()
+        .stack_size(8 * 1024 * 1024)
+        .spawn(move || catch_unwind(|| run_to_string(source)).ok())
+        .unwrap()
+        .join()
+        .unwrap()
+}
+
+#[test]
+fn test_no_panic_on_nasty_inputs() {
+    let mut panicked = Vec::new();
+    for input in nasty_inputs() {
+        if run_on_main_sized_stack(input.clone()).is_none() {
+            panicked.push(input.chars().take(40).collect::<String>());
+        }
+    }
+    assert!(panicked.is_empty(), "panicked on: {:#?}", panicked);
+}
+
+#[test]
+fn test_deep_nesting_is_a_syntax_error() {
+    let output =
+        run_on_main_sized_stack(format!("{}1{}", "(".repeat(1_000), ")".repeat(1_000))).unwrap();
+    assert!(
+        output.contains("expression too deeply nested"),
+        "{}",
+        output
+    );
+
+    // long operator chains aren't nesting, and run
+    let output =
+        run_on_main_sized_stack("print ".to_string() + &["1"; 100_000].join(" + ")).unwrap();
+    assert_eq!(output, "100000\n");
+    let output =
+        run_on_main_sized_stack("print ".to_string() + &["True"; 100_000].join(" and ")).unwrap();
+    assert_eq!(output, "True\n");
+    let output = run_on_main_sized_stack(
+        "print ".to_string() + &["False"; 100_000].join(" or ") + " or \"last\"",
+    )
+    .unwrap();
+    assert_eq!(output, "last\n");
+
+    // but reasonable nesting is fine
+    let output = run_to_string("print ".to_string() + &["1"; 100].join(" + "));
+    assert_eq!(output, "100\n");
+    let output = run_to_string(format!("print {}1{}", "(".repeat(50), ")".repeat(50)));
+    assert_eq!(output, "1\n");
+}
+
+#[test]
+fn test_deep_recursion_is_a_recursion_error() {
+    for source in [
+        "def f():\n  return f()\nf()",
+        "exec(\"def f():\n  return f()\nf()\")",
+        "s = \"exec(s)\"\nexec(s)",
+    ] {
+        let output = run_on_main_sized_stack(source.to_string()).unwrap();
+        assert!(
+            output.contains("maximum recursion depth exceeded"),
+            "{}: {}",
+            source,
+            output
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use myton::{lex, parse, resolve, run_to_string, Interpreter, MyWrite, TokenKind, TracebackKind};
+use std::cell::RefCell;
+use std::rc::Rc;
+use walkdir::WalkDir;
+
+// lexes, parses, resolves and executes the source one stage at a time
+fn run_in_stages(source: &str) -> String {
+    let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+    let mut interpreter = Interpreter::new_with_output(output.clone());
+    let ast = parse(lex(source).unwrap()).unwrap();
+    let program = resolve(&ast).unwrap();
+    interpreter.execute(program).unwrap();
+    let res = output.borrow().get_string().unwrap();
+    res
+}
+
+#[test]
+fn test_lex() {
+    let kinds: Vec<_> = lex("x = 1\n")
+        .unwrap()
+        .into_iter()
+        .map(|token| token.kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            TokenKind::Identifier,
+            TokenKind::Equal,
+            TokenKind::Number,
+            TokenKind::Newline,
+            TokenKind::Eof
+        ]
+    );
+
+    let error = lex("if True:\n  x = 1\n\ty = 2\n").unwrap_err();
+    assert!(matches!(error.kind(), TracebackKind::TabError));
+    assert_eq!(error.line(), 3);
+}
+
+#[test]
+fn test_parse() {
+    let ast = parse(lex("print 1\nprint 2\n").unwrap()).unwrap();
+    assert_eq!(format!("{:?}", ast), "Ast { statements: 2 }");
+
+    let errors = parse(lex("x = (\nprint 2\ny = ]\n").unwrap()).unwrap_err();
+    assert_eq!(errors.len(), 2);
+    assert!(errors
+        .iter()
+        .all(|error| matches!(error.kind(), TracebackKind::SyntaxError)));
+}
+
+#[test]
+fn test_parse_has_no_side_effects() {
+    // nothing is printed before executing, and a program can run twice
+    let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+    let mut interpreter = Interpreter::new_with_output(output.clone());
+    let ast = parse(lex("print \"hi\"\n").unwrap()).unwrap();
+    assert_eq!(output.borrow().get_string().unwrap(), "");
+
+    interpreter.execute(resolve(&ast).unwrap()).unwrap();
+    interpreter.execute(resolve(&ast).unwrap()).unwrap();
+    assert_eq!(output.borrow().get_string().unwrap(), "hi\nhi\n");
+}
+
+#[test]
+fn test_resolve() {
+    let ast = parse(lex("def f():\n  return 1\nreturn 2\nreturn 3\n").unwrap()).unwrap();
+    let errors = resolve(&ast).unwrap_err();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].line(), 3);
+
+    let program = resolve(&parse(lex("len = 1\n").unwrap()).unwrap()).unwrap();
+    assert_eq!(program.warnings().len(), 1);
+}
+
+#[test]
+fn test_execute() {
+    let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+    let mut interpreter = Interpreter::new_with_output(output.clone());
+    let source = "def counter():\n  n = 0\n  def next():\n    nonlocal n\n    n = n + 1\n    return n\n  return next\nc = counter()\n";
+    interpreter
+        .execute(resolve(&parse(lex(source).unwrap()).unwrap()).unwrap())
+        .unwrap();
+
+    // what it defined is there for the runs after it
+    interpreter.run("c()\nprint(c())".to_string()).unwrap();
+    assert_eq!(output.borrow().get_string().unwrap(), "2\n");
+
+    let error = interpreter
+        .execute(resolve(&parse(lex("x = [1]\nx[3]\n").unwrap()).unwrap()).unwrap())
+        .unwrap_err();
+    assert!(matches!(error.kind(), TracebackKind::IndexError));
+    assert_eq!(error.line(), 2);
+}
+
+#[test]
+fn test_stages_match_run_to_string() {
+    // every test script which runs without errors nor input
+    let files = WalkDir::new("tests")
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().unwrap_or_default() == "my")
+        .map(|e| e.path().to_str().unwrap().to_string())
+        .filter(|path| {
+            !std::path::Path::new(&path.replace(".my", ".err")).exists()
+                && !std::path::Path::new(&path.replace(".my", ".in")).exists()
+        })
+        .collect::<Vec<_>>();
+    assert!(!files.is_empty());
+
+    // the scripts get as much stack as from the command line
+    std::thread::Builder::new()
+        .stack_size(8 * 1024 * 1024)
+        .spawn(move || {
+            for path in files {
+                let source = std::fs::read_to_string(&path).unwrap();
+                assert_eq!(
+                    run_in_stages(&source),
+                    run_to_string(source.clone()),
+                    "file: {}",
+                    path
+                );
+            }
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
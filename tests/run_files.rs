@@ -1,13 +1,31 @@
-use ::myton::run_to_string;
-use snailquote::escape;
-use std::env::args;
+use ::myton::test_support::line_diff;
+use ::myton::{run_to_string, run_to_strings_with_input, Interpreter, MyWrite};
+use std::cell::RefCell;
+use std::rc::Rc;
 use walkdir::{self, WalkDir};
 
+// the scripts get as much stack as from the command line,
+// more than the test threads have
+fn run_on_main_sized_stack(content: String, input: String) -> (String, String) {
+    std::thread::Builder::new()
+        .stack_size(8 * 1024 * 1024)
+        .spawn(move || run_to_strings_with_input(content, input))
+        .unwrap()
+        .join()
+        .unwrap()
+}
+
 #[test]
 fn test_files() {
     // finds recursively all files in the tests directory
-    // ending with .my and executes them
+    // ending with .my and executes them, with the lines of
+    // the .in file (if any) as what input() reads
     // then compares the output with the content of the .out file
+    // and the errors with the content of the .err file (if any)
+    //
+    // MYTON_UPDATE=1 cargo test rewrites the .out and .err files instead
+
+    let update = std::env::var("MYTON_UPDATE").is_ok_and(|value| value == "1");
 
     let files = WalkDir::new("tests")
         .into_iter()
@@ -20,23 +38,80 @@ fn test_files() {
         let path = file.path().to_str().unwrap();
 
         let content = std::fs::read_to_string(path).unwrap();
+        let input = std::fs::read_to_string(path.replace(".my", ".in")).unwrap_or_default();
 
-        let output = run_to_string(content);
+        let (output, errors) = run_on_main_sized_stack(content, input);
 
         let out_path = path.replace(".my", ".out");
+        let err_path = path.replace(".my", ".err");
 
-        if args().any(|x| x == "--update") {
+        if update {
             std::fs::write(out_path, output).unwrap();
+            if errors.is_empty() {
+                std::fs::remove_file(err_path).ok();
+            } else {
+                std::fs::write(err_path, errors).unwrap();
+            }
         } else {
-            if let Ok(expected) = std::fs::read_to_string(out_path) {
-                let message = format!(
-                    "\nfile: {}\nexpected:\n{}\ngot:\n{}",
-                    path, &expected, &output
+            let expected = std::fs::read_to_string(&out_path).unwrap_or_else(|_| {
+                panic!(
+                    "No .out file found for {}, run MYTON_UPDATE=1 cargo test to create it",
+                    path
+                )
+            });
+            if let Some(diff) = line_diff(&expected, &output) {
+                panic!(
+                    "\nfile: {}\noutput differs from {}, {}",
+                    path, out_path, diff
+                );
+            }
+
+            let expected_errors = std::fs::read_to_string(&err_path).unwrap_or_default();
+            if let Some(diff) = line_diff(&expected_errors, &errors) {
+                panic!(
+                    "\nfile: {}\nerrors differ from {}, {}",
+                    path, err_path, diff
                 );
-                assert_eq!(output, expected, "{}", message);
-            } else {
-                //panic!("No .out file found for {}", path);
             }
         }
     }
 }
+
+#[test]
+fn test_files_run_twice() {
+    // every script which runs without errors nor input, run a second
+    // time in the same interpreter: its text parsed again must not
+    // pick up the distances resolved for the first parse
+    let files = WalkDir::new("tests")
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().unwrap_or_default() == "my")
+        .map(|e| e.path().to_str().unwrap().to_string())
+        .filter(|path| {
+            !std::path::Path::new(&path.replace(".my", ".err")).exists()
+                && !std::path::Path::new(&path.replace(".my", ".in")).exists()
+        })
+        .collect::<Vec<_>>();
+
+    std::thread::Builder::new()
+        .stack_size(8 * 1024 * 1024)
+        .spawn(move || {
+            for path in files {
+                let content = std::fs::read_to_string(&path).unwrap();
+                let once = run_to_string(content.clone());
+
+                let output = Rc::new(RefCell::new(Box::new(Vec::new()) as Box<dyn MyWrite>));
+                let mut interpreter = Interpreter::new_with_output(output.clone());
+                interpreter.run(content.clone()).unwrap();
+                interpreter.run(content).unwrap();
+                let twice = output.borrow().get_string().unwrap();
+
+                if let Some(diff) = line_diff(&(once.clone() + &once), &twice) {
+                    panic!("\nfile: {}\nsecond run differs, {}", path, diff);
+                }
+            }
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
@@ -0,0 +1,87 @@
+use myton::{Interpreter, MyWrite, RunStats};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+fn interpreter() -> Interpreter {
+    Interpreter::new_with_output(Rc::new(RefCell::new(
+        Box::new(Vec::new()) as Box<dyn MyWrite>
+    )))
+}
+
+fn counts(stats: RunStats) -> (u64, u64, usize, u64, u64) {
+    (
+        stats.statements,
+        stats.calls,
+        stats.peak_depth,
+        stats.lists,
+        stats.instances,
+    )
+}
+
+#[test]
+fn test_counts_of_a_deterministic_program() {
+    let mut interpreter = interpreter();
+    interpreter
+        .run(
+            "
+class Point:
+  def __init__(x):
+    this.x = x
+
+def countdown(n):
+  if n == 0:
+    return [n]
+  return countdown(n - 1)
+
+points = []
+for i in [1, 2, 3]:
+  points.append(Point(i))
+print countdown(4)
+"
+            .to_string(),
+        )
+        .unwrap();
+
+    // 5 at the top, 1 in the class body, 2 per point and 2 per countdown
+    assert_eq!(counts(interpreter.last_run_stats()), (22, 11, 5, 3, 3));
+}
+
+#[test]
+fn test_stats_reset_between_runs() {
+    let mut interpreter = interpreter();
+    interpreter
+        .run("def double(x):\n  return x * 2\n".to_string())
+        .unwrap();
+    assert_eq!(counts(interpreter.last_run_stats()), (1, 0, 0, 0, 0));
+
+    interpreter
+        .run("a = double(1)\nb = double(a)\n".to_string())
+        .unwrap();
+    assert_eq!(counts(interpreter.last_run_stats()), (4, 2, 1, 0, 0));
+
+    // a failed run is counted up to the error
+    interpreter
+        .run("c = [double(b)]\nd = c[5]\ne = 1\n".to_string())
+        .unwrap_err();
+    assert_eq!(counts(interpreter.last_run_stats()), (3, 1, 1, 1, 0));
+}
+
+#[test]
+fn test_stats_flag() {
+    let path = std::env::temp_dir().join("myton_stats_flag.my");
+    std::fs::write(&path, "xs = [1]\nprint xs\n").unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_myton"))
+        .arg("--stats")
+        .arg(path)
+        .output()
+        .unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "[1]\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr
+            .starts_with("statements: 2, calls: 0, peak depth: 0, lists: 1, instances: 0, time: "),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,32 @@
+use myton::SendInterpreter;
+use std::thread;
+
+fn assert_send<T: Send>() {}
+
+#[test]
+fn test_run_in_another_thread() {
+    assert_send::<SendInterpreter>();
+
+    let interpreter = SendInterpreter::new();
+    let (stdout, stderr) = interpreter.run("x = 20\nprint x + 1");
+    assert_eq!(stdout, "21\n");
+    assert_eq!(stderr, "");
+
+    // globals survive the move to another thread
+    let handle = thread::spawn(move || {
+        let output = interpreter.run("print x * 2\nprint undefined");
+        (interpreter, output)
+    });
+    let (interpreter, (stdout, stderr)) = handle.join().unwrap();
+    assert_eq!(stdout, "40\n");
+    assert!(
+        stderr.contains("Undefined variable 'undefined'"),
+        "{}",
+        stderr
+    );
+
+    // each run only returns its own output
+    let (stdout, stderr) = interpreter.run("print \"done\"");
+    assert_eq!(stdout, "done\n");
+    assert_eq!(stderr, "");
+}